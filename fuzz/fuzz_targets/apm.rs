@@ -0,0 +1,11 @@
+#![no_main]
+use gptinfo::reader::{apm, source::Raw};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// Driver descriptors and partition map entries
+fuzz_target!(|data: &[u8]| {
+	if let Ok(mut disk) = Raw::new(Cursor::new(data)) {
+		let _ = apm::read(&mut disk);
+	}
+});
@@ -0,0 +1,11 @@
+#![no_main]
+use gptinfo::reader::source::Compressed;
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// gzip, xz and zstd streams, xz indexes and zstd seek tables
+fuzz_target!(|data: &[u8]| {
+	if let Ok(mut image) = Compressed::new(Cursor::new(data)) {
+		let _ = gptinfo::read_gpt_from(&mut image);
+	}
+});
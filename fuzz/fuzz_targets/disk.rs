@@ -0,0 +1,25 @@
+#![no_main]
+use gptinfo::reader::{fs, slots, source::Raw};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// Whole images: exercises header validation, the backup fallback and entry
+// array bounds together, then the A/B boot slots and filesystems of the
+// entries
+fuzz_target!(|data: &[u8]| {
+	if let Ok(disk) = gptinfo::read_gpt(Cursor::new(data)) {
+		for entry in &disk.entries {
+			let _ = entry.sectors();
+		}
+		if let Ok(mut src) = Raw::new(Cursor::new(data)) {
+			let _ = slots::read(&disk, &mut src);
+			for entry in &disk.entries {
+				let _ = fs::probe(
+					&mut src,
+					entry.first_lba.saturating_mul(disk.sector_size),
+					entry.sectors().saturating_mul(disk.sector_size),
+				);
+			}
+		}
+	}
+});
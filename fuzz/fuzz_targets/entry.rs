@@ -0,0 +1,9 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	if let Ok(Some(entry)) = gptinfo::reader::parse_entry(data, 1) {
+		let _ = entry.sectors();
+		let _ = entry.partition_type().to_string();
+	}
+});
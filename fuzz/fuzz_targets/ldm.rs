@@ -0,0 +1,16 @@
+#![no_main]
+use gptinfo::reader::{ldm, source::Raw};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// Private headers, tables of contents and VBLK records, including ones
+// split across VBLKs
+fuzz_target!(|data: &[u8]| {
+	let mut disk = match Raw::new(Cursor::new(data)) {
+		Ok(disk) => disk,
+		Err(_) => return,
+	};
+	if let Ok(table) = gptinfo::read_table(&mut disk) {
+		let _ = ldm::read(&mut disk, &table);
+	}
+});
@@ -0,0 +1,14 @@
+#![no_main]
+use gptinfo::reader::{luks, source::Raw};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// A LUKS header at the start of the input: LUKS1, or both copies of a
+// LUKS2 header and their JSON metadata
+fuzz_target!(|data: &[u8]| {
+	let mut volume = match Raw::new(Cursor::new(data)) {
+		Ok(volume) => volume,
+		Err(_) => return,
+	};
+	let _ = luks::read(&mut volume, 0);
+});
@@ -0,0 +1,14 @@
+#![no_main]
+use gptinfo::reader::{lvm, source::Raw};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// A physical volume at the start of the input: its label, the metadata
+// area header and the text metadata it points to
+fuzz_target!(|data: &[u8]| {
+	let mut pv = match Raw::new(Cursor::new(data)) {
+		Ok(pv) => pv,
+		Err(_) => return,
+	};
+	let _ = lvm::read(&mut pv, 0);
+});
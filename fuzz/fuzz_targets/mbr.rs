@@ -0,0 +1,17 @@
+#![no_main]
+use gptinfo::reader::{disklabel, mbr, source::Raw};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// Extended boot record chains, and the labels inside every partition
+fuzz_target!(|data: &[u8]| {
+	let mut disk = match Raw::new(Cursor::new(data)) {
+		Ok(disk) => disk,
+		Err(_) => return,
+	};
+	if let Ok(table) = mbr::read(&mut disk) {
+		for entry in &table.entries {
+			let _ = disklabel::read(&mut disk, entry.first_lba, 512);
+		}
+	}
+});
@@ -0,0 +1,14 @@
+#![no_main]
+use gptinfo::reader::{md, source::Raw};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// md-raid superblocks of every version, at each place they may be in the
+// input
+fuzz_target!(|data: &[u8]| {
+	let mut member = match Raw::new(Cursor::new(data)) {
+		Ok(member) => member,
+		Err(_) => return,
+	};
+	let _ = md::read(&mut member, 0, data.len() as u64);
+});
@@ -0,0 +1,11 @@
+#![no_main]
+use gptinfo::reader::source::Qcow2;
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// qcow2 headers and L1/L2 tables, read through to the GPT
+fuzz_target!(|data: &[u8]| {
+	if let Ok(mut image) = Qcow2::new(Cursor::new(data)) {
+		let _ = gptinfo::read_gpt_from(&mut image);
+	}
+});
@@ -0,0 +1,11 @@
+#![no_main]
+use gptinfo::reader::source::Vhd;
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// VHD footers, dynamic headers and block allocation tables
+fuzz_target!(|data: &[u8]| {
+	if let Ok(mut image) = Vhd::new(Cursor::new(data)) {
+		let _ = gptinfo::read_gpt_from(&mut image);
+	}
+});
@@ -0,0 +1,11 @@
+#![no_main]
+use gptinfo::reader::source::Vhdx;
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// VHDX headers, region tables, metadata and allocation tables
+fuzz_target!(|data: &[u8]| {
+	if let Ok(mut image) = Vhdx::new(Cursor::new(data)) {
+		let _ = gptinfo::read_gpt_from(&mut image);
+	}
+});
@@ -0,0 +1,11 @@
+#![no_main]
+use gptinfo::reader::source::Vmdk;
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// VMDK sparse headers, grain tables and compressed grains
+fuzz_target!(|data: &[u8]| {
+	if let Ok(mut image) = Vmdk::new(Cursor::new(data)) {
+		let _ = gptinfo::read_gpt_from(&mut image);
+	}
+});
@@ -0,0 +1,13 @@
+#![no_main]
+use gptinfo::reader::{source::Raw, zfs};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// ZFS vdev labels, at the front and back of the input
+fuzz_target!(|data: &[u8]| {
+	let mut vdev = match Raw::new(Cursor::new(data)) {
+		Ok(vdev) => vdev,
+		Err(_) => return,
+	};
+	let _ = zfs::read(&mut vdev, 0, data.len() as u64);
+});
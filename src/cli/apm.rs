@@ -0,0 +1,52 @@
+use super::{Nested, Output, Row, Table};
+use gptinfo::reader::apm::Apm;
+
+/// One row per map entry, under a row for the disk.
+pub(crate) fn apm_table(
+	map: &Apm,
+	loc: &str,
+	nested: &Nested,
+	output: &Output,
+) -> Table {
+	let mut writer = Table::new(output.columns.len());
+	writer.push_cells(output.header(true));
+
+	if output.nested() {
+		// The map has no header of its own; its entries span the disk
+		let end = map
+			.entries
+			.iter()
+			.map(|entry| entry.end())
+			.max()
+			.unwrap_or(0);
+		let row = Row::span(
+			&format!("Disk ({})", loc),
+			"Apple Partition Map",
+			0,
+			end,
+			map.block_size,
+			output.units,
+		);
+		writer.push_cells(output.cells(&row));
+	}
+
+	for entry in map.entries.iter() {
+		let fs = nested.filesystems.get(&entry.number);
+		let row = Row {
+			attrs: entry.status_flags().join(", "),
+			fs: fs.map(|fs| fs.kind.to_string()).unwrap_or_default(),
+			label: fs.map(|fs| fs.label.clone()).unwrap_or_default(),
+			..Row::span(
+				&entry.name,
+				&entry.kind,
+				entry.start,
+				entry.end(),
+				map.block_size,
+				output.units,
+			)
+		};
+		writer.push_cells(output.cells(&row));
+	}
+
+	writer
+}
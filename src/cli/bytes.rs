@@ -0,0 +1,98 @@
+use super::CliError;
+use std::str::FromStr;
+
+// Prefixes of successive powers of the base, from kilo to yotta
+const PREFIXES: [char; 8] = ['K', 'M', 'G', 'T', 'P', 'E', 'Z', 'Y'];
+
+/// How sizes are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Units {
+	/// Powers of 1024: KiB, MiB, GiB
+	Iec,
+	/// Powers of 1000: kB, MB, GB
+	Si,
+	/// Exact byte counts
+	Bytes,
+}
+
+impl FromStr for Units {
+	type Err = CliError;
+
+	fn from_str(input: &str) -> Result<Units, CliError> {
+		match input {
+			"iec" => Ok(Units::Iec),
+			"si" => Ok(Units::Si),
+			_ => Err(CliError::InvalidUnits(input.to_string())),
+		}
+	}
+}
+
+/// The size of `start` to `end`, inclusive, in sectors of `sector_size`
+/// bytes, to one decimal place in the largest unit it has one of.
+pub(crate) fn format_bytes(
+	end: u64,
+	start: u64,
+	sector_size: u64,
+	units: Units,
+) -> String {
+	// u128 so that no sector count/size combination can overflow
+	let raw =
+		(u128::from(end.saturating_sub(start)) + 1) * u128::from(sector_size);
+	let base = match units {
+		Units::Iec => 1024,
+		Units::Si => 1000,
+		Units::Bytes => return raw.to_string(),
+	};
+	if raw < base {
+		return format!("{} B", raw);
+	}
+
+	let mut power = 0;
+	let mut unit = base;
+	while power + 1 < PREFIXES.len() && raw >= unit * base {
+		power += 1;
+		unit *= base;
+	}
+	let mut tenths = in_tenths(raw, unit);
+	if tenths >= base * 10 && power + 1 < PREFIXES.len() {
+		// Rounded up to a whole one of the next unit
+		power += 1;
+		tenths = in_tenths(raw, unit * base);
+	}
+
+	let prefix = PREFIXES[power];
+	let unit = match units {
+		Units::Si if prefix == 'K' => "kB".to_string(),
+		Units::Si => format!("{}B", prefix),
+		_ => format!("{}iB", prefix),
+	};
+	format!("{}.{} {}", tenths / 10, tenths % 10, unit)
+}
+
+/// `raw` in tenths of `unit`, rounded half up. Works on the whole and
+/// fractional parts apart so that nothing near `u128::MAX` overflows.
+fn in_tenths(raw: u128, unit: u128) -> u128 {
+	raw / unit * 10 + (raw % unit * 10 + unit / 2) / unit
+}
+
+/// Parses a byte count given on the command line: decimal with an optional
+/// K, M, G, T, P or E suffix for a power of 1024, or `0x` hex.
+pub(crate) fn parse_bytes(input: &str) -> Result<u64, CliError> {
+	let invalid = || CliError::InvalidBytes(input.to_string());
+	if let Some(hex) = input.strip_prefix("0x") {
+		return u64::from_str_radix(hex, 16).map_err(|_| invalid());
+	}
+
+	let (number, shift) =
+		match input.char_indices().last().and_then(|(at, unit)| {
+			Some((at, "KMGTPE".find(unit.to_ascii_uppercase())?))
+		}) {
+			Some((at, unit)) => (&input[..at], 10 * (unit as u32 + 1)),
+			None => (input, 0),
+		};
+	number
+		.parse::<u64>()
+		.ok()
+		.and_then(|number| number.checked_mul(1 << shift))
+		.ok_or_else(invalid)
+}
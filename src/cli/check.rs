@@ -0,0 +1,126 @@
+use super::{CliError, Frame, Table};
+use anyhow::{Context as _, Result};
+use colored::Colorize;
+use gptinfo::{
+	system::{self, Divergence, LiveDisk},
+	Disk,
+};
+use nix::errno::Errno;
+use std::{fs::File, path::Path};
+
+/// Compares the kernel's partitions of `device` with `disk`, optionally
+/// asking the kernel to catch up. Fails with [`CliError::Diverged`] if they
+/// still differ afterwards.
+pub(crate) fn check(
+	device: &str,
+	file: &File,
+	disk: &Disk,
+	reread: bool,
+	frame: &Frame,
+) -> Result<()> {
+	let load = || -> Result<LiveDisk> {
+		LiveDisk::load(Path::new(device))
+			.context("Failed to read kernel partitions")?
+			.ok_or_else(|| CliError::NotBlockDevice(device.to_string()).into())
+	};
+
+	let mut divergences = load()?.divergences(disk);
+	if !divergences.is_empty() {
+		println!();
+		divergence_table(&divergences, disk.sector_size).draw(frame);
+
+		if reread {
+			request_reread(file, disk, divergences);
+			divergences = load()?.divergences(disk);
+		}
+	}
+
+	if divergences.is_empty() {
+		println!("{}", "Kernel partitions match the on-disk table.".green());
+		Ok(())
+	} else {
+		Err(CliError::Diverged.into())
+	}
+}
+
+/// `BLKRRPART` when nothing is in use, otherwise `BLKPG` partition by
+/// partition.
+fn request_reread(file: &File, disk: &Disk, divergences: Vec<Divergence>) {
+	match system::reread_partitions(file) {
+		Ok(()) => println!("Kernel re-read the partition table."),
+		Err(err) if err.raw_os_error() == Some(Errno::EBUSY as i32) => {
+			println!("Disk is in use, updating partitions individually.");
+			for outcome in
+				system::sync_partitions(file, disk.sector_size, divergences)
+			{
+				let number = outcome.divergence.number();
+				match outcome.result {
+					Ok(()) => println!("Partition {}: updated", number),
+					Err(err) => eprintln!(
+						"{} Partition {}: {}",
+						"Warning:".yellow().bold(),
+						number,
+						err
+					),
+				}
+			}
+		}
+		Err(err) => eprintln!(
+			"{} Failed to re-read partition table: {}",
+			"Warning:".yellow().bold(),
+			err
+		),
+	}
+}
+
+fn divergence_table(divergences: &[Divergence], sector_size: u64) -> Table {
+	let mut writer = Table::new(6);
+	writer.push_cells(vec![
+		"Partition".bold(),
+		"Problem".bold(),
+		"Kernel Start".bold(),
+		"Kernel Sectors".bold(),
+		"Disk Start".bold(),
+		"Disk Sectors".bold(),
+	]);
+
+	for divergence in divergences {
+		let (problem, kernel, entry) = match divergence {
+			Divergence::Stale { entry, kernel } => {
+				("stale", Some(kernel), Some(entry))
+			}
+			Divergence::Missing { entry } => {
+				("missing in kernel", None, Some(entry))
+			}
+			Divergence::Extra { kernel } => ("not on disk", Some(kernel), None),
+		};
+		let lba = |bytes: u64| (bytes / sector_size).to_string();
+
+		writer.push_cells(vec![
+			divergence.number().to_string().as_str().into(),
+			problem.yellow(),
+			kernel
+				.map(|k| lba(k.start))
+				.unwrap_or_default()
+				.as_str()
+				.into(),
+			kernel
+				.map(|k| lba(k.size))
+				.unwrap_or_default()
+				.as_str()
+				.into(),
+			entry
+				.map(|e| e.first_lba.to_string())
+				.unwrap_or_default()
+				.as_str()
+				.into(),
+			entry
+				.map(|e| e.sectors().to_string())
+				.unwrap_or_default()
+				.as_str()
+				.into(),
+		]);
+	}
+
+	writer
+}
@@ -0,0 +1,246 @@
+use super::{bytes, CliError, Frame, Units};
+use colored::{ColoredString, Colorize};
+use gptinfo::{reader::slots, Guid, PartitionEntry};
+use std::str::FromStr;
+
+// Attribute bits every GPT partition may have
+const GPT_ATTRIBUTES: [(u32, &str); 3] =
+	[(0, "required"), (1, "no-block-io"), (2, "legacy-boot")];
+const BASIC_DATA: Guid =
+	Guid::from_groups(0xEBD0A0A2, 0xB9E5, 0x4433, 0x87C0, 0x68B6B72699C7);
+const BASIC_DATA_ATTRIBUTES: [(u32, &str); 4] = [
+	(60, "read-only"),
+	(61, "shadow-copy"),
+	(62, "hidden"),
+	(63, "no-automount"),
+];
+// The priority, tries and successful bits of ChromeOS kernels
+const CHROMEOS_KERNEL_BITS: u64 = 0x1FF << 48;
+
+/// A column of the partition tables, as named by --columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Column {
+	Name,
+	Type,
+	Start,
+	End,
+	Sectors,
+	Size,
+	Uuid,
+	Attrs,
+	Fs,
+	Label,
+}
+
+const COLUMNS: [(&str, Column); 10] = [
+	("name", Column::Name),
+	("type", Column::Type),
+	("start", Column::Start),
+	("end", Column::End),
+	("sectors", Column::Sectors),
+	("size", Column::Size),
+	("uuid", Column::Uuid),
+	("attrs", Column::Attrs),
+	("fs", Column::Fs),
+	("label", Column::Label),
+];
+
+/// The columns shown when --columns isn't given.
+pub(crate) const DEFAULT_COLUMNS: [Column; 6] = [
+	Column::Name,
+	Column::Type,
+	Column::Start,
+	Column::End,
+	Column::Sectors,
+	Column::Size,
+];
+
+/// The columns given to --columns, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Columns(pub(crate) Vec<Column>);
+
+impl FromStr for Columns {
+	type Err = CliError;
+
+	fn from_str(input: &str) -> Result<Columns, CliError> {
+		input
+			.split(',')
+			.map(|name| {
+				COLUMNS
+					.iter()
+					.find(|&&(known, _)| known == name.trim())
+					.map(|&(_, column)| column)
+					.ok_or_else(|| CliError::InvalidColumn(name.to_string()))
+			})
+			.collect::<Result<_, _>>()
+			.map(Columns)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+	Table,
+	Csv,
+	Tsv,
+}
+
+impl FromStr for Format {
+	type Err = CliError;
+
+	fn from_str(input: &str) -> Result<Format, CliError> {
+		match input {
+			"table" => Ok(Format::Table),
+			"csv" => Ok(Format::Csv),
+			"tsv" => Ok(Format::Tsv),
+			_ => Err(CliError::InvalidFormat(input.to_string())),
+		}
+	}
+}
+
+/// How the partition tables are printed.
+pub(crate) struct Output {
+	pub(crate) columns: Vec<Column>,
+	pub(crate) format: Format,
+	pub(crate) header: bool,
+	pub(crate) units: Units,
+	pub(crate) frame: Frame,
+}
+
+impl Output {
+	/// Whether rows for the disk and what's beneath partitions are shown,
+	/// along with the tables that follow. CSV and TSV have a row per
+	/// partition and nothing else.
+	pub(crate) fn nested(&self) -> bool { self.format == Format::Table }
+
+	/// Whether the filesystems of partitions need to be probed.
+	pub(crate) fn probes(&self) -> bool {
+		self.columns
+			.iter()
+			.any(|&column| column == Column::Fs || column == Column::Label)
+	}
+
+	/// The headings of the chosen columns. Apple Partition Maps count
+	/// blocks, and have status flags for attributes.
+	pub(crate) fn header(&self, apm: bool) -> Vec<ColoredString> {
+		self.columns
+			.iter()
+			.map(|column| {
+				match column {
+					Column::Name => "Name",
+					Column::Type => "Type",
+					Column::Start => "Start",
+					Column::End => "End",
+					Column::Sectors if apm => "Blocks",
+					Column::Sectors => "Sectors",
+					Column::Size => "Size",
+					Column::Uuid => "Unique GUID",
+					Column::Attrs if apm => "Status",
+					Column::Attrs => "Attributes",
+					Column::Fs => "Filesystem",
+					Column::Label => "Label",
+				}
+				.bold()
+			})
+			.collect()
+	}
+
+	/// The cells of `row` in the chosen columns.
+	pub(crate) fn cells(&self, row: &Row) -> Vec<ColoredString> {
+		self.columns
+			.iter()
+			.map(|column| {
+				match column {
+					Column::Name => &row.name,
+					Column::Type => &row.kind,
+					Column::Start => &row.start,
+					Column::End => &row.end,
+					Column::Sectors => &row.sectors,
+					Column::Size => &row.size,
+					Column::Uuid => &row.uuid,
+					Column::Attrs => &row.attrs,
+					Column::Fs => &row.fs,
+					Column::Label => &row.label,
+				}
+				.as_str()
+				.into()
+			})
+			.collect()
+	}
+}
+
+/// What a row of a partition table can show, whichever columns are chosen.
+#[derive(Default)]
+pub(crate) struct Row {
+	pub(crate) name: String,
+	pub(crate) kind: String,
+	pub(crate) start: String,
+	pub(crate) end: String,
+	pub(crate) sectors: String,
+	pub(crate) size: String,
+	pub(crate) uuid: String,
+	pub(crate) attrs: String,
+	pub(crate) fs: String,
+	pub(crate) label: String,
+}
+
+impl Row {
+	/// A row spanning `first` to `last`, inclusive, in sectors of
+	/// `sector_size` bytes, with its size in `units`.
+	pub(crate) fn span(
+		name: &str,
+		kind: &str,
+		first: u64,
+		last: u64,
+		sector_size: u64,
+		units: Units,
+	) -> Row {
+		Row {
+			name: name.to_string(),
+			kind: kind.to_string(),
+			start: first.to_string(),
+			end: last.to_string(),
+			sectors: last.saturating_sub(first).saturating_add(1).to_string(),
+			size: bytes::format_bytes(last, first, sector_size, units),
+			..Row::default()
+		}
+	}
+}
+
+/// The attribute bits of a GPT entry, by name where the type defines them.
+pub(crate) fn gpt_attributes(entry: &PartitionEntry) -> String {
+	let mut names = vec![];
+	let mut named = 0u64;
+	let mut add = |bits: &[(u32, &'static str)]| {
+		for &(bit, name) in bits.iter() {
+			if entry.attributes & 1 << bit != 0 {
+				names.push(name.to_string());
+				named |= 1 << bit;
+			}
+		}
+	};
+	add(&GPT_ATTRIBUTES);
+	if entry.type_guid == BASIC_DATA {
+		add(&BASIC_DATA_ATTRIBUTES);
+	}
+
+	if slots::is_chromeos_kernel(entry.type_guid)
+		&& entry.attributes & CHROMEOS_KERNEL_BITS != 0
+	{
+		let state = slots::kernel_state(entry.attributes);
+		names.push(format!(
+			"priority {}, tries {}",
+			state.priority, state.tries
+		));
+		if state.successful {
+			names.push("successful".to_string());
+		}
+		named |= CHROMEOS_KERNEL_BITS;
+	}
+
+	for bit in 0..64 {
+		if entry.attributes & !named & 1 << bit != 0 {
+			names.push(format!("bit {}", bit));
+		}
+	}
+	names.join(", ")
+}
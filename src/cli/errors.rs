@@ -0,0 +1,62 @@
+use thiserror::Error as ThisError;
+
+/// Errors of the command line rather than of reading disks.
+#[derive(ThisError, Debug)]
+pub(crate) enum CliError {
+	#[error("No disks found in /sys/block. Pass one with `--device`.")]
+	NoDevices,
+
+	#[error("`{0}` is not a disk known to the kernel.")]
+	NotBlockDevice(String),
+
+	#[error("The kernel's partitions differ from the on-disk table.")]
+	Diverged,
+
+	#[error("{0} of the {1} disks failed; see the warnings above.")]
+	Failed(usize, usize),
+
+	#[error(
+		"Invalid byte count `{0}`, expected a number with an optional K, M, \
+		 G, T, P or E suffix, or 0x followed by hex digits."
+	)]
+	InvalidBytes(String),
+
+	#[error("`{0}` already includes an offset; drop --offset.")]
+	DuplicateOffset(String),
+
+	#[error(
+		"--mounts, --tree and --check describe whole disks, not byte ranges \
+		 of one."
+	)]
+	WholeDiskOnly,
+
+	#[error(
+		"--absolute needs an offset that is a multiple of the {0}-byte block \
+		 size."
+	)]
+	UnalignedOffset(u64),
+
+	#[error("--mounts, --tree, --check and --map need a GPT disk, not {0}.")]
+	NotGpt(&'static str),
+
+	#[error(
+		"This binary must be run as root. See `gptinfo --help` for more \
+		 options."
+	)]
+	Root,
+
+	#[error(
+		"Unknown column `{0}`, expected name, type, start, end, sectors, \
+		 size, uuid, attrs, fs or label."
+	)]
+	InvalidColumn(String),
+
+	#[error("Unknown output format `{0}`, expected table, csv or tsv.")]
+	InvalidFormat(String),
+
+	#[error("Unknown units `{0}`, expected iec or si.")]
+	InvalidUnits(String),
+
+	#[error("Unknown color choice `{0}`, expected auto, always or never.")]
+	InvalidColor(String),
+}
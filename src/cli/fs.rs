@@ -0,0 +1,59 @@
+use gptinfo::{
+	reader::fs::{self, Filesystem},
+	BlockSource, PartitionTable,
+};
+use std::collections::BTreeMap;
+
+/// Filesystems found in partitions, by partition number.
+pub(crate) type Filesystems = BTreeMap<u32, Filesystem>;
+
+/// Probes every partition of `table` for a filesystem or volume metadata.
+pub(crate) fn read_filesystems(
+	table: &PartitionTable,
+	src: &mut dyn BlockSource,
+) -> Filesystems {
+	// Partition number, first byte and size in bytes
+	let partitions: Vec<(u32, u64, u64)> = match table {
+		PartitionTable::Gpt(disk) => disk
+			.entries
+			.iter()
+			.map(|entry| {
+				(
+					entry.number,
+					entry.first_lba.saturating_mul(disk.sector_size),
+					entry.sectors().saturating_mul(disk.sector_size),
+				)
+			})
+			.collect(),
+		PartitionTable::Mbr(mbr) => mbr
+			.entries
+			.iter()
+			.filter(|entry| !entry.is_extended())
+			.map(|entry| {
+				(
+					entry.number,
+					entry.first_lba.saturating_mul(mbr.sector_size),
+					entry.sectors.saturating_mul(mbr.sector_size),
+				)
+			})
+			.collect(),
+		PartitionTable::Apm(map) => map
+			.entries
+			.iter()
+			.map(|entry| {
+				(
+					entry.number,
+					entry.start.saturating_mul(map.block_size),
+					entry.blocks.saturating_mul(map.block_size),
+				)
+			})
+			.collect(),
+	};
+
+	partitions
+		.into_iter()
+		.filter_map(|(number, offset, size)| {
+			fs::probe(src, offset, size).map(|found| (number, found))
+		})
+		.collect()
+}
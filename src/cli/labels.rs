@@ -0,0 +1,88 @@
+use super::{Output, Row, Table};
+use colored::Colorize;
+use gptinfo::{
+	reader::disklabel::{self, Disklabel},
+	BlockSource, PartitionTable,
+};
+use std::collections::BTreeMap;
+
+/// Labels found inside partitions, by partition number.
+pub(crate) type Labels = BTreeMap<u32, Disklabel>;
+
+/// Reads the BSD disklabels and Solaris VTOCs of the partitions whose type
+/// may hold one. A damaged label is reported and left out rather than
+/// failing the whole disk.
+pub(crate) fn read_labels(
+	table: &PartitionTable,
+	src: &mut dyn BlockSource,
+) -> Labels {
+	let (sector_size, candidates): (u64, Vec<(u32, u64)>) = match table {
+		PartitionTable::Gpt(disk) => (
+			disk.sector_size,
+			disk.entries
+				.iter()
+				.filter(|entry| {
+					disklabel::gpt_type_holds_label(entry.type_guid)
+				})
+				.map(|entry| (entry.number, entry.first_lba))
+				.collect(),
+		),
+		PartitionTable::Mbr(mbr) => (
+			mbr.sector_size,
+			mbr.entries
+				.iter()
+				.filter(|entry| disklabel::mbr_type_holds_label(entry.kind))
+				.map(|entry| (entry.number, entry.first_lba))
+				.collect(),
+		),
+		PartitionTable::Apm(_) => return Labels::new(),
+	};
+
+	let mut labels = Labels::new();
+	for (number, first_lba) in candidates {
+		match disklabel::read(src, first_lba, sector_size) {
+			Ok(Some(label)) => {
+				labels.insert(number, label);
+			}
+			Ok(None) => {}
+			Err(err) => eprintln!(
+				"{} Partition {}: {}",
+				"Warning:".yellow().bold(),
+				number,
+				err
+			),
+		}
+	}
+	labels
+}
+
+/// One row per partition of `label`, drawn as a tree under the row of the
+/// partition holding it, followed by `after` empty cells to size columns.
+pub(crate) fn push_label(
+	writer: &mut Table,
+	label: &Disklabel,
+	sector_size: u64,
+	output: &Output,
+	after: usize,
+) {
+	for (idx, part) in label.partitions.iter().enumerate() {
+		let branch = if idx + 1 == label.partitions.len() {
+			"└─"
+		} else {
+			"├─"
+		};
+
+		let row = Row::span(
+			&format!("{} {}", branch, part.name),
+			&part.kind,
+			part.first_lba,
+			part.last_lba(),
+			sector_size,
+			output.units,
+		);
+		writer.push_cells(output.cells(&row));
+		for _ in 0..after {
+			writer.push_cell("".into());
+		}
+	}
+}
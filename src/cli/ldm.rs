@@ -0,0 +1,144 @@
+use super::{bytes, Table, Units};
+use colored::Colorize;
+use gptinfo::{
+	reader::ldm::{self, Layout, Ldm},
+	BlockSource, PartitionTable,
+};
+
+/// One row per dynamic volume, with its components and their extents
+/// drawn as a tree beneath it, under a row for the disk group.
+pub(crate) fn ldm_table(ldm: &Ldm, units: Units) -> Table {
+	let mut writer = Table::new(7);
+	writer.push_cells(vec![
+		"Name".bold(),
+		"Type".bold(),
+		"Disk".bold(),
+		"Start".bold(),
+		"End".bold(),
+		"Sectors".bold(),
+		"Size".bold(),
+	]);
+
+	let size = |sectors: u64| {
+		bytes::format_bytes(
+			sectors.saturating_sub(1),
+			0,
+			ldm.sector_size,
+			units,
+		)
+	};
+	let this_disk = ldm.this_disk();
+
+	// The area of this disk that extents are allocated from
+	let end = ldm
+		.data_start
+		.saturating_add(ldm.data_sectors.saturating_sub(1));
+	writer.push_cells(vec![
+		format!("Disk Group ({})", ldm.disk_group).as_str().into(),
+		format!("LDM {}.{}", ldm.version.0, ldm.version.1)
+			.as_str()
+			.into(),
+		this_disk.map_or("", |disk| disk.name.as_str()).into(),
+		ldm.data_start.to_string().as_str().into(),
+		end.to_string().as_str().into(),
+		ldm.data_sectors.to_string().as_str().into(),
+		size(ldm.data_sectors).as_str().into(),
+	]);
+
+	for volume in ldm.volumes.iter() {
+		writer.push_cells(vec![
+			volume.name.as_str().into(),
+			volume.layout().into(),
+			"".into(),
+			"".into(),
+			"".into(),
+			volume.sectors.to_string().as_str().into(),
+			size(volume.sectors).as_str().into(),
+		]);
+
+		for (idx, component) in volume.components.iter().enumerate() {
+			let last = idx + 1 == volume.components.len();
+			let kind = match component.layout {
+				Layout::Concatenated => "concatenated".to_string(),
+				Layout::Striped => {
+					format!(
+						"striped, {} stripes",
+						size(component.stripe_sectors)
+					)
+				}
+				Layout::Raid5 => {
+					format!(
+						"RAID-5, {} stripes",
+						size(component.stripe_sectors)
+					)
+				}
+				Layout::Unknown(kind) => format!("type {}", kind),
+			};
+			let sectors: u64 =
+				component.extents.iter().map(|extent| extent.sectors).sum();
+			writer.push_cells(vec![
+				format!(
+					"{} {}",
+					if last { "└─" } else { "├─" },
+					component.name
+				)
+				.as_str()
+				.into(),
+				kind.as_str().into(),
+				"".into(),
+				"".into(),
+				"".into(),
+				sectors.to_string().as_str().into(),
+				size(sectors).as_str().into(),
+			]);
+
+			let indent = if last { "   " } else { "│  " };
+			for (idx, extent) in component.extents.iter().enumerate() {
+				let branch = if idx + 1 == component.extents.len() {
+					"└─"
+				} else {
+					"├─"
+				};
+				// Only this disk's data area is known, so extents on the
+				// others go without LBAs
+				let (start, end) = match this_disk {
+					Some(disk) if disk.id == extent.disk => {
+						let start = ldm.data_start.saturating_add(extent.start);
+						let end = start
+							.saturating_add(extent.sectors.saturating_sub(1));
+						(start.to_string(), end.to_string())
+					}
+					_ => (String::new(), String::new()),
+				};
+				writer.push_cells(vec![
+					format!("{}{} {}", indent, branch, extent.name)
+						.as_str()
+						.into(),
+					"extent".into(),
+					ldm.disk_name(extent.disk).unwrap_or("?").into(),
+					start.as_str().into(),
+					end.as_str().into(),
+					extent.sectors.to_string().as_str().into(),
+					size(extent.sectors).as_str().into(),
+				]);
+			}
+		}
+	}
+
+	writer
+}
+
+/// Reads the LDM database of a Windows dynamic disk. A damaged one is
+/// reported and left out rather than failing the whole disk.
+pub(crate) fn read_ldm(
+	table: &PartitionTable,
+	src: &mut dyn BlockSource,
+) -> Option<Ldm> {
+	match ldm::read(src, table) {
+		Ok(ldm) => ldm,
+		Err(err) => {
+			eprintln!("{} {}", "Warning:".yellow().bold(), err);
+			None
+		}
+	}
+}
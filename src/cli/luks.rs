@@ -0,0 +1,194 @@
+use super::{bytes, Table, Units};
+use colored::Colorize;
+use gptinfo::{
+	reader::luks::{self, Kdf, Luks},
+	BlockSource, PartitionTable,
+};
+use std::collections::BTreeMap;
+
+/// Partitions typed as encrypted, by partition number.
+pub(crate) type Encrypted = BTreeMap<u32, Volume>;
+
+pub(crate) struct Volume {
+	pub(crate) first_lba: u64,
+	/// Whether the partition type calls for a LUKS header, unlike plain
+	/// dm-crypt
+	pub(crate) has_header: bool,
+	pub(crate) header: Option<Luks>,
+}
+
+/// Reads the LUKS headers of the partitions typed as encrypted. A missing
+/// or damaged header where one belongs is reported, and the partition is
+/// listed without one.
+pub(crate) fn read_encrypted(
+	table: &PartitionTable,
+	src: &mut dyn BlockSource,
+) -> Encrypted {
+	// Partition number, first LBA and whether a header belongs there
+	let (sector_size, candidates): (u64, Vec<(u32, u64, bool)>) = match table {
+		PartitionTable::Gpt(disk) => (
+			disk.sector_size,
+			disk.entries
+				.iter()
+				.filter(|entry| luks::gpt_type_is_encrypted(entry.type_guid))
+				.map(|entry| {
+					(
+						entry.number,
+						entry.first_lba,
+						luks::gpt_type_has_header(entry.type_guid),
+					)
+				})
+				.collect(),
+		),
+		PartitionTable::Mbr(mbr) => (
+			mbr.sector_size,
+			mbr.entries
+				.iter()
+				.filter(|entry| luks::mbr_type_is_encrypted(entry.kind))
+				.map(|entry| (entry.number, entry.first_lba, true))
+				.collect(),
+		),
+		PartitionTable::Apm(_) => return Encrypted::new(),
+	};
+
+	let mut encrypted = Encrypted::new();
+	for (number, first_lba, has_header) in candidates {
+		let header =
+			match luks::read(src, first_lba.saturating_mul(sector_size)) {
+				Ok(header) => header,
+				Err(err) => {
+					eprintln!(
+						"{} Partition {}: {}",
+						"Warning:".yellow().bold(),
+						number,
+						err
+					);
+					None
+				}
+			};
+		if header.is_none() && has_header {
+			eprintln!(
+				"{} Partition {}: No LUKS header found.",
+				"Warning:".yellow().bold(),
+				number
+			);
+		}
+		encrypted.insert(
+			number,
+			Volume {
+				first_lba,
+				has_header,
+				header,
+			},
+		);
+	}
+	encrypted
+}
+
+/// One row per encrypted partition with the parameters of its LUKS header,
+/// and the key slots in use drawn as a tree beneath it.
+pub(crate) fn luks_table(
+	encrypted: &Encrypted,
+	sector_size: u64,
+	units: Units,
+) -> Table {
+	let mut writer = Table::new(7);
+	writer.push_cells(vec![
+		"Name".bold(),
+		"Type".bold(),
+		"Cipher".bold(),
+		"Key Slots".bold(),
+		"PBKDF".bold(),
+		"Data Start".bold(),
+		"UUID".bold(),
+	]);
+
+	for (number, volume) in encrypted.iter() {
+		let header = match &volume.header {
+			Some(header) => header,
+			None => {
+				writer.push_cells(vec![
+					format!("Partition {}", number).as_str().into(),
+					if volume.has_header {
+						"no header"
+					} else {
+						"plain dm-crypt"
+					}
+					.into(),
+					"".into(),
+					"".into(),
+					"".into(),
+					"".into(),
+					"".into(),
+				]);
+				continue;
+			}
+		};
+
+		let name = if header.label.is_empty() {
+			format!("Partition {}", number)
+		} else {
+			format!("Partition {} ({})", number, header.label)
+		};
+		let cipher = match header.key_bits {
+			Some(bits) => format!("{}, {}-bit key", header.cipher, bits),
+			None => header.cipher.clone(),
+		};
+		let start = volume
+			.first_lba
+			.saturating_add(header.data_offset / sector_size);
+		writer.push_cells(vec![
+			name.as_str().into(),
+			format!("LUKS{}", header.version).as_str().into(),
+			cipher.as_str().into(),
+			format!("{} of {}", header.keyslots.len(), header.slot_count)
+				.as_str()
+				.into(),
+			"".into(),
+			start.to_string().as_str().into(),
+			header.uuid.as_str().into(),
+		]);
+
+		for (idx, slot) in header.keyslots.iter().enumerate() {
+			let branch = if idx + 1 == header.keyslots.len() {
+				"└─"
+			} else {
+				"├─"
+			};
+			let kdf = match &slot.kdf {
+				Kdf::Pbkdf2 { hash, iterations } => {
+					format!("pbkdf2-{}, {} iterations", hash, iterations)
+				}
+				Kdf::Argon2 {
+					kind,
+					time,
+					memory,
+					cpus,
+				} => format!(
+					"{}, {} iterations, {}, {} threads",
+					kind,
+					time,
+					bytes::format_bytes(
+						memory.saturating_sub(1),
+						0,
+						1024,
+						units
+					),
+					cpus
+				),
+				Kdf::Unknown(kind) => kind.clone(),
+			};
+			writer.push_cells(vec![
+				format!("{} Slot {}", branch, slot.number).as_str().into(),
+				"".into(),
+				"".into(),
+				"".into(),
+				kdf.as_str().into(),
+				"".into(),
+				"".into(),
+			]);
+		}
+	}
+
+	writer
+}
@@ -0,0 +1,174 @@
+use super::{bytes, Table, Units};
+use colored::Colorize;
+use gptinfo::{
+	reader::lvm::{self, PhysicalVolume},
+	BlockSource, PartitionTable,
+};
+use std::collections::BTreeMap;
+
+/// LVM physical volumes found inside partitions, by partition number, with
+/// the first LBA of the partition holding each.
+pub(crate) type Pvs = BTreeMap<u32, (u64, PhysicalVolume)>;
+
+/// Reads the LVM physical volumes of the partitions typed as one. A
+/// damaged one is reported and left out rather than failing the whole
+/// disk.
+pub(crate) fn read_pvs(
+	table: &PartitionTable,
+	src: &mut dyn BlockSource,
+) -> Pvs {
+	let (sector_size, candidates): (u64, Vec<(u32, u64)>) = match table {
+		PartitionTable::Gpt(disk) => (
+			disk.sector_size,
+			disk.entries
+				.iter()
+				.filter(|entry| lvm::gpt_type_holds_pv(entry.type_guid))
+				.map(|entry| (entry.number, entry.first_lba))
+				.collect(),
+		),
+		PartitionTable::Mbr(mbr) => (
+			mbr.sector_size,
+			mbr.entries
+				.iter()
+				.filter(|entry| lvm::mbr_type_holds_pv(entry.kind))
+				.map(|entry| (entry.number, entry.first_lba))
+				.collect(),
+		),
+		PartitionTable::Apm(_) => return Pvs::new(),
+	};
+
+	let mut pvs = Pvs::new();
+	for (number, first_lba) in candidates {
+		match lvm::read(src, first_lba.saturating_mul(sector_size)) {
+			Ok(Some(pv)) => {
+				pvs.insert(number, (first_lba, pv));
+			}
+			Ok(None) => {}
+			Err(err) => eprintln!(
+				"{} Partition {}: {}",
+				"Warning:".yellow().bold(),
+				number,
+				err
+			),
+		}
+	}
+	pvs
+}
+
+/// A row for the volume group of the physical volume in the partition
+/// starting at `first_lba`, and one per logical volume with extents on it,
+/// with those segments drawn as a tree beneath it.
+pub(crate) fn lvm_table(
+	pv: &PhysicalVolume,
+	first_lba: u64,
+	sector_size: u64,
+	units: Units,
+) -> Table {
+	let mut writer = Table::new(6);
+	writer.push_cells(vec![
+		"Name".bold(),
+		"Type".bold(),
+		"Start".bold(),
+		"End".bold(),
+		"Sectors".bold(),
+		"Size".bold(),
+	]);
+
+	let kind = format!("PV {}", pv.uuid);
+	let group = match &pv.group {
+		Some(group) => group,
+		None => {
+			let sectors = pv.device_size / sector_size;
+			let end = first_lba.saturating_add(sectors.saturating_sub(1));
+			writer.push_cells(vec![
+				"No Volume Group".into(),
+				kind.as_str().into(),
+				first_lba.to_string().as_str().into(),
+				end.to_string().as_str().into(),
+				sectors.to_string().as_str().into(),
+				bytes::format_bytes(end, first_lba, sector_size, units)
+					.as_str()
+					.into(),
+			]);
+			return writer;
+		}
+	};
+
+	// Extents as LBAs of the disk, and their length in sectors
+	let lbas = |start: u64, len: u64| {
+		let (offset, len) = group.extent_bytes(start, len);
+		let start = first_lba.saturating_add(offset / sector_size);
+		(start, len / sector_size)
+	};
+	let mut push_row =
+		|name: &str, kind: &str, start: Option<u64>, sectors: u64| {
+			let last = sectors.saturating_sub(1);
+			let (start, end) = match start {
+				Some(start) => {
+					let end = start.saturating_add(last);
+					(start.to_string(), end.to_string())
+				}
+				None => (String::new(), String::new()),
+			};
+			writer.push_cells(vec![
+				name.into(),
+				kind.into(),
+				start.as_str().into(),
+				end.as_str().into(),
+				sectors.to_string().as_str().into(),
+				bytes::format_bytes(last, 0, sector_size, units)
+					.as_str()
+					.into(),
+			]);
+		};
+
+	// The area of this PV that extents are allocated from
+	let (start, sectors) = lbas(0, group.pe_count);
+	push_row(
+		&format!("Volume Group ({})", group.name),
+		&kind,
+		Some(start),
+		sectors,
+	);
+
+	for volume in group.volumes.iter() {
+		// Hidden volumes are bracketed, as `lvs -a` shows them
+		let name = if volume.visible {
+			volume.name.clone()
+		} else {
+			format!("[{}]", volume.name)
+		};
+		push_row(&name, "logical volume", None, lbas(0, volume.extents).1);
+
+		for (idx, segment) in volume.segments.iter().enumerate() {
+			let branch = if idx + 1 == volume.segments.len() {
+				"└─"
+			} else {
+				"├─"
+			};
+			// Each extent on this PV holds one of every stripe
+			let last = segment.volume_extent.saturating_add(
+				segment
+					.extents
+					.saturating_mul(segment.stripes)
+					.saturating_sub(1),
+			);
+			let kind = match segment.stripes {
+				1 => segment.kind.clone(),
+				stripes => format!("{}, {} stripes", segment.kind, stripes),
+			};
+			let (start, sectors) = lbas(segment.start, segment.extents);
+			push_row(
+				&format!(
+					"{} extents {}-{}",
+					branch, segment.volume_extent, last
+				),
+				&kind,
+				Some(start),
+				sectors,
+			);
+		}
+	}
+
+	writer
+}
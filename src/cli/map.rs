@@ -0,0 +1,235 @@
+use super::{bytes, Frame, Units};
+use colored::{Color, ColoredString, Colorize};
+use gptinfo::Disk;
+
+// Used when output doesn't go to a terminal
+const DEFAULT_WIDTH: usize = 80;
+// Partitions cycle through these, so neighbours differ
+const PALETTE: [Color; 6] = [
+	Color::Blue,
+	Color::Green,
+	Color::Magenta,
+	Color::Cyan,
+	Color::Yellow,
+	Color::Red,
+];
+
+/// What a stretch of LBAs holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+	Mbr,
+	Header,
+	Entries,
+	/// The index of the partition in `Disk::entries`
+	Partition(usize),
+	Free,
+}
+
+struct Region {
+	first: u64,
+	last: u64,
+	kind: Kind,
+}
+
+impl Region {
+	fn sectors(&self) -> u64 { (self.last - self.first).saturating_add(1) }
+}
+
+/// The disk from LBA 0 to the backup header as regions in order, with the
+/// LBAs nothing claims as free.
+fn regions(disk: &Disk) -> Vec<Region> {
+	let header = &disk.header;
+	// The header read may be the backup one
+	let primary = header.current_lba.min(header.backup_lba);
+	let backup = header.current_lba.max(header.backup_lba);
+	let array = (u64::from(header.num_partition_entries)
+		* u64::from(header.partition_entry_size))
+	.div_ceil(disk.sector_size)
+	.max(1);
+	let (primary_array, backup_array) = if header.current_lba == primary {
+		(header.partition_entry_lba, backup.saturating_sub(array))
+	} else {
+		(primary + 1, header.partition_entry_lba)
+	};
+
+	let mut claimed = vec![
+		(0, 0, Kind::Mbr),
+		(primary, primary, Kind::Header),
+		(
+			primary_array,
+			primary_array.saturating_add(array - 1),
+			Kind::Entries,
+		),
+		(
+			backup_array,
+			backup_array.saturating_add(array - 1),
+			Kind::Entries,
+		),
+		(backup, backup, Kind::Header),
+	];
+	for (idx, entry) in disk.entries.iter().enumerate() {
+		let last = entry.last_lba.min(backup);
+		if entry.first_lba <= last {
+			claimed.push((entry.first_lba, last, Kind::Partition(idx)));
+		}
+	}
+	claimed.sort_by_key(|&(first, last, _)| (first, last));
+
+	let mut regions = vec![];
+	let mut next = 0;
+	for (first, last, kind) in claimed {
+		if first > next {
+			regions.push(Region {
+				first: next,
+				last: first - 1,
+				kind: Kind::Free,
+			});
+		}
+		regions.push(Region { first, last, kind });
+		next = next.max(last.saturating_add(1));
+	}
+	regions
+}
+
+/// Shares `width` cells out between `regions` by size, giving each at least
+/// one so that single-sector structures still show. When there are more
+/// regions than cells, the smallest get none, and one cell is kept back to
+/// stand in for all of them.
+fn cells(regions: &[Region], width: usize) -> Vec<usize> {
+	let mut shown = vec![true; regions.len()];
+	let mut width = width;
+	if regions.len() > width {
+		width = width.saturating_sub(1);
+		let mut order: Vec<usize> = (0..regions.len()).collect();
+		order.sort_by_key(|&idx| regions[idx].sectors());
+		for &idx in order.iter().take(regions.len() - width) {
+			shown[idx] = false;
+		}
+	}
+	let sectors = |idx: usize| {
+		if shown[idx] {
+			u128::from(regions[idx].sectors())
+		} else {
+			0
+		}
+	};
+
+	let total: u128 = (0..regions.len()).map(sectors).sum();
+	let count = shown.iter().filter(|&&shown| shown).count();
+	let spare = width.saturating_sub(count) as u128;
+	let shares: Vec<u128> =
+		(0..regions.len()).map(|idx| sectors(idx) * spare).collect();
+	let mut cells: Vec<usize> = shares
+		.iter()
+		.zip(shown.iter())
+		.map(|(share, &shown)| {
+			if shown {
+				1 + (share / total.max(1)) as usize
+			} else {
+				0
+			}
+		})
+		.collect();
+
+	// Largest remainders first take the cells rounding down left over
+	let left = width.saturating_sub(cells.iter().sum());
+	let mut order: Vec<usize> =
+		(0..regions.len()).filter(|&idx| shown[idx]).collect();
+	order.sort_by_key(|&idx| std::cmp::Reverse(shares[idx] % total.max(1)));
+	for &idx in order.iter().take(left) {
+		cells[idx] += 1;
+	}
+	cells
+}
+
+/// `text` centered in `width` cells of `fill`.
+fn centered(text: &str, fill: char, width: usize) -> String {
+	if text.len() > width {
+		return fill.to_string().repeat(width);
+	}
+	let before = (width - text.len()) / 2;
+	let after = width - text.len() - before;
+	format!(
+		"{}{}{}",
+		fill.to_string().repeat(before),
+		text,
+		fill.to_string().repeat(after)
+	)
+}
+
+/// Draws the disk as a bar from LBA 0 to the backup header, sized to the
+/// terminal, followed by a key to it and the free space it shows.
+pub(crate) fn draw_map(disk: &Disk, frame: &Frame, units: Units) {
+	let (side, block, shade, merged) = if frame.ascii {
+		('|', '#', '.', '~')
+	} else {
+		('│', '█', '░', '…')
+	};
+	let regions = regions(disk);
+	let width = frame.width.unwrap_or(DEFAULT_WIDTH).saturating_sub(2);
+	let color = |idx: usize| PALETTE[idx % PALETTE.len()];
+
+	let cells = cells(&regions, width);
+	let hidden = width > 0 && cells.contains(&0);
+	let mut merged_shown = false;
+	print!("{}", side);
+	for (region, &cells) in regions.iter().zip(cells.iter()) {
+		if cells == 0 {
+			// Every region too small for a cell of its own shares this one
+			if hidden && !merged_shown {
+				print!("{}", merged);
+				merged_shown = true;
+			}
+			continue;
+		}
+		let segment: ColoredString = match region.kind {
+			Kind::Mbr => "M".repeat(cells).bold(),
+			Kind::Header => "H".repeat(cells).bold(),
+			Kind::Entries => "E".repeat(cells).bold(),
+			Kind::Partition(idx) => {
+				let number = disk.entries[idx].number.to_string();
+				centered(&number, block, cells).color(color(idx))
+			}
+			Kind::Free => shade.to_string().repeat(cells).dimmed(),
+		};
+		print!("{}", segment);
+	}
+	println!("{}", side);
+
+	let mut key: Vec<String> = disk
+		.entries
+		.iter()
+		.enumerate()
+		.map(|(idx, entry)| {
+			format!(
+				"{} {}",
+				entry.number.to_string().color(color(idx)),
+				entry.name
+			)
+		})
+		.collect();
+	key.extend(vec![
+		format!("{} protective MBR", "M".bold()),
+		format!("{} header", "H".bold()),
+		format!("{} partition entries", "E".bold()),
+		format!("{} free", shade.to_string().dimmed()),
+	]);
+	if hidden {
+		key.push(format!("{} regions too small to show", merged));
+	}
+	println!("{}", key.join(", "));
+
+	for region in regions.iter().filter(|region| region.kind == Kind::Free) {
+		println!(
+			"Free: {} to {} ({})",
+			region.first,
+			region.last,
+			bytes::format_bytes(
+				region.last,
+				region.first,
+				disk.sector_size,
+				units
+			)
+		);
+	}
+}
@@ -0,0 +1,62 @@
+use super::{labels, Nested, Output, Row, Table};
+use gptinfo::reader::mbr::Mbr;
+
+/// One row per primary and logical partition, under a row for the disk.
+pub(crate) fn mbr_table(
+	mbr: &Mbr,
+	loc: &str,
+	nested: &Nested,
+	output: &Output,
+) -> Table {
+	let mut writer = Table::new(output.columns.len());
+	writer.push_cells(output.header(false));
+
+	if output.nested() {
+		// Like an Apple Partition Map, the table doesn't record the disk
+		// size
+		let end = mbr
+			.entries
+			.iter()
+			.map(|entry| entry.last_lba())
+			.max()
+			.unwrap_or(0);
+		let row = Row::span(
+			&format!("Disk ({})", loc),
+			"MBR",
+			0,
+			end,
+			mbr.sector_size,
+			output.units,
+		);
+		writer.push_cells(output.cells(&row));
+	}
+
+	for entry in mbr.entries.iter() {
+		let kind = match entry.type_name() {
+			Some(name) => format!("{} ({:#04x})", name, entry.kind),
+			None => format!("{:#04x}", entry.kind),
+		};
+		let fs = nested.filesystems.get(&entry.number);
+		let row = Row {
+			attrs: if entry.bootable { "active" } else { "" }.to_string(),
+			fs: fs.map(|fs| fs.kind.to_string()).unwrap_or_default(),
+			label: fs.map(|fs| fs.label.clone()).unwrap_or_default(),
+			..Row::span(
+				&entry.number.to_string(),
+				&kind,
+				entry.first_lba,
+				entry.last_lba(),
+				mbr.sector_size,
+				output.units,
+			)
+		};
+		writer.push_cells(output.cells(&row));
+		if let (true, Some(label)) =
+			(output.nested(), nested.labels.get(&entry.number))
+		{
+			labels::push_label(&mut writer, label, mbr.sector_size, output, 0);
+		}
+	}
+
+	writer
+}
@@ -0,0 +1,211 @@
+use super::Table;
+use colored::Colorize;
+use gptinfo::{
+	reader::md::{self, Probe, Superblock},
+	BlockSource, PartitionTable,
+};
+use std::collections::BTreeMap;
+
+/// md-raid superblocks found in partitions, by partition number, with those
+/// that couldn't be looked for all the way.
+pub(crate) type Members = BTreeMap<u32, Probe>;
+
+/// Reads the md-raid superblocks of the partitions typed as array members.
+/// A damaged one is reported and left out rather than failing the whole
+/// disk.
+pub(crate) fn read_members(
+	table: &PartitionTable,
+	src: &mut dyn BlockSource,
+) -> Members {
+	// Partition number, first byte and size in bytes
+	let candidates: Vec<(u32, u64, u64)> = match table {
+		PartitionTable::Gpt(disk) => disk
+			.entries
+			.iter()
+			.filter(|entry| md::gpt_type_holds_member(entry.type_guid))
+			.map(|entry| {
+				let sectors = entry
+					.last_lba
+					.saturating_sub(entry.first_lba)
+					.saturating_add(1);
+				(
+					entry.number,
+					entry.first_lba.saturating_mul(disk.sector_size),
+					sectors.saturating_mul(disk.sector_size),
+				)
+			})
+			.collect(),
+		PartitionTable::Mbr(mbr) => mbr
+			.entries
+			.iter()
+			.filter(|entry| md::mbr_type_holds_member(entry.kind))
+			.map(|entry| {
+				(
+					entry.number,
+					entry.first_lba.saturating_mul(mbr.sector_size),
+					entry.sectors.saturating_mul(mbr.sector_size),
+				)
+			})
+			.collect(),
+		PartitionTable::Apm(_) => return Members::new(),
+	};
+
+	let mut members = Members::new();
+	for (number, offset, size) in candidates {
+		match md::read(src, offset, size) {
+			Ok(Probe::Missing) => {}
+			Ok(probe) => {
+				members.insert(number, probe);
+			}
+			Err(err) => eprintln!(
+				"{} Partition {}: {}",
+				"Warning:".yellow().bold(),
+				number,
+				err
+			),
+		}
+	}
+	members
+}
+
+fn state(superblock: &Superblock) -> &'static str {
+	if superblock.clean {
+		"clean"
+	} else {
+		"active"
+	}
+}
+
+/// One row per partition that is an md-raid member, with the array it
+/// belongs to and its part in it.
+pub(crate) fn md_table(members: &Members) -> Table {
+	let mut writer = Table::new(8);
+	writer.push_cells(vec![
+		"Name".bold(),
+		"Version".bold(),
+		"Array".bold(),
+		"Array UUID".bold(),
+		"Level".bold(),
+		"Role".bold(),
+		"Events".bold(),
+		"State".bold(),
+	]);
+
+	for (number, probe) in members.iter() {
+		let superblock = match probe {
+			Probe::Found(superblock) => superblock,
+			_ => {
+				let mut cells = vec![
+					format!("Partition {}", number).as_str().into(),
+					"not checked (the image can't be read out of order)".into(),
+				];
+				cells.extend(vec!["".into(); 6]);
+				writer.push_cells(cells);
+				continue;
+			}
+		};
+		writer.push_cells(vec![
+			format!("Partition {}", number).as_str().into(),
+			superblock.version.into(),
+			superblock.name.as_str().into(),
+			superblock.uuid.as_str().into(),
+			format!("{}, {} disks", superblock.level, superblock.raid_disks)
+				.as_str()
+				.into(),
+			superblock.role.to_string().as_str().into(),
+			superblock.events.to_string().as_str().into(),
+			state(superblock).into(),
+		]);
+	}
+
+	writer
+}
+
+/// Array members seen across every disk shown, by array UUID, with where
+/// each was found.
+#[derive(Default)]
+pub(crate) struct Arrays(BTreeMap<String, Vec<(String, Superblock)>>);
+
+impl Arrays {
+	/// Adds the members found on the disk at `loc`.
+	pub(crate) fn add(&mut self, loc: &str, members: &Members) {
+		for (number, probe) in members.iter() {
+			let superblock = match probe {
+				Probe::Found(superblock) => superblock,
+				_ => continue,
+			};
+			self.0.entry(superblock.uuid.clone()).or_default().push((
+				format!("{} partition {}", loc, number),
+				superblock.clone(),
+			));
+		}
+	}
+
+	/// A row for each array with more than one member seen, and its
+	/// members beneath it, with how far each is behind the freshest. `None`
+	/// if there's nothing to compare.
+	pub(crate) fn table(&self) -> Option<Table> {
+		let mut writer = Table::new(5);
+		writer.push_cells(vec![
+			"Name".bold(),
+			"Level".bold(),
+			"Role".bold(),
+			"Events".bold(),
+			"Freshness".bold(),
+		]);
+
+		let mut compared = false;
+		for (uuid, members) in self.0.iter() {
+			if members.len() < 2 {
+				continue;
+			}
+			compared = true;
+
+			let freshest = members
+				.iter()
+				.map(|(_, superblock)| superblock.events)
+				.max()
+				.unwrap_or(0);
+			let first = &members[0].1;
+			let name = if first.name.is_empty() {
+				uuid.as_str()
+			} else {
+				first.name.as_str()
+			};
+			writer.push_cells(vec![
+				format!("Array ({})", name).as_str().into(),
+				first.level.to_string().as_str().into(),
+				"".into(),
+				freshest.to_string().as_str().into(),
+				"".into(),
+			]);
+
+			for (idx, (loc, superblock)) in members.iter().enumerate() {
+				let branch = if idx + 1 == members.len() {
+					"└─"
+				} else {
+					"├─"
+				};
+				let behind = freshest - superblock.events;
+				let freshness = if behind == 0 {
+					"freshest".to_string()
+				} else {
+					format!("stale, {} events behind", behind)
+				};
+				writer.push_cells(vec![
+					format!("{} {}", branch, loc).as_str().into(),
+					"".into(),
+					superblock.role.to_string().as_str().into(),
+					superblock.events.to_string().as_str().into(),
+					freshness.as_str().into(),
+				]);
+			}
+		}
+
+		if compared {
+			Some(writer)
+		} else {
+			None
+		}
+	}
+}
@@ -1,16 +1,571 @@
+mod apm;
+mod bytes;
+mod check;
+mod columns;
+mod errors;
+mod fs;
+mod labels;
+mod ldm;
+mod luks;
+mod lvm;
+mod map;
+mod mbr;
+mod md;
+mod nested;
+mod slots;
+mod summary;
 mod table;
+mod terminal;
+mod zfs;
 
+use anyhow::{Context as _, Result};
+pub(crate) use apm::apm_table;
+pub(crate) use bytes::Units;
+pub(crate) use check::check;
 use clap::Clap;
-pub(crate) use table::Table;
+use colored::{ColoredString, Colorize};
+use columns::{Column, Columns, Row, DEFAULT_COLUMNS};
+pub(crate) use columns::{Format, Output};
+pub(crate) use errors::CliError;
+pub(crate) use fs::Filesystems;
+use gptinfo::{
+	guid::UserTypes,
+	system::{self, Holder, LiveDisk, Mount},
+	Disk, PartitionTable, TableStatus,
+};
+pub(crate) use labels::{read_labels, Labels};
+pub(crate) use luks::Encrypted;
+pub(crate) use lvm::Pvs;
+pub(crate) use map::draw_map;
+pub(crate) use mbr::mbr_table;
+pub(crate) use md::{Arrays, Members};
+pub(crate) use nested::Nested;
+use nix::unistd::Uid;
+pub(crate) use slots::BootSlots;
+use std::{fs::File, io, path::Path};
+pub(crate) use summary::summary_table;
+pub(crate) use table::{Frame, Table};
+pub(crate) use terminal::{set_color, ColorChoice};
+pub(crate) use zfs::Pools;
 
 #[derive(Clap)]
 #[clap(version = "1.0", author = "Safin S. <safinsingh.dev@gmail.com>")]
 pub(crate) struct Opts {
-	#[clap(short, long, default_value = "/dev/sda")]
-	/// Block device to read GPT from
-	pub(crate) device: String,
+	#[clap(short, long)]
+	/// Block device or disk image (raw, qcow2, VHD, VHDX or VMDK, or a gzip,
+	/// xz or zstd compressed raw image) to read GPT from [default: the first
+	/// disk in /sys/block]
+	pub(crate) device: Option<String>,
+
+	#[clap(
+		long,
+		parse(try_from_str = bytes::parse_bytes),
+		conflicts_with_all = &["all", "list"]
+	)]
+	/// Read the disk stored this many bytes into the input, such as a VM
+	/// image inside a partition. Also written as `--device <path>@<offset>`.
+	/// Takes K, M, G and T suffixes, or 0x hex
+	pub(crate) offset: Option<u64>,
+
+	#[clap(
+		long,
+		parse(try_from_str = bytes::parse_bytes),
+		conflicts_with_all = &["all", "list"]
+	)]
+	/// Size of the disk read at --offset [default: the rest of the input]
+	pub(crate) length: Option<u64>,
+
+	#[clap(long)]
+	/// Count the LBAs of a disk read at --offset from the start of the
+	/// input rather than from the start of that disk
+	pub(crate) absolute: bool,
+
+	#[clap(long)]
+	/// Follow qcow2 backing files named by absolute paths or paths leaving
+	/// the directory of the image
+	pub(crate) allow_backing_paths: bool,
+
+	#[clap(short, long, conflicts_with = "device")]
+	/// Show a table for every disk in /sys/block, then compare the event
+	/// counts of md-raid members found on more than one of them
+	pub(crate) all: bool,
+
+	#[clap(short, long, conflicts_with_all = &["device", "all"])]
+	/// Summarize every disk in /sys/block instead of listing partitions
+	pub(crate) list: bool,
+
+	#[clap(long)]
+	/// Comma-separated columns of the partition table, from name, type,
+	/// start, end, sectors, size, uuid, attrs, fs and label [default:
+	/// name,type,start,end,sectors,size]
+	pub(crate) columns: Option<Columns>,
+
+	#[clap(
+		long,
+		default_value = "table",
+		possible_values = &["table", "csv", "tsv"]
+	)]
+	/// Print the partition table as a drawn table, or as CSV or TSV with a
+	/// row per partition and nothing else
+	pub(crate) format: Format,
+
+	#[clap(long)]
+	/// Leave out the row of column headings
+	pub(crate) no_header: bool,
+
+	#[clap(long, default_value = "iec", possible_values = &["iec", "si"])]
+	/// Write sizes in powers of 1024 (KiB, MiB) or of 1000 (kB, MB)
+	pub(crate) units: Units,
+
+	#[clap(long)]
+	/// Write sizes as exact byte counts
+	pub(crate) bytes: bool,
+
+	#[clap(
+		long,
+		default_value = "auto",
+		possible_values = &["auto", "always", "never"]
+	)]
+	/// Color the output always, never, or only on a terminal when NO_COLOR
+	/// isn't set
+	pub(crate) color: ColorChoice,
+
+	#[clap(long)]
+	/// Draw table borders and tree lines in ASCII
+	pub(crate) ascii: bool,
+
+	#[clap(short, long)]
+	/// Show the kernel device node, mount points and mount options of each
+	/// partition
+	pub(crate) mounts: bool,
 
 	#[clap(short, long)]
-	/// Show GUID of each partition
-	pub(crate) guid: bool,
+	/// Like --mounts, with the devices stacked on each partition (dm-crypt,
+	/// LVM, md-raid, bcache) drawn as a tree beneath it
+	pub(crate) tree: bool,
+
+	#[clap(long)]
+	/// Draw the disk beneath the partition table as a bar from LBA 0 to
+	/// the backup header, with each partition and the free space between
+	pub(crate) map: bool,
+
+	#[clap(short, long)]
+	/// Compare the on-disk table with the partitions the kernel is using
+	pub(crate) check: bool,
+
+	#[clap(long, requires = "check")]
+	/// With --check, ask the kernel to re-read a table it is out of sync
+	/// with (BLKRRPART, or BLKPG per partition while the disk is busy)
+	pub(crate) reread: bool,
+}
+
+impl Opts {
+	/// How sizes are written; --bytes takes precedence over --units.
+	pub(crate) fn units(&self) -> Units {
+		if self.bytes {
+			Units::Bytes
+		} else {
+			self.units
+		}
+	}
+
+	/// How tables are drawn: fitted to the terminal, if there is one.
+	pub(crate) fn frame(&self) -> Frame {
+		Frame {
+			ascii: self.ascii,
+			width: terminal::width(),
+		}
+	}
+
+	/// How to print `table`. Apple Partition Maps show their status flags
+	/// unless other columns are chosen.
+	pub(crate) fn output(&self, table: &PartitionTable) -> Output {
+		let columns = match (&self.columns, table) {
+			(Some(columns), _) => columns.0.clone(),
+			(None, PartitionTable::Apm(_)) => {
+				let mut columns = DEFAULT_COLUMNS.to_vec();
+				columns.push(Column::Attrs);
+				columns
+			}
+			(None, _) => DEFAULT_COLUMNS.to_vec(),
+		};
+		Output {
+			columns,
+			format: self.format,
+			header: !self.no_header,
+			units: self.units(),
+			frame: self.frame(),
+		}
+	}
+}
+
+/// A byte range of the input that holds the disk.
+pub(crate) struct Range {
+	pub(crate) offset: u64,
+	pub(crate) length: Option<u64>,
+}
+
+/// Splits `<path>@<offset>` into the path and the range it names, taking
+/// --offset and --length into account. A file whose name contains `@` is
+/// taken as is.
+pub(crate) fn range(
+	device: &str,
+	opts: &Opts,
+) -> Result<(String, Option<Range>)> {
+	let (path, offset) = match device.rsplit_once('@') {
+		Some((path, offset)) if !Path::new(device).exists() => {
+			if opts.offset.is_some() {
+				return Err(
+					CliError::DuplicateOffset(device.to_string()).into()
+				);
+			}
+			(path, Some(bytes::parse_bytes(offset)?))
+		}
+		_ => (device, opts.offset),
+	};
+
+	let range = match (offset, opts.length) {
+		(None, None) => None,
+		(offset, length) => Some(Range {
+			offset: offset.unwrap_or(0),
+			length,
+		}),
+	};
+	if range.is_some() && (opts.mounts || opts.tree || opts.check) {
+		return Err(CliError::WholeDiskOnly.into());
+	}
+	Ok((path.to_string(), range))
+}
+
+/// Moves the LBAs of a table found `offset` bytes into the input, and of
+/// the metadata inside its partitions, to count from the start of the
+/// input.
+pub(crate) fn make_absolute(
+	table: &mut PartitionTable,
+	nested: &mut Nested,
+	offset: u64,
+) -> Result<()> {
+	let block_size = match table {
+		PartitionTable::Gpt(disk) => disk.sector_size,
+		PartitionTable::Apm(map) => map.block_size,
+		PartitionTable::Mbr(mbr) => mbr.sector_size,
+	};
+	if offset % block_size != 0 {
+		return Err(CliError::UnalignedOffset(block_size).into());
+	}
+	let shift = offset / block_size;
+
+	match table {
+		PartitionTable::Gpt(disk) => {
+			let header = &mut disk.header;
+			for lba in [
+				&mut header.current_lba,
+				&mut header.backup_lba,
+				&mut header.first_usable_lba,
+				&mut header.last_usable_lba,
+				&mut header.partition_entry_lba,
+			]
+			.iter_mut()
+			{
+				**lba = lba.saturating_add(shift);
+			}
+			for entry in disk.entries.iter_mut() {
+				entry.first_lba = entry.first_lba.saturating_add(shift);
+				entry.last_lba = entry.last_lba.saturating_add(shift);
+			}
+		}
+		PartitionTable::Apm(map) => {
+			for entry in map.entries.iter_mut() {
+				entry.start = entry.start.saturating_add(shift);
+			}
+		}
+		PartitionTable::Mbr(mbr) => {
+			for entry in mbr.entries.iter_mut() {
+				entry.first_lba = entry.first_lba.saturating_add(shift);
+			}
+		}
+	}
+	nested.shift(shift);
+	Ok(())
+}
+
+/// The device nodes to show when none was given.
+pub(crate) fn default_devices(all: bool) -> Result<Vec<String>> {
+	let devices =
+		system::block_devices().context("Failed to list block devices")?;
+	let chosen: Vec<_> = if all {
+		devices.iter().collect()
+	} else {
+		system::default_device(&devices).into_iter().collect()
+	};
+
+	if chosen.is_empty() {
+		return Err(CliError::NoDevices.into());
+	}
+	Ok(chosen
+		.iter()
+		.map(|device| device.path.to_string_lossy().into_owned())
+		.collect())
+}
+
+/// Runs `show` on each of `devices`, telling it whether a disk came before.
+/// When there are several, one that fails is reported and the rest still
+/// shown, but the run fails once they are, so that scripts see it.
+pub(crate) fn show_each(
+	devices: &[String],
+	mut show: impl FnMut(&str, bool) -> Result<()>,
+) -> Result<()> {
+	let (mut shown, mut failed) = (0, 0);
+	for device in devices.iter() {
+		match show(device, shown > 0) {
+			Ok(()) => shown += 1,
+			Err(err) if devices.len() > 1 => {
+				eprintln!("{} {:#}", "Warning:".yellow().bold(), err);
+				failed += 1;
+			}
+			Err(err) => return Err(err),
+		}
+	}
+
+	if failed > 0 {
+		return Err(CliError::Failed(failed, devices.len()).into());
+	}
+	Ok(())
+}
+
+/// Opens `loc` for reading, pointing out missing privileges when a device
+/// node can't be opened.
+pub(crate) fn open(loc: &str) -> Result<File> {
+	match File::open(loc) {
+		Err(err)
+			if err.kind() == io::ErrorKind::PermissionDenied
+				&& !Uid::effective().is_root() =>
+		{
+			Err(CliError::Root.into())
+		}
+		file => file.with_context(|| format!("Failed to open {}", loc)),
+	}
+}
+
+/// Reports damaged copies of the table, since only the usable one is
+/// rendered.
+pub(crate) fn warn_damaged(disk: &Disk) {
+	for &(which, status) in
+		[("Primary", disk.primary), ("Backup", disk.backup)].iter()
+	{
+		if status != TableStatus::Valid {
+			eprintln!(
+				"{} {} GPT is {}",
+				"Warning:".yellow().bold(),
+				which,
+				status
+			);
+		}
+	}
+}
+
+pub(crate) fn disk_table(
+	disk: &Disk,
+	loc: &str,
+	types: &UserTypes,
+	nested: &Nested,
+	output: &Output,
+	live: Option<&LiveDisk>,
+	tree: bool,
+) -> Result<Table> {
+	let mounts = if live.is_some() { 3 } else { 0 };
+	let mut writer = Table::new(output.columns.len() + mounts);
+
+	writer.push_cells(output.header(false));
+	if live.is_some() {
+		writer.push_cells(vec![
+			"Device".bold(),
+			"Mount Point".bold(),
+			"Options".bold(),
+		]);
+	}
+
+	let header = &disk.header;
+	if output.nested() {
+		let (start, end) = (header.first_usable_lba, header.last_usable_lba);
+		let row = Row {
+			uuid: header.disk_guid.to_string(),
+			..Row::span(
+				&format!("Disk ({})", loc),
+				"Block Device",
+				start,
+				end,
+				disk.sector_size,
+				output.units,
+			)
+		};
+		writer.push_cells(output.cells(&row));
+		if let Some(live) = live {
+			let device = &live.device;
+			let mounts =
+				live.mounts.iter().filter(|m| m.is_of(device.dev, &device.path));
+			writer.push_cells(mount_cells(&live.device.path, mounts, false));
+			if tree {
+				// Whole-disk LVM physical volumes and md members
+				let holders = system::holders(&live.device.name)
+					.context("Failed to read holders")?;
+				push_holders(&mut writer, &holders, "", output, live);
+			}
+		}
+	}
+
+	for entry in disk.entries.iter() {
+		// Types neither built in nor configured show as their GUID
+		let kind = types.resolve(entry.type_guid);
+		let fs = nested.filesystems.get(&entry.number);
+		let row = Row {
+			uuid: entry.unique_guid.to_string(),
+			attrs: columns::gpt_attributes(entry),
+			fs: fs.map(|fs| fs.kind.to_string()).unwrap_or_default(),
+			label: fs.map(|fs| fs.label.clone()).unwrap_or_default(),
+			..Row::span(
+				&entry.name,
+				&kind.to_string(),
+				entry.first_lba,
+				entry.last_lba,
+				disk.sector_size,
+				output.units,
+			)
+		};
+		writer.push_cells(output.cells(&row));
+		if let Some(live) = live {
+			let partition = live.partition(entry, disk.sector_size);
+			writer.push_cells(match partition {
+				Some(partition) => mount_cells(
+					&partition.path(),
+					live.mounts_of(partition).into_iter(),
+					live.is_swap(partition),
+				),
+				// The kernel hasn't registered this entry
+				None => vec!["".into(), "".into(), "".into()],
+			});
+			if let (true, true, Some(partition)) =
+				(tree, output.nested(), partition)
+			{
+				let holders = system::holders(&partition.name)
+					.context("Failed to read holders")?;
+				push_holders(&mut writer, &holders, "", output, live);
+			}
+		}
+		if let (true, Some(label)) =
+			(output.nested(), nested.labels.get(&entry.number))
+		{
+			labels::push_label(
+				&mut writer,
+				label,
+				disk.sector_size,
+				output,
+				mounts,
+			);
+		}
+	}
+
+	Ok(writer)
+}
+
+/// One row per device stacked on the row above, indented as a tree.
+fn push_holders(
+	writer: &mut Table,
+	holders: &[Holder],
+	indent: &str,
+	output: &Output,
+	live: &LiveDisk,
+) {
+	for (idx, holder) in holders.iter().enumerate() {
+		let last = idx + 1 == holders.len();
+		let branch = if last { "└─" } else { "├─" };
+
+		let row = Row {
+			name: format!("{}{} {}", indent, branch, holder.display_name()),
+			kind: holder.kind.to_string(),
+			size: bytes::format_bytes(
+				holder.size.saturating_sub(1),
+				0,
+				1,
+				output.units,
+			),
+			..Row::default()
+		};
+		writer.push_cells(output.cells(&row));
+		// /proc/swaps may list either node
+		let swap = live.swaps.contains(&holder.node())
+			|| live.swaps.contains(&holder.path());
+		let mounts = live
+			.mounts
+			.iter()
+			.filter(|m| m.is_of(holder.dev, &holder.path()));
+		writer.push_cells(mount_cells(&holder.path(), mounts, swap));
+
+		let indent = format!("{}{}", indent, if last { "   " } else { "│  " });
+		push_holders(writer, &holder.holders, &indent, output, live);
+	}
+}
+
+/// Device, mount point(s) and options of the first mount. Swap is shown
+/// as `[SWAP]`, like lsblk.
+fn mount_cells<'a>(
+	node: &Path,
+	mounts: impl Iterator<Item = &'a Mount>,
+	swap: bool,
+) -> Vec<ColoredString> {
+	let mounts: Vec<_> = mounts.collect();
+
+	let mut points: Vec<_> = mounts
+		.iter()
+		.map(|mount| mount.mount_point.to_string_lossy().into_owned())
+		.collect();
+	if swap {
+		points.push("[SWAP]".to_string());
+	}
+	let options = mounts.first().map(|mount| mount.options.as_str());
+
+	vec![
+		node.to_string_lossy().as_ref().into(),
+		points.join(", ").as_str().into(),
+		options.unwrap_or("").into(),
+	]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fails_after_showing_every_disk() {
+		let devices = vec!["/dev/sda".to_string(), "/dev/sdb".to_string()];
+		let mut seen = vec![];
+		let result = show_each(&devices, |device, separate| {
+			seen.push((device.to_string(), separate));
+			if device == "/dev/sda" {
+				Err(CliError::Diverged.into())
+			} else {
+				Ok(())
+			}
+		});
+
+		// The diverged disk doesn't hide the other, nor the failure
+		assert_eq!(
+			seen,
+			vec![
+				("/dev/sda".to_string(), false),
+				("/dev/sdb".to_string(), false)
+			]
+		);
+		assert_eq!(
+			result.unwrap_err().to_string(),
+			"1 of the 2 disks failed; see the warnings above."
+		);
+
+		let single =
+			show_each(&devices[..1], |_, _| Err(CliError::Diverged.into()));
+		assert!(matches!(
+			single.unwrap_err().downcast_ref(),
+			Some(CliError::Diverged)
+		));
+	}
 }
@@ -0,0 +1,105 @@
+use super::{
+	fs, ldm, luks, lvm, md, slots, zfs, BootSlots, Encrypted, Filesystems,
+	Labels, Members, Output, Pools, Pvs,
+};
+use gptinfo::{reader::ldm::Ldm, BlockSource, PartitionTable};
+
+/// Metadata found inside the partitions of a table.
+pub(crate) struct Nested {
+	/// Sector size of the table, which the LBAs below count in
+	pub(crate) sector_size: u64,
+	/// Only probed for when the fs or label columns are shown
+	pub(crate) filesystems: Filesystems,
+	pub(crate) labels: Labels,
+	pub(crate) ldm: Option<Ldm>,
+	pub(crate) pvs: Pvs,
+	pub(crate) encrypted: Encrypted,
+	pub(crate) members: Members,
+	pub(crate) pools: Pools,
+	pub(crate) slots: Option<BootSlots>,
+}
+
+impl Nested {
+	/// Reads what the partitions of `table` hold, reporting damaged
+	/// metadata as warnings. Filesystems are probed for if `probe` is set.
+	pub(crate) fn read(
+		table: &PartitionTable,
+		src: &mut dyn BlockSource,
+		probe: bool,
+	) -> Nested {
+		let sector_size = match table {
+			PartitionTable::Gpt(disk) => disk.sector_size,
+			PartitionTable::Apm(map) => map.block_size,
+			PartitionTable::Mbr(mbr) => mbr.sector_size,
+		};
+		Nested {
+			sector_size,
+			filesystems: if probe {
+				fs::read_filesystems(table, src)
+			} else {
+				Filesystems::new()
+			},
+			labels: super::read_labels(table, src),
+			ldm: ldm::read_ldm(table, src),
+			pvs: lvm::read_pvs(table, src),
+			encrypted: luks::read_encrypted(table, src),
+			members: md::read_members(table, src),
+			pools: zfs::read_pools(table, src),
+			slots: slots::read_slots(table, src),
+		}
+	}
+
+	/// Moves every LBA `shift` sectors on.
+	pub(crate) fn shift(&mut self, shift: u64) {
+		for part in self
+			.labels
+			.values_mut()
+			.flat_map(|label| &mut label.partitions)
+		{
+			part.first_lba = part.first_lba.saturating_add(shift);
+		}
+		if let Some(ldm) = &mut self.ldm {
+			ldm.data_start = ldm.data_start.saturating_add(shift);
+		}
+		let pvs = self.pvs.values_mut().map(|(first_lba, _)| first_lba);
+		let encrypted = self
+			.encrypted
+			.values_mut()
+			.map(|volume| &mut volume.first_lba);
+		for first_lba in pvs.chain(encrypted) {
+			*first_lba = first_lba.saturating_add(shift);
+		}
+	}
+
+	/// Draws the tables that follow the partition table: the LDM database,
+	/// each physical volume, the encrypted partitions, the md-raid members,
+	/// the ZFS vdevs, then the A/B boot slots, as `output` asks.
+	pub(crate) fn draw(&self, output: &Output) {
+		let (units, frame) = (output.units, &output.frame);
+		if let Some(ldm) = &self.ldm {
+			println!();
+			ldm::ldm_table(ldm, units).draw(frame);
+		}
+		for (first_lba, pv) in self.pvs.values() {
+			println!();
+			lvm::lvm_table(pv, *first_lba, self.sector_size, units).draw(frame);
+		}
+		if !self.encrypted.is_empty() {
+			println!();
+			luks::luks_table(&self.encrypted, self.sector_size, units)
+				.draw(frame);
+		}
+		if !self.members.is_empty() {
+			println!();
+			md::md_table(&self.members).draw(frame);
+		}
+		if !self.pools.is_empty() {
+			println!();
+			zfs::zfs_table(&self.pools).draw(frame);
+		}
+		if let Some(slots) = &self.slots {
+			println!();
+			slots::slots_table(slots).draw(frame);
+		}
+	}
+}
@@ -0,0 +1,108 @@
+use super::Table;
+use colored::Colorize;
+use gptinfo::{
+	reader::slots::{self, Scheme, Slots},
+	BlockSource, PartitionTable,
+};
+
+/// The A/B boot slots of a disk, with the names of the partitions in each.
+pub(crate) struct BootSlots {
+	slots: Slots,
+	names: Vec<Vec<String>>,
+}
+
+/// Reads the boot slots of a ChromeOS or Android disk. A damaged boot
+/// control block is reported and the slots left out.
+pub(crate) fn read_slots(
+	table: &PartitionTable,
+	src: &mut dyn BlockSource,
+) -> Option<BootSlots> {
+	let disk = match table {
+		PartitionTable::Gpt(disk) => disk,
+		_ => return None,
+	};
+	let slots = match slots::read(disk, src) {
+		Ok(slots) => slots?,
+		Err(err) => {
+			eprintln!("{} {}", "Warning:".yellow().bold(), err);
+			return None;
+		}
+	};
+
+	let names = slots
+		.slots
+		.iter()
+		.map(|slot| {
+			let suffix = format!("_{}", slot.name);
+			disk.entries
+				.iter()
+				.filter(|entry| slot.partitions.contains(&entry.number))
+				.map(|entry| match slots.scheme {
+					// Every partition of an Android slot has its suffix
+					Scheme::ChromeOs => entry.name.clone(),
+					_ => entry.name.trim_end_matches(&suffix).to_string(),
+				})
+				.collect()
+		})
+		.collect();
+	Some(BootSlots { slots, names })
+}
+
+fn yes_no(value: bool) -> &'static str {
+	if value {
+		"yes"
+	} else {
+		"no"
+	}
+}
+
+/// A row for where the slot state comes from, and one beneath it for each
+/// slot.
+pub(crate) fn slots_table(boot: &BootSlots) -> Table {
+	let mut writer = Table::new(7);
+	writer.push_cells(vec![
+		"Name".bold(),
+		"Partitions".bold(),
+		"Priority".bold(),
+		"Tries Left".bold(),
+		"Successful".bold(),
+		"Bootable".bold(),
+		"Active".bold(),
+	]);
+
+	let source = match boot.slots.scheme {
+		Scheme::ChromeOs => "ChromeOS (kernel attributes)",
+		Scheme::AndroidMisc => "Android (misc)",
+		Scheme::AndroidAttributes => "Android (boot attributes)",
+		Scheme::Android => "Android (no slot metadata)",
+	};
+	let mut cells = vec![source.into()];
+	cells.extend(vec!["".into(); 6]);
+	writer.push_cells(cells);
+
+	let slots = &boot.slots.slots;
+	for (idx, (slot, names)) in slots.iter().zip(&boot.names).enumerate() {
+		let branch = if idx + 1 == slots.len() {
+			"└─"
+		} else {
+			"├─"
+		};
+		let mut cells = vec![
+			format!("{} Slot {}", branch, slot.name).as_str().into(),
+			names.join(", ").as_str().into(),
+		];
+		match slot.state {
+			Some(state) => cells.extend(vec![
+				state.priority.to_string().as_str().into(),
+				state.tries.to_string().as_str().into(),
+				yes_no(state.successful).into(),
+				yes_no(state.bootable).into(),
+				yes_no(state.active).into(),
+			]),
+			None => cells.extend(vec!["".into(); 5]),
+		}
+		writer.push_cells(cells);
+	}
+
+	writer
+}
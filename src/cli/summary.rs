@@ -0,0 +1,69 @@
+use super::{bytes, open, Table, Units};
+use anyhow::Result;
+use colored::Colorize;
+use gptinfo::{
+	reader::source::Raw, system::BlockDevice, Error, PartitionTable,
+};
+use std::io::{Read, Seek, SeekFrom};
+
+const MBR_SIGNATURE: [u8; 2] = [0x55, 0xAA];
+
+/// One row per disk: its partition table type, size, model and partition
+/// count.
+pub(crate) fn summary_table(
+	devices: &[BlockDevice],
+	units: Units,
+) -> Result<Table> {
+	let mut writer = Table::new(5);
+	writer.push_cells(vec![
+		"Device".bold(),
+		"Label".bold(),
+		"Size".bold(),
+		"Model".bold(),
+		"Partitions".bold(),
+	]);
+
+	for device in devices {
+		let (label, partitions) = match label(device) {
+			Ok(label) => label,
+			Err(err) if err.downcast_ref::<Error>().is_some() => {
+				return Err(err)
+			}
+			Err(_) => ("unreadable", device.partitions.len()),
+		};
+
+		writer.push_cells(vec![
+			device.path.to_string_lossy().as_ref().into(),
+			label.into(),
+			bytes::format_bytes(device.size.saturating_sub(1), 0, 1, units)
+				.as_str()
+				.into(),
+			device.model.as_deref().unwrap_or("").into(),
+			partitions.to_string().as_str().into(),
+		]);
+	}
+
+	Ok(writer)
+}
+
+/// The partition table type and its partition count. Tables gptinfo can't
+/// read fall back to the kernel's count.
+fn label(device: &BlockDevice) -> Result<(&'static str, usize)> {
+	let mut file = open(&device.path.to_string_lossy())?;
+	let kernel_count = device.partitions.len();
+
+	match gptinfo::read_table(&mut Raw::new(&mut file)?) {
+		Ok(PartitionTable::Gpt(disk)) => Ok(("gpt", disk.entries.len())),
+		Ok(PartitionTable::Apm(map)) => Ok(("apm", map.entries.len())),
+		Ok(PartitionTable::Mbr(mbr)) => Ok(("mbr", mbr.entries.len())),
+		Err(Error::InvalidSignature) => {
+			let mut mbr = [0u8; 512];
+			file.seek(SeekFrom::Start(0))?;
+			let is_mbr = file.read_exact(&mut mbr).is_ok()
+				&& mbr[510..] == MBR_SIGNATURE;
+			Ok((if is_mbr { "mbr" } else { "none" }, kernel_count))
+		}
+		Err(Error::Io(err)) => Err(err.into()),
+		Err(_) => Ok(("gpt (damaged)", kernel_count)),
+	}
+}
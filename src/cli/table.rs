@@ -1,4 +1,7 @@
-use colored::{ColoredString, Colorize};
+use super::{Format, Output};
+use colored::{ColoredString, Colorize, Styles};
+use std::mem;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 pub(crate) struct Table {
 	state: Vec<Vec<ColoredString>>,
@@ -36,54 +39,250 @@ impl Table {
 		}
 	}
 
-	pub(crate) fn draw(&self) {
+	/// Draws the table with borders in `frame`.
+	pub(crate) fn draw(&self, frame: &Frame) {
+		self.draw_rows(&self.state, frame)
+	}
+
+	/// Prints the table in the format of `output`, leaving out the header
+	/// row unless it asks for one.
+	pub(crate) fn print(&self, output: &Output) {
+		let rows = if output.header {
+			&self.state[..]
+		} else {
+			&self.state[1..]
+		};
+		match output.format {
+			Format::Table => self.draw_rows(rows, &output.frame),
+			Format::Csv => print_delimited(rows, ',', csv_field),
+			Format::Tsv => print_delimited(rows, '\t', tsv_field),
+		}
+	}
+
+	fn draw_rows(&self, rows: &[Vec<ColoredString>], frame: &Frame) {
+		let border = if frame.ascii { &ASCII } else { &ROUNDED };
+		let rows: Vec<Vec<ColoredString>> = rows
+			.iter()
+			.map(|row| row.iter().map(|cell| border.cell(cell)).collect())
+			.collect();
+
 		let mut col_widths = vec![0usize; self.cols];
 
 		// For row in table, for col in enumerate(row cells), if col width in
 		// arr is smaller, set it to cell width
-		for row in self.state.iter() {
+		for row in rows.iter() {
 			for (col, cell) in row.iter().enumerate() {
-				if col_widths[col] < cell.len() {
-					col_widths[col] = cell.len();
+				if col_widths[col] < width(cell) {
+					col_widths[col] = width(cell);
 				}
 			}
 		}
+		if let Some(limit) = frame.width {
+			// Break lines between words before breaking any word
+			let mut words = vec![0usize; self.cols];
+			for row in rows.iter() {
+				for (col, cell) in row.iter().enumerate() {
+					let longest = cell.split(' ').map(width).max().unwrap_or(0);
+					words[col] = words[col].max(longest);
+				}
+			}
+			fit(&mut col_widths, &words, limit);
+			fit(&mut col_widths, &[], limit);
+		}
 
 		// Draw top
-		print!("╭");
-		// Clone :(
-		for (col, width) in col_widths.clone().into_iter().enumerate() {
-			for _ in 0..width + 2 {
-				print!("─");
-			}
+		print!("{}", border.top[0]);
+		for (col, &width) in col_widths.iter().enumerate() {
+			print!("{}", border.line.to_string().repeat(width + 2));
 			if col != self.cols - 1 {
-				print!("┬");
+				print!("{}", border.top[1]);
 			}
 		}
-		print!("╮");
-		// Draw content
-		for row in self.state.iter() {
-			print!("\n│ ");
-			for (idx, cell) in row.iter().enumerate() {
-				print!("{}", cell);
-				for _ in 0..(col_widths[idx] - cell.len()) {
-					print!(" ");
-				}
-				if idx != row.len() {
-					print!(" │ ")
+		print!("{}", border.top[2]);
+		// Draw content, with cells too wide for their column wrapped onto
+		// the lines below
+		for row in rows.iter() {
+			let cells: Vec<_> = row
+				.iter()
+				.enumerate()
+				.map(|(col, cell)| wrap(cell, col_widths[col]))
+				.collect();
+			let height = cells.iter().map(Vec::len).max().unwrap_or(1);
+			for line in 0..height {
+				print!("\n{} ", border.side);
+				for (idx, lines) in cells.iter().enumerate() {
+					let text = lines.get(line).map_or("", String::as_str);
+					print!("{}", restyle(&row[idx], text));
+					let pad = col_widths[idx].saturating_sub(width(text));
+					print!("{} {} ", " ".repeat(pad), border.side);
 				}
 			}
 		}
 		// Draw bottom
-		print!("\n╰");
-		for (col, width) in col_widths.into_iter().enumerate() {
-			for _ in 0..width + 2 {
-				print!("─");
-			}
+		print!("\n{}", border.bottom[0]);
+		for (col, &width) in col_widths.iter().enumerate() {
+			print!("{}", border.line.to_string().repeat(width + 2));
 			if col != self.cols - 1 {
-				print!("┴");
+				print!("{}", border.bottom[1]);
+			}
+		}
+		println!("{}", border.bottom[2]);
+	}
+}
+
+/// How tables are drawn.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Frame {
+	/// Borders and tree lines in ASCII rather than box-drawing characters
+	pub(crate) ascii: bool,
+	/// Terminal columns to fit the table into, if any
+	pub(crate) width: Option<usize>,
+}
+
+struct Border {
+	top: [char; 3],
+	bottom: [char; 3],
+	line: char,
+	side: char,
+	/// Box-drawing characters of tree lines in cells, and what replaces
+	/// each
+	tree: &'static [(char, char)],
+}
+
+const ROUNDED: Border = Border {
+	top: ['╭', '┬', '╮'],
+	bottom: ['╰', '┴', '╯'],
+	line: '─',
+	side: '│',
+	tree: &[],
+};
+
+const ASCII: Border = Border {
+	top: ['+', '+', '+'],
+	bottom: ['+', '+', '+'],
+	line: '-',
+	side: '|',
+	tree: &[('├', '|'), ('└', '`'), ('─', '-'), ('│', '|')],
+};
+
+impl Border {
+	/// `cell` with its tree lines drawn in this border's characters.
+	fn cell(&self, cell: &ColoredString) -> ColoredString {
+		if !cell
+			.chars()
+			.any(|c| self.tree.iter().any(|&(from, _)| from == c))
+		{
+			return cell.clone();
+		}
+		let text: String = cell
+			.chars()
+			.map(|c| {
+				self.tree
+					.iter()
+					.find(|&&(from, _)| from == c)
+					.map_or(c, |&(_, to)| to)
+			})
+			.collect();
+		restyle(cell, &text)
+	}
+}
+
+// Columns aren't narrowed past this to fit the terminal, or past their
+// longest word while others can be
+const MIN_WIDTH: usize = 8;
+
+/// Narrows the widest of `widths` until a table with them fits in `limit`
+/// columns, or none can be narrowed any further: past `floors`, or past
+/// [`MIN_WIDTH`] where a column has no floor.
+fn fit(widths: &mut [usize], floors: &[usize], limit: usize) {
+	// A border and a space either side of each cell
+	let frame = 3 * widths.len() + 1;
+	while widths.iter().sum::<usize>() + frame > limit {
+		let widest = widths
+			.iter_mut()
+			.enumerate()
+			.filter(|(col, width)| {
+				**width > floors.get(*col).copied().unwrap_or(MIN_WIDTH)
+			})
+			.max_by_key(|(_, width)| **width);
+		match widest {
+			Some((_, widest)) => *widest -= 1,
+			None => break,
+		}
+	}
+}
+
+/// Splits `cell` into lines of at most `limit` columns, breaking between
+/// words where it can and within them where it must.
+fn wrap(cell: &str, limit: usize) -> Vec<String> {
+	if width(cell) <= limit {
+		return vec![cell.to_string()];
+	}
+
+	let mut lines = vec![];
+	let mut line = String::new();
+	for word in cell.split(' ') {
+		if !line.is_empty() {
+			if width(&line) + 1 + width(word) > limit {
+				lines.push(mem::take(&mut line));
+			} else {
+				line.push(' ');
 			}
 		}
-		println!("╯");
+		for c in word.chars() {
+			let more = c.width().unwrap_or(0);
+			if !line.is_empty() && width(&line) + more > limit {
+				lines.push(mem::take(&mut line));
+			}
+			line.push(c);
+		}
+	}
+	lines.push(line);
+	lines
+}
+
+/// `text` in the colors and weight of `cell`.
+fn restyle(cell: &ColoredString, text: &str) -> ColoredString {
+	let mut styled = ColoredString::from(text);
+	if let Some(color) = cell.fgcolor() {
+		styled = styled.color(color);
+	}
+	if let Some(color) = cell.bgcolor() {
+		styled = styled.on_color(color);
+	}
+	if cell.style().contains(Styles::Bold) {
+		styled = styled.bold();
 	}
+	styled
+}
+
+/// Display width of a cell in terminal columns: wide CJK and emoji
+/// characters take two, combining marks none.
+fn width(cell: &str) -> usize { UnicodeWidthStr::width(cell) }
+
+/// Prints one line per row, with the fields escaped by `field` and joined
+/// by `separator`. Colors are left out.
+fn print_delimited(
+	rows: &[Vec<ColoredString>],
+	separator: char,
+	field: fn(&str) -> String,
+) {
+	for row in rows.iter() {
+		let fields: Vec<_> = row.iter().map(|cell| field(cell)).collect();
+		println!("{}", fields.join(&separator.to_string()));
+	}
+}
+
+/// Quotes fields that hold a comma, quote or line break, as RFC 4180 does.
+fn csv_field(cell: &str) -> String {
+	if cell.contains(&[',', '"', '\n', '\r'][..]) {
+		format!("\"{}\"", cell.replace('"', "\"\""))
+	} else {
+		cell.to_string()
+	}
+}
+
+/// TSV has no escapes, so tabs and line breaks become spaces.
+fn tsv_field(cell: &str) -> String {
+	cell.replace(&['\t', '\n', '\r'][..], " ")
 }
@@ -0,0 +1,67 @@
+use super::CliError;
+use nix::{
+	ioctl_read_bad,
+	libc::{self, winsize},
+	unistd,
+};
+use std::{env, io::stdout, os::unix::io::AsRawFd, str::FromStr};
+
+// <asm-generic/ioctls.h>
+ioctl_read_bad!(tiocgwinsz, libc::TIOCGWINSZ, winsize);
+
+/// When to color the output, as given to --color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorChoice {
+	/// Only on a terminal, and not when NO_COLOR is set
+	Auto,
+	Always,
+	Never,
+}
+
+impl FromStr for ColorChoice {
+	type Err = CliError;
+
+	fn from_str(input: &str) -> Result<ColorChoice, CliError> {
+		match input {
+			"auto" => Ok(ColorChoice::Auto),
+			"always" => Ok(ColorChoice::Always),
+			"never" => Ok(ColorChoice::Never),
+			_ => Err(CliError::InvalidColor(input.to_string())),
+		}
+	}
+}
+
+/// Turns colors on or off for everything printed from here on.
+pub(crate) fn set_color(choice: ColorChoice) {
+	let color = match choice {
+		ColorChoice::Always => true,
+		ColorChoice::Never => false,
+		// https://no-color.org: set and not empty
+		ColorChoice::Auto => {
+			env::var_os("NO_COLOR").map_or(true, |value| value.is_empty())
+				&& unistd::isatty(stdout().as_raw_fd()).unwrap_or(false)
+		}
+	};
+	colored::control::set_override(color);
+}
+
+/// Columns of the terminal stdout is written to: COLUMNS if set, otherwise
+/// what the terminal reports. `None` when output goes elsewhere.
+pub(crate) fn width() -> Option<usize> {
+	let columns = env::var("COLUMNS").ok().and_then(|var| var.parse().ok());
+	if let Some(columns) = columns.filter(|&columns| columns > 0) {
+		return Some(columns);
+	}
+
+	let mut size = winsize {
+		ws_row: 0,
+		ws_col: 0,
+		ws_xpixel: 0,
+		ws_ypixel: 0,
+	};
+	// SAFETY: TIOCGWINSZ only writes a winsize to the pointer
+	match unsafe { tiocgwinsz(stdout().as_raw_fd(), &mut size) } {
+		Ok(_) if size.ws_col > 0 => Some(size.ws_col.into()),
+		_ => None,
+	}
+}
@@ -0,0 +1,137 @@
+use super::Table;
+use colored::Colorize;
+use gptinfo::{
+	reader::zfs::{self, Label, Zfs},
+	BlockSource, PartitionTable,
+};
+use std::collections::BTreeMap;
+
+// Why the back labels of a vdev may be missing
+const NOT_CHECKED: &str = "not checked (the image can't be read out of order)";
+
+/// ZFS vdev labels found in partitions, by partition number.
+pub(crate) type Pools = BTreeMap<u32, Zfs>;
+
+/// Reads the vdev labels of the partitions typed as ZFS. A vdev whose
+/// every label is damaged is reported and left out rather than failing the
+/// whole disk.
+pub(crate) fn read_pools(
+	table: &PartitionTable,
+	src: &mut dyn BlockSource,
+) -> Pools {
+	let disk = match table {
+		PartitionTable::Gpt(disk) => disk,
+		_ => return Pools::new(),
+	};
+
+	let mut pools = Pools::new();
+	for entry in disk
+		.entries
+		.iter()
+		.filter(|entry| zfs::gpt_type_holds_pool(entry.type_guid))
+	{
+		let sectors = entry
+			.last_lba
+			.saturating_sub(entry.first_lba)
+			.saturating_add(1);
+		match zfs::read(
+			src,
+			entry.first_lba.saturating_mul(disk.sector_size),
+			sectors.saturating_mul(disk.sector_size),
+		) {
+			Ok(Some(vdev)) => {
+				pools.insert(entry.number, vdev);
+			}
+			Ok(None) => {}
+			Err(err) => eprintln!(
+				"{} Partition {}: {}",
+				"Warning:".yellow().bold(),
+				entry.number,
+				err
+			),
+		}
+	}
+	pools
+}
+
+/// The pool, GUID, TXG and state cells of a label.
+fn label_cells(label: &Label) -> Vec<String> {
+	let optional = |value: Option<u64>| {
+		value.map(|value| value.to_string()).unwrap_or_default()
+	};
+	vec![
+		label.pool.clone().unwrap_or_default(),
+		optional(label.pool_guid),
+		label.guid.to_string(),
+		label.txg.to_string(),
+		label.state.to_string(),
+	]
+}
+
+/// One row per partition that is a ZFS vdev, from its freshest label. When
+/// the copies don't agree, each is listed beneath it.
+pub(crate) fn zfs_table(pools: &Pools) -> Table {
+	let mut writer = Table::new(7);
+	writer.push_cells(vec![
+		"Name".bold(),
+		"Pool".bold(),
+		"Pool GUID".bold(),
+		"Vdev GUID".bold(),
+		"TXG".bold(),
+		"State".bold(),
+		"Labels".bold(),
+	]);
+
+	for (number, vdev) in pools.iter() {
+		let freshest = match vdev.freshest() {
+			Some(label) => label,
+			None => continue,
+		};
+		let consistent = vdev.consistent();
+		let mut cells = vec![format!("Partition {}", number)];
+		cells.extend(label_cells(freshest));
+		let agreement = match (consistent, vdev.labels.len()) {
+			(true, 4) => "4 of 4 agree".to_string(),
+			(true, _) => format!("front 2 agree, back {}", NOT_CHECKED),
+			(false, 4) => "inconsistent".to_string(),
+			(false, _) => format!("inconsistent, back {}", NOT_CHECKED),
+		};
+		cells.push(agreement);
+		writer.push_cells(
+			cells.iter().map(|cell| cell.as_str().into()).collect(),
+		);
+		if consistent {
+			continue;
+		}
+
+		for idx in 0..4 {
+			let branch = if idx == 3 { "└─" } else { "├─" };
+			let end = if idx < 2 { "front" } else { "back" };
+			let mut cells = vec![format!("{} Label {}", branch, idx)];
+			match vdev.labels.get(idx) {
+				None => {
+					cells.extend(vec![String::new(); 5]);
+					cells.push(format!("{}, {}", end, NOT_CHECKED));
+				}
+				Some(Some(label)) => {
+					cells.extend(label_cells(label));
+					let agrees = if label == freshest {
+						"matches"
+					} else {
+						"differs"
+					};
+					cells.push(format!("{}, {}", end, agrees));
+				}
+				Some(None) => {
+					cells.extend(vec![String::new(); 5]);
+					cells.push(format!("{}, missing or damaged", end));
+				}
+			}
+			writer.push_cells(
+				cells.iter().map(|cell| cell.as_str().into()).collect(),
+			);
+		}
+	}
+
+	writer
+}
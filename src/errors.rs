@@ -1,22 +1,79 @@
+use std::{io, path::PathBuf};
 use thiserror::Error as ThisError;
 
 #[derive(ThisError, Debug)]
-pub(crate) enum Error {
+#[non_exhaustive]
+pub enum Error {
 	#[error("Failed to validate signature of EFI partition.")]
 	InvalidSignature,
 
-	#[error("Internal: undefined LBA pointer at index `{0}`.")]
-	UndefLbaPtr(u16),
+	#[error("Invalid GPT header: {0}.")]
+	InvalidHeader(&'static str),
 
-	#[error("Unrecognized partition type GUID.")]
-	UnrecognizedGUID,
+	#[error("GPT header checksum mismatch.")]
+	HeaderChecksum,
 
-	#[error("Cannot display non-UTF8 UTF16 characters.")]
-	UTF16,
+	#[error("GPT partition entry array checksum mismatch.")]
+	EntriesChecksum,
+
+	#[error("Invalid partition entry {0}: {1}.")]
+	InvalidEntry(u32, &'static str),
+
+	#[error("Invalid Apple Partition Map: {0}.")]
+	InvalidApm(&'static str),
+
+	#[error("Invalid {0}: {1}.")]
+	InvalidLabel(&'static str, &'static str),
+
+	#[error("Invalid LDM database: {0}.")]
+	InvalidLdm(&'static str),
+
+	#[error("Invalid LVM physical volume: {0}.")]
+	InvalidLvm(&'static str),
+
+	#[error("Invalid LUKS header: {0}.")]
+	InvalidLuks(&'static str),
+
+	#[error("Invalid md superblock: {0}.")]
+	InvalidMd(&'static str),
+
+	#[error("Invalid ZFS label: {0}.")]
+	InvalidZfs(&'static str),
+
+	#[error("Invalid Android boot control block: {0}.")]
+	InvalidBootControl(&'static str),
+
+	#[error("Truncated {0}: need {1} bytes, got {2}.")]
+	Truncated(&'static str, usize, usize),
 
 	#[error(
-		"This binary must be run as root. See `gptinfo --help` for more \
-		 options."
+		"Invalid GUID `{0}`, expected XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX."
 	)]
-	Root,
+	InvalidGUID(String),
+
+	#[error("Failed to load partition type definitions from `{0}`: {1}")]
+	Config(PathBuf, String),
+
+	#[error("Invalid {0} image: {1}.")]
+	InvalidImage(&'static str, &'static str),
+
+	#[error("Unsupported {0} image: {1}.")]
+	UnsupportedImage(&'static str, &'static str),
+
+	#[error(
+		"Backing file `{0}` is an absolute path or leaves the directory of \
+		 the image naming it."
+	)]
+	UnsafeBackingFile(String),
+
+	#[error(
+		"Byte range ending at {0} is past the end of the disk ({1} bytes)."
+	)]
+	OutOfRange(u64, u64),
+
+	#[error("Cannot display non-UTF8 UTF16 characters.")]
+	UTF16,
+
+	#[error(transparent)]
+	Io(#[from] io::Error),
 }
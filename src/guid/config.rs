@@ -0,0 +1,87 @@
+use super::{Guid, PartitionType};
+use crate::{Error, Result};
+use serde::Deserialize;
+use std::{
+	collections::HashMap,
+	env, fs,
+	path::{Path, PathBuf},
+};
+
+const SYSTEM_CONFIG: &str = "/etc/gptinfo/types.toml";
+
+#[derive(Deserialize, Default)]
+struct TypeConfig {
+	#[serde(default)]
+	types: HashMap<String, UserType>,
+}
+
+#[derive(Deserialize)]
+struct UserType {
+	name: String,
+	#[serde(default)]
+	aliases: Vec<String>,
+}
+
+/// Partition types defined by the user. These are looked up before the
+/// built-in table, so they may also rename built-in types.
+#[derive(Default)]
+pub struct UserTypes {
+	types: HashMap<Guid, UserType>,
+}
+
+impl UserTypes {
+	/// Loads `/etc/gptinfo/types.toml`, then the per-user
+	/// `$XDG_CONFIG_HOME/gptinfo/types.toml` (or `~/.config/...`) over it.
+	/// Missing files are skipped.
+	pub fn load() -> Result<UserTypes> {
+		let mut this = Self::default();
+
+		let mut paths = vec![PathBuf::from(SYSTEM_CONFIG)];
+		if let Some(dir) = env::var_os("XDG_CONFIG_HOME")
+			.map(PathBuf::from)
+			.or_else(|| {
+				env::var_os("HOME")
+					.map(|home| PathBuf::from(home).join(".config"))
+			}) {
+			paths.push(dir.join("gptinfo").join("types.toml"));
+		}
+
+		for path in paths {
+			if path.is_file() {
+				this.merge_file(&path)?;
+			}
+		}
+
+		Ok(this)
+	}
+
+	/// Merges the definitions in `path` over the current ones.
+	pub fn merge_file(&mut self, path: &Path) -> Result<()> {
+		let raw = fs::read_to_string(path)?;
+		self.merge_str(&raw)
+			.map_err(|err| Error::Config(path.to_path_buf(), err))
+	}
+
+	fn merge_str(&mut self, raw: &str) -> Result<(), String> {
+		let config: TypeConfig =
+			toml::from_str(raw).map_err(|err| err.to_string())?;
+		for (guid, kind) in config.types {
+			let guid = guid.parse().map_err(|err: Error| err.to_string())?;
+			self.types.insert(guid, kind);
+		}
+		Ok(())
+	}
+
+	/// Resolves `guid` against the user definitions, then the built-in
+	/// table.
+	pub fn resolve(&self, guid: Guid) -> PartitionType {
+		match self.types.get(&guid) {
+			Some(user) => PartitionType {
+				guid,
+				name: Some(user.name.clone()),
+				aliases: user.aliases.clone(),
+			},
+			None => PartitionType::from_guid(guid),
+		}
+	}
+}
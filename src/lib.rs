@@ -0,0 +1,27 @@
+//! Reads GUID Partition Tables, and Apple Partition Maps and MBRs, from
+//! disks and disk images, along with the volume metadata nested in their
+//! partitions: BSD disklabels, Solaris VTOCs, Windows LDM databases, LVM
+//! physical volumes, LUKS headers, md-raid superblocks and ZFS vdev labels.
+//!
+//! ```no_run
+//! let file = std::fs::File::open("/dev/sda")?;
+//! let disk = gptinfo::read_gpt(file)?;
+//! for entry in &disk.entries {
+//!     println!("{} {}", entry.name, entry.partition_type());
+//! }
+//! # Ok::<(), gptinfo::Error>(())
+//! ```
+
+mod errors;
+pub mod guid;
+pub mod reader;
+pub mod system;
+
+pub use errors::Error;
+pub use guid::{Guid, PartitionType};
+pub use reader::{
+	read_gpt, read_gpt_from, read_table, source::BlockSource, Disk,
+	GptHeader, PartitionEntry, PartitionTable, TableStatus,
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -1,18 +1,145 @@
 mod cli;
-mod errors;
-mod guid;
-mod reader;
 
 use anyhow::{Context as _, Result};
 use clap::Clap;
-use errors::Error;
+use gptinfo::{
+	guid::UserTypes,
+	reader::{self, source::Slice},
+	system::{self, LiveDisk},
+	PartitionTable,
+};
+use std::path::Path;
 
 fn main() -> Result<()> {
-	let cli::Opts { device, guid } = cli::Opts::parse();
+	let opts = cli::Opts::parse();
+	cli::set_color(opts.color);
 
-	reader::Reader::new(&device, guid)
-		.context("Failed to create Reader")?
-		.run()?;
+	if opts.list {
+		let devices =
+			system::block_devices().context("Failed to list block devices")?;
+		cli::summary_table(&devices, opts.units())?.draw(&opts.frame());
+		return Ok(());
+	}
+
+	let types = UserTypes::load()
+		.context("Failed to load partition type definitions")?;
+
+	let devices = match opts.device.clone() {
+		Some(device) => vec![device],
+		None => cli::default_devices(opts.all)?,
+	};
+
+	let mut arrays = cli::Arrays::default();
+	let result = cli::show_each(&devices, |device, separate| {
+		show(device, &types, &opts, &mut arrays, separate)
+	});
+
+	// Members of one array on several disks, to tell which is freshest
+	if let (cli::Format::Table, Some(table)) = (opts.format, arrays.table()) {
+		println!();
+		table.draw(&opts.frame());
+	}
+
+	result
+}
+
+fn show(
+	device: &str,
+	types: &UserTypes,
+	opts: &cli::Opts,
+	arrays: &mut cli::Arrays,
+	separate: bool,
+) -> Result<()> {
+	let (path, range) = cli::range(device, opts)?;
+	let file = cli::open(&path)?;
+	let mut source = reader::source::from_file_with(
+		file.try_clone()?,
+		path.as_ref(),
+		opts.allow_backing_paths,
+	)
+	.with_context(|| format!("Failed to open {}", path))?;
+	if let Some(range) = &range {
+		source = Box::new(
+			Slice::new(source, range.offset, range.length)
+				.with_context(|| format!("Failed to open {}", device))?,
+		);
+	}
+	let mut table = gptinfo::read_table(&mut *source)
+		.with_context(|| format!("Failed to read GPT from {}", device))?;
+	let output = opts.output(&table);
+	let mut nested = cli::Nested::read(&table, &mut *source, output.probes());
+
+	let loc = match &range {
+		Some(range) => {
+			if opts.absolute {
+				cli::make_absolute(&mut table, &mut nested, range.offset)?;
+			}
+			format!("{}@{}", path, range.offset)
+		}
+		None => path,
+	};
+	arrays.add(&loc, &nested.members);
+
+	let disk = match table {
+		PartitionTable::Gpt(disk) => disk,
+		PartitionTable::Apm(map) => {
+			if opts.mounts || opts.tree || opts.check || opts.map {
+				return Err(
+					cli::CliError::NotGpt("an Apple Partition Map").into()
+				);
+			}
+			if separate {
+				println!();
+			}
+			cli::apm_table(&map, &loc, &nested, &output).print(&output);
+			return Ok(());
+		}
+		PartitionTable::Mbr(mbr) => {
+			if opts.mounts || opts.tree || opts.check || opts.map {
+				return Err(cli::CliError::NotGpt("an MBR").into());
+			}
+			if separate {
+				println!();
+			}
+			cli::mbr_table(&mbr, &loc, &nested, &output).print(&output);
+			if output.nested() {
+				nested.draw(&output);
+			}
+			return Ok(());
+		}
+	};
+
+	let live = if opts.mounts || opts.tree {
+		LiveDisk::load(Path::new(&loc)).context("Failed to read mounts")?
+	} else {
+		None
+	};
+
+	if separate {
+		println!();
+	}
+	cli::warn_damaged(&disk);
+	cli::disk_table(
+		&disk,
+		&loc,
+		types,
+		&nested,
+		&output,
+		live.as_ref(),
+		opts.tree,
+	)?
+	.print(&output);
+	if opts.map && output.nested() {
+		println!();
+		cli::draw_map(&disk, &output.frame, output.units);
+	}
+	if output.nested() {
+		nested.draw(&output);
+	}
+
+	if opts.check {
+		cli::check(&loc, &file, &disk, opts.reread, &output.frame)?;
+	}
 
 	Ok(())
 }
@@ -0,0 +1,143 @@
+use super::{
+	bytes::{self, Fields},
+	source::BlockSource,
+};
+use crate::{Error, Result};
+
+const DRIVER_DESCRIPTOR_SIGNATURE: &[u8] = b"ER";
+const ENTRY_SIGNATURE: &[u8] = b"PM";
+// Fields up to the processor type; the rest of the block is boot code
+// arguments and padding
+const ENTRY_SIZE: usize = 136;
+// Block sizes tried when the driver descriptor doesn't name one that
+// works: hard disks use 512, CDs and hybrid ISO images 2048
+const BLOCK_SIZES: [u64; 2] = [512, 2048];
+// One entry per block; real maps hold a few dozen
+const MAX_ENTRIES: u32 = 1024;
+
+// Partition status bits, as named in Inside Macintosh
+const STATUS_FLAGS: [(u32, &str); 8] = [
+	(1, "valid"),
+	(1 << 1, "allocated"),
+	(1 << 2, "in use"),
+	(1 << 3, "bootable"),
+	(1 << 4, "readable"),
+	(1 << 5, "writable"),
+	(1 << 30, "automount"),
+	(1 << 31, "startup"),
+];
+
+/// An Apple Partition Map, as used on old Mac media and some hybrid ISO
+/// images.
+#[derive(Debug, Clone)]
+pub struct Apm {
+	/// Size of the blocks entries are counted in
+	pub block_size: u64,
+	/// Every entry of the map, including the map's own and free space
+	pub entries: Vec<ApmEntry>,
+}
+
+/// A partition map entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApmEntry {
+	/// 1-based position in the map, which is also the block it's stored in
+	pub number: u32,
+	pub name: String,
+	/// The type string, such as `Apple_HFS` or `Apple_Free`
+	pub kind: String,
+	pub start: u64,
+	pub blocks: u64,
+	pub status: u32,
+}
+
+impl ApmEntry {
+	/// The last block covered, which is `start` for empty entries.
+	pub fn end(&self) -> u64 {
+		self.start.saturating_add(self.blocks.saturating_sub(1))
+	}
+
+	/// Names of the status bits that are set.
+	pub fn status_flags(&self) -> Vec<&'static str> {
+		STATUS_FLAGS
+			.iter()
+			.filter(|&&(bit, _)| self.status & bit != 0)
+			.map(|&(_, name)| name)
+			.collect()
+	}
+}
+
+/// Reads the Apple Partition Map of `src`. Fails with
+/// [`Error::InvalidSignature`] if it has none.
+pub fn read(src: &mut dyn BlockSource) -> Result<Apm> {
+	let mut block = [0u8; 512];
+	if src.read_at(0, &mut block).is_err() {
+		return Err(Error::InvalidSignature);
+	}
+
+	// The driver descriptor record is optional, and its block size isn't
+	// always the one the map counts in
+	let size =
+		u64::from(Fields::new(&block, "driver descriptor", 4)?.u16_be(2)?);
+	let described = (block[..2] == DRIVER_DESCRIPTOR_SIGNATURE[..]
+		&& size.is_power_of_two()
+		&& (512..=4096).contains(&size))
+	.then_some(size);
+
+	for block_size in described.into_iter().chain(BLOCK_SIZES.iter().copied()) {
+		if let Some(entries) = read_entries(src, block_size)? {
+			return Ok(Apm {
+				block_size,
+				entries,
+			});
+		}
+	}
+	Err(Error::InvalidSignature)
+}
+
+/// The entries of a map counting in `block_size` blocks, or `None` if the
+/// first one isn't where it should be.
+fn read_entries(
+	src: &mut dyn BlockSource,
+	block_size: u64,
+) -> Result<Option<Vec<ApmEntry>>> {
+	let mut raw = [0u8; ENTRY_SIZE];
+	let mut entries = vec![];
+	let mut count = 1;
+
+	let mut number = 1;
+	while number <= count {
+		if src
+			.read_at(u64::from(number) * block_size, &mut raw)
+			.is_err()
+		{
+			break;
+		}
+		let fields = Fields::new(&raw, "partition map entry", ENTRY_SIZE)?;
+		if fields.slice(0, 2)? != ENTRY_SIGNATURE {
+			break;
+		}
+		if number == 1 {
+			// Every entry repeats the size of the map; the first one rules
+			count = fields.u32_be(4)?;
+			if !(1..=MAX_ENTRIES).contains(&count) {
+				return Err(Error::InvalidApm("entry count out of range"));
+			}
+		}
+
+		entries.push(ApmEntry {
+			number,
+			name: bytes::c_string(fields.slice(16, 32)?),
+			kind: bytes::c_string(fields.slice(48, 32)?),
+			start: u64::from(fields.u32_be(8)?),
+			blocks: u64::from(fields.u32_be(12)?),
+			status: fields.u32_be(88)?,
+		});
+		number += 1;
+	}
+
+	Ok(if entries.is_empty() {
+		None
+	} else {
+		Some(entries)
+	})
+}
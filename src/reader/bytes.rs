@@ -1,72 +1,101 @@
-use crate::Error;
-use anyhow::Result;
-use colored::ColoredString;
+use crate::{Error, Guid, Result};
+use std::convert::TryInto;
 
-pub(crate) fn format_bytes(end: u64, start: u64) -> String {
-	let raw = (end - start + 1) * 512;
-	match raw {
-		// 1024 bytes in a MB
-		1..=1023 => {
-			format!("{}B", raw)
-		}
-		// 1048576 bytes in a KB
-		1024..=1048575 => {
-			format!("{}K", raw / 1024)
-		}
-		// 1073741824 bytes in a GB
-		1048576..=1073741823 => {
-			format!("{}M", raw / 1024 / 1024)
-		}
-		// 1099511627776 bytes in a TB
-		1073741824..=1099511627775 => {
-			format!("{}G", raw / 1024 / 1024 / 1024)
-		}
-		// 1125899906842624 bytes in a PB
-		1099511627776..=1125899906842624 => {
-			format!("{}T", raw / 1024 / 1024 / 1024 / 1024)
+/// Bounds-checked reads out of an on-disk structure, little-endian unless
+/// noted.
+pub(crate) struct Fields<'a> {
+	bytes: &'a [u8],
+	what: &'static str,
+}
+
+impl<'a> Fields<'a> {
+	/// Fails with [`Error::Truncated`] unless `bytes` holds at least
+	/// `min_len` bytes.
+	pub(crate) fn new(
+		bytes: &'a [u8],
+		what: &'static str,
+		min_len: usize,
+	) -> Result<Fields<'a>> {
+		if bytes.len() < min_len {
+			return Err(Error::Truncated(what, min_len, bytes.len()));
 		}
-		// if you have more than 1023 terabytes of
-		// storage... go do something useful instead
-		// of reading this
-		x => panic!("a meaningful error {}", x),
+		Ok(Self { bytes, what })
 	}
-}
 
-pub(crate) fn string_from_bytes(bytes: &[u8; 72]) -> Result<ColoredString> {
-	let (front, slice, back) = unsafe { bytes.align_to::<u16>() };
-	if front.is_empty() && back.is_empty() {
-		Ok(ColoredString::from(String::from_utf16(slice)?.as_str()))
-	} else {
-		Err(Error::UTF16.into())
+	pub(crate) fn slice(&self, offset: usize, len: usize) -> Result<&'a [u8]> {
+		offset
+			.checked_add(len)
+			.and_then(|end| self.bytes.get(offset..end))
+			.ok_or_else(|| {
+				Error::Truncated(
+					self.what,
+					offset.saturating_add(len),
+					self.bytes.len(),
+				)
+			})
+	}
+
+	pub(crate) fn u16(&self, offset: usize) -> Result<u16> {
+		// unwrap: `slice` returns exactly the requested length
+		Ok(u16::from_le_bytes(
+			self.slice(offset, 2)?.try_into().unwrap(),
+		))
 	}
-}
 
-pub(crate) fn guid_from_bytes(
-	kind_p1: &[u8; 4],
-	kind_p2: &[u8; 2],
-	kind_p3: &[u8; 2],
-	kind_p4: &[u8; 2],
-	kind_p5: &[u8; 6],
-) -> String {
-	let mut guid = String::new();
-	for ch in kind_p1 {
-		guid.push_str(&format!("{:01$x?}", ch, 2));
+	pub(crate) fn u32(&self, offset: usize) -> Result<u32> {
+		Ok(u32::from_le_bytes(
+			self.slice(offset, 4)?.try_into().unwrap(),
+		))
 	}
-	guid.push('-');
-	for ch in kind_p2 {
-		guid.push_str(&format!("{:01$x?}", ch, 2));
+
+	pub(crate) fn u64(&self, offset: usize) -> Result<u64> {
+		Ok(u64::from_le_bytes(
+			self.slice(offset, 8)?.try_into().unwrap(),
+		))
 	}
-	guid.push('-');
-	for ch in kind_p3 {
-		guid.push_str(&format!("{:01$x?}", ch, 2));
+
+	pub(crate) fn u16_be(&self, offset: usize) -> Result<u16> {
+		Ok(u16::from_be_bytes(
+			self.slice(offset, 2)?.try_into().unwrap(),
+		))
 	}
-	guid.push('-');
-	for ch in kind_p4 {
-		guid.push_str(&format!("{:01$x?}", ch, 2));
+
+	pub(crate) fn u32_be(&self, offset: usize) -> Result<u32> {
+		Ok(u32::from_be_bytes(
+			self.slice(offset, 4)?.try_into().unwrap(),
+		))
+	}
+
+	pub(crate) fn u64_be(&self, offset: usize) -> Result<u64> {
+		Ok(u64::from_be_bytes(
+			self.slice(offset, 8)?.try_into().unwrap(),
+		))
 	}
-	guid.push('-');
-	for ch in kind_p5 {
-		guid.push_str(&format!("{:01$x?}", ch, 2));
+
+	pub(crate) fn guid(&self, offset: usize) -> Result<Guid> {
+		Ok(Guid::from_bytes_le(
+			self.slice(offset, 16)?.try_into().unwrap(),
+		))
 	}
-	guid.to_ascii_uppercase()
+}
+
+/// Decodes a NUL-terminated UTF-16LE string. A trailing odd byte is
+/// ignored.
+pub fn string_from_bytes(bytes: &[u8]) -> Result<String> {
+	let units: Vec<u16> = bytes
+		.chunks_exact(2)
+		.map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+		.take_while(|&unit| unit != 0)
+		.collect();
+	String::from_utf16(&units).map_err(|_| Error::UTF16)
+}
+
+/// Decodes a NUL-padded string of single-byte characters, replacing any
+/// that aren't valid UTF-8.
+pub(crate) fn c_string(bytes: &[u8]) -> String {
+	let len = bytes
+		.iter()
+		.position(|&byte| byte == 0)
+		.unwrap_or(bytes.len());
+	String::from_utf8_lossy(&bytes[..len]).into_owned()
 }
@@ -0,0 +1,321 @@
+use super::{bytes::Fields, source::BlockSource};
+use crate::{guid::Guid, Error, Result};
+use std::fmt;
+
+const BSD_MAGIC: u32 = 0x8256_4557;
+// Partitions that fit in the label's sector after the 148-byte header
+const BSD_MAX_PARTITIONS: u16 = 22;
+const BSD_PARTITIONS_OFFSET: usize = 148;
+const BSD_PARTITION_SIZE: usize = 16;
+// The raw partition covering the whole slice (or disk), `c`
+const BSD_RAW_PARTITION: usize = 2;
+
+const VTOC_MAGIC: u16 = 0xDABE;
+const VTOC_SANITY: u32 = 0x600D_DEEE;
+const SUN_PARTITIONS: usize = 8;
+const SOLARIS_MAX_PARTITIONS: u16 = 16;
+
+// Filesystem types numbered the same by every BSD; later ones diverge
+const BSD_FSTYPES: [&str; 14] = [
+	"unused",
+	"swap",
+	"Version 6",
+	"Version 7",
+	"System V",
+	"4.1BSD",
+	"Eighth Edition",
+	"4.2BSD",
+	"MSDOS",
+	"4.4LFS",
+	"unknown",
+	"HPFS",
+	"ISO9660",
+	"boot",
+];
+
+// Partition tags, as format(1M) names them
+const VTOC_TAGS: [&str; 13] = [
+	"unassigned",
+	"boot",
+	"root",
+	"swap",
+	"usr",
+	"backup",
+	"stand",
+	"var",
+	"home",
+	"alternates",
+	"cache",
+	"reserved",
+	"system",
+];
+
+// GPT partitions that hold a label: the BSDs' data partitions, and the
+// Solaris slices that cover the disk
+const GPT_TYPES: [Guid; 5] = [
+	Guid::from_groups(0x516E7CB4, 0x6ECF, 0x11D6, 0x8FF8, 0x00022D09712B),
+	Guid::from_groups(0x85D5E45A, 0x237C, 0x11E1, 0xB4B3, 0xE89A8F7FC3A7),
+	Guid::from_groups(0x824CC7A0, 0x36A8, 0x11E3, 0x890A, 0x952519AD3F61),
+	Guid::from_groups(0x6A85CF4D, 0x1DD2, 0x11B2, 0x99A6, 0x080020736631),
+	Guid::from_groups(0x6A8B642B, 0x1DD2, 0x11B2, 0x99A6, 0x080020736631),
+];
+// FreeBSD, OpenBSD, NetBSD and the two Solaris IDs
+const MBR_TYPES: [u8; 5] = [0xA5, 0xA6, 0xA9, 0x82, 0xBF];
+
+/// A label that divides a partition (or MBR slice) further.
+#[derive(Debug, Clone)]
+pub struct Disklabel {
+	pub kind: LabelKind,
+	/// Used partitions, in label order
+	pub partitions: Vec<LabelPartition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelKind {
+	/// FreeBSD, NetBSD, MidnightBSD and older OpenBSD
+	Bsd,
+	/// OpenBSD's version 1 label, with 48-bit sizes and offsets
+	OpenBsd,
+	/// The big-endian SMI label of SPARC systems
+	Sun,
+	/// The little-endian VTOC of Solaris on x86
+	SolarisX86,
+}
+
+impl fmt::Display for LabelKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Bsd => "BSD disklabel",
+			Self::OpenBsd => "OpenBSD disklabel",
+			Self::Sun => "Sun VTOC",
+			Self::SolarisX86 => "Solaris x86 VTOC",
+		})
+	}
+}
+
+/// A partition of a [`Disklabel`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelPartition {
+	/// The letter (`a`, `b`, ...) of BSD partitions, or `s0`, `s1`, ... for
+	/// Solaris slices
+	pub name: String,
+	/// The filesystem type of BSD partitions, or the tag of Solaris slices
+	pub kind: String,
+	/// Counted from the start of the disk, not the partition holding the
+	/// label
+	pub first_lba: u64,
+	pub sectors: u64,
+}
+
+impl LabelPartition {
+	/// The last sector covered, which is `first_lba` for empty partitions.
+	pub fn last_lba(&self) -> u64 {
+		self.first_lba
+			.saturating_add(self.sectors.saturating_sub(1))
+	}
+}
+
+/// Whether GPT partitions of this type may hold a label.
+pub fn gpt_type_holds_label(type_guid: Guid) -> bool {
+	GPT_TYPES.contains(&type_guid)
+}
+
+/// Whether MBR partitions of this type may hold a label.
+pub fn mbr_type_holds_label(kind: u8) -> bool { MBR_TYPES.contains(&kind) }
+
+/// Reads the label of the partition starting at `first_lba`: a BSD
+/// disklabel or Solaris x86 VTOC in its second sector, or a Sun label in
+/// its first. Returns `None` if it has none of them.
+pub fn read(
+	src: &mut dyn BlockSource,
+	first_lba: u64,
+	sector_size: u64,
+) -> Result<Option<Disklabel>> {
+	if let Some(sector) =
+		read_sector(src, first_lba.saturating_add(1), sector_size)
+	{
+		if let Some(label) = read_bsd(&sector, first_lba)? {
+			return Ok(Some(label));
+		}
+		if let Some(label) = read_solaris_x86(&sector, first_lba)? {
+			return Ok(Some(label));
+		}
+	}
+	match read_sector(src, first_lba, sector_size) {
+		Some(sector) => read_sun(&sector, first_lba),
+		None => Ok(None),
+	}
+}
+
+/// The first 512 bytes of sector `lba`, or `None` if it's out of range.
+fn read_sector(
+	src: &mut dyn BlockSource,
+	lba: u64,
+	sector_size: u64,
+) -> Option<[u8; 512]> {
+	let mut sector = [0u8; 512];
+	let offset = lba.checked_mul(sector_size)?;
+	src.read_at(offset, &mut sector).ok()?;
+	Some(sector)
+}
+
+/// XOR of the 16-bit words of `bytes`, which is zero for a label with a
+/// valid checksum.
+fn checksum(bytes: &[u8], word: fn([u8; 2]) -> u16) -> u16 {
+	bytes
+		.chunks_exact(2)
+		.fold(0, |sum, pair| sum ^ word([pair[0], pair[1]]))
+}
+
+fn read_bsd(sector: &[u8], first_lba: u64) -> Result<Option<Disklabel>> {
+	let fields = Fields::new(sector, "BSD disklabel", 512)?;
+	if fields.u32(0)? != BSD_MAGIC || fields.u32(132)? != BSD_MAGIC {
+		return Ok(None);
+	}
+	let invalid = |reason| Error::InvalidLabel("BSD disklabel", reason);
+
+	let count = fields.u16(138)?;
+	if count > BSD_MAX_PARTITIONS {
+		return Err(invalid("too many partitions"));
+	}
+	let end = BSD_PARTITIONS_OFFSET + usize::from(count) * BSD_PARTITION_SIZE;
+	if checksum(fields.slice(0, end)?, u16::from_le_bytes) != 0 {
+		return Err(invalid("checksum mismatch"));
+	}
+
+	// OpenBSD keeps the high bits of sizes and offsets in what used to be
+	// spare fields
+	let openbsd = fields.u16(114)? == 1;
+	let mut raw = vec![];
+	for idx in 0..usize::from(count) {
+		let offset = BSD_PARTITIONS_OFFSET + idx * BSD_PARTITION_SIZE;
+		let mut size = u64::from(fields.u32(offset)?);
+		let mut start = u64::from(fields.u32(offset + 4)?);
+		if openbsd {
+			start |= u64::from(fields.u16(offset + 8)?) << 32;
+			size |= u64::from(fields.u16(offset + 10)?) << 32;
+		}
+		raw.push((fields.slice(offset + 12, 1)?[0], start, size));
+	}
+
+	// OpenBSD and NetBSD count from the start of the disk and FreeBSD from
+	// the start of the slice. A raw partition starting where the slice
+	// does gives the former away
+	let absolute = openbsd
+		|| (first_lba != 0
+			&& raw.get(BSD_RAW_PARTITION).map(|&(_, start, _)| start)
+				== Some(first_lba));
+	let base = if absolute { 0 } else { first_lba };
+
+	let partitions = raw
+		.into_iter()
+		.enumerate()
+		.filter(|&(_, (_, _, size))| size != 0)
+		.map(|(idx, (fstype, start, size))| LabelPartition {
+			name: char::from(b'a' + idx as u8).to_string(),
+			kind: BSD_FSTYPES.get(usize::from(fstype)).map_or_else(
+				|| format!("type {}", fstype),
+				|name| name.to_string(),
+			),
+			first_lba: base.saturating_add(start),
+			sectors: size,
+		})
+		.collect();
+
+	Ok(Some(Disklabel {
+		kind: if openbsd {
+			LabelKind::OpenBsd
+		} else {
+			LabelKind::Bsd
+		},
+		partitions,
+	}))
+}
+
+/// The name of a VTOC tag, or its number if it's not a known one.
+fn vtoc_tag(tag: u16) -> String {
+	VTOC_TAGS
+		.get(usize::from(tag))
+		.map_or_else(|| format!("tag {:#x}", tag), |name| name.to_string())
+}
+
+fn read_solaris_x86(
+	sector: &[u8],
+	first_lba: u64,
+) -> Result<Option<Disklabel>> {
+	let fields = Fields::new(sector, "Solaris x86 VTOC", 512)?;
+	if fields.u16(508)? != VTOC_MAGIC || fields.u32(12)? != VTOC_SANITY {
+		return Ok(None);
+	}
+	let invalid = |reason| Error::InvalidLabel("Solaris x86 VTOC", reason);
+
+	if checksum(sector, u16::from_le_bytes) != 0 {
+		return Err(invalid("checksum mismatch"));
+	}
+	let count = fields.u16(30)?;
+	if count > SOLARIS_MAX_PARTITIONS {
+		return Err(invalid("too many slices"));
+	}
+
+	let mut partitions = vec![];
+	for idx in 0..usize::from(count) {
+		let offset = 72 + idx * 12;
+		let sectors = u64::from(fields.u32(offset + 8)?);
+		if sectors == 0 {
+			continue;
+		}
+		partitions.push(LabelPartition {
+			name: format!("s{}", idx),
+			kind: vtoc_tag(fields.u16(offset)?),
+			first_lba: first_lba
+				.saturating_add(u64::from(fields.u32(offset + 4)?)),
+			sectors,
+		});
+	}
+
+	Ok(Some(Disklabel {
+		kind: LabelKind::SolarisX86,
+		partitions,
+	}))
+}
+
+fn read_sun(sector: &[u8], first_lba: u64) -> Result<Option<Disklabel>> {
+	let fields = Fields::new(sector, "Sun VTOC", 512)?;
+	if fields.u16_be(508)? != VTOC_MAGIC {
+		return Ok(None);
+	}
+	if checksum(sector, u16::from_be_bytes) != 0 {
+		return Err(Error::InvalidLabel("Sun VTOC", "checksum mismatch"));
+	}
+
+	// Slices start on a cylinder boundary
+	let cylinder =
+		u64::from(fields.u16_be(436)?) * u64::from(fields.u16_be(438)?);
+	// Tags live in the VTOC extension, which older labels go without
+	let tagged = fields.u32_be(188)? == VTOC_SANITY;
+
+	let mut partitions = vec![];
+	for idx in 0..SUN_PARTITIONS {
+		let offset = 444 + idx * 8;
+		let sectors = u64::from(fields.u32_be(offset + 4)?);
+		if sectors == 0 {
+			continue;
+		}
+		let start = u64::from(fields.u32_be(offset)?) * cylinder;
+		partitions.push(LabelPartition {
+			name: format!("s{}", idx),
+			kind: if tagged {
+				vtoc_tag(fields.u16_be(142 + idx * 4)?)
+			} else {
+				"unknown".to_string()
+			},
+			first_lba: first_lba.saturating_add(start),
+			sectors,
+		});
+	}
+
+	Ok(Some(Disklabel {
+		kind: LabelKind::Sun,
+		partitions,
+	}))
+}
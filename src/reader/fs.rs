@@ -0,0 +1,152 @@
+use super::{bytes, md, source::BlockSource, zfs};
+
+// Enough of the start of a partition to hold every signature probed here:
+// btrfs puts its superblock at 64K
+const PROBE_SIZE: usize = 68 * 1024;
+const EXT_SUPERBLOCK: usize = 1024;
+const EXT_MAGIC: u16 = 0xEF53;
+const EXT_COMPAT_JOURNAL: u32 = 0x4;
+// Extents, 64-bit block numbers and flexible block groups
+const EXT4_INCOMPAT: u32 = 0x40 | 0x80 | 0x200;
+const BTRFS_SUPERBLOCK: usize = 64 * 1024;
+const F2FS_MAGIC: u32 = 0xF2F5_2010;
+const ISO9660_DESCRIPTOR: usize = 32 * 1024;
+// Swap ends its first page with its signature, whatever the page size
+const SWAP_PAGE_SIZES: [usize; 4] = [4096, 8192, 16384, 65536];
+const FAT_NO_NAME: &str = "NO NAME";
+// LVM labels are in one of the first four sectors
+const LVM_LABEL_SECTORS: usize = 4;
+
+/// What a partition is formatted with, in the names blkid gives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filesystem {
+	/// Such as `ext4`, `vfat` or `crypto_LUKS`
+	pub kind: &'static str,
+	/// Empty where there is none, or the format has no label this reads
+	pub label: String,
+}
+
+impl Filesystem {
+	fn new(kind: &'static str, label: String) -> Filesystem {
+		Filesystem { kind, label }
+	}
+}
+
+/// Recognizes the filesystem or volume metadata in the `size` bytes from
+/// byte `offset` of `src`. Returns `None` if there's nothing known there.
+pub fn probe(
+	src: &mut dyn BlockSource,
+	offset: u64,
+	size: u64,
+) -> Option<Filesystem> {
+	// Arrays with their superblock at the end hold a filesystem at the
+	// start, so they go first, as in blkid
+	if let Ok(md::Probe::Found(superblock)) = md::read(src, offset, size) {
+		return Some(Filesystem::new("linux_raid_member", superblock.name));
+	}
+
+	let len = size.min(PROBE_SIZE as u64) as usize;
+	let mut raw = vec![0u8; len];
+	if src.read_at(offset, &mut raw).is_err() {
+		return None;
+	}
+	probe_start(&raw).or_else(|| {
+		let vdev = zfs::read(src, offset, size).ok()??;
+		let pool = vdev.freshest()?.pool.clone();
+		Some(Filesystem::new("zfs_member", pool.unwrap_or_default()))
+	})
+}
+
+/// Looks for the signatures found at the start of a partition.
+fn probe_start(raw: &[u8]) -> Option<Filesystem> {
+	let at = |offset: usize, len: usize| raw.get(offset..offset + len);
+	let u16_at =
+		|offset: usize| at(offset, 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+	let u32_at = |offset: usize| {
+		at(offset, 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+	};
+	let label = |offset: usize, len: usize| {
+		at(offset, len).map(bytes::c_string).unwrap_or_default()
+	};
+
+	if at(0, 6) == Some(b"LUKS\xBA\xBE") {
+		// Only LUKS2 has a label
+		let label = if at(6, 2) == Some(&[0, 2]) {
+			label(24, 48)
+		} else {
+			String::new()
+		};
+		return Some(Filesystem::new("crypto_LUKS", label));
+	}
+	if (0..LVM_LABEL_SECTORS).any(|sector| {
+		at(sector * 512, 8) == Some(b"LABELONE")
+			&& at(sector * 512 + 24, 8) == Some(b"LVM2 001")
+	}) {
+		return Some(Filesystem::new("LVM2_member", String::new()));
+	}
+	if at(0, 4) == Some(b"XFSB") {
+		return Some(Filesystem::new("xfs", label(108, 12)));
+	}
+	if u16_at(EXT_SUPERBLOCK + 56) == Some(EXT_MAGIC) {
+		let compat = u32_at(EXT_SUPERBLOCK + 92).unwrap_or(0);
+		let incompat = u32_at(EXT_SUPERBLOCK + 96).unwrap_or(0);
+		let kind = if incompat & EXT4_INCOMPAT != 0 {
+			"ext4"
+		} else if compat & EXT_COMPAT_JOURNAL != 0 {
+			"ext3"
+		} else {
+			"ext2"
+		};
+		return Some(Filesystem::new(kind, label(EXT_SUPERBLOCK + 120, 16)));
+	}
+	if at(BTRFS_SUPERBLOCK + 64, 8) == Some(b"_BHRfS_M") {
+		return Some(Filesystem::new(
+			"btrfs",
+			label(BTRFS_SUPERBLOCK + 299, 256),
+		));
+	}
+	for &page in SWAP_PAGE_SIZES.iter() {
+		match at(page - 10, 10) {
+			Some(b"SWAPSPACE2") => {
+				return Some(Filesystem::new("swap", label(1024 + 28, 16)));
+			}
+			Some(b"SWAP-SPACE") => {
+				return Some(Filesystem::new("swap", String::new()));
+			}
+			_ => {}
+		}
+	}
+	if u32_at(1024) == Some(F2FS_MAGIC) {
+		return Some(Filesystem::new("f2fs", String::new()));
+	}
+	if at(0, 4) == Some(b"hsqs") {
+		return Some(Filesystem::new("squashfs", String::new()));
+	}
+	if at(ISO9660_DESCRIPTOR + 1, 5) == Some(b"CD001") {
+		let label = label(ISO9660_DESCRIPTOR + 40, 32);
+		return Some(Filesystem::new("iso9660", label.trim_end().to_string()));
+	}
+	if at(3, 8) == Some(b"NTFS    ") {
+		return Some(Filesystem::new("ntfs", String::new()));
+	}
+	if at(3, 8) == Some(b"EXFAT   ") {
+		return Some(Filesystem::new("exfat", String::new()));
+	}
+	if at(510, 2) == Some(&[0x55, 0xAA]) {
+		// FAT32 moved the volume label to make room for its own fields
+		let label_at = if at(82, 5) == Some(b"FAT32") {
+			Some(71)
+		} else if at(54, 3) == Some(b"FAT") {
+			Some(43)
+		} else {
+			None
+		};
+		if let Some(label_at) = label_at {
+			let label = label(label_at, 11);
+			let label = label.trim_end();
+			let label = if label == FAT_NO_NAME { "" } else { label };
+			return Some(Filesystem::new("vfat", label.to_string()));
+		}
+	}
+	None
+}
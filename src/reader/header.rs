@@ -1,16 +1,48 @@
-#[repr(C)]
-#[derive(Debug)]
-pub(crate) struct HeaderEntry {
-	pub(crate) signature: [u8; 8],
-	// stuff we dont need
-	_offset: [u8; 32],
-	// first/last usable lbas
-	pub(crate) first_lba: [u8; 8],
-	pub(crate) last_lba: [u8; 8],
-	// 5-part disk guid??? idek its like weird or smthn
-	pub(crate) kind_p1: [u8; 4],
-	pub(crate) kind_p2: [u8; 2],
-	pub(crate) kind_p3: [u8; 2],
-	pub(crate) kind_p4: [u8; 2],
-	pub(crate) kind_p5: [u8; 6],
+use super::{bytes::Fields, GptHeader};
+use crate::Result;
+
+const SIGNATURE: &[u8] = b"EFI PART";
+// Bytes covered by the fields below; the rest of the sector is reserved
+pub(crate) const MIN_HEADER_SIZE: usize = 92;
+// Offset of the header CRC, which is zeroed while computing it
+const HEADER_CRC_OFFSET: usize = 16;
+
+/// Decodes a GPT header from the start of `bytes`, without validating it.
+/// Returns `Ok(None)` when the signature doesn't match.
+pub fn parse(bytes: &[u8]) -> Result<Option<GptHeader>> {
+	let fields = Fields::new(bytes, "GPT header", MIN_HEADER_SIZE)?;
+	if fields.slice(0, 8)? != SIGNATURE {
+		return Ok(None);
+	}
+
+	Ok(Some(GptHeader {
+		revision: fields.u32(8)?,
+		header_size: fields.u32(12)?,
+		header_crc32: fields.u32(HEADER_CRC_OFFSET)?,
+		// 4 reserved bytes
+		current_lba: fields.u64(24)?,
+		backup_lba: fields.u64(32)?,
+		first_usable_lba: fields.u64(40)?,
+		last_usable_lba: fields.u64(48)?,
+		disk_guid: fields.guid(56)?,
+		partition_entry_lba: fields.u64(72)?,
+		num_partition_entries: fields.u32(80)?,
+		partition_entry_size: fields.u32(84)?,
+		partition_entries_crc32: fields.u32(88)?,
+	}))
+}
+
+/// CRC32 of the first `header_size` bytes with the CRC field zeroed, or
+/// `None` if `bytes` is too short to hold them.
+pub(crate) fn checksum(bytes: &[u8], header_size: usize) -> Option<u32> {
+	if header_size < MIN_HEADER_SIZE {
+		return None;
+	}
+	let header = bytes.get(..header_size)?;
+
+	let mut hasher = crc32fast::Hasher::new();
+	hasher.update(&header[..HEADER_CRC_OFFSET]);
+	hasher.update(&[0u8; 4]);
+	hasher.update(&header[HEADER_CRC_OFFSET + 4..]);
+	Some(hasher.finalize())
 }
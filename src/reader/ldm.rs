@@ -0,0 +1,483 @@
+use super::{
+	bytes::{self, Fields},
+	mbr,
+	source::BlockSource,
+	PartitionTable,
+};
+use crate::{guid::Guid, Error, Result};
+use std::collections::BTreeMap;
+
+const PRIVHEAD_MAGIC: &[u8] = b"PRIVHEAD";
+const TOCBLOCK_MAGIC: &[u8] = b"TOCBLOCK";
+const VMDB_MAGIC: &[u8] = b"VMDB";
+const VBLK_MAGIC: &[u8] = b"VBLK";
+// The private header of an MBR disk sits in this sector
+const MBR_PRIVHEAD_LBA: u64 = 6;
+const MBR_DYNAMIC: u8 = 0x42;
+const GPT_METADATA: Guid =
+	Guid::from_groups(0x5808C8AA, 0x7E8F, 0x42E0, 0x85D2, 0xE1E90434CFB3);
+// The database is 1MiB; anything far larger isn't one
+const MAX_DATABASE: u64 = 16 * 1024 * 1024;
+// Records split across more VBLKs than this are dropped
+const MAX_FRAGMENTS: u16 = 64;
+const VBLK_HEADER_SIZE: usize = 16;
+
+// Record types, with the revision in the high nibble
+const VBLK_COMPONENT: u8 = 0x32;
+const VBLK_PARTITION: u8 = 0x33;
+const VBLK_DISK: u8 = 0x34;
+const VBLK_DISK4: u8 = 0x44;
+const VBLK_VOLUME: u8 = 0x51;
+// Record flags that add optional fields
+const FLAG_COMPONENT_STRIPE: u8 = 0x10;
+
+/// The Logical Disk Manager database of a Windows dynamic disk. Every disk
+/// of a disk group carries a copy describing all of them.
+#[derive(Debug, Clone)]
+pub struct Ldm {
+	/// Version of the private header: 2.11 up to Windows XP, 2.12 after
+	pub version: (u16, u16),
+	pub disk_group: String,
+	/// GUID of the disk the database was read from
+	pub disk_guid: String,
+	/// Size of the sectors everything is counted in
+	pub sector_size: u64,
+	/// First sector of the area extents are counted from
+	pub data_start: u64,
+	pub data_sectors: u64,
+	pub disks: Vec<LdmDisk>,
+	pub volumes: Vec<Volume>,
+}
+
+/// A member disk of the group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LdmDisk {
+	pub id: u64,
+	pub name: String,
+	pub guid: String,
+}
+
+/// A dynamic volume: one or more components holding the same data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Volume {
+	pub id: u64,
+	pub name: String,
+	pub sectors: u64,
+	/// More than one for mirrors
+	pub components: Vec<Component>,
+}
+
+/// A copy of a volume's data, laid out over one or more extents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+	pub id: u64,
+	pub name: String,
+	pub layout: Layout,
+	/// Sectors per stripe of striped and RAID-5 components
+	pub stripe_sectors: u64,
+	pub extents: Vec<Extent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+	/// Extents follow one another
+	Concatenated,
+	Striped,
+	Raid5,
+	Unknown(u8),
+}
+
+/// A run of sectors on one disk of the group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extent {
+	pub id: u64,
+	pub name: String,
+	/// The [`LdmDisk`] it's on
+	pub disk: u64,
+	/// Counted from the disk's [`data_start`](Ldm::data_start)
+	pub start: u64,
+	/// Where it goes in the component
+	pub volume_offset: u64,
+	pub sectors: u64,
+}
+
+impl Ldm {
+	/// The disk the database was read from, if it lists it.
+	pub fn this_disk(&self) -> Option<&LdmDisk> {
+		self.disks
+			.iter()
+			.find(|disk| disk.guid.eq_ignore_ascii_case(&self.disk_guid))
+	}
+
+	pub fn disk_name(&self, id: u64) -> Option<&str> {
+		self.disks
+			.iter()
+			.find(|disk| disk.id == id)
+			.map(|disk| disk.name.as_str())
+	}
+}
+
+impl Volume {
+	/// The volume type as Disk Management names it.
+	pub fn layout(&self) -> &'static str {
+		match self.components.as_slice() {
+			[component] => match component.layout {
+				Layout::Concatenated if component.extents.len() > 1 => {
+					"spanned"
+				}
+				Layout::Concatenated => "simple",
+				Layout::Striped => "striped",
+				Layout::Raid5 => "RAID-5",
+				Layout::Unknown(_) => "unknown",
+			},
+			[] => "empty",
+			_ => "mirror",
+		}
+	}
+}
+
+/// Reads the LDM database of a disk with a Windows dynamic partition (MBR
+/// type 0x42) or an LDM metadata partition. Returns `None` for other
+/// disks.
+pub fn read(
+	src: &mut dyn BlockSource,
+	table: &PartitionTable,
+) -> Result<Option<Ldm>> {
+	let (privhead, sector_size) = match table {
+		PartitionTable::Gpt(disk) => {
+			// At the end of the metadata partition
+			match disk
+				.entries
+				.iter()
+				.find(|entry| entry.type_guid == GPT_METADATA)
+			{
+				Some(entry) => (entry.last_lba, disk.sector_size),
+				None => return Ok(None),
+			}
+		}
+		PartitionTable::Mbr(table) => {
+			if !table.entries.iter().any(|entry| entry.kind == MBR_DYNAMIC) {
+				return Ok(None);
+			}
+			(MBR_PRIVHEAD_LBA, mbr::SECTOR_SIZE)
+		}
+		PartitionTable::Apm(_) => return Ok(None),
+	};
+	read_at(src, privhead, sector_size).map(Some)
+}
+
+/// Reads the database whose private header is in sector `privhead`.
+pub fn read_at(
+	src: &mut dyn BlockSource,
+	privhead: u64,
+	sector_size: u64,
+) -> Result<Ldm> {
+	let invalid = Error::InvalidLdm;
+
+	let sector = read_bytes(src, privhead, sector_size, 512)
+		.ok_or(invalid("private header out of bounds"))?;
+	let head = Fields::new(&sector, "LDM private header", 512)?;
+	if head.slice(0, 8)? != PRIVHEAD_MAGIC {
+		return Err(invalid("no private header"));
+	}
+	let version = (head.u16_be(0x0C)?, head.u16_be(0x0E)?);
+	if version.0 != 2 {
+		return Err(invalid("unsupported version"));
+	}
+	let disk_guid = bytes::c_string(head.slice(0x30, 64)?);
+	let disk_group = bytes::c_string(head.slice(0xF0, 32)?);
+	let data_start = head.u64_be(0x11B)?;
+	let data_sectors = head.u64_be(0x123)?;
+	let config_start = head.u64_be(0x12B)?;
+	let config_sectors = head.u64_be(0x133)?;
+	if config_sectors.saturating_mul(sector_size) > MAX_DATABASE {
+		return Err(invalid("database too large"));
+	}
+
+	// The first table of contents names where the VBLKs are
+	let toc = read_bytes(src, config_start.saturating_add(1), sector_size, 512)
+		.ok_or(invalid("table of contents out of bounds"))?;
+	let toc = Fields::new(&toc, "LDM table of contents", 512)?;
+	if toc.slice(0, 8)? != TOCBLOCK_MAGIC {
+		return Err(invalid("no table of contents"));
+	}
+	if bytes::c_string(toc.slice(0x24, 8)?) != "config" {
+		return Err(invalid("no config bitmap"));
+	}
+	let vmdb_lba = toc.u64_be(0x2E)?;
+	if vmdb_lba >= config_sectors {
+		return Err(invalid("VMDB out of bounds"));
+	}
+
+	// The VMDB header and its VBLKs fill the rest of the config area
+	let len = (config_sectors - vmdb_lba) * sector_size;
+	let vmdb = read_bytes(
+		src,
+		config_start.saturating_add(vmdb_lba),
+		sector_size,
+		len as usize,
+	)
+	.ok_or(invalid("VMDB out of bounds"))?;
+	let header = Fields::new(&vmdb, "VMDB", 0x16)?;
+	if header.slice(0, 4)? != VMDB_MAGIC {
+		return Err(invalid("no VMDB"));
+	}
+	let count = header.u32_be(4)? as usize;
+	let size = header.u32_be(8)? as usize;
+	let first = header.u32_be(0x0C)? as usize;
+	if size <= VBLK_HEADER_SIZE
+		|| count.checked_mul(size).map_or(true, |end| end > vmdb.len())
+	{
+		return Err(invalid("VBLK area out of bounds"));
+	}
+
+	let mut records = vec![];
+	let mut fragments: BTreeMap<u32, Vec<Option<&[u8]>>> = BTreeMap::new();
+	for idx in first / size..count {
+		let vblk = &vmdb[idx * size..(idx + 1) * size];
+		let fields = Fields::new(vblk, "VBLK", VBLK_HEADER_SIZE)?;
+		if fields.slice(0, 4)? != VBLK_MAGIC {
+			return Err(invalid("VBLK signature mismatch"));
+		}
+		match fields.u16_be(0x0E)? {
+			// Unused
+			0 => {}
+			1 => records.push(vblk.to_vec()),
+			total if total > MAX_FRAGMENTS => {}
+			total => {
+				let parts = fragments
+					.entry(fields.u32_be(8)?)
+					.or_insert_with(|| vec![None; usize::from(total)]);
+				let number = usize::from(fields.u16_be(0x0C)?);
+				if let Some(part) = parts.get_mut(number) {
+					*part = Some(vblk);
+				}
+			}
+		}
+	}
+	// Records too long for one VBLK continue after the header of the next
+	for parts in fragments.values() {
+		if let Some(parts) = parts.iter().copied().collect::<Option<Vec<_>>>() {
+			let mut record = parts[0].to_vec();
+			for part in &parts[1..] {
+				record.extend(&part[VBLK_HEADER_SIZE..]);
+			}
+			records.push(record);
+		}
+	}
+
+	let mut database = Database::default();
+	for record in records.iter() {
+		// A record this version can't decode leaves the rest usable
+		let _ = database.add(record);
+	}
+
+	let disks = std::mem::take(&mut database.disks);
+	Ok(Ldm {
+		version,
+		disk_group,
+		disk_guid,
+		sector_size,
+		data_start,
+		data_sectors,
+		disks,
+		volumes: database.assemble(),
+	})
+}
+
+/// `len` bytes from the start of sector `lba`, or `None` if they're out of
+/// range.
+fn read_bytes(
+	src: &mut dyn BlockSource,
+	lba: u64,
+	sector_size: u64,
+	len: usize,
+) -> Option<Vec<u8>> {
+	let mut buf = vec![0u8; len];
+	src.read_at(lba.checked_mul(sector_size)?, &mut buf).ok()?;
+	Some(buf)
+}
+
+/// Records before they're linked up by their parent IDs.
+#[derive(Default)]
+struct Database {
+	disks: Vec<LdmDisk>,
+	volumes: Vec<Volume>,
+	/// With the ID of their volume
+	components: Vec<(u64, Component)>,
+	/// With the ID of their component
+	extents: Vec<(u64, Extent)>,
+}
+
+impl Database {
+	/// Decodes one record. Fields are mostly variable-length, so each is
+	/// found by skipping the ones before it.
+	fn add(&mut self, record: &[u8]) -> Result<()> {
+		let fields = Fields::new(record, "VBLK record", 0x18)?;
+		let flags = fields.slice(0x12, 1)?[0];
+		let kind = fields.slice(0x13, 1)?[0];
+
+		let mut vars = Vars::new(record, 0x18);
+		let id = vars.number()?;
+		let name = vars.string()?;
+
+		match kind {
+			VBLK_VOLUME => {
+				vars.string()?; // type: "gen" or "raid5"
+				vars.string()?; // whether drive letters are disabled
+				vars.skip(14)?; // state: "ACTIVE"
+				vars.skip(7)?; // type again, as a number, and flags
+				vars.number()?; // number of components
+				vars.skip(16)?; // commit IDs
+				let sectors = vars.number()?;
+				self.volumes.push(Volume {
+					id,
+					name,
+					sectors,
+					components: vec![],
+				});
+			}
+			VBLK_COMPONENT => {
+				vars.string()?; // state: "ACTIVE"
+				let layout = match vars.byte()? {
+					1 => Layout::Striped,
+					2 => Layout::Concatenated,
+					3 => Layout::Raid5,
+					other => Layout::Unknown(other),
+				};
+				vars.skip(4)?;
+				vars.number()?; // number of extents
+				vars.skip(16)?; // commit IDs
+				let volume = vars.number()?;
+				let stripe_sectors = if flags & FLAG_COMPONENT_STRIPE != 0 {
+					vars.skip(1)?;
+					vars.number()?
+				} else {
+					0
+				};
+				self.components.push((
+					volume,
+					Component {
+						id,
+						name,
+						layout,
+						stripe_sectors,
+						extents: vec![],
+					},
+				));
+			}
+			VBLK_PARTITION => {
+				vars.skip(12)?; // commit ID
+				let start = vars.u64()?;
+				let volume_offset = vars.u64()?;
+				let sectors = vars.number()?;
+				let component = vars.number()?;
+				let disk = vars.number()?;
+				self.extents.push((
+					component,
+					Extent {
+						id,
+						name,
+						disk,
+						start,
+						volume_offset,
+						sectors,
+					},
+				));
+			}
+			VBLK_DISK => {
+				let guid = vars.string()?;
+				self.disks.push(LdmDisk { id, name, guid });
+			}
+			VBLK_DISK4 => {
+				let raw = vars.take(16)?;
+				let hex: String =
+					raw.iter().map(|byte| format!("{:02x}", byte)).collect();
+				let guid = format!(
+					"{}-{}-{}-{}-{}",
+					&hex[..8],
+					&hex[8..12],
+					&hex[12..16],
+					&hex[16..20],
+					&hex[20..]
+				);
+				self.disks.push(LdmDisk { id, name, guid });
+			}
+			// Disk groups and record types this doesn't list
+			_ => {}
+		}
+		Ok(())
+	}
+
+	/// Volumes with their components in the order they were created (as
+	/// IDs are handed out), and components with their extents in volume
+	/// order.
+	fn assemble(mut self) -> Vec<Volume> {
+		self.volumes.sort_by_key(|volume| volume.id);
+		self.components.sort_by_key(|(_, component)| component.id);
+		self.extents.sort_by_key(|(_, extent)| extent.volume_offset);
+		for (parent, extent) in self.extents {
+			if let Some((_, component)) = self
+				.components
+				.iter_mut()
+				.find(|(_, component)| component.id == parent)
+			{
+				component.extents.push(extent);
+			}
+		}
+		for (parent, component) in self.components {
+			if let Some(volume) =
+				self.volumes.iter_mut().find(|volume| volume.id == parent)
+			{
+				volume.components.push(component);
+			}
+		}
+		self.volumes
+	}
+}
+
+/// Reads a record's fields in order.
+struct Vars<'a> {
+	record: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> Vars<'a> {
+	fn new(record: &'a [u8], pos: usize) -> Vars<'a> { Vars { record, pos } }
+
+	fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+		let bytes =
+			Fields::new(self.record, "VBLK record", 0)?.slice(self.pos, len)?;
+		self.pos += len;
+		Ok(bytes)
+	}
+
+	fn skip(&mut self, len: usize) -> Result<()> { self.take(len).map(drop) }
+
+	fn byte(&mut self) -> Result<u8> { Ok(self.take(1)?[0]) }
+
+	fn u64(&mut self) -> Result<u64> {
+		Ok(self
+			.take(8)?
+			.iter()
+			.fold(0, |n, &byte| n << 8 | u64::from(byte)))
+	}
+
+	/// A big-endian number of up to 8 bytes, after its length.
+	fn number(&mut self) -> Result<u64> {
+		let len = usize::from(self.byte()?);
+		if len > 8 {
+			return Err(Error::InvalidLdm("number too long"));
+		}
+		Ok(self
+			.take(len)?
+			.iter()
+			.fold(0, |n, &byte| n << 8 | u64::from(byte)))
+	}
+
+	/// A string, after its length.
+	fn string(&mut self) -> Result<String> {
+		let len = usize::from(self.byte()?);
+		Ok(bytes::c_string(self.take(len)?))
+	}
+}
@@ -0,0 +1,318 @@
+use super::{
+	bytes::{self, Fields},
+	source::BlockSource,
+};
+use crate::{guid::Guid, Error, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+const MAGIC: &[u8] = b"LUKS\xBA\xBE";
+const SECONDARY_MAGIC: &[u8] = b"SKUL\xBA\xBE";
+// Long enough for the LUKS1 header and the binary part of a LUKS2 one
+const HEADER_SIZE: usize = 4096;
+// LUKS2 headers, with their JSON area, are a power of two in this range
+const MIN_LUKS2_SIZE: u64 = 16 * 1024;
+const MAX_LUKS2_SIZE: u64 = 4 * 1024 * 1024;
+const LUKS1_SLOTS: usize = 8;
+const LUKS2_SLOTS: usize = 32;
+const LUKS1_SLOT_ACTIVE: u32 = 0x00AC_71F3;
+const LUKS1_SLOT_INACTIVE: u32 = 0x0000_DEAD;
+// LUKS1 counts the payload offset in these regardless of the disk
+const LUKS1_SECTOR_SIZE: u64 = 512;
+
+const LUKS_GPT_TYPE: Guid =
+	Guid::from_groups(0xCA7D7CCB, 0x63ED, 0x4C53, 0x861C, 0x1742536059CC);
+const PLAIN_GPT_TYPE: Guid =
+	Guid::from_groups(0x7FFEC5C9, 0x2D00, 0x49B7, 0x8941, 0x3EA10A5586B7);
+const LUKS_MBR_TYPE: u8 = 0xE8;
+
+/// The header of a LUKS volume. Only the parameters are read: salts,
+/// digests and key slot areas are left alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Luks {
+	/// 1 or 2
+	pub version: u16,
+	pub uuid: String,
+	/// Empty unless set, and always on LUKS1, which has no labels
+	pub label: String,
+	/// In dm-crypt's `cipher-mode` form, such as `aes-xts-plain64`
+	pub cipher: String,
+	/// Size of the volume key, or `None` on a LUKS2 volume with no key
+	/// slots left to say
+	pub key_bits: Option<u64>,
+	/// Where the encrypted data starts, in bytes from the start of the
+	/// header
+	pub data_offset: u64,
+	/// How many key slots the format has room for
+	pub slot_count: usize,
+	/// Key slots in use, by number
+	pub keyslots: Vec<Keyslot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keyslot {
+	pub number: u32,
+	/// How the passphrase is turned into a key for the slot
+	pub kdf: Kdf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Kdf {
+	Pbkdf2 {
+		hash: String,
+		iterations: u64,
+	},
+	Argon2 {
+		/// `argon2i` or `argon2id`
+		kind: String,
+		/// Iterations
+		time: u64,
+		/// In KiB
+		memory: u64,
+		/// Threads
+		cpus: u64,
+	},
+	/// Named by LUKS2 metadata, but not known here
+	Unknown(String),
+}
+
+/// Whether GPT partitions of this type should hold an encrypted volume.
+pub fn gpt_type_is_encrypted(type_guid: Guid) -> bool {
+	[LUKS_GPT_TYPE, PLAIN_GPT_TYPE].contains(&type_guid)
+}
+
+/// Whether the encrypted volume of GPT partitions of this type has a LUKS
+/// header, unlike plain dm-crypt ones.
+pub fn gpt_type_has_header(type_guid: Guid) -> bool {
+	type_guid == LUKS_GPT_TYPE
+}
+
+/// Whether MBR partitions of this type hold a LUKS volume.
+pub fn mbr_type_is_encrypted(kind: u8) -> bool { kind == LUKS_MBR_TYPE }
+
+/// Reads the LUKS header at byte `offset` of `src`. Returns `None` if
+/// there is none.
+pub fn read(src: &mut dyn BlockSource, offset: u64) -> Result<Option<Luks>> {
+	let mut raw = [0u8; HEADER_SIZE];
+	if src.read_at(offset, &mut raw).is_err() {
+		return Ok(None);
+	}
+	let header = Fields::new(&raw, "LUKS header", HEADER_SIZE)?;
+	if header.slice(0, 6)? != MAGIC {
+		return Ok(None);
+	}
+
+	match header.u16_be(6)? {
+		1 => read_luks1(&header).map(Some),
+		2 => read_luks2(src, offset).map(Some),
+		_ => Err(Error::InvalidLuks("unsupported version")),
+	}
+}
+
+fn read_luks1(header: &Fields) -> Result<Luks> {
+	let invalid = Error::InvalidLuks;
+
+	let cipher = format!(
+		"{}-{}",
+		bytes::c_string(header.slice(8, 32)?),
+		bytes::c_string(header.slice(40, 32)?)
+	);
+	let hash = bytes::c_string(header.slice(72, 32)?);
+	let data_offset = u64::from(header.u32_be(104)?) * LUKS1_SECTOR_SIZE;
+	let key_bits = u64::from(header.u32_be(108)?) * 8;
+
+	let mut keyslots = vec![];
+	for number in 0..LUKS1_SLOTS {
+		let slot = 208 + number * 48;
+		match header.u32_be(slot)? {
+			LUKS1_SLOT_ACTIVE => keyslots.push(Keyslot {
+				number: number as u32,
+				kdf: Kdf::Pbkdf2 {
+					hash: hash.clone(),
+					iterations: u64::from(header.u32_be(slot + 4)?),
+				},
+			}),
+			LUKS1_SLOT_INACTIVE => {}
+			_ => return Err(invalid("invalid key slot state")),
+		}
+	}
+
+	Ok(Luks {
+		version: 1,
+		uuid: bytes::c_string(header.slice(168, 40)?),
+		label: String::new(),
+		cipher,
+		key_bits: Some(key_bits),
+		data_offset,
+		slot_count: LUKS1_SLOTS,
+		keyslots,
+	})
+}
+
+/// The binary header of a LUKS2 volume, and the JSON metadata after it.
+struct Luks2Header {
+	seqid: u64,
+	size: u64,
+	label: String,
+	uuid: String,
+	json: String,
+}
+
+/// Reads both copies of a LUKS2 header and keeps the newer of those whose
+/// checksum matches, as cryptsetup does.
+fn read_luks2(src: &mut dyn BlockSource, offset: u64) -> Result<Luks> {
+	let primary = read_luks2_header(src, offset, MAGIC);
+	// The secondary copy directly follows the primary. Without a primary
+	// to say how long it is, it's looked for at every offset it can have
+	let sizes: Vec<u64> = match &primary {
+		Ok(primary) => vec![primary.size],
+		Err(_) => (MIN_LUKS2_SIZE.trailing_zeros()
+			..=MAX_LUKS2_SIZE.trailing_zeros())
+			.map(|shift| 1 << shift)
+			.collect(),
+	};
+	let secondary = sizes.into_iter().find_map(|size| {
+		read_luks2_header(src, offset.saturating_add(size), SECONDARY_MAGIC)
+			.ok()
+	});
+	let header = match (primary, secondary) {
+		(Ok(primary), Some(secondary)) if secondary.seqid > primary.seqid => {
+			secondary
+		}
+		(Ok(primary), _) => primary,
+		(Err(_), Some(secondary)) => secondary,
+		(Err(err), None) => return Err(err),
+	};
+
+	let metadata: Metadata = serde_json::from_str(&header.json)
+		.map_err(|_| Error::InvalidLuks("malformed JSON metadata"))?;
+
+	// The first segment is where the data starts
+	let segment = metadata
+		.segments
+		.iter()
+		.filter_map(|(number, segment)| {
+			number.parse::<u32>().ok().map(|number| (number, segment))
+		})
+		.min_by_key(|&(number, _)| number)
+		.map(|(_, segment)| segment)
+		.ok_or(Error::InvalidLuks("no data segment"))?;
+	let data_offset = segment
+		.offset
+		.parse()
+		.map_err(|_| Error::InvalidLuks("invalid segment offset"))?;
+
+	let mut keyslots = vec![];
+	let mut key_bits = None;
+	for (number, slot) in metadata.keyslots {
+		let number = number
+			.parse()
+			.map_err(|_| Error::InvalidLuks("invalid key slot number"))?;
+		key_bits = Some(slot.key_size.saturating_mul(8));
+		let kdf = slot.kdf;
+		let kdf = match kdf.kind.as_str() {
+			"pbkdf2" => Kdf::Pbkdf2 {
+				hash: kdf.hash.unwrap_or_default(),
+				iterations: kdf.iterations.unwrap_or(0),
+			},
+			"argon2i" | "argon2id" => Kdf::Argon2 {
+				time: kdf.time.unwrap_or(0),
+				memory: kdf.memory.unwrap_or(0),
+				cpus: kdf.cpus.unwrap_or(0),
+				kind: kdf.kind,
+			},
+			_ => Kdf::Unknown(kdf.kind),
+		};
+		keyslots.push(Keyslot { number, kdf });
+	}
+	keyslots.sort_by_key(|slot| slot.number);
+
+	Ok(Luks {
+		version: 2,
+		uuid: header.uuid,
+		label: header.label,
+		cipher: segment.encryption.clone(),
+		key_bits,
+		data_offset,
+		slot_count: LUKS2_SLOTS,
+		keyslots,
+	})
+}
+
+fn read_luks2_header(
+	src: &mut dyn BlockSource,
+	offset: u64,
+	magic: &[u8],
+) -> Result<Luks2Header> {
+	let invalid = Error::InvalidLuks;
+
+	let mut raw = [0u8; HEADER_SIZE];
+	if src.read_at(offset, &mut raw).is_err() {
+		return Err(invalid("header out of bounds"));
+	}
+	let header = Fields::new(&raw, "LUKS2 header", HEADER_SIZE)?;
+	if header.slice(0, 6)? != magic || header.u16_be(6)? != 2 {
+		return Err(invalid("no LUKS2 header"));
+	}
+	let size = header.u64_be(8)?;
+	if !size.is_power_of_two()
+		|| !(MIN_LUKS2_SIZE..=MAX_LUKS2_SIZE).contains(&size)
+	{
+		return Err(invalid("invalid header size"));
+	}
+	if bytes::c_string(header.slice(72, 32)?) != "sha256" {
+		return Err(invalid("unsupported checksum algorithm"));
+	}
+
+	// The checksum covers the JSON area too, with its own field zeroed
+	let mut full = vec![0u8; size as usize];
+	if src.read_at(offset, &mut full).is_err() {
+		return Err(invalid("header out of bounds"));
+	}
+	let expected = full[448..480].to_vec();
+	full[448..512].fill(0);
+	if Sha256::digest(&full)[..] != expected[..] {
+		return Err(invalid("header checksum mismatch"));
+	}
+
+	Ok(Luks2Header {
+		seqid: header.u64_be(16)?,
+		size,
+		label: bytes::c_string(header.slice(24, 48)?),
+		uuid: bytes::c_string(header.slice(168, 40)?),
+		json: bytes::c_string(&full[HEADER_SIZE..]),
+	})
+}
+
+/// The parts of the LUKS2 JSON metadata shown here. Everything else,
+/// including digests and salts, is skipped.
+#[derive(Deserialize)]
+struct Metadata {
+	keyslots: BTreeMap<String, JsonKeyslot>,
+	segments: BTreeMap<String, JsonSegment>,
+}
+
+#[derive(Deserialize)]
+struct JsonKeyslot {
+	key_size: u64,
+	kdf: JsonKdf,
+}
+
+#[derive(Deserialize)]
+struct JsonKdf {
+	#[serde(rename = "type")]
+	kind: String,
+	hash: Option<String>,
+	iterations: Option<u64>,
+	time: Option<u64>,
+	memory: Option<u64>,
+	cpus: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct JsonSegment {
+	/// Numbers too large for JSON doubles are kept in strings
+	offset: String,
+	encryption: String,
+}
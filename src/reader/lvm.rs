@@ -0,0 +1,552 @@
+use super::{
+	bytes::{self, Fields},
+	source::BlockSource,
+};
+use crate::{guid::Guid, Error, Result};
+
+const LABEL_MAGIC: &[u8] = b"LABELONE";
+const LABEL_TYPE: &[u8] = b"LVM2 001";
+// The label is in one of the first four sectors
+const LABEL_SECTORS: u64 = 4;
+const SECTOR_SIZE: usize = 512;
+const MDA_MAGIC: &[u8] = b" LVM2 x[5A%r0N*>";
+const MDA_HEADER_SIZE: u64 = 512;
+// Seed of LVM's checksums, which are CRC-32 without the final inversion
+const CRC_INITIAL: u32 = 0xF597_A6CF;
+// Location lists end with an empty entry well before this
+const MAX_LOCATIONS: usize = 16;
+// Metadata is a few KiB of text; anything past this isn't
+const MAX_METADATA: u64 = 16 * 1024 * 1024;
+// Sections go four deep; the limit keeps hostile input off the stack
+const MAX_DEPTH: usize = 16;
+const RAW_LOCN_IGNORED: u32 = 1;
+
+const GPT_TYPE: Guid =
+	Guid::from_groups(0xE6D6D379, 0xF507, 0x44C2, 0xA23C, 0x238F2A3DF928);
+const MBR_TYPE: u8 = 0x8E;
+
+/// An LVM2 physical volume: its label and the volume group metadata it
+/// carries.
+#[derive(Debug, Clone)]
+pub struct PhysicalVolume {
+	/// In LVM's `xxxxxx-xxxx-xxxx-xxxx-xxxx-xxxx-xxxxxx` form
+	pub uuid: String,
+	/// Size recorded when the PV was created, in bytes
+	pub device_size: u64,
+	/// `None` for a PV that isn't in a group, or keeps no metadata
+	pub group: Option<VolumeGroup>,
+}
+
+/// The parts of a volume group that concern one of its PVs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeGroup {
+	pub name: String,
+	pub uuid: String,
+	/// Bumped on every metadata change
+	pub seqno: u64,
+	/// Size of a physical extent, in bytes
+	pub extent_size: u64,
+	/// Where the PV's first extent starts, in bytes from the start of the PV
+	pub pe_start: u64,
+	pub pe_count: u64,
+	/// Logical volumes with at least one segment on the PV
+	pub volumes: Vec<LogicalVolume>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogicalVolume {
+	pub name: String,
+	/// Hidden volumes are the internals of others, such as RAID images
+	pub visible: bool,
+	/// Size in extents, on every PV
+	pub extents: u64,
+	/// The parts of the volume on this PV, in volume order
+	pub segments: Vec<Segment>,
+}
+
+/// A run of extents of a logical volume on this PV.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+	/// `linear`, or the segment type, such as `striped`
+	pub kind: String,
+	/// First extent of the volume the segment holds
+	pub volume_extent: u64,
+	/// First physical extent it occupies on the PV
+	pub start: u64,
+	/// Extents on this PV, which hold `extents * stripes` of the volume
+	pub extents: u64,
+	/// How many PVs the segment is striped across, 1 if it's linear
+	pub stripes: u64,
+}
+
+impl VolumeGroup {
+	/// The byte range of the PV that physical extents `start..start + len`
+	/// occupy.
+	pub fn extent_bytes(&self, start: u64, len: u64) -> (u64, u64) {
+		let offset = self
+			.pe_start
+			.saturating_add(start.saturating_mul(self.extent_size));
+		(offset, len.saturating_mul(self.extent_size))
+	}
+}
+
+/// Whether GPT partitions of this type hold a PV.
+pub fn gpt_type_holds_pv(type_guid: Guid) -> bool { type_guid == GPT_TYPE }
+
+/// Whether MBR partitions of this type hold a PV.
+pub fn mbr_type_holds_pv(kind: u8) -> bool { kind == MBR_TYPE }
+
+/// LVM's CRC-32 of `bytes`.
+fn checksum(bytes: &[u8]) -> u32 {
+	let mut hasher = crc32fast::Hasher::new_with_initial(!CRC_INITIAL);
+	hasher.update(bytes);
+	!hasher.finalize()
+}
+
+/// Reads the physical volume starting at byte `offset` of `src`. Returns
+/// `None` if it has no LVM2 label.
+pub fn read(
+	src: &mut dyn BlockSource,
+	offset: u64,
+) -> Result<Option<PhysicalVolume>> {
+	let invalid = Error::InvalidLvm;
+
+	let mut sector = [0u8; SECTOR_SIZE];
+	let mut found = false;
+	for number in 0..LABEL_SECTORS {
+		let at = offset.saturating_add(number * SECTOR_SIZE as u64);
+		if src.read_at(at, &mut sector).is_err() {
+			return Ok(None);
+		}
+		let fields = Fields::new(&sector, "LVM label", SECTOR_SIZE)?;
+		if fields.slice(0, 8)? == LABEL_MAGIC && fields.u64(8)? == number {
+			found = true;
+			break;
+		}
+	}
+	if !found {
+		return Ok(None);
+	}
+
+	let label = Fields::new(&sector, "LVM label", SECTOR_SIZE)?;
+	if label.slice(24, 8)? != LABEL_TYPE {
+		return Err(invalid("unsupported label type"));
+	}
+	if checksum(label.slice(20, SECTOR_SIZE - 20)?) != label.u32(16)? {
+		return Err(invalid("label checksum mismatch"));
+	}
+
+	// The PV header follows the label header in the same sector
+	let header = label.u32(20)? as usize;
+	let uuid = bytes::c_string(label.slice(header, 32)?);
+	let device_size = label.u64(header + 32)?;
+
+	// Data areas, then metadata areas, each list ending with an empty entry
+	let mut lists: [Vec<(u64, u64)>; 2] = [vec![], vec![]];
+	let mut pos = header + 40;
+	for list in lists.iter_mut() {
+		loop {
+			let (start, size) = (label.u64(pos)?, label.u64(pos + 8)?);
+			pos += 16;
+			if start == 0 {
+				break;
+			}
+			if list.len() == MAX_LOCATIONS {
+				return Err(invalid("too many areas"));
+			}
+			list.push((start, size));
+		}
+	}
+
+	let mut group = None;
+	for &(start, size) in lists[1].iter() {
+		if let Some(text) =
+			read_metadata(src, offset.saturating_add(start), size)?
+		{
+			group = Some(parse_group(&text, &uuid)?);
+			break;
+		}
+	}
+
+	Ok(Some(PhysicalVolume {
+		uuid: format_uuid(&uuid),
+		device_size,
+		group,
+	}))
+}
+
+/// Inserts dashes into a 32-character LVM UUID.
+fn format_uuid(raw: &str) -> String {
+	if raw.len() != 32 || !raw.is_ascii() {
+		return raw.to_string();
+	}
+	let mut out = String::new();
+	let mut pos = 0;
+	for &len in [6, 4, 4, 4, 4, 4, 6].iter() {
+		if pos > 0 {
+			out.push('-');
+		}
+		out.push_str(&raw[pos..pos + len]);
+		pos += len;
+	}
+	out
+}
+
+/// The current metadata text of the metadata area at byte `start`, or
+/// `None` if the area holds none.
+fn read_metadata(
+	src: &mut dyn BlockSource,
+	start: u64,
+	size: u64,
+) -> Result<Option<String>> {
+	let invalid = Error::InvalidLvm;
+
+	let mut raw = [0u8; MDA_HEADER_SIZE as usize];
+	if src.read_at(start, &mut raw).is_err() {
+		return Err(invalid("metadata area out of bounds"));
+	}
+	let header = Fields::new(&raw, "LVM metadata area header", raw.len())?;
+	if header.slice(4, 16)? != MDA_MAGIC {
+		return Err(invalid("no metadata area header"));
+	}
+	if checksum(header.slice(4, raw.len() - 4)?) != header.u32(0)? {
+		return Err(invalid("metadata area checksum mismatch"));
+	}
+
+	// The first location is the committed metadata
+	let (offset, len) = (header.u64(40)?, header.u64(48)?);
+	let flags = header.u32(60)?;
+	if offset == 0 || flags & RAW_LOCN_IGNORED != 0 {
+		return Ok(None);
+	}
+	if len > MAX_METADATA
+		|| size <= MDA_HEADER_SIZE
+		|| offset < MDA_HEADER_SIZE
+		|| offset >= size
+	{
+		return Err(invalid("metadata out of bounds"));
+	}
+
+	// The area is a ring buffer after its header; text reaching its end
+	// carries on from the start
+	let mut text = vec![0u8; len as usize];
+	let first = len.min(size - offset) as usize;
+	let read = src
+		.read_at(start + offset, &mut text[..first])
+		.and_then(|()| {
+			src.read_at(start + MDA_HEADER_SIZE, &mut text[first..])
+		});
+	if read.is_err() {
+		return Err(invalid("metadata out of bounds"));
+	}
+	if checksum(&text) != header.u32(56)? {
+		return Err(invalid("metadata checksum mismatch"));
+	}
+	Ok(Some(bytes::c_string(&text)))
+}
+
+/// The group described by metadata `text`, as seen from the PV with raw
+/// UUID `pv_uuid`.
+fn parse_group(text: &str, pv_uuid: &str) -> Result<VolumeGroup> {
+	let invalid = Error::InvalidLvm;
+
+	let top = Parser::new(text).parse()?;
+	// The group is the only section at the top level
+	let (name, group) = top
+		.iter()
+		.find_map(|(key, value)| match value {
+			Value::Section(section) => Some((key, section)),
+			_ => None,
+		})
+		.ok_or(invalid("metadata names no volume group"))?;
+
+	let extent_size = number(group, "extent_size")?.saturating_mul(512);
+	let pvs = section(group, "physical_volumes")?;
+	let (pv_name, pv) = pvs
+		.iter()
+		.find_map(|(key, value)| match value {
+			Value::Section(pv)
+				if string(pv, "id")
+					.is_ok_and(|id| id.replace('-', "") == pv_uuid) =>
+			{
+				Some((key.as_str(), pv))
+			}
+			_ => None,
+		})
+		.ok_or(invalid("metadata doesn't list this PV"))?;
+
+	let mut volumes = vec![];
+	let lvs = match get(group, "logical_volumes") {
+		Some(Value::Section(lvs)) => lvs.as_slice(),
+		_ => &[],
+	};
+	for (lv_name, lv) in lvs.iter() {
+		let lv = match lv {
+			Value::Section(lv) => lv,
+			_ => continue,
+		};
+		let visible = match get(lv, "status") {
+			Some(Value::Array(flags)) => flags
+				.iter()
+				.any(|flag| *flag == Value::String("VISIBLE".into())),
+			_ => false,
+		};
+
+		let mut extents = 0;
+		let mut segments = vec![];
+		for (_, segment) in lv.iter() {
+			let segment = match segment {
+				Value::Section(segment) => segment,
+				_ => continue,
+			};
+			let count = number(segment, "extent_count")?;
+			let volume_extent = number(segment, "start_extent")?;
+			extents = extents.max(volume_extent.saturating_add(count));
+
+			// Only striped segments map straight onto PVs; the others are
+			// made of hidden volumes that do
+			let stripes = match get(segment, "stripes") {
+				Some(Value::Array(stripes)) => stripes,
+				_ => continue,
+			};
+			let stripe_count = (stripes.len() / 2).max(1) as u64;
+			let kind = match string(segment, "type")? {
+				"striped" if stripe_count == 1 => "linear",
+				kind => kind,
+			};
+			for pair in stripes.chunks_exact(2) {
+				match pair {
+					[Value::String(pv), Value::Number(start)]
+						if pv == pv_name =>
+					{
+						segments.push(Segment {
+							kind: kind.to_string(),
+							volume_extent,
+							start: *start,
+							extents: count / stripe_count,
+							stripes: stripe_count,
+						});
+					}
+					_ => {}
+				}
+			}
+		}
+
+		if !segments.is_empty() {
+			volumes.push(LogicalVolume {
+				name: lv_name.clone(),
+				visible,
+				extents,
+				segments,
+			});
+		}
+	}
+
+	Ok(VolumeGroup {
+		name: name.clone(),
+		uuid: string(group, "id")?.to_string(),
+		seqno: number(group, "seqno")?,
+		extent_size,
+		pe_start: number(pv, "pe_start")?.saturating_mul(512),
+		pe_count: number(pv, "pe_count")?,
+		volumes,
+	})
+}
+
+/// A value of LVM's metadata format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+	Number(u64),
+	String(String),
+	Array(Vec<Value>),
+	Section(Vec<(String, Value)>),
+}
+
+fn get<'a>(section: &'a [(String, Value)], key: &str) -> Option<&'a Value> {
+	section
+		.iter()
+		.find(|(name, _)| name == key)
+		.map(|(_, value)| value)
+}
+
+fn section<'a>(
+	parent: &'a [(String, Value)],
+	key: &str,
+) -> Result<&'a [(String, Value)]> {
+	match get(parent, key) {
+		Some(Value::Section(section)) => Ok(section),
+		_ => Err(Error::InvalidLvm("metadata section missing")),
+	}
+}
+
+fn number(section: &[(String, Value)], key: &str) -> Result<u64> {
+	match get(section, key) {
+		Some(Value::Number(value)) => Ok(*value),
+		_ => Err(Error::InvalidLvm("metadata number missing")),
+	}
+}
+
+fn string<'a>(section: &'a [(String, Value)], key: &str) -> Result<&'a str> {
+	match get(section, key) {
+		Some(Value::String(value)) => Ok(value),
+		_ => Err(Error::InvalidLvm("metadata string missing")),
+	}
+}
+
+/// Parses the `key = value` and `name { ... }` text LVM stores metadata
+/// in.
+struct Parser<'a> {
+	text: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn new(text: &'a str) -> Parser<'a> {
+		Parser {
+			text: text.as_bytes(),
+			pos: 0,
+		}
+	}
+
+	fn parse(mut self) -> Result<Vec<(String, Value)>> {
+		let top = self.section(0)?;
+		if self.pos < self.text.len() {
+			return Err(Error::InvalidLvm("unbalanced braces in metadata"));
+		}
+		Ok(top)
+	}
+
+	fn peek(&self) -> Option<u8> { self.text.get(self.pos).copied() }
+
+	/// Skips whitespace and `#` comments.
+	fn skip(&mut self) {
+		while let Some(byte) = self.peek() {
+			match byte {
+				b'#' => {
+					while !matches!(self.peek(), None | Some(b'\n')) {
+						self.pos += 1;
+					}
+				}
+				byte if byte.is_ascii_whitespace() => self.pos += 1,
+				_ => break,
+			}
+		}
+	}
+
+	/// Entries up to a closing brace or the end of the text.
+	fn section(&mut self, depth: usize) -> Result<Vec<(String, Value)>> {
+		if depth > MAX_DEPTH {
+			return Err(Error::InvalidLvm("metadata nested too deeply"));
+		}
+
+		let mut entries = vec![];
+		loop {
+			self.skip();
+			match self.peek() {
+				None | Some(b'}') => return Ok(entries),
+				Some(_) => {}
+			}
+
+			let key = self.word();
+			if key.is_empty() {
+				return Err(Error::InvalidLvm("metadata key expected"));
+			}
+			self.skip();
+			let value = match self.peek() {
+				Some(b'{') => {
+					self.pos += 1;
+					let section = self.section(depth + 1)?;
+					if self.peek() != Some(b'}') {
+						return Err(Error::InvalidLvm(
+							"unbalanced braces in metadata",
+						));
+					}
+					self.pos += 1;
+					Value::Section(section)
+				}
+				Some(b'=') => {
+					self.pos += 1;
+					self.skip();
+					self.value()?
+				}
+				_ => return Err(Error::InvalidLvm("metadata value expected")),
+			};
+			entries.push((key, value));
+		}
+	}
+
+	/// A run of name characters.
+	fn word(&mut self) -> String {
+		let start = self.pos;
+		while let Some(byte) = self.peek() {
+			if !(byte.is_ascii_alphanumeric() || b"_.+-".contains(&byte)) {
+				break;
+			}
+			self.pos += 1;
+		}
+		String::from_utf8_lossy(&self.text[start..self.pos]).into_owned()
+	}
+
+	fn value(&mut self) -> Result<Value> {
+		match self.peek() {
+			Some(b'"') => {
+				self.pos += 1;
+				let mut value = vec![];
+				loop {
+					match self.peek() {
+						None => {
+							return Err(Error::InvalidLvm(
+								"unterminated string in metadata",
+							))
+						}
+						Some(b'"') => break,
+						Some(b'\\') => {
+							self.pos += 1;
+							if let Some(byte) = self.peek() {
+								value.push(byte);
+							}
+						}
+						Some(byte) => value.push(byte),
+					}
+					self.pos += 1;
+				}
+				self.pos += 1;
+				Ok(Value::String(String::from_utf8_lossy(&value).into_owned()))
+			}
+			Some(b'[') => {
+				self.pos += 1;
+				let mut items = vec![];
+				loop {
+					self.skip();
+					match self.peek() {
+						Some(b']') => break,
+						Some(b',') => self.pos += 1,
+						Some(b'[') => {
+							return Err(Error::InvalidLvm(
+								"nested array in metadata",
+							))
+						}
+						Some(_) => items.push(self.value()?),
+						None => {
+							return Err(Error::InvalidLvm(
+								"unterminated array in metadata",
+							))
+						}
+					}
+				}
+				self.pos += 1;
+				Ok(Value::Array(items))
+			}
+			_ => {
+				// Numbers, and the odd float LVM writes; only whole
+				// numbers are used here
+				let word = self.word();
+				match word.parse() {
+					Ok(number) => Ok(Value::Number(number)),
+					Err(_) if !word.is_empty() => Ok(Value::String(word)),
+					Err(_) => Err(Error::InvalidLvm("metadata value expected")),
+				}
+			}
+		}
+	}
+}
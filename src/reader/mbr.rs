@@ -0,0 +1,191 @@
+use super::{bytes::Fields, source::BlockSource};
+use crate::{Error, Result};
+
+/// MBR tables always count in 512-byte sectors here; 4Kn disks with an
+/// MBR are rare enough to leave out
+pub const SECTOR_SIZE: u64 = 512;
+
+const SIGNATURE: [u8; 2] = [0x55, 0xAA];
+const TABLE_OFFSET: usize = 446;
+const ENTRY_SIZE: usize = 16;
+const PROTECTIVE: u8 = 0xEE;
+// Extended boot records chained past this many are taken to be a loop
+const MAX_LOGICAL: u32 = 128;
+
+// The usual partition type IDs, as fdisk names them
+const TYPE_NAMES: [(u8, &str); 28] = [
+	(0x01, "FAT12"),
+	(0x04, "FAT16 <32M"),
+	(0x05, "Extended"),
+	(0x06, "FAT16"),
+	(0x07, "HPFS/NTFS/exFAT"),
+	(0x0B, "W95 FAT32"),
+	(0x0C, "W95 FAT32 (LBA)"),
+	(0x0E, "W95 FAT16 (LBA)"),
+	(0x0F, "W95 Extended (LBA)"),
+	(0x11, "Hidden FAT12"),
+	(0x27, "Hidden NTFS WinRE"),
+	(0x42, "Windows dynamic"),
+	(0x82, "Linux swap / Solaris"),
+	(0x83, "Linux"),
+	(0x85, "Linux extended"),
+	(0x8E, "Linux LVM"),
+	(0xA5, "FreeBSD"),
+	(0xA6, "OpenBSD"),
+	(0xA8, "Darwin UFS"),
+	(0xA9, "NetBSD"),
+	(0xAB, "Darwin boot"),
+	(0xAF, "HFS / HFS+"),
+	(0xBE, "Solaris boot"),
+	(0xBF, "Solaris"),
+	(0xEE, "GPT"),
+	(0xEF, "EFI (FAT-12/16/32)"),
+	(0xFB, "VMware VMFS"),
+	(0xFD, "Linux raid autodetect"),
+];
+
+/// A classic MBR (DOS) partition table.
+#[derive(Debug, Clone)]
+pub struct Mbr {
+	pub sector_size: u64,
+	/// Primary entries, then the logical partitions of the extended one
+	pub entries: Vec<MbrEntry>,
+}
+
+/// A used slot of the MBR, or a logical partition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MbrEntry {
+	/// 1-4 for primary entries and 5 onwards for logical ones, matching
+	/// the kernel's numbering
+	pub number: u32,
+	/// The partition type ID
+	pub kind: u8,
+	pub bootable: bool,
+	pub first_lba: u64,
+	pub sectors: u64,
+}
+
+impl MbrEntry {
+	/// The last sector covered, which is `first_lba` for empty entries.
+	pub fn last_lba(&self) -> u64 {
+		self.first_lba
+			.saturating_add(self.sectors.saturating_sub(1))
+	}
+
+	/// Whether this is a container for logical partitions.
+	pub fn is_extended(&self) -> bool { is_extended(self.kind) }
+
+	/// The name of the partition type, if it's a well-known one.
+	pub fn type_name(&self) -> Option<&'static str> {
+		TYPE_NAMES
+			.iter()
+			.find(|&&(kind, _)| kind == self.kind)
+			.map(|&(_, name)| name)
+	}
+}
+
+fn is_extended(kind: u8) -> bool { matches!(kind, 0x05 | 0x0F | 0x85) }
+
+/// Reads the MBR of `src` and the logical partitions chained from its
+/// extended partition. Fails with [`Error::InvalidSignature`] if there is
+/// no MBR, or only the protective one of a GPT disk.
+pub fn read(src: &mut dyn BlockSource) -> Result<Mbr> {
+	let primary = match read_table(src, 0)? {
+		Some(entries) => entries,
+		None => return Err(Error::InvalidSignature),
+	};
+	if primary.iter().any(|entry| entry.kind == PROTECTIVE) {
+		return Err(Error::InvalidSignature);
+	}
+
+	let mut entries: Vec<_> = primary
+		.into_iter()
+		.filter(|entry| entry.kind != 0)
+		.collect();
+	let extended = entries
+		.iter()
+		.find(|entry| entry.is_extended())
+		.map(|entry| entry.first_lba);
+	if let Some(base) = extended {
+		read_logical(src, base, &mut entries)?;
+	}
+
+	Ok(Mbr {
+		sector_size: SECTOR_SIZE,
+		entries,
+	})
+}
+
+/// Follows the chain of extended boot records starting at `base`. Each
+/// holds one logical partition, counted from the record, and a link to
+/// the next record, counted from `base`. Records without a partition
+/// don't use up a number, as with Linux.
+fn read_logical(
+	src: &mut dyn BlockSource,
+	base: u64,
+	entries: &mut Vec<MbrEntry>,
+) -> Result<()> {
+	let mut ebr = base;
+	let mut number = 5;
+	for _ in 0..MAX_LOGICAL {
+		let mut table = match read_table(src, ebr)? {
+			Some(table) => table,
+			// A broken link ends the chain; what came before still stands
+			None => return Ok(()),
+		};
+
+		let next = &table[1];
+		let next = (next.is_extended() && next.first_lba != 0)
+			.then_some(next.first_lba);
+
+		let logical = &mut table[0];
+		if logical.kind != 0 && logical.sectors != 0 {
+			logical.number = number;
+			logical.first_lba = ebr.saturating_add(logical.first_lba);
+			entries.push(logical.clone());
+			number += 1;
+		}
+
+		match next {
+			Some(next) => ebr = base.saturating_add(next),
+			None => return Ok(()),
+		}
+	}
+	Ok(())
+}
+
+/// The four entries of the table in sector `lba`, numbered by slot and
+/// including empty ones, or `None` if it has no boot signature or its boot
+/// flags aren't valid.
+fn read_table(
+	src: &mut dyn BlockSource,
+	lba: u64,
+) -> Result<Option<Vec<MbrEntry>>> {
+	let mut sector = [0u8; SECTOR_SIZE as usize];
+	let read = lba
+		.checked_mul(SECTOR_SIZE)
+		.map(|offset| src.read_at(offset, &mut sector));
+	if !matches!(read, Some(Ok(()))) || sector[510..] != SIGNATURE {
+		return Ok(None);
+	}
+
+	let fields = Fields::new(&sector, "MBR", SECTOR_SIZE as usize)?;
+	let mut entries = vec![];
+	for idx in 0..4 {
+		let offset = TABLE_OFFSET + idx * ENTRY_SIZE;
+		let entry = fields.slice(offset, ENTRY_SIZE)?;
+		// Boot sectors of filesystems end in the same signature; their
+		// code doesn't leave valid boot flags where the table would be
+		if entry[0] & 0x7F != 0 {
+			return Ok(None);
+		}
+		entries.push(MbrEntry {
+			number: idx as u32 + 1,
+			kind: entry[4],
+			bootable: entry[0] == 0x80,
+			first_lba: u64::from(fields.u32(offset + 8)?),
+			sectors: u64::from(fields.u32(offset + 12)?),
+		});
+	}
+	Ok(Some(entries))
+}
@@ -0,0 +1,269 @@
+use super::{
+	bytes::{self, Fields},
+	source::BlockSource,
+};
+use crate::{guid::Guid, Error, Result};
+use std::{convert::TryInto, fmt};
+
+const MAGIC: u32 = 0xA92B_4EFC;
+const SUPERBLOCK_SIZE: usize = 4096;
+// 0.90 superblocks sit in the last 64K-aligned 64K of the device
+const V0_RESERVED: u64 = 64 * 1024;
+// 1.0 superblocks sit 8K from the end, aligned down to 4K
+const V1_END_OFFSET: u64 = 8 * 1024;
+const V1_ALIGN: u64 = 4 * 1024;
+// Roles in the dev_roles array of 1.x superblocks that aren't a slot
+const ROLE_SPARE: u16 = 0xFFFF;
+const ROLE_FAULTY: u16 = 0xFFFE;
+const ROLE_JOURNAL: u16 = 0xFFFD;
+// Device state bits of 0.90 superblocks
+const V0_DISK_FAULTY: u32 = 1 << 0;
+const V0_DISK_ACTIVE: u32 = 1 << 1;
+const V0_STATE_CLEAN: u32 = 1 << 0;
+
+const GPT_TYPE: Guid =
+	Guid::from_groups(0xA19D880F, 0x05FC, 0x4D3B, 0xA006, 0x743F0F84911E);
+const MBR_TYPE: u8 = 0xFD;
+
+/// The md-raid superblock of an array member.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Superblock {
+	/// `0.90`, `1.0`, `1.1` or `1.2`, which says where it was found
+	pub version: &'static str,
+	/// In mdadm's `xxxxxxxx:xxxxxxxx:xxxxxxxx:xxxxxxxx` form
+	pub uuid: String,
+	/// Such as `host:0`; empty on 0.90, which has no names
+	pub name: String,
+	pub level: Level,
+	pub raid_disks: u32,
+	/// What this member does in the array
+	pub role: Role,
+	/// Bumped on every update; the member with the most is the freshest
+	pub events: u64,
+	/// Whether the array was stopped cleanly, with nothing to resync
+	pub clean: bool,
+}
+
+/// What [`read`] found in a partition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Probe {
+	Found(Superblock),
+	Missing,
+	/// Nothing at the start, and the end, where 1.0 and 0.90 put theirs,
+	/// not read because the disk isn't
+	/// [`seekable`](BlockSource::seekable)
+	Skipped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+	Linear,
+	Multipath,
+	Raid(u32),
+	Unknown(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+	/// Holds this slot of the array
+	Active(u32),
+	Spare,
+	Faulty,
+	/// Holds the write journal
+	Journal,
+}
+
+impl From<i32> for Level {
+	fn from(level: i32) -> Level {
+		match level {
+			-1 => Level::Linear,
+			-4 => Level::Multipath,
+			0..=6 | 10 => Level::Raid(level as u32),
+			_ => Level::Unknown(level),
+		}
+	}
+}
+
+impl fmt::Display for Level {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Level::Linear => write!(f, "linear"),
+			Level::Multipath => write!(f, "multipath"),
+			Level::Raid(level) => write!(f, "raid{}", level),
+			Level::Unknown(level) => write!(f, "level {}", level),
+		}
+	}
+}
+
+impl fmt::Display for Role {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Role::Active(slot) => write!(f, "active {}", slot),
+			Role::Spare => write!(f, "spare"),
+			Role::Faulty => write!(f, "faulty"),
+			Role::Journal => write!(f, "journal"),
+		}
+	}
+}
+
+/// Whether GPT partitions of this type hold an array member.
+pub fn gpt_type_holds_member(type_guid: Guid) -> bool { type_guid == GPT_TYPE }
+
+/// Whether MBR partitions of this type hold an array member.
+pub fn mbr_type_holds_member(kind: u8) -> bool { kind == MBR_TYPE }
+
+/// Looks for a superblock in the `size` bytes from byte `offset` of `src`,
+/// in each place a version puts it.
+pub fn read(
+	src: &mut dyn BlockSource,
+	offset: u64,
+	size: u64,
+) -> Result<Probe> {
+	let v1_end = size.saturating_sub(V1_END_OFFSET) & !(V1_ALIGN - 1);
+	let v0_end = (size & !(V0_RESERVED - 1)).saturating_sub(V0_RESERVED);
+	let places = [
+		("1.1", 0),
+		("1.2", V1_ALIGN),
+		("1.0", v1_end),
+		("0.90", v0_end),
+	];
+
+	for &(version, at) in places.iter() {
+		if at.saturating_add(SUPERBLOCK_SIZE as u64) > size {
+			continue;
+		}
+		// Going back to the start of the next partition from the end of
+		// this one would mean decompressing the image again
+		if matches!(version, "1.0" | "0.90") && !src.seekable() {
+			return Ok(Probe::Skipped);
+		}
+		let mut raw = [0u8; SUPERBLOCK_SIZE];
+		if src.read_at(offset.saturating_add(at), &mut raw).is_err() {
+			continue;
+		}
+		let fields = Fields::new(&raw, "md superblock", SUPERBLOCK_SIZE)?;
+		if fields.u32(0)? != MAGIC {
+			continue;
+		}
+
+		let major = fields.u32(4)?;
+		let superblock = match (version, major) {
+			("0.90", 0) => read_v0(&fields)?,
+			("0.90", _) => continue,
+			(_, 1) => {
+				// Each 1.x version is only valid where it belongs
+				if fields.u64(144)?.checked_mul(512) != Some(at) {
+					continue;
+				}
+				read_v1(&fields, version)?
+			}
+			_ => return Err(Error::InvalidMd("unsupported version")),
+		};
+		return Ok(Probe::Found(superblock));
+	}
+	Ok(Probe::Missing)
+}
+
+/// Adds up the little-endian words of `bytes`, and folds the carry back
+/// in, as md checksums do.
+fn checksum(bytes: &[u8]) -> u32 {
+	let sum: u64 = bytes
+		.chunks_exact(4)
+		.map(|word| u64::from(u32::from_le_bytes(word.try_into().unwrap())))
+		.sum();
+	let tail = bytes.chunks_exact(4).remainder();
+	let sum = match tail {
+		[low, high] => sum + u64::from(u16::from_le_bytes([*low, *high])),
+		_ => sum,
+	};
+	((sum & 0xFFFF_FFFF) + (sum >> 32)) as u32
+}
+
+fn read_v0(fields: &Fields) -> Result<Superblock> {
+	let mut raw = fields.slice(0, SUPERBLOCK_SIZE)?.to_vec();
+	let stored = fields.u32(152)?;
+	raw[152..156].fill(0);
+	if checksum(&raw) != stored {
+		return Err(Error::InvalidMd("checksum mismatch"));
+	}
+
+	// The UUID is split between the first word after the versions and
+	// three later ones
+	let uuid = [20, 52, 56, 60]
+		.iter()
+		.map(|&at| fields.u32(at).map(|word| format!("{:08x}", word)))
+		.collect::<Result<Vec<_>>>()?
+		.join(":");
+
+	// The descriptor of this device closes the superblock
+	let this = SUPERBLOCK_SIZE - 32 * 4;
+	let state = fields.u32(this + 16)?;
+	let role = if state & V0_DISK_FAULTY != 0 {
+		Role::Faulty
+	} else if state & V0_DISK_ACTIVE != 0 {
+		Role::Active(fields.u32(this + 12)?)
+	} else {
+		Role::Spare
+	};
+
+	Ok(Superblock {
+		version: "0.90",
+		uuid,
+		name: String::new(),
+		level: Level::from(fields.u32(28)? as i32),
+		raid_disks: fields.u32(40)?,
+		role,
+		events: (u64::from(fields.u32(160)?) << 32)
+			| u64::from(fields.u32(156)?),
+		clean: fields.u32(132)? & V0_STATE_CLEAN != 0,
+	})
+}
+
+fn read_v1(fields: &Fields, version: &'static str) -> Result<Superblock> {
+	let invalid = Error::InvalidMd;
+
+	let max_dev = fields.u32(220)? as usize;
+	let size = 256 + max_dev * 2;
+	if size > SUPERBLOCK_SIZE {
+		return Err(invalid("too many devices"));
+	}
+	let mut raw = fields.slice(0, size)?.to_vec();
+	raw[216..220].fill(0);
+	if checksum(&raw) != fields.u32(216)? {
+		return Err(invalid("checksum mismatch"));
+	}
+
+	let uuid = fields
+		.slice(16, 16)?
+		.chunks_exact(4)
+		.map(|word| {
+			word.iter()
+				.map(|byte| format!("{:02x}", byte))
+				.collect::<String>()
+		})
+		.collect::<Vec<_>>()
+		.join(":");
+
+	let number = fields.u32(160)? as usize;
+	if number >= max_dev {
+		return Err(invalid("device number out of range"));
+	}
+	let role = match fields.u16(256 + number * 2)? {
+		ROLE_SPARE => Role::Spare,
+		ROLE_FAULTY => Role::Faulty,
+		ROLE_JOURNAL => Role::Journal,
+		slot => Role::Active(u32::from(slot)),
+	};
+
+	Ok(Superblock {
+		version,
+		uuid,
+		name: bytes::c_string(fields.slice(32, 32)?),
+		level: Level::from(fields.u32(72)? as i32),
+		raid_disks: fields.u32(92)?,
+		role,
+		events: fields.u64(200)?,
+		// Resync starts from here; all the way means there's none to do
+		clean: fields.u64(208)? == u64::MAX,
+	})
+}
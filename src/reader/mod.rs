@@ -1,207 +1,363 @@
+pub mod apm;
 mod bytes;
+pub mod disklabel;
+pub mod fs;
 mod header;
+pub mod ldm;
+pub mod luks;
+pub mod lvm;
+pub mod mbr;
+pub mod md;
 mod partition;
+pub mod slots;
+pub mod source;
+pub mod zfs;
 
-use crate::{cli::Table, errors::Error, guid};
-use anyhow::{Context as _, Result};
-use colored::Colorize;
-use nix::unistd::Uid;
-use std::{fs::File, os::unix::fs::FileExt};
+use crate::{
+	guid::{Guid, PartitionType},
+	Error, Result,
+};
+use apm::Apm;
+pub use bytes::string_from_bytes as decode_name;
+pub use header::parse as parse_header;
+use mbr::Mbr;
+pub use partition::parse as parse_entry;
+use source::{BlockSource, Raw};
+use std::{
+	fmt,
+	io::{self, Read, Seek},
+};
 
-const MBR_OFFSET: u16 = 512;
-const GPT_HEADER_SIG: u64 = 0x5452415020494645;
-const UNUSED_PARTITION: &str = "Unused entry";
+// Logical sector sizes probed for the primary header, in order
+const SECTOR_SIZES: [u64; 2] = [512, 4096];
 
-pub(crate) enum ReaderKind {
-	Header,
-	Entry,
+/// A disk (or image) with a GUID Partition Table.
+#[derive(Debug, Clone)]
+pub struct Disk {
+	/// Logical sector size the table was found with
+	pub sector_size: u64,
+	/// The header `entries` were read through: the primary one unless it
+	/// is damaged
+	pub header: GptHeader,
+	/// Used entries of the partition entry array, in table order
+	pub entries: Vec<PartitionEntry>,
+	pub primary: TableStatus,
+	pub backup: TableStatus,
 }
 
-impl ReaderKind {
-	fn analyze(
-		&mut self,
-		bytes: &mut [u8; 512],
-		loc: &str,
-		writer: &mut Table,
-		guid: bool,
-	) -> Result<()> {
+/// The fields of a GPT header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GptHeader {
+	pub revision: u32,
+	pub header_size: u32,
+	pub header_crc32: u32,
+	pub current_lba: u64,
+	pub backup_lba: u64,
+	pub first_usable_lba: u64,
+	pub last_usable_lba: u64,
+	pub disk_guid: Guid,
+	pub partition_entry_lba: u64,
+	pub num_partition_entries: u32,
+	pub partition_entry_size: u32,
+	pub partition_entries_crc32: u32,
+}
+
+/// A used slot of the partition entry array.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionEntry {
+	/// 1-based position in the entry array, matching the kernel's
+	/// partition numbering
+	pub number: u32,
+	pub type_guid: Guid,
+	pub unique_guid: Guid,
+	pub first_lba: u64,
+	pub last_lba: u64,
+	pub attributes: u64,
+	pub name: String,
+}
+
+impl PartitionEntry {
+	/// Number of sectors covered, inclusive of both ends.
+	pub fn sectors(&self) -> u64 {
+		// saturating: a partition spanning every LBA has 2^64 sectors
+		(self.last_lba - self.first_lba).saturating_add(1)
+	}
+
+	/// The partition type according to the built-in table. See
+	/// [`UserTypes::resolve`](crate::guid::UserTypes::resolve) to include
+	/// user definitions.
+	pub fn partition_type(&self) -> PartitionType {
+		PartitionType::from_guid(self.type_guid)
+	}
+}
+
+/// Reads the GPT of the raw disk or image `src`, probing 512 and
+/// 4096-byte logical sectors.
+///
+/// The primary table is preferred; the backup is used when the primary is
+/// missing or fails validation. Fails if neither copy is usable.
+pub fn read_gpt<R: Read + Seek>(src: R) -> Result<Disk> {
+	read_gpt_from(&mut Raw::new(src)?)
+}
+
+/// Like [`read_gpt`], for disks in any format [`source`] can read. The
+/// backup table of disks that aren't
+/// [`seekable`](BlockSource::seekable) is left unread.
+pub fn read_gpt_from(src: &mut dyn BlockSource) -> Result<Disk> {
+	let len = src.size();
+
+	for &sector_size in SECTOR_SIZES.iter() {
+		let last_lba = match (len / sector_size).checked_sub(1) {
+			Some(lba) if lba > 1 => lba,
+			_ => continue,
+		};
+
+		let primary = read_copy(src, 1, sector_size)?;
+		let backup_lba = match &primary {
+			Copy::Valid(header, _) | Copy::Broken(_, Some(header)) => {
+				header.backup_lba
+			}
+			Copy::Broken(..) => last_lba,
+		};
+		let backup = if src.seekable() {
+			read_copy(src, backup_lba, sector_size)?
+		} else {
+			Copy::Broken(TableStatus::Skipped, None)
+		};
+
+		let (primary_status, backup_status) =
+			(primary.status(), backup.status());
+		if primary_status == TableStatus::Missing
+			&& matches!(
+				backup_status,
+				TableStatus::Missing | TableStatus::Skipped
+			) {
+			continue;
+		}
+
+		return match (primary, backup) {
+			(Copy::Valid(header, entries), _)
+			| (_, Copy::Valid(header, entries)) => Ok(Disk {
+				sector_size,
+				header,
+				entries,
+				primary: primary_status,
+				backup: backup_status,
+			}),
+			(Copy::Broken(status, _), _) => Err(status.into_error()),
+		};
+	}
+
+	Err(Error::InvalidSignature)
+}
+
+/// A partition table of any scheme gptinfo reads.
+#[derive(Debug, Clone)]
+pub enum PartitionTable {
+	Gpt(Disk),
+	Apm(Apm),
+	Mbr(Mbr),
+}
+
+/// Reads the GPT of `src`, or if there is no GPT signature at all, its
+/// Apple Partition Map or MBR. Fails with [`Error::InvalidSignature`] if it
+/// has none of them.
+pub fn read_table(src: &mut dyn BlockSource) -> Result<PartitionTable> {
+	match read_gpt_from(src) {
+		Err(Error::InvalidSignature) => match apm::read(src) {
+			Err(Error::InvalidSignature) => {
+				mbr::read(src).map(PartitionTable::Mbr)
+			}
+			apm => apm.map(PartitionTable::Apm),
+		},
+		gpt => gpt.map(PartitionTable::Gpt),
+	}
+}
+
+/// Outcome of validating one copy of the GPT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableStatus {
+	Valid,
+	/// No header signature at the expected LBA
+	Missing,
+	/// Not read because the disk isn't [`seekable`](BlockSource::seekable)
+	Skipped,
+	/// A header field is out of range
+	Invalid(&'static str),
+	/// The entry in this slot can't be decoded
+	InvalidEntry(u32, &'static str),
+	HeaderChecksum,
+	EntriesChecksum,
+}
+
+impl TableStatus {
+	fn into_error(self) -> Error {
+		match self {
+			Self::Valid | Self::Missing | Self::Skipped => {
+				Error::InvalidSignature
+			}
+			Self::Invalid(reason) => Error::InvalidHeader(reason),
+			Self::InvalidEntry(number, reason) => {
+				Error::InvalidEntry(number, reason)
+			}
+			Self::HeaderChecksum => Error::HeaderChecksum,
+			Self::EntriesChecksum => Error::EntriesChecksum,
+		}
+	}
+}
+
+impl fmt::Display for TableStatus {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		match self {
-			Self::Header => {
-				// SAFETY: guaranteed to be safe because we guarantee the layout
-				// of the struct
-				let entry = unsafe {
-					&mut *(bytes.as_ptr() as *mut header::HeaderEntry)
-				};
-
-				// Magic signature in the first 8 bytes of LBA1 to verify EFI
-				// system integrity
-				let signature = u64::from_le_bytes(entry.signature);
-
-				if signature == GPT_HEADER_SIG {
-					// Begin disk section
-					writer.push_cell("Name".bold());
-					if guid {
-						writer.push_cell("Unique GUID".bold());
-					}
-
-					writer.push_cells(vec![
-						"Type".bold(),
-						"Start".bold(),
-						"End".bold(),
-						"Sectors".bold(),
-						"Size".bold(),
-					]);
-
-					let start = u64::from_le_bytes(entry.first_lba);
-					let end = u64::from_le_bytes(entry.last_lba);
-
-					// reverse guid parts for endianness
-					entry.kind_p1.reverse();
-					entry.kind_p2.reverse();
-					entry.kind_p3.reverse();
-
-					writer.push_cell(format!("Disk ({})", loc).as_str().into());
-					if guid {
-						writer.push_cell(
-							bytes::guid_from_bytes(
-								&entry.kind_p1,
-								&entry.kind_p2,
-								&entry.kind_p3,
-								&entry.kind_p4,
-								&entry.kind_p5,
-							)
-							.as_str()
-							.into(),
-						);
-					}
-
-					writer.push_cells(vec![
-						"Block Device".into(),
-						start.to_string().as_str().into(),
-						end.to_string().as_str().into(),
-						(end + start).to_string().as_str().into(),
-						bytes::format_bytes(end, start).as_str().into(),
-					]);
-					Ok(())
-				} else {
-					Err(Error::InvalidSignature.into())
-				}
+			Self::Valid => f.write_str("valid"),
+			Self::Missing => f.write_str("missing"),
+			Self::Skipped => f.write_str(
+				"not checked (the image can't be read out of order)",
+			),
+			Self::Invalid(reason) => write!(f, "invalid ({})", reason),
+			Self::InvalidEntry(number, reason) => {
+				write!(f, "invalid (partition entry {}: {})", number, reason)
 			}
-			Self::Entry => {
-				// SAFETY: guaranteed to be safe because we guarantee the layout
-				// of the struct
-				let entry = unsafe {
-					&mut *(bytes.as_ptr() as *mut partition::PartitionEntry)
-				};
-
-				// account for endianness
-				entry.kind_p1.reverse();
-				entry.kind_p2.reverse();
-				entry.kind_p3.reverse();
-				entry.kind_p4.reverse();
-
-				// Get partition type from GUID
-				let kind = guid::match_partition_guid(
-					&entry.kind_p1,
-					&entry.kind_p2,
-					&entry.kind_p3,
-					&entry.kind_p4,
-				)?;
-
-				if kind != UNUSED_PARTITION {
-					let start = u64::from_le_bytes(entry.first_lba);
-					let end = u64::from_le_bytes(entry.last_lba);
-
-					writer.push_cell(bytes::string_from_bytes(&entry.name)?);
-					if guid {
-						entry.ukind_p1.reverse();
-						entry.ukind_p2.reverse();
-						entry.ukind_p3.reverse();
-
-						writer.push_cell(
-							bytes::guid_from_bytes(
-								&entry.ukind_p1,
-								&entry.ukind_p2,
-								&entry.ukind_p3,
-								&entry.ukind_p4,
-								&entry.ukind_p5,
-							)
-							.as_str()
-							.into(),
-						);
-					}
-
-					writer.push_cells(vec![
-						kind.into(),
-						start.to_string().as_str().into(),
-						end.to_string().as_str().into(),
-						(end - start + 1).to_string().as_str().into(),
-						bytes::format_bytes(end, start).as_str().into(),
-					]);
-				}
-				Ok(())
+			Self::HeaderChecksum => f.write_str("corrupt (header checksum)"),
+			Self::EntriesChecksum => {
+				f.write_str("corrupt (partition entry array checksum)")
 			}
 		}
 	}
 }
 
-pub(crate) struct Reader<'a> {
-	loc: &'a str,
-	lba: u16,
-	bytes: [u8; 512],
-	writer: Table,
-	guid: bool,
+enum Copy {
+	Valid(GptHeader, Vec<PartitionEntry>),
+	/// The header is kept when it decoded, so its backup LBA can still be
+	/// followed
+	Broken(TableStatus, Option<GptHeader>),
 }
 
-impl<'a> Reader<'a> {
-	pub(crate) fn new(loc: &'a str, guid: bool) -> Result<Reader<'a>> {
-		if !Uid::effective().is_root() {
-			return Err(Error::Root.into());
+impl Copy {
+	fn status(&self) -> TableStatus {
+		match self {
+			Self::Valid(..) => TableStatus::Valid,
+			Self::Broken(status, _) => *status,
 		}
+	}
+}
+
+// Upper bound on the entry array; the spec's minimum is 16KiB and real
+// tables don't go far past it
+const MAX_ENTRY_ARRAY: u64 = 16 * 1024 * 1024;
 
-		Ok(Self {
-			loc,
-			lba: 1,
-			bytes: [0u8; 512],
-			writer: Table::new(if guid { 7 } else { 6 }),
-			guid,
-		})
+/// Reads and validates the header at `lba` and the entry array it points
+/// to.
+fn read_copy(
+	src: &mut dyn BlockSource,
+	lba: u64,
+	sector_size: u64,
+) -> Result<Copy> {
+	let broken = |status, header| Ok(Copy::Broken(status, header));
+
+	let mut sector = vec![0u8; sector_size as usize];
+	let offset = match lba.checked_mul(sector_size) {
+		Some(offset) => offset,
+		None => return broken(TableStatus::Missing, None),
+	};
+	match src.read_at(offset, &mut sector) {
+		// Past the end, as the backup of a truncated image is
+		Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+			return broken(TableStatus::Missing, None)
+		}
+		Err(err) => return Err(err.into()),
+		Ok(()) => {}
 	}
 
-	pub(crate) fn read(&mut self) -> Result<ReaderKind> {
-		let entry_size = match self.lba {
-			1 => 512,
-			2..=33 => 128,
-			pos => return Err(Error::UndefLbaPtr(pos).into()),
-		};
-		let offset = MBR_OFFSET + entry_size * (self.lba - 1);
-
-		File::open(self.loc)
-			.context("Failed to open GPT entry for reading")?
-			.read_at(&mut self.bytes, offset.into())
-			.with_context(|| {
-				format!("Failed read GPT entry at offset: {}b", offset)
-			})?;
-
-		match self.lba {
-			1 => Ok(ReaderKind::Header),
-			2..=33 => Ok(ReaderKind::Entry),
-			pos => Err(Error::UndefLbaPtr(pos).into()),
+	let header = match header::parse(&sector)? {
+		Some(header) => header,
+		None => return broken(TableStatus::Missing, None),
+	};
+
+	let header_size = header.header_size as usize;
+	match header::checksum(&sector, header_size) {
+		None => {
+			return broken(
+				TableStatus::Invalid("header size out of range"),
+				Some(header),
+			)
 		}
+		Some(crc) if crc != header.header_crc32 => {
+			return broken(TableStatus::HeaderChecksum, Some(header))
+		}
+		Some(_) => {}
+	}
+	// A header copied elsewhere can't be trusted to point at the other
+	if header.current_lba != lba {
+		return broken(
+			TableStatus::Invalid("header is not at its own LBA"),
+			None,
+		);
+	}
+
+	if header.first_usable_lba > header.last_usable_lba {
+		return broken(
+			TableStatus::Invalid("usable LBA range is empty"),
+			Some(header),
+		);
 	}
 
-	pub(crate) fn run(mut self) -> Result<()> {
-		while let Ok(mut reader) = self.read() {
-			reader.analyze(
-				&mut self.bytes,
-				self.loc,
-				&mut self.writer,
-				self.guid,
-			)?;
-			self.lba += 1;
+	let entry_size = u64::from(header.partition_entry_size);
+	if entry_size < partition::MIN_ENTRY_SIZE as u64 || entry_size % 8 != 0 {
+		return broken(
+			TableStatus::Invalid("partition entry size out of range"),
+			Some(header),
+		);
+	}
+	let array_len = u64::from(header.num_partition_entries) * entry_size;
+	if array_len > MAX_ENTRY_ARRAY {
+		return broken(
+			TableStatus::Invalid("partition entry array too large"),
+			Some(header),
+		);
+	}
+
+	let mut array = vec![0u8; array_len as usize];
+	let read = header
+		.partition_entry_lba
+		.checked_mul(sector_size)
+		.map(|offset| src.read_at(offset, &mut array));
+	match read {
+		Some(Ok(())) => {}
+		Some(Err(err)) if err.kind() != io::ErrorKind::UnexpectedEof => {
+			return Err(err.into())
+		}
+		_ => {
+			return broken(
+				TableStatus::Invalid("partition entry array out of bounds"),
+				Some(header),
+			)
 		}
+	}
+	if crc32fast::hash(&array) != header.partition_entries_crc32 {
+		return broken(TableStatus::EntriesChecksum, Some(header));
+	}
 
-		self.writer.draw();
-		Ok(())
+	// A bad entry spoils this copy only, so the other can still be used
+	let mut entries = vec![];
+	for (idx, raw) in array.chunks_exact(entry_size as usize).enumerate() {
+		let number = idx as u32 + 1;
+		let status = match partition::parse(raw, number) {
+			Ok(Some(entry)) => {
+				entries.push(entry);
+				continue;
+			}
+			Ok(None) => continue,
+			Err(Error::InvalidEntry(_, reason)) => {
+				TableStatus::InvalidEntry(number, reason)
+			}
+			Err(Error::UTF16) => {
+				TableStatus::InvalidEntry(number, "name isn't valid UTF-16")
+			}
+			Err(err) => return Err(err),
+		};
+		return broken(status, Some(header));
 	}
+
+	Ok(Copy::Valid(header, entries))
 }
@@ -1,23 +1,35 @@
-#[repr(C)]
-#[derive(Debug)]
-pub(crate) struct PartitionEntry {
-	// A GUID contains 4 "parts" in this order: 4 bytes, 2 byte, 2 byte, 8
-	// However, they are read as little endian, so we must reverse
-	// these in place (kind of. guids are stupid)
-	pub(crate) kind_p1: [u8; 4],
-	pub(crate) kind_p2: [u8; 2],
-	pub(crate) kind_p3: [u8; 2],
-	pub(crate) kind_p4: [u8; 8],
-	// 5-part unique guid??? idek its like weird or smthn
-	pub(crate) ukind_p1: [u8; 4],
-	pub(crate) ukind_p2: [u8; 2],
-	pub(crate) ukind_p3: [u8; 2],
-	pub(crate) ukind_p4: [u8; 2],
-	pub(crate) ukind_p5: [u8; 6],
-	// lba ptr thingies
-	pub(crate) first_lba: [u8; 8],
-	pub(crate) last_lba: [u8; 8],
-	// Ignore flags
-	_offset2: [u8; 8],
-	pub(crate) name: [u8; 72],
+use super::{
+	bytes::{self, Fields},
+	PartitionEntry,
+};
+use crate::{Error, Result};
+
+// The fields defined by the spec; larger entries pad with reserved bytes
+pub(crate) const MIN_ENTRY_SIZE: usize = 128;
+
+/// Decodes the entry in slot `number` (1-based) from the start of `bytes`.
+/// Returns `Ok(None)` for unused slots.
+pub fn parse(bytes: &[u8], number: u32) -> Result<Option<PartitionEntry>> {
+	let fields = Fields::new(bytes, "partition entry", MIN_ENTRY_SIZE)?;
+
+	let type_guid = fields.guid(0)?;
+	if type_guid.is_nil() {
+		return Ok(None);
+	}
+
+	let first_lba = fields.u64(32)?;
+	let last_lba = fields.u64(40)?;
+	if first_lba > last_lba {
+		return Err(Error::InvalidEntry(number, "ends before it starts"));
+	}
+
+	Ok(Some(PartitionEntry {
+		number,
+		type_guid,
+		unique_guid: fields.guid(16)?,
+		first_lba,
+		last_lba,
+		attributes: fields.u64(48)?,
+		name: bytes::string_from_bytes(fields.slice(56, 72)?)?,
+	}))
 }
@@ -0,0 +1,273 @@
+use super::{bytes::Fields, source::BlockSource, Disk, PartitionEntry};
+use crate::{guid::Guid, Error, Result};
+
+const CHROMEOS_KERNEL: Guid =
+	Guid::from_groups(0xFE3A2A5D, 0x4F32, 0x41A7, 0xB725, 0xACCC3285A309);
+const CHROMEOS_ROOTFS: Guid =
+	Guid::from_groups(0x3CB8E202, 0x3B7E, 0x47DD, 0x8A3C, 0x7FF2A13CFCEC);
+const ANDROID_MISC: Guid =
+	Guid::from_groups(0xEF32A33B, 0xA409, 0x486C, 0x9141, 0x9FFB711F6266);
+
+// ChromeOS kernel attribute bits
+const CROS_PRIORITY_SHIFT: u32 = 48;
+const CROS_TRIES_SHIFT: u32 = 52;
+const CROS_SUCCESSFUL: u64 = 1 << 56;
+// The A/B attribute bits some Android bootloaders keep on `boot_a` and
+// `boot_b` instead of in misc
+const AB_PRIORITY_SHIFT: u32 = 48;
+const AB_ACTIVE: u64 = 1 << 50;
+const AB_TRIES_SHIFT: u32 = 51;
+const AB_SUCCESSFUL: u64 = 1 << 54;
+const AB_UNBOOTABLE: u64 = 1 << 55;
+const AB_BITS: u64 = 0xFF << 48;
+
+// The boot control block of AOSP's boot_control HAL, in the slot suffix
+// field of the bootloader message that opens misc
+const BOOT_CONTROL_OFFSET: u64 = 2048;
+const BOOT_CONTROL_SIZE: usize = 32;
+const BOOT_CONTROL_MAGIC: u32 = 0x4241_4342;
+const BOOT_CONTROL_SLOTS: usize = 4;
+
+/// The boot slots of an A/B disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slots {
+	/// Where the slots and their state come from
+	pub scheme: Scheme,
+	pub slots: Vec<Slot>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+	/// Slots are the ChromeOS kernel partitions, each with its state in
+	/// its attribute bits
+	ChromeOs,
+	/// Slots are the `_a` and `_b` partitions, with their state in the
+	/// boot control block of the misc partition
+	AndroidMisc,
+	/// Likewise, with their state in the attribute bits of the boot
+	/// partitions
+	AndroidAttributes,
+	/// Likewise, with no state found
+	Android,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot {
+	/// `A` or `B` on ChromeOS, as in `KERN-A`; `a` or `b` on Android
+	pub name: String,
+	/// Partitions of this slot, by number
+	pub partitions: Vec<u32>,
+	/// `None` if nothing on the disk says
+	pub state: Option<SlotState>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotState {
+	/// The bootable slot with the highest priority is tried first
+	pub priority: u8,
+	/// Boot attempts left before the slot is given up on
+	pub tries: u8,
+	/// Whether the slot has booted all the way since it was updated
+	pub successful: bool,
+	pub bootable: bool,
+	/// Whether the bootloader will try this slot next
+	pub active: bool,
+}
+
+/// Whether GPT partitions of this type hold a ChromeOS kernel.
+pub fn is_chromeos_kernel(type_guid: Guid) -> bool {
+	type_guid == CHROMEOS_KERNEL
+}
+
+/// The state in the attribute bits of a ChromeOS kernel partition. Whether
+/// it's active depends on the other kernels, so it's left unset.
+pub fn kernel_state(attributes: u64) -> SlotState {
+	let priority = ((attributes >> CROS_PRIORITY_SHIFT) & 0xF) as u8;
+	let tries = ((attributes >> CROS_TRIES_SHIFT) & 0xF) as u8;
+	let successful = attributes & CROS_SUCCESSFUL != 0;
+	SlotState {
+		priority,
+		tries,
+		successful,
+		bootable: priority > 0 && (successful || tries > 0),
+		active: false,
+	}
+}
+
+/// Finds the A/B boot slots of `disk`: its ChromeOS kernels, or its
+/// Android partitions with `_a` and `_b` suffixes. Returns `None` on other
+/// disks, and an error if the Android boot control block is damaged.
+pub fn read(disk: &Disk, src: &mut dyn BlockSource) -> Result<Option<Slots>> {
+	let kernels: Vec<_> = disk
+		.entries
+		.iter()
+		.filter(|entry| is_chromeos_kernel(entry.type_guid))
+		.collect();
+	if !kernels.is_empty() {
+		return Ok(Some(chromeos(disk, &kernels)));
+	}
+
+	let mut slots: Vec<Slot> = ["a", "b"]
+		.iter()
+		.map(|&name| {
+			let suffix = format!("_{}", name);
+			Slot {
+				name: name.to_string(),
+				partitions: disk
+					.entries
+					.iter()
+					.filter(|entry| entry.name.ends_with(&suffix))
+					.map(|entry| entry.number)
+					.collect(),
+				state: None,
+			}
+		})
+		.collect();
+	if slots.iter().any(|slot| slot.partitions.is_empty()) {
+		return Ok(None);
+	}
+
+	let misc = disk
+		.entries
+		.iter()
+		.find(|entry| entry.name == "misc" || entry.type_guid == ANDROID_MISC);
+	if let Some(misc) = misc {
+		let offset = misc.first_lba.saturating_mul(disk.sector_size);
+		if let Some(states) = read_boot_control(src, offset)? {
+			for (slot, state) in slots.iter_mut().zip(states) {
+				slot.state = Some(state);
+			}
+			return Ok(Some(Slots {
+				scheme: Scheme::AndroidMisc,
+				slots,
+			}));
+		}
+	}
+
+	let boot: Vec<_> = slots
+		.iter()
+		.map(|slot| {
+			let name = format!("boot_{}", slot.name);
+			disk.entries.iter().find(|entry| entry.name == name)
+		})
+		.collect();
+	let scheme = if boot
+		.iter()
+		.flatten()
+		.any(|entry| entry.attributes & AB_BITS != 0)
+	{
+		for (slot, entry) in slots.iter_mut().zip(boot) {
+			slot.state = entry.map(|entry| ab_state(entry.attributes));
+		}
+		Scheme::AndroidAttributes
+	} else {
+		Scheme::Android
+	};
+	Ok(Some(Slots { scheme, slots }))
+}
+
+/// A slot per ChromeOS kernel, with the root filesystem named after it.
+fn chromeos(disk: &Disk, kernels: &[&PartitionEntry]) -> Slots {
+	let mut slots: Vec<Slot> = kernels
+		.iter()
+		.map(|kernel| {
+			let name = match kernel.name.strip_prefix("KERN-") {
+				Some(name) => name.to_string(),
+				None => kernel.name.clone(),
+			};
+
+			let root = format!("ROOT-{}", name);
+			let mut partitions = vec![kernel.number];
+			partitions.extend(
+				disk.entries
+					.iter()
+					.filter(|entry| {
+						entry.type_guid == CHROMEOS_ROOTFS && entry.name == root
+					})
+					.map(|entry| entry.number),
+			);
+
+			Slot {
+				name,
+				partitions,
+				state: Some(kernel_state(kernel.attributes)),
+			}
+		})
+		.collect();
+
+	mark_active(slots.iter_mut().flat_map(|slot| slot.state.as_mut()));
+
+	Slots {
+		scheme: Scheme::ChromeOs,
+		slots,
+	}
+}
+
+fn ab_state(attributes: u64) -> SlotState {
+	let unbootable = attributes & AB_UNBOOTABLE != 0;
+	SlotState {
+		priority: ((attributes >> AB_PRIORITY_SHIFT) & 0x3) as u8,
+		tries: ((attributes >> AB_TRIES_SHIFT) & 0x7) as u8,
+		successful: attributes & AB_SUCCESSFUL != 0,
+		bootable: !unbootable,
+		active: attributes & AB_ACTIVE != 0,
+	}
+}
+
+/// Reads the state of slots `a` and `b` from the boot control block of the
+/// misc partition at byte `offset`. Returns `None` if there is none.
+fn read_boot_control(
+	src: &mut dyn BlockSource,
+	offset: u64,
+) -> Result<Option<Vec<SlotState>>> {
+	let invalid = Error::InvalidBootControl;
+
+	let mut raw = [0u8; BOOT_CONTROL_SIZE];
+	if src
+		.read_at(offset.saturating_add(BOOT_CONTROL_OFFSET), &mut raw)
+		.is_err()
+	{
+		return Ok(None);
+	}
+	let fields = Fields::new(&raw, "boot control block", BOOT_CONTROL_SIZE)?;
+	if fields.u32(4)? != BOOT_CONTROL_MAGIC {
+		return Ok(None);
+	}
+	if crc32fast::hash(&raw[..28]) != fields.u32(28)? {
+		return Err(invalid("checksum mismatch"));
+	}
+	let slot_count = usize::from(raw[9] & 0x7);
+	if !(2..=BOOT_CONTROL_SLOTS).contains(&slot_count) {
+		return Err(invalid("invalid slot count"));
+	}
+
+	// Each slot is packed into two bytes: a 4-bit priority, 3 bits of
+	// tries left and a successful bit, then a verity bit
+	let mut states: Vec<SlotState> = raw[12..16]
+		.chunks_exact(2)
+		.map(|info| SlotState {
+			priority: info[0] & 0xF,
+			tries: (info[0] >> 4) & 0x7,
+			successful: info[0] & 0x80 != 0,
+			bootable: info[0] & 0xF != 0,
+			active: false,
+		})
+		.collect();
+	mark_active(states.iter_mut());
+	Ok(Some(states))
+}
+
+/// Marks the bootable slot with the highest priority as the active one,
+/// the first among equals.
+fn mark_active<'a>(states: impl Iterator<Item = &'a mut SlotState>) {
+	let mut first: Option<&'a mut SlotState> = None;
+	for state in states.filter(|state| state.bootable) {
+		match &first {
+			Some(first) if first.priority >= state.priority => {}
+			_ => first = Some(state),
+		}
+	}
+	if let Some(first) = first {
+		first.active = true;
+	}
+}
@@ -0,0 +1,497 @@
+use super::{check_bounds, read_exact_at, BlockSource};
+use crate::{reader::bytes::Fields, Error, Result};
+use flate2::read::MultiGzDecoder;
+use std::io::{self, BufReader, Chain, Cursor, Read, Seek, SeekFrom, Take};
+use xz2::{
+	read::XzDecoder,
+	stream::{Stream, CONCATENATED},
+};
+use zstd::stream::{raw, zio};
+
+pub(super) const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+pub(super) const XZ_MAGIC: [u8; 6] = *b"\xfd7zXZ\0";
+pub(super) const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+const XZ_HEADER_SIZE: u64 = 12;
+const XZ_FOOTER_MAGIC: [u8; 2] = *b"YZ";
+// Enough for `xz -9`, which needs 65MiB to decompress
+const XZ_MEMORY_LIMIT: u64 = 512 * 1024 * 1024;
+
+const ZSTD_SKIPPABLE_MAGIC: u32 = 0x184D_2A5E;
+const ZSTD_SEEKABLE_MAGIC: u32 = 0x8F92_EAB1;
+const ZSTD_SEEK_TABLE_FOOTER: u64 = 9;
+const ZSTD_CHECKSUM_FLAG: u8 = 1 << 7;
+
+// Upper bound on an xz index or zstd seek table kept in memory
+const MAX_INDEX_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Codec {
+	Gzip,
+	Xz,
+	Zstd,
+}
+
+/// A gzip, xz or zstd compressed raw image, decompressed as it is read.
+///
+/// Multi-block xz files and zstd files with a seek table are decompressed
+/// from the block or frame holding the offset read. Anything else can
+/// only be decompressed from the start, so it is not
+/// [`seekable`](BlockSource::seekable) and its size may be unknown.
+pub struct Compressed<R: Read> {
+	codec: Codec,
+	/// The file, while no decoder holds it
+	inner: Option<R>,
+	decoder: Option<Open<R>>,
+	frames: Vec<Frame>,
+	size: u64,
+	seekable: bool,
+}
+
+/// A run of compressed data that decompresses on its own: the whole file,
+/// or a block or frame of a seekable one.
+struct Frame {
+	/// Offset and length of its data on the disk
+	start: u64,
+	len: u64,
+	/// Offset and length of the compressed data in the file
+	offset: u64,
+	size: u64,
+	/// Wrapping making a lone xz block a complete stream
+	prefix: Vec<u8>,
+	suffix: Vec<u8>,
+}
+
+type Framed<R> = Chain<Chain<Cursor<Vec<u8>>, Take<R>>, Cursor<Vec<u8>>>;
+
+enum Decoder<R: Read> {
+	Gzip(MultiGzDecoder<Framed<R>>),
+	Xz(XzDecoder<Framed<R>>),
+	Zstd(zio::Reader<BufReader<Framed<R>>, raw::Decoder>),
+}
+
+struct Open<R: Read> {
+	frame: usize,
+	/// Disk offset of the next byte the decoder yields
+	pos: u64,
+	decoder: Decoder<R>,
+}
+
+impl<R: Read + Seek> Compressed<R> {
+	/// Detects the codec from the magic and looks for an xz index or zstd
+	/// seek table to seek with.
+	pub fn new(mut inner: R) -> Result<Compressed<R>> {
+		let len = inner.seek(SeekFrom::End(0))?;
+		let mut magic = [0u8; 6];
+		read_exact_at(&mut inner, 0, &mut magic, "compressed", "truncated")?;
+		let codec = if magic.starts_with(&GZIP_MAGIC) {
+			Codec::Gzip
+		} else if magic == XZ_MAGIC {
+			Codec::Xz
+		} else if magic.starts_with(&ZSTD_MAGIC) {
+			Codec::Zstd
+		} else {
+			return Err(Error::InvalidImage("compressed", "unknown format"));
+		};
+
+		// A damaged or missing index still leaves the start readable, as
+		// with a partial download
+		let frames = match codec {
+			Codec::Gzip => None,
+			Codec::Xz => xz_blocks(&mut inner, len).ok().flatten(),
+			Codec::Zstd => zstd_frames(&mut inner, len).ok().flatten(),
+		};
+		let size = frames
+			.as_ref()
+			.and_then(|frames| frames.last())
+			.map_or(u64::MAX, |frame| frame.start + frame.len);
+		let (frames, seekable) = match frames {
+			Some(frames) if frames.len() > 1 => (frames, true),
+			_ => (
+				vec![Frame {
+					start: 0,
+					len: size,
+					offset: 0,
+					size: len,
+					prefix: vec![],
+					suffix: vec![],
+				}],
+				false,
+			),
+		};
+
+		Ok(Self {
+			codec,
+			inner: Some(inner),
+			decoder: None,
+			frames,
+			size,
+			seekable,
+		})
+	}
+
+	/// Reads `out` at `pos` within `frame`, continuing the current decoder
+	/// if it hasn't passed `pos` yet.
+	fn read_frame(
+		&mut self,
+		frame: usize,
+		pos: u64,
+		out: &mut [u8],
+	) -> io::Result<()> {
+		let reuse = matches!(
+			&self.decoder,
+			Some(open) if open.frame == frame && open.pos <= pos
+		);
+		if !reuse {
+			self.open(frame)?;
+		}
+		// unwrap: opened above
+		let open = self.decoder.as_mut().unwrap();
+
+		let skip = pos - open.pos;
+		let skipped =
+			io::copy(&mut (&mut open.decoder).take(skip), &mut io::sink());
+		let read = match skipped {
+			Ok(skipped) if skipped == skip => open.decoder.read_exact(out),
+			Ok(_) => Err(io::ErrorKind::UnexpectedEof.into()),
+			Err(err) => Err(err),
+		};
+		match read {
+			Ok(()) => {
+				open.pos = pos + out.len() as u64;
+				Ok(())
+			}
+			Err(err) => {
+				self.close();
+				Err(err)
+			}
+		}
+	}
+
+	fn open(&mut self, frame: usize) -> io::Result<()> {
+		self.close();
+		let Frame {
+			start,
+			offset,
+			size,
+			prefix,
+			suffix,
+			..
+		} = &self.frames[frame];
+		// Set up the decoder first: once it takes the file there's no
+		// giving it back on failure
+		let xz = match self.codec {
+			Codec::Xz => Some(
+				Stream::new_stream_decoder(XZ_MEMORY_LIMIT, CONCATENATED)
+					.map_err(io::Error::other)?,
+			),
+			_ => None,
+		};
+		let zstd = match self.codec {
+			Codec::Zstd => Some(raw::Decoder::new()?),
+			_ => None,
+		};
+
+		let mut inner = self.inner.take().ok_or_else(|| {
+			io::Error::other("compressed image closed after an error")
+		})?;
+		if let Err(err) = inner.seek(SeekFrom::Start(*offset)) {
+			self.inner = Some(inner);
+			return Err(err);
+		}
+		let framed = Cursor::new(prefix.clone())
+			.chain(inner.take(*size))
+			.chain(Cursor::new(suffix.clone()));
+
+		let decoder = match (xz, zstd) {
+			(Some(stream), _) => {
+				Decoder::Xz(XzDecoder::new_stream(framed, stream))
+			}
+			(_, Some(zstd)) => {
+				Decoder::Zstd(zio::Reader::new(BufReader::new(framed), zstd))
+			}
+			_ => Decoder::Gzip(MultiGzDecoder::new(framed)),
+		};
+		self.decoder = Some(Open {
+			frame,
+			pos: *start,
+			decoder,
+		});
+		Ok(())
+	}
+
+	/// Drops the decoder, taking back the file.
+	fn close(&mut self) {
+		if let Some(open) = self.decoder.take() {
+			let framed = match open.decoder {
+				Decoder::Gzip(decoder) => decoder.into_inner(),
+				Decoder::Xz(decoder) => decoder.into_inner(),
+				Decoder::Zstd(decoder) => decoder.into_inner().into_inner(),
+			};
+			self.inner =
+				Some(framed.into_inner().0.into_inner().1.into_inner());
+		}
+	}
+}
+
+impl<R: Read> Read for Decoder<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match self {
+			Self::Gzip(decoder) => decoder.read(buf),
+			Self::Xz(decoder) => decoder.read(buf),
+			Self::Zstd(decoder) => decoder.read(buf),
+		}
+	}
+}
+
+impl<R: Read + Seek> BlockSource for Compressed<R> {
+	fn size(&self) -> u64 { self.size }
+
+	fn seekable(&self) -> bool { self.seekable }
+
+	fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+		check_bounds(offset, buf.len(), self.size)?;
+
+		let mut done = 0;
+		while done < buf.len() {
+			let pos = offset + done as u64;
+			let frame = self.frames.partition_point(|frame| {
+				frame.start.saturating_add(frame.len) <= pos
+			});
+			let end = match self.frames.get(frame) {
+				Some(frame) => frame.start.saturating_add(frame.len),
+				None => return Err(io::ErrorKind::UnexpectedEof.into()),
+			};
+			let len = (end - pos).min((buf.len() - done) as u64) as usize;
+			self.read_frame(frame, pos, &mut buf[done..done + len])?;
+			done += len;
+		}
+		Ok(())
+	}
+}
+
+/// The blocks of the xz streams in the file, from their indexes, each
+/// wrapped to decompress on its own. `None` if a stream has no index.
+fn xz_blocks<R: Read + Seek>(
+	inner: &mut R,
+	len: u64,
+) -> Result<Option<Vec<Frame>>> {
+	let mut streams = vec![];
+	let mut end = len;
+	while end > 0 {
+		// Streams may be followed by padding
+		let mut word = [0u8; 4];
+		let at = end.saturating_sub(4);
+		read_exact_at(inner, at, &mut word, "xz", "truncated stream")?;
+		if word == [0; 4] {
+			end -= 4;
+			continue;
+		}
+		let stream = match xz_stream(inner, end)? {
+			Some(stream) => stream,
+			None => return Ok(None),
+		};
+		end = stream.offset;
+		streams.push(stream);
+	}
+
+	let mut blocks = vec![];
+	let mut start = 0u64;
+	for XzStream {
+		offset,
+		header,
+		records,
+	} in streams.into_iter().rev()
+	{
+		let flags = [header[6], header[7]];
+		let mut block = offset + XZ_HEADER_SIZE;
+		for (unpadded, uncompressed) in records {
+			let size = unpadded.div_ceil(4) * 4;
+			blocks.push(Frame {
+				start,
+				len: uncompressed,
+				offset: block,
+				size,
+				prefix: header.to_vec(),
+				suffix: xz_lone_index(unpadded, uncompressed, flags),
+			});
+			block += size;
+			start = start
+				.checked_add(uncompressed)
+				.ok_or(Error::InvalidImage("xz", "size out of range"))?;
+		}
+	}
+	Ok(Some(blocks))
+}
+
+/// A stream of an xz file.
+struct XzStream {
+	offset: u64,
+	header: [u8; 12],
+	/// Unpadded and uncompressed size of each block, from the index
+	records: Vec<(u64, u64)>,
+}
+
+/// The xz stream ending at `end`, if it has a valid index.
+fn xz_stream<R: Read + Seek>(
+	inner: &mut R,
+	end: u64,
+) -> Result<Option<XzStream>> {
+	let mut footer = [0u8; 12];
+	if end < 2 * XZ_HEADER_SIZE {
+		return Ok(None);
+	}
+	read_exact_at(inner, end - 12, &mut footer, "xz", "truncated footer")?;
+	let fields = Fields::new(&footer, "xz stream footer", 12)?;
+	if footer[10..] != XZ_FOOTER_MAGIC
+		|| crc32fast::hash(&footer[4..10]) != fields.u32(0)?
+	{
+		return Ok(None);
+	}
+	let index_size = (u64::from(fields.u32(4)?) + 1) * 4;
+	if index_size > MAX_INDEX_SIZE || index_size + 24 > end {
+		return Ok(None);
+	}
+
+	let index_start = end - 12 - index_size;
+	let mut index = vec![0u8; index_size as usize];
+	read_exact_at(inner, index_start, &mut index, "xz", "truncated index")?;
+	let (body, crc) = index.split_at(index.len() - 4);
+	if body.first() != Some(&0)
+		|| crc32fast::hash(body) != Fields::new(crc, "xz index", 4)?.u32(0)?
+	{
+		return Ok(None);
+	}
+
+	let mut at = 1;
+	let count = match varint(body, &mut at) {
+		Some(count) => count,
+		None => return Ok(None),
+	};
+	let mut records = vec![];
+	let mut blocks_size = 0u64;
+	for _ in 0..count {
+		match (varint(body, &mut at), varint(body, &mut at)) {
+			(Some(unpadded), Some(uncompressed)) if unpadded > 0 => {
+				records.push((unpadded, uncompressed));
+				blocks_size =
+					blocks_size.saturating_add(unpadded.div_ceil(4) * 4);
+			}
+			_ => return Ok(None),
+		}
+	}
+
+	let offset = match (index_start - XZ_HEADER_SIZE).checked_sub(blocks_size) {
+		Some(offset) => offset,
+		None => return Ok(None),
+	};
+	let mut header = [0u8; 12];
+	read_exact_at(inner, offset, &mut header, "xz", "truncated header")?;
+	if header[..6] != XZ_MAGIC || header[6..8] != footer[8..10] {
+		return Ok(None);
+	}
+	Ok(Some(XzStream {
+		offset,
+		header,
+		records,
+	}))
+}
+
+/// An index and footer ending a stream after a single block, so it can be
+/// decompressed without the rest of the file.
+fn xz_lone_index(unpadded: u64, uncompressed: u64, flags: [u8; 2]) -> Vec<u8> {
+	let mut index = vec![0];
+	for &value in [1, unpadded, uncompressed].iter() {
+		let mut value = value;
+		while value >= 0x80 {
+			index.push(value as u8 | 0x80);
+			value >>= 7;
+		}
+		index.push(value as u8);
+	}
+	index.resize(index.len().div_ceil(4) * 4, 0);
+	index.extend(&crc32fast::hash(&index).to_le_bytes());
+
+	let mut footer = ((index.len() / 4 - 1) as u32).to_le_bytes().to_vec();
+	footer.extend(&flags);
+	index.extend(&crc32fast::hash(&footer).to_le_bytes());
+	index.extend(footer);
+	index.extend(&XZ_FOOTER_MAGIC);
+	index
+}
+
+/// Decodes a multibyte integer of an xz index at `at`, advancing it.
+fn varint(bytes: &[u8], at: &mut usize) -> Option<u64> {
+	let mut value = 0u64;
+	for shift in 0..9 {
+		let byte = *bytes.get(*at)?;
+		*at += 1;
+		value |= u64::from(byte & 0x7f) << (7 * shift);
+		if byte & 0x80 == 0 {
+			return Some(value);
+		}
+	}
+	None
+}
+
+/// The frames listed in the seek table of a zstd file written in the
+/// seekable format. `None` if there is no seek table.
+fn zstd_frames<R: Read + Seek>(
+	inner: &mut R,
+	len: u64,
+) -> Result<Option<Vec<Frame>>> {
+	let mut footer = [0u8; ZSTD_SEEK_TABLE_FOOTER as usize];
+	if len < ZSTD_SEEK_TABLE_FOOTER + 8 {
+		return Ok(None);
+	}
+	let at = len - ZSTD_SEEK_TABLE_FOOTER;
+	read_exact_at(inner, at, &mut footer, "zstd", "truncated seek table")?;
+	let fields = Fields::new(&footer, "zstd seek table", 9)?;
+	let descriptor = footer[4];
+	if fields.u32(5)? != ZSTD_SEEKABLE_MAGIC || descriptor & 0x7c != 0 {
+		return Ok(None);
+	}
+
+	let entry_size = if descriptor & ZSTD_CHECKSUM_FLAG != 0 {
+		12
+	} else {
+		8
+	};
+	let table_size = u64::from(fields.u32(0)?) * entry_size;
+	if table_size > MAX_INDEX_SIZE || table_size + 17 > len {
+		return Ok(None);
+	}
+	let table_start = at - table_size;
+	let mut header = [0u8; 8];
+	read_exact_at(inner, table_start - 8, &mut header, "zstd", "truncated")?;
+	let header = Fields::new(&header, "zstd seek table", 8)?;
+	if header.u32(0)? != ZSTD_SKIPPABLE_MAGIC
+		|| u64::from(header.u32(4)?) != table_size + ZSTD_SEEK_TABLE_FOOTER
+	{
+		return Ok(None);
+	}
+
+	let mut table = vec![0u8; table_size as usize];
+	read_exact_at(inner, table_start, &mut table, "zstd", "truncated")?;
+	let mut frames = vec![];
+	let (mut start, mut offset) = (0u64, 0u64);
+	for entry in table.chunks_exact(entry_size as usize) {
+		let entry = Fields::new(entry, "zstd seek table entry", 8)?;
+		let (size, len) = (u64::from(entry.u32(0)?), u64::from(entry.u32(4)?));
+		frames.push(Frame {
+			start,
+			len,
+			offset,
+			size,
+			prefix: vec![],
+			suffix: vec![],
+		});
+		start += len;
+		offset += size;
+	}
+	// The frames come one after the other, up to the seek table
+	if offset != table_start - 8 {
+		return Ok(None);
+	}
+	Ok(Some(frames))
+}
@@ -0,0 +1,200 @@
+//! Where the bytes of a disk come from: a raw file or block device, a
+//! virtual disk image or a compressed raw image, whose format is detected
+//! from its magic.
+
+mod compressed;
+mod qcow2;
+mod raw;
+mod slice;
+mod vhd;
+mod vhdx;
+mod vmdk;
+
+use crate::{Error, Result};
+pub use compressed::Compressed;
+pub use qcow2::Qcow2;
+pub use raw::Raw;
+pub use slice::Slice;
+use std::{
+	fs::File,
+	io::{self, Read, Seek, SeekFrom},
+	path::{Component, Path},
+};
+pub use vhd::Vhd;
+pub use vhdx::Vhdx;
+pub use vmdk::Vmdk;
+
+// Backing files may point at each other, so the chain is cut off here
+const MAX_BACKING_DEPTH: usize = 16;
+
+/// Random access to the bytes of a disk.
+pub trait BlockSource {
+	/// Size of the disk in bytes, or `u64::MAX` if it can't be known
+	/// without reading to the end.
+	fn size(&self) -> u64;
+
+	/// Whether reads far into the disk are as cheap as reads near its
+	/// start. Compressed streams have to be decompressed up to the offset
+	/// read, so the backup GPT at the end of those is skipped.
+	fn seekable(&self) -> bool { true }
+
+	/// Fills `buf` with the bytes starting at `offset`. Reads extending
+	/// past the end of the disk fail with `UnexpectedEof`.
+	fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()>;
+}
+
+impl<S: BlockSource + ?Sized> BlockSource for Box<S> {
+	fn size(&self) -> u64 { (**self).size() }
+
+	fn seekable(&self) -> bool { (**self).seekable() }
+
+	fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+		(**self).read_at(offset, buf)
+	}
+}
+
+/// Opens the disk or image at `path`, detecting its format.
+pub fn open(path: &Path) -> Result<Box<dyn BlockSource>> {
+	from_file(File::open(path)?, path)
+}
+
+/// Like [`open`], for a file that is already open. `path` is used to
+/// locate backing files.
+pub fn from_file(file: File, path: &Path) -> Result<Box<dyn BlockSource>> {
+	from_file_with(file, path, false)
+}
+
+/// Like [`from_file`]. Backing file names come from the image, so they
+/// must stay within its directory unless `allow_backing_paths` is set:
+/// otherwise a crafted image could have any file on the host read as its
+/// backing file.
+pub fn from_file_with(
+	file: File,
+	path: &Path,
+	allow_backing_paths: bool,
+) -> Result<Box<dyn BlockSource>> {
+	detect(file, path, allow_backing_paths, 0)
+}
+
+fn detect(
+	mut file: File,
+	path: &Path,
+	allow_backing_paths: bool,
+	depth: usize,
+) -> Result<Box<dyn BlockSource>> {
+	let len = file.seek(SeekFrom::End(0))?;
+	let start = peek(&mut file, 0)?;
+	// Fixed VHDs are raw data followed by a footer
+	let end = peek(&mut file, len.saturating_sub(512))?;
+
+	if start.starts_with(&qcow2::MAGIC) {
+		let mut image = Qcow2::new(file)?;
+		if let Some(name) = image.backing_file() {
+			if depth == MAX_BACKING_DEPTH {
+				return Err(Error::InvalidImage(
+					"qcow2",
+					"backing file chain too long",
+				));
+			}
+			let escapes = Path::new(name).components().any(|part| {
+				!matches!(part, Component::Normal(_) | Component::CurDir)
+			});
+			if escapes && !allow_backing_paths {
+				return Err(Error::UnsafeBackingFile(name.to_string()));
+			}
+			// Relative names are relative to the image, not the working
+			// directory
+			let backing = path.parent().unwrap_or(Path::new("")).join(name);
+			image.set_backing(detect(
+				File::open(&backing)?,
+				&backing,
+				allow_backing_paths,
+				depth + 1,
+			)?);
+		}
+		return Ok(Box::new(image));
+	}
+	if start.starts_with(&vmdk::MAGIC) {
+		return Ok(Box::new(Vmdk::new(file)?));
+	}
+	if start.starts_with(vmdk::DESCRIPTOR) {
+		return Err(Error::UnsupportedImage(
+			"vmdk",
+			"text descriptor (pass the extent file instead)",
+		));
+	}
+	if start.starts_with(&vhdx::MAGIC) {
+		return Ok(Box::new(Vhdx::new(file)?));
+	}
+	if start.starts_with(&vhd::FOOTER_MAGIC)
+		|| end.starts_with(&vhd::FOOTER_MAGIC)
+	{
+		return Ok(Box::new(Vhd::new(file)?));
+	}
+	if start.starts_with(&compressed::GZIP_MAGIC)
+		|| start.starts_with(&compressed::XZ_MAGIC)
+		|| start.starts_with(&compressed::ZSTD_MAGIC)
+	{
+		return Ok(Box::new(Compressed::new(file)?));
+	}
+
+	Ok(Box::new(Raw::new(file)?))
+}
+
+/// The first bytes at `offset`, zero-padded if the file ends sooner.
+fn peek(file: &mut File, offset: u64) -> io::Result<[u8; 32]> {
+	let mut magic = [0u8; 32];
+	let mut read = vec![];
+	file.seek(SeekFrom::Start(offset))?;
+	file.take(magic.len() as u64).read_to_end(&mut read)?;
+	magic[..read.len()].copy_from_slice(&read);
+	Ok(magic)
+}
+
+/// Fails reads extending past `size`, as [`BlockSource::read_at`] should.
+fn check_bounds(offset: u64, len: usize, size: u64) -> io::Result<()> {
+	match offset.checked_add(len as u64) {
+		Some(end) if end <= size => Ok(()),
+		_ => Err(io::ErrorKind::UnexpectedEof.into()),
+	}
+}
+
+/// Splits a read of `buf` at `offset` into pieces that don't cross a
+/// `block_size` boundary, handing each to `read` with its disk offset.
+fn for_each_block(
+	offset: u64,
+	buf: &mut [u8],
+	block_size: u64,
+	mut read: impl FnMut(u64, &mut [u8]) -> io::Result<()>,
+) -> io::Result<()> {
+	let mut done = 0;
+	while done < buf.len() {
+		let pos = offset + done as u64;
+		let left = block_size - pos % block_size;
+		let len = (left.min((buf.len() - done) as u64)) as usize;
+		read(pos, &mut buf[done..done + len])?;
+		done += len;
+	}
+	Ok(())
+}
+
+/// Reads `buf` at `offset` while opening an image, reporting running out
+/// of file as a malformed `format` image.
+fn read_exact_at<R: Read + Seek>(
+	inner: &mut R,
+	offset: u64,
+	buf: &mut [u8],
+	format: &'static str,
+	what: &'static str,
+) -> Result<()> {
+	inner.seek(SeekFrom::Start(offset))?;
+	inner.read_exact(buf).map_err(|err| match err.kind() {
+		io::ErrorKind::UnexpectedEof => Error::InvalidImage(format, what),
+		_ => err.into(),
+	})
+}
+
+/// An error for malformed image structures found while reading.
+fn invalid_data(format: &str, what: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", format, what))
+}
@@ -0,0 +1,307 @@
+use super::{
+	check_bounds, for_each_block, invalid_data, read_exact_at, BlockSource,
+};
+use crate::{reader::bytes::Fields, Error, Result};
+use flate2::read::DeflateDecoder;
+use std::{
+	collections::HashMap,
+	io::{self, Read, Seek, SeekFrom},
+};
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+const FORMAT: &str = "qcow2";
+pub(super) const MAGIC: [u8; 4] = *b"QFI\xfb";
+
+const HEADER_V2_SIZE: usize = 72;
+const HEADER_V3_SIZE: usize = 104;
+
+// Host offset bits of L1 and uncompressed L2 entries
+const OFFSET_MASK: u64 = 0x00ff_ffff_ffff_fe00;
+const L2_COMPRESSED: u64 = 1 << 62;
+const L2_ZERO: u64 = 1;
+
+// Incompatible feature bits
+const DIRTY: u64 = 1;
+const CORRUPT: u64 = 1 << 1;
+const EXTERNAL_DATA: u64 = 1 << 2;
+const COMPRESSION_TYPE: u64 = 1 << 3;
+const EXTENDED_L2: u64 = 1 << 4;
+
+// Limits qemu itself enforces
+const MAX_L1_SIZE: u64 = 32 * 1024 * 1024;
+const MAX_BACKING_NAME: u32 = 1023;
+// L2 tables kept in memory before the cache is dropped
+const MAX_CACHED_TABLES: usize = 64;
+
+/// A qcow2 image, read through its L1 and L2 tables. Unallocated clusters
+/// are served from the backing file, or read as zeros without one.
+pub struct Qcow2<R> {
+	inner: R,
+	size: u64,
+	cluster_bits: u32,
+	l1: Vec<u64>,
+	/// L2 tables read so far, by host offset
+	l2: HashMap<u64, Vec<u64>>,
+	zstd: bool,
+	/// The last compressed cluster, by host offset, since sector-sized
+	/// reads hit the same one repeatedly
+	inflated: Option<(u64, Vec<u8>)>,
+	backing_file: Option<String>,
+	backing: Option<Box<dyn BlockSource>>,
+}
+
+enum Cluster {
+	Data(u64),
+	/// Host offset and length of the compressed data
+	Compressed(u64, u64),
+	Zero,
+	Unallocated,
+}
+
+impl<R: Read + Seek> Qcow2<R> {
+	/// Reads the header and L1 table. A backing file named in the header
+	/// must be attached with [`set_backing`](Self::set_backing) before
+	/// reading clusters the image doesn't allocate.
+	pub fn new(mut inner: R) -> Result<Qcow2<R>> {
+		let mut raw = [0u8; HEADER_V3_SIZE + 1];
+		read_exact_at(&mut inner, 0, &mut raw, FORMAT, "truncated header")?;
+		let fields = Fields::new(&raw, "qcow2 header", HEADER_V2_SIZE)?;
+
+		let version = fields.u32_be(4)?;
+		if version != 2 && version != 3 {
+			return Err(unsupported("unknown version"));
+		}
+		let cluster_bits = fields.u32_be(20)?;
+		if !(9..=21).contains(&cluster_bits) {
+			return Err(invalid("cluster size out of range"));
+		}
+		if fields.u32_be(32)? != 0 {
+			return Err(unsupported("encrypted images"));
+		}
+
+		let mut zstd = false;
+		if version == 3 {
+			let incompatible = fields.u64_be(72)?;
+			if incompatible & EXTERNAL_DATA != 0 {
+				return Err(unsupported("external data files"));
+			}
+			if incompatible & EXTENDED_L2 != 0 {
+				return Err(unsupported("extended L2 entries"));
+			}
+			if incompatible & !(DIRTY | CORRUPT | COMPRESSION_TYPE) != 0 {
+				return Err(unsupported("unknown incompatible features"));
+			}
+			if incompatible & COMPRESSION_TYPE != 0 {
+				if fields.u32_be(100)? as usize <= HEADER_V3_SIZE {
+					return Err(invalid("header too short"));
+				}
+				zstd = match raw[HEADER_V3_SIZE] {
+					0 => false,
+					1 => true,
+					_ => return Err(unsupported("unknown compression type")),
+				};
+			}
+		}
+
+		let l1_len = u64::from(fields.u32_be(36)?) * 8;
+		if l1_len > MAX_L1_SIZE {
+			return Err(invalid("L1 table too large"));
+		}
+		let mut l1 = vec![0u8; l1_len as usize];
+		read_exact_at(
+			&mut inner,
+			fields.u64_be(40)?,
+			&mut l1,
+			FORMAT,
+			"L1 table out of bounds",
+		)?;
+
+		let (name_offset, name_len) = (fields.u64_be(8)?, fields.u32_be(16)?);
+		let backing_file = if name_offset == 0 || name_len == 0 {
+			None
+		} else if name_len > MAX_BACKING_NAME {
+			return Err(invalid("backing file name too long"));
+		} else {
+			let mut name = vec![0u8; name_len as usize];
+			read_exact_at(
+				&mut inner,
+				name_offset,
+				&mut name,
+				FORMAT,
+				"backing file name out of bounds",
+			)?;
+			Some(
+				String::from_utf8(name)
+					.map_err(|_| invalid("backing file name is not UTF-8"))?,
+			)
+		};
+
+		Ok(Self {
+			inner,
+			size: fields.u64_be(24)?,
+			cluster_bits,
+			l1: be_u64s(&l1),
+			l2: HashMap::new(),
+			zstd,
+			inflated: None,
+			backing_file,
+			backing: None,
+		})
+	}
+
+	/// The backing file named in the header, usually relative to the
+	/// image's directory.
+	pub fn backing_file(&self) -> Option<&str> { self.backing_file.as_deref() }
+
+	/// Serves unallocated clusters from `backing`.
+	pub fn set_backing(&mut self, backing: Box<dyn BlockSource>) {
+		self.backing = Some(backing);
+	}
+
+	fn cluster_size(&self) -> u64 { 1 << self.cluster_bits }
+
+	/// Where the cluster containing the guest offset `pos` is stored.
+	fn cluster(&mut self, pos: u64) -> io::Result<Cluster> {
+		let cluster = pos >> self.cluster_bits;
+		let per_table = 1 << (self.cluster_bits - 3);
+
+		// The L1 table may be shorter than the disk needs
+		let table = match self.l1.get((cluster / per_table) as usize) {
+			Some(&entry) if entry & OFFSET_MASK != 0 => entry & OFFSET_MASK,
+			_ => return Ok(Cluster::Unallocated),
+		};
+		let entry = self.l2_table(table)?[(cluster % per_table) as usize];
+
+		if entry & L2_COMPRESSED != 0 {
+			// The offset takes the low bits, the count of additional
+			// 512-byte sectors the rest
+			let offset_bits = 62 - (self.cluster_bits - 8);
+			let host = entry & ((1 << offset_bits) - 1);
+			let sectors = (entry & (L2_COMPRESSED - 1)) >> offset_bits;
+			return Ok(Cluster::Compressed(
+				host,
+				(sectors + 1) * 512 - (host & 511),
+			));
+		}
+
+		let host = entry & OFFSET_MASK;
+		Ok(if entry & L2_ZERO != 0 {
+			Cluster::Zero
+		} else if host == 0 {
+			Cluster::Unallocated
+		} else if host & (self.cluster_size() - 1) != 0 {
+			return Err(invalid_data(FORMAT, "unaligned data cluster"));
+		} else {
+			Cluster::Data(host)
+		})
+	}
+
+	fn l2_table(&mut self, offset: u64) -> io::Result<&[u64]> {
+		if offset & (self.cluster_size() - 1) != 0 {
+			return Err(invalid_data(FORMAT, "unaligned L2 table"));
+		}
+		if !self.l2.contains_key(&offset) {
+			let mut raw = vec![0u8; self.cluster_size() as usize];
+			self.inner.seek(SeekFrom::Start(offset))?;
+			self.inner.read_exact(&mut raw)?;
+			if self.l2.len() == MAX_CACHED_TABLES {
+				self.l2.clear();
+			}
+			self.l2.insert(offset, be_u64s(&raw));
+		}
+		// unwrap: inserted above
+		Ok(self.l2.get(&offset).unwrap())
+	}
+
+	fn inflate(&mut self, host: u64, len: u64) -> io::Result<&[u8]> {
+		if self.inflated.as_ref().map(|(at, _)| *at) != Some(host) {
+			// The sector count may run past the end of the file for the
+			// last cluster
+			let mut compressed = vec![];
+			self.inner.seek(SeekFrom::Start(host))?;
+			(&mut self.inner).take(len).read_to_end(&mut compressed)?;
+
+			let mut data = vec![0u8; self.cluster_size() as usize];
+			let decompressed = if self.zstd {
+				ZstdDecoder::with_buffer(compressed.as_slice())
+					.and_then(|mut decoder| decoder.read_exact(&mut data))
+			} else {
+				DeflateDecoder::new(compressed.as_slice()).read_exact(&mut data)
+			};
+			decompressed.map_err(|_| {
+				invalid_data(FORMAT, "corrupt compressed cluster")
+			})?;
+			self.inflated = Some((host, data));
+		}
+		// unwrap: set above
+		Ok(&self.inflated.as_ref().unwrap().1)
+	}
+
+	fn read_backing(&mut self, pos: u64, out: &mut [u8]) -> io::Result<()> {
+		match &mut self.backing {
+			Some(backing) => {
+				// Backing files may be smaller than the image
+				let available =
+					backing.size().saturating_sub(pos).min(out.len() as u64)
+						as usize;
+				if available > 0 {
+					backing.read_at(pos, &mut out[..available])?;
+				}
+				out[available..].iter_mut().for_each(|byte| *byte = 0);
+			}
+			None if self.backing_file.is_some() => {
+				return Err(io::Error::new(
+					io::ErrorKind::NotFound,
+					"qcow2: backing file not attached",
+				))
+			}
+			None => out.iter_mut().for_each(|byte| *byte = 0),
+		}
+		Ok(())
+	}
+}
+
+impl<R: Read + Seek> BlockSource for Qcow2<R> {
+	fn size(&self) -> u64 { self.size }
+
+	fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+		check_bounds(offset, buf.len(), self.size)?;
+
+		let cluster_size = self.cluster_size();
+		for_each_block(offset, buf, cluster_size, |pos, out| {
+			let within = pos & (cluster_size - 1);
+			match self.cluster(pos)? {
+				Cluster::Data(host) => {
+					self.inner.seek(SeekFrom::Start(host + within))?;
+					self.inner.read_exact(out)
+				}
+				Cluster::Compressed(host, deflated) => {
+					let data = self.inflate(host, deflated)?;
+					out.copy_from_slice(&data[within as usize..][..out.len()]);
+					Ok(())
+				}
+				Cluster::Zero => {
+					out.iter_mut().for_each(|byte| *byte = 0);
+					Ok(())
+				}
+				Cluster::Unallocated => self.read_backing(pos, out),
+			}
+		})
+	}
+}
+
+fn be_u64s(raw: &[u8]) -> Vec<u64> {
+	raw.chunks_exact(8)
+		.map(|chunk| {
+			let mut bytes = [0u8; 8];
+			bytes.copy_from_slice(chunk);
+			u64::from_be_bytes(bytes)
+		})
+		.collect()
+}
+
+fn invalid(what: &'static str) -> Error { Error::InvalidImage(FORMAT, what) }
+
+fn unsupported(what: &'static str) -> Error {
+	Error::UnsupportedImage(FORMAT, what)
+}
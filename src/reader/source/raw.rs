@@ -0,0 +1,24 @@
+use super::BlockSource;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// A disk stored byte for byte: a block device or a raw image.
+pub struct Raw<R> {
+	inner: R,
+	size: u64,
+}
+
+impl<R: Read + Seek> Raw<R> {
+	pub fn new(mut inner: R) -> io::Result<Raw<R>> {
+		let size = inner.seek(SeekFrom::End(0))?;
+		Ok(Self { inner, size })
+	}
+}
+
+impl<R: Read + Seek> BlockSource for Raw<R> {
+	fn size(&self) -> u64 { self.size }
+
+	fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+		self.inner.seek(SeekFrom::Start(offset))?;
+		self.inner.read_exact(buf)
+	}
+}
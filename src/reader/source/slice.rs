@@ -0,0 +1,43 @@
+use super::{check_bounds, BlockSource};
+use crate::{Error, Result};
+use std::io;
+
+/// A byte range of another disk, read as a disk of its own: an image
+/// stored in a partition or at an offset into a file.
+pub struct Slice<S> {
+	inner: S,
+	offset: u64,
+	size: u64,
+}
+
+impl<S: BlockSource> Slice<S> {
+	/// The `length` bytes of `inner` starting at `offset`, or everything
+	/// after `offset` without a length. Fails if the range doesn't fit.
+	pub fn new(inner: S, offset: u64, length: Option<u64>) -> Result<Slice<S>> {
+		let available = inner.size().checked_sub(offset);
+		let size = match (available, length) {
+			(Some(available), None) => available,
+			(Some(available), Some(length)) if length <= available => length,
+			_ => {
+				let end = offset.saturating_add(length.unwrap_or(0));
+				return Err(Error::OutOfRange(end, inner.size()));
+			}
+		};
+		Ok(Self {
+			inner,
+			offset,
+			size,
+		})
+	}
+}
+
+impl<S: BlockSource> BlockSource for Slice<S> {
+	fn size(&self) -> u64 { self.size }
+
+	fn seekable(&self) -> bool { self.inner.seekable() }
+
+	fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+		check_bounds(offset, buf.len(), self.size)?;
+		self.inner.read_at(self.offset + offset, buf)
+	}
+}
@@ -0,0 +1,186 @@
+use super::{
+	check_bounds, for_each_block, invalid_data, read_exact_at, BlockSource,
+};
+use crate::{reader::bytes::Fields, Error, Result};
+use std::{
+	convert::TryInto,
+	io::{self, Read, Seek, SeekFrom},
+};
+
+const FORMAT: &str = "vhd";
+pub(super) const FOOTER_MAGIC: [u8; 8] = *b"conectix";
+const DYNAMIC_MAGIC: [u8; 8] = *b"cxsparse";
+
+const FOOTER_SIZE: u64 = 512;
+const DYNAMIC_HEADER_SIZE: usize = 1024;
+
+// Disk types
+const FIXED: u32 = 2;
+const DYNAMIC: u32 = 3;
+const DIFFERENCING: u32 = 4;
+
+const UNALLOCATED: u32 = 0xFFFF_FFFF;
+// The spec allows up to 2040GiB, i.e. about a million 2MiB blocks
+const MAX_BAT_SIZE: u64 = 32 * 1024 * 1024;
+
+/// A Virtual PC / Hyper-V VHD image, either fixed (raw data followed by a
+/// footer) or dynamic (blocks allocated through a table).
+pub struct Vhd<R> {
+	inner: R,
+	size: u64,
+	/// Sector of each block's bitmap, `None` when unallocated. Empty for
+	/// fixed images.
+	bat: Vec<Option<u32>>,
+	block_size: u64,
+	/// Bytes of sector bitmap in front of each block's data
+	bitmap_size: u64,
+}
+
+impl<R: Read + Seek> Vhd<R> {
+	/// Reads the footer at the end of `inner` and, for dynamic images, the
+	/// header and block allocation table it points to.
+	pub fn new(mut inner: R) -> Result<Vhd<R>> {
+		let len = inner.seek(SeekFrom::End(0))?;
+		let mut footer = [0u8; FOOTER_SIZE as usize];
+		let at = len
+			.checked_sub(FOOTER_SIZE)
+			.ok_or_else(|| invalid("truncated footer"))?;
+		read_exact_at(&mut inner, at, &mut footer, FORMAT, "truncated footer")?;
+
+		if footer[..8] != FOOTER_MAGIC {
+			// Dynamic images keep a copy at the start in case the end is
+			// lost
+			read_exact_at(
+				&mut inner,
+				0,
+				&mut footer,
+				FORMAT,
+				"missing footer",
+			)?;
+			if footer[..8] != FOOTER_MAGIC {
+				return Err(invalid("missing footer"));
+			}
+		}
+		let fields = Fields::new(&footer, "vhd footer", FOOTER_SIZE as usize)?;
+		if checksum(&footer, 64) != fields.u32_be(64)? {
+			return Err(invalid("footer checksum mismatch"));
+		}
+
+		let size = fields.u64_be(48)?;
+		match fields.u32_be(60)? {
+			FIXED => {
+				return Ok(Self {
+					inner,
+					// The data is everything in front of the footer
+					size: size.min(at),
+					bat: vec![],
+					block_size: 0,
+					bitmap_size: 0,
+				});
+			}
+			DYNAMIC => {}
+			DIFFERENCING => return Err(unsupported("differencing disks")),
+			_ => return Err(unsupported("unknown disk type")),
+		}
+
+		let mut raw = [0u8; DYNAMIC_HEADER_SIZE];
+		read_exact_at(
+			&mut inner,
+			fields.u64_be(16)?,
+			&mut raw,
+			FORMAT,
+			"dynamic header out of bounds",
+		)?;
+		if raw[..8] != DYNAMIC_MAGIC {
+			return Err(invalid("missing dynamic header"));
+		}
+		let header = Fields::new(&raw, "vhd header", DYNAMIC_HEADER_SIZE)?;
+		if checksum(&raw, 36) != header.u32_be(36)? {
+			return Err(invalid("dynamic header checksum mismatch"));
+		}
+
+		let block_size = u64::from(header.u32_be(32)?);
+		if block_size < 512 || !block_size.is_power_of_two() {
+			return Err(invalid("block size out of range"));
+		}
+		let bat_len = u64::from(header.u32_be(28)?) * 4;
+		if bat_len > MAX_BAT_SIZE {
+			return Err(invalid("block allocation table too large"));
+		}
+		let mut bat = vec![0u8; bat_len as usize];
+		read_exact_at(
+			&mut inner,
+			header.u64_be(16)?,
+			&mut bat,
+			FORMAT,
+			"block allocation table out of bounds",
+		)?;
+
+		// One bit per sector, padded to whole sectors
+		let bitmap_size = (block_size / 512).div_ceil(8).div_ceil(512) * 512;
+		Ok(Self {
+			inner,
+			size,
+			bat: bat
+				.chunks_exact(4)
+				// unwrap: chunks of exactly 4 bytes
+				.map(|entry| {
+					match u32::from_be_bytes(entry.try_into().unwrap()) {
+						UNALLOCATED => None,
+						sector => Some(sector),
+					}
+				})
+				.collect(),
+			block_size,
+			bitmap_size,
+		})
+	}
+}
+
+impl<R: Read + Seek> BlockSource for Vhd<R> {
+	fn size(&self) -> u64 { self.size }
+
+	fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+		check_bounds(offset, buf.len(), self.size)?;
+		if self.block_size == 0 {
+			self.inner.seek(SeekFrom::Start(offset))?;
+			return self.inner.read_exact(buf);
+		}
+
+		let (block_size, bitmap_size) = (self.block_size, self.bitmap_size);
+		for_each_block(offset, buf, block_size, |pos, out| {
+			let block = (pos / block_size) as usize;
+			match self.bat.get(block).copied().flatten() {
+				Some(sector) => {
+					let data = u64::from(sector) * 512 + bitmap_size;
+					self.inner
+						.seek(SeekFrom::Start(data + pos % block_size))?;
+					self.inner.read_exact(out).map_err(|_| {
+						invalid_data(FORMAT, "block out of bounds")
+					})
+				}
+				None => {
+					out.iter_mut().for_each(|byte| *byte = 0);
+					Ok(())
+				}
+			}
+		})
+	}
+}
+
+/// One's complement of the byte sum, skipping the checksum field at
+/// `field`.
+fn checksum(bytes: &[u8], field: usize) -> u32 {
+	let sum = bytes
+		.iter()
+		.enumerate()
+		.filter(|&(idx, _)| !(field..field + 4).contains(&idx))
+		.fold(0u32, |sum, (_, &byte)| sum.wrapping_add(u32::from(byte)));
+	!sum
+}
+
+fn invalid(what: &'static str) -> Error { Error::InvalidImage(FORMAT, what) }
+
+fn unsupported(what: &'static str) -> Error {
+	Error::UnsupportedImage(FORMAT, what)
+}
@@ -0,0 +1,324 @@
+use super::{
+	check_bounds, for_each_block, invalid_data, read_exact_at, BlockSource,
+};
+use crate::{guid::Guid, reader::bytes::Fields, Error, Result};
+use std::io::{self, Read, Seek, SeekFrom};
+
+const FORMAT: &str = "vhdx";
+pub(super) const MAGIC: [u8; 8] = *b"vhdxfile";
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+
+// Both copies of the header and of the region table sit at fixed offsets
+const HEADERS: [u64; 2] = [64 * KIB, 128 * KIB];
+const REGION_TABLES: [u64; 2] = [192 * KIB, 256 * KIB];
+const HEADER_SIZE: usize = 4 * KIB as usize;
+const TABLE_SIZE: usize = 64 * KIB as usize;
+const MAX_TABLE_ENTRIES: usize = 2047;
+
+const BAT_REGION: Guid =
+	Guid::from_groups(0x2DC27766, 0xF623, 0x4200, 0x9D64, 0x115E9BFD4A08);
+const METADATA_REGION: Guid =
+	Guid::from_groups(0x8B7CA206, 0x4790, 0x4B9A, 0xB8FE, 0x575F050F886E);
+const FILE_PARAMETERS: Guid =
+	Guid::from_groups(0xCAA16737, 0xFA36, 0x4D43, 0xB3B6, 0x33F0AA44E76B);
+const VIRTUAL_DISK_SIZE: Guid =
+	Guid::from_groups(0x2FA54224, 0xCD1B, 0x4876, 0xB211, 0x5DBED83BF4B8);
+const LOGICAL_SECTOR_SIZE: Guid =
+	Guid::from_groups(0x8141BF1D, 0xA96F, 0x4709, 0xBA47, 0xF233A8FAAB5F);
+
+const HAS_PARENT: u32 = 1 << 1;
+const REQUIRED_REGION: u32 = 1;
+const REQUIRED_ITEM: u32 = 1 << 2;
+
+// Payload block states; the lower ones all read as zeros
+const FULLY_PRESENT: u64 = 6;
+const PARTIALLY_PRESENT: u64 = 7;
+
+/// A Hyper-V VHDX image, read through its block allocation table.
+pub struct Vhdx<R> {
+	inner: R,
+	size: u64,
+	block_size: u64,
+	/// Payload blocks per sector bitmap block, which are interleaved in the
+	/// table
+	chunk_ratio: u64,
+	bat_offset: u64,
+	bat_entries: u64,
+}
+
+impl<R: Read + Seek> Vhdx<R> {
+	/// Reads the newest valid header, the region table and the metadata
+	/// items needed to address the disk.
+	pub fn new(mut inner: R) -> Result<Vhdx<R>> {
+		let mut magic = [0u8; 8];
+		read_exact_at(&mut inner, 0, &mut magic, FORMAT, "truncated header")?;
+		if magic != MAGIC {
+			return Err(invalid("missing file identifier"));
+		}
+
+		// The one with the highest sequence number was written last
+		let mut newest: Option<(u64, Vec<u8>)> = None;
+		for &offset in HEADERS.iter() {
+			let raw =
+				match read_table(&mut inner, offset, HEADER_SIZE, b"head")? {
+					Some(raw) => raw,
+					None => continue,
+				};
+			let sequence = Fields::new(&raw, "vhdx header", 80)?.u64(8)?;
+			if newest.as_ref().map_or(true, |(seen, _)| sequence > *seen) {
+				newest = Some((sequence, raw));
+			}
+		}
+		let raw = match newest {
+			Some((_, raw)) => raw,
+			None => return Err(invalid("no valid header")),
+		};
+		let header = Fields::new(&raw, "vhdx header", 80)?;
+		if !header.guid(48)?.is_nil() {
+			return Err(unsupported("log replay required"));
+		}
+		if u16::from_le_bytes([raw[66], raw[67]]) != 1 {
+			return Err(unsupported("unknown version"));
+		}
+
+		let (bat, metadata) = regions(&mut inner)?;
+		let metadata = Metadata::read(&mut inner, metadata)?;
+		if metadata.flags & HAS_PARENT != 0 {
+			return Err(unsupported("differencing disks"));
+		}
+		let block_size = u64::from(metadata.block_size);
+		if !(MIB..=256 * MIB).contains(&block_size)
+			|| !block_size.is_power_of_two()
+		{
+			return Err(invalid("block size out of range"));
+		}
+		let sector_size = u64::from(metadata.sector_size);
+		if sector_size != 512 && sector_size != 4096 {
+			return Err(invalid("logical sector size out of range"));
+		}
+
+		let chunk_ratio = (1 << 23) * sector_size / block_size;
+		let blocks = metadata.size.div_ceil(block_size);
+		let bat_entries = blocks + blocks.saturating_sub(1) / chunk_ratio;
+		if bat_entries.saturating_mul(8) > u64::from(bat.1) {
+			return Err(invalid("block allocation table too small"));
+		}
+
+		Ok(Self {
+			inner,
+			size: metadata.size,
+			block_size,
+			chunk_ratio,
+			bat_offset: bat.0,
+			bat_entries,
+		})
+	}
+
+	fn bat_entry(&mut self, block: u64) -> io::Result<u64> {
+		let index = block + block / self.chunk_ratio;
+		if index >= self.bat_entries {
+			return Err(invalid_data(FORMAT, "block out of range"));
+		}
+		let mut entry = [0u8; 8];
+		self.inner
+			.seek(SeekFrom::Start(self.bat_offset + index * 8))?;
+		self.inner.read_exact(&mut entry)?;
+		Ok(u64::from_le_bytes(entry))
+	}
+}
+
+impl<R: Read + Seek> BlockSource for Vhdx<R> {
+	fn size(&self) -> u64 { self.size }
+
+	fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+		check_bounds(offset, buf.len(), self.size)?;
+
+		let block_size = self.block_size;
+		for_each_block(offset, buf, block_size, |pos, out| {
+			let entry = self.bat_entry(pos / block_size)?;
+			match entry & 7 {
+				FULLY_PRESENT => {
+					let data = entry & !(MIB - 1);
+					self.inner
+						.seek(SeekFrom::Start(data + pos % block_size))?;
+					self.inner.read_exact(out)
+				}
+				PARTIALLY_PRESENT => Err(invalid_data(
+					FORMAT,
+					"partially present block without a parent",
+				)),
+				0..=3 => {
+					out.iter_mut().for_each(|byte| *byte = 0);
+					Ok(())
+				}
+				_ => Err(invalid_data(FORMAT, "unknown block state")),
+			}
+		})
+	}
+}
+
+/// The metadata items gptinfo needs.
+struct Metadata {
+	block_size: u32,
+	flags: u32,
+	size: u64,
+	sector_size: u32,
+}
+
+impl Metadata {
+	fn read<R: Read + Seek>(
+		inner: &mut R,
+		(region, len): (u64, u32),
+	) -> Result<Metadata> {
+		if (len as usize) < TABLE_SIZE {
+			return Err(invalid("metadata region too small"));
+		}
+		let mut raw = vec![0u8; TABLE_SIZE];
+		read_exact_at(inner, region, &mut raw, FORMAT, "truncated metadata")?;
+		if raw[..8] != *b"metadata" {
+			return Err(invalid("missing metadata table"));
+		}
+		let table = Fields::new(&raw, "vhdx metadata table", 32)?;
+		let count = usize::from(u16::from_le_bytes([raw[10], raw[11]]));
+		if count > MAX_TABLE_ENTRIES {
+			return Err(invalid("too many metadata items"));
+		}
+
+		let (mut block_size, mut flags, mut size, mut sector_size) =
+			(None, None, None, None);
+		for idx in 0..count {
+			let at = 32 + idx * 32;
+			let id = table.guid(at)?;
+			let offset = table.u32(at + 16)?;
+			let item_len = table.u32(at + 20)?;
+			let item_flags = table.u32(at + 24)?;
+
+			let mut item = [0u8; 8];
+			let needed: u64 = match id {
+				FILE_PARAMETERS | VIRTUAL_DISK_SIZE => 8,
+				LOGICAL_SECTOR_SIZE => 4,
+				_ if item_flags & REQUIRED_ITEM != 0 => {
+					return Err(unsupported("unknown required metadata"))
+				}
+				_ => continue,
+			};
+			if u64::from(item_len) < needed
+				|| u64::from(offset) + needed > u64::from(len)
+			{
+				return Err(invalid("metadata item out of bounds"));
+			}
+			read_exact_at(
+				inner,
+				region + u64::from(offset),
+				&mut item[..needed as usize],
+				FORMAT,
+				"metadata item out of bounds",
+			)?;
+
+			let item = Fields::new(&item, "vhdx metadata item", 8)?;
+			match id {
+				FILE_PARAMETERS => {
+					block_size = Some(item.u32(0)?);
+					flags = Some(item.u32(4)?);
+				}
+				VIRTUAL_DISK_SIZE => size = Some(item.u64(0)?),
+				_ => sector_size = Some(item.u32(0)?),
+			}
+		}
+
+		match (block_size, flags, size, sector_size) {
+			(Some(block_size), Some(flags), Some(size), Some(sector_size)) => {
+				Ok(Self {
+					block_size,
+					flags,
+					size,
+					sector_size,
+				})
+			}
+			_ => Err(invalid("missing required metadata")),
+		}
+	}
+}
+
+/// Offset and length of the block allocation table and metadata regions,
+/// from the first valid region table.
+fn regions<R: Read + Seek>(inner: &mut R) -> Result<((u64, u32), (u64, u32))> {
+	let mut table = None;
+	for &offset in REGION_TABLES.iter() {
+		table = read_table(inner, offset, TABLE_SIZE, b"regi")?;
+		if table.is_some() {
+			break;
+		}
+	}
+	let raw = table.ok_or_else(|| invalid("no valid region table"))?;
+	let table = Fields::new(&raw, "vhdx region table", TABLE_SIZE)?;
+
+	let count = table.u32(8)? as usize;
+	if count > MAX_TABLE_ENTRIES {
+		return Err(invalid("too many regions"));
+	}
+
+	let (mut bat, mut metadata) = (None, None);
+	for idx in 0..count {
+		let at = 16 + idx * 32;
+		let region = (table.u64(at + 16)?, table.u32(at + 24)?);
+		match table.guid(at)? {
+			BAT_REGION => bat = Some(region),
+			METADATA_REGION => metadata = Some(region),
+			_ if table.u32(at + 28)? & REQUIRED_REGION != 0 => {
+				return Err(unsupported("unknown required region"))
+			}
+			_ => {}
+		}
+	}
+
+	match (bat, metadata) {
+		(Some(bat), Some(metadata)) => Ok((bat, metadata)),
+		_ => Err(invalid("missing required region")),
+	}
+}
+
+/// Reads a `len`-byte structure at `offset`, returning it if it carries
+/// `signature` and a matching CRC-32C at bytes 4 to 8.
+fn read_table<R: Read + Seek>(
+	inner: &mut R,
+	offset: u64,
+	len: usize,
+	signature: &[u8; 4],
+) -> Result<Option<Vec<u8>>> {
+	let mut raw = vec![0u8; len];
+	read_exact_at(inner, offset, &mut raw, FORMAT, "truncated header")?;
+	if raw[..4] != signature[..] {
+		return Ok(None);
+	}
+
+	let stored = Fields::new(&raw, "vhdx header", 8)?.u32(4)?;
+	raw[4..8].copy_from_slice(&[0; 4]);
+	let valid = crc32c(&raw) == stored;
+	raw[4..8].copy_from_slice(&stored.to_le_bytes());
+	Ok(if valid { Some(raw) } else { None })
+}
+
+/// CRC-32C (Castagnoli), which VHDX uses instead of the zlib polynomial.
+fn crc32c(bytes: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for &byte in bytes {
+		crc ^= u32::from(byte);
+		for _ in 0..8 {
+			crc = if crc & 1 != 0 {
+				(crc >> 1) ^ 0x82F6_3B78
+			} else {
+				crc >> 1
+			};
+		}
+	}
+	!crc
+}
+
+fn invalid(what: &'static str) -> Error { Error::InvalidImage(FORMAT, what) }
+
+fn unsupported(what: &'static str) -> Error {
+	Error::UnsupportedImage(FORMAT, what)
+}
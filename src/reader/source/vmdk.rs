@@ -0,0 +1,215 @@
+use super::{
+	check_bounds, for_each_block, invalid_data, read_exact_at, BlockSource,
+};
+use crate::{reader::bytes::Fields, Error, Result};
+use flate2::read::ZlibDecoder;
+use std::io::{self, Read, Seek, SeekFrom};
+
+const FORMAT: &str = "vmdk";
+pub(super) const MAGIC: [u8; 4] = *b"KDMV";
+/// Start of a text descriptor, which only points at the extent files
+pub(super) const DESCRIPTOR: &[u8] = b"# Disk DescriptorFile";
+
+const SECTOR: u64 = 512;
+const HEADER_SIZE: usize = 79;
+// Stream-optimized images write the grain directory last and say so here;
+// the real offset is in the footer copy of the header
+const GD_AT_END: u64 = u64::MAX;
+
+const COMPRESSED: u32 = 1 << 16;
+const DEFLATE: u16 = 1;
+
+// Grain table entries with special meaning
+const UNALLOCATED: u32 = 0;
+const ZERO_GRAIN: u32 = 1;
+
+const MAX_GRAIN_SECTORS: u64 = 1 << 16;
+const MAX_DESCRIPTOR_SECTORS: u64 = 2048;
+
+/// A VMware monolithic sparse VMDK extent, including stream-optimized
+/// ones with compressed grains.
+pub struct Vmdk<R> {
+	inner: R,
+	size: u64,
+	grain_size: u64,
+	gtes_per_gt: u64,
+	gd_offset: u64,
+	gd_entries: u64,
+	compressed: bool,
+	/// The last compressed grain, by sector
+	inflated: Option<(u32, Vec<u8>)>,
+}
+
+impl<R: Read + Seek> Vmdk<R> {
+	/// Reads the sparse extent header, or its footer copy for
+	/// stream-optimized images.
+	pub fn new(mut inner: R) -> Result<Vmdk<R>> {
+		let mut raw = [0u8; HEADER_SIZE];
+		read_exact_at(&mut inner, 0, &mut raw, FORMAT, "truncated header")?;
+		if raw[..4] != MAGIC {
+			return Err(invalid("missing header"));
+		}
+		if Fields::new(&raw, "vmdk header", HEADER_SIZE)?.u64(56)? == GD_AT_END
+		{
+			let len = inner.seek(SeekFrom::End(0))?;
+			let footer = len
+				.checked_sub(2 * SECTOR)
+				.ok_or_else(|| invalid("truncated footer"))?;
+			read_exact_at(
+				&mut inner,
+				footer,
+				&mut raw,
+				FORMAT,
+				"missing footer",
+			)?;
+			if raw[..4] != MAGIC {
+				return Err(invalid("missing footer"));
+			}
+		}
+		let header = Fields::new(&raw, "vmdk header", HEADER_SIZE)?;
+
+		if !(1..=3).contains(&header.u32(4)?) {
+			return Err(unsupported("unknown version"));
+		}
+		let flags = header.u32(8)?;
+		let compressed = flags & COMPRESSED != 0;
+		if compressed && u16::from_le_bytes([raw[77], raw[78]]) != DEFLATE {
+			return Err(unsupported("unknown compression algorithm"));
+		}
+
+		let grain_size = header.u64(20)?;
+		if !(1..=MAX_GRAIN_SECTORS).contains(&grain_size)
+			|| !grain_size.is_power_of_two()
+		{
+			return Err(invalid("grain size out of range"));
+		}
+		let gtes_per_gt = u64::from(header.u32(44)?);
+		if gtes_per_gt == 0 {
+			return Err(invalid("empty grain tables"));
+		}
+		let capacity = header.u64(12)?;
+		let size = capacity
+			.checked_mul(SECTOR)
+			.ok_or_else(|| invalid("capacity out of range"))?;
+
+		let (descriptor, descriptor_len) = (header.u64(28)?, header.u64(36)?);
+		if descriptor != 0 && descriptor_len <= MAX_DESCRIPTOR_SECTORS {
+			let mut text = vec![0u8; (descriptor_len * SECTOR) as usize];
+			read_exact_at(
+				&mut inner,
+				descriptor.saturating_mul(SECTOR),
+				&mut text,
+				FORMAT,
+				"descriptor out of bounds",
+			)?;
+			// Delta disks only hold what changed since their parent
+			if String::from_utf8_lossy(&text).contains("parentFileNameHint") {
+				return Err(unsupported("delta disks"));
+			}
+		}
+
+		Ok(Self {
+			inner,
+			size,
+			grain_size,
+			gtes_per_gt,
+			gd_offset: header.u64(56)?,
+			gd_entries: capacity.div_ceil(grain_size * gtes_per_gt),
+			compressed,
+			inflated: None,
+		})
+	}
+
+	fn read_u32(&mut self, sector: u64, index: u64) -> io::Result<u32> {
+		let mut raw = [0u8; 4];
+		let offset = sector
+			.checked_mul(SECTOR)
+			.and_then(|offset| offset.checked_add(index * 4))
+			.ok_or_else(|| invalid_data(FORMAT, "table out of range"))?;
+		self.inner.seek(SeekFrom::Start(offset))?;
+		self.inner.read_exact(&mut raw)?;
+		Ok(u32::from_le_bytes(raw))
+	}
+
+	/// The sector holding the grain that covers `pos`, or a special entry.
+	fn grain(&mut self, pos: u64) -> io::Result<u32> {
+		let grain = pos / SECTOR / self.grain_size;
+		let table = grain / self.gtes_per_gt;
+		if table >= self.gd_entries {
+			return Ok(UNALLOCATED);
+		}
+		match self.read_u32(self.gd_offset, table)? {
+			0 => Ok(UNALLOCATED),
+			table_sector => {
+				self.read_u32(u64::from(table_sector), grain % self.gtes_per_gt)
+			}
+		}
+	}
+
+	fn inflate(&mut self, sector: u32) -> io::Result<&[u8]> {
+		if self.inflated.as_ref().map(|(at, _)| *at) != Some(sector) {
+			let grain_bytes = self.grain_size * SECTOR;
+
+			// A marker with the grain's LBA and compressed size comes
+			// first
+			let mut marker = [0u8; 12];
+			self.inner
+				.seek(SeekFrom::Start(u64::from(sector) * SECTOR))?;
+			self.inner.read_exact(&mut marker)?;
+			let len = u64::from(u32::from_le_bytes([
+				marker[8], marker[9], marker[10], marker[11],
+			]));
+			if len > 2 * grain_bytes {
+				return Err(invalid_data(FORMAT, "compressed grain too large"));
+			}
+
+			let mut deflated = vec![0u8; len as usize];
+			self.inner.read_exact(&mut deflated)?;
+			let mut data = vec![0u8; grain_bytes as usize];
+			ZlibDecoder::new(deflated.as_slice())
+				.read_exact(&mut data)
+				.map_err(|_| {
+					invalid_data(FORMAT, "corrupt compressed grain")
+				})?;
+			self.inflated = Some((sector, data));
+		}
+		// unwrap: set above
+		Ok(&self.inflated.as_ref().unwrap().1)
+	}
+}
+
+impl<R: Read + Seek> BlockSource for Vmdk<R> {
+	fn size(&self) -> u64 { self.size }
+
+	fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+		check_bounds(offset, buf.len(), self.size)?;
+
+		let grain_bytes = self.grain_size * SECTOR;
+		for_each_block(offset, buf, grain_bytes, |pos, out| {
+			let within = pos % grain_bytes;
+			match self.grain(pos)? {
+				UNALLOCATED | ZERO_GRAIN => {
+					out.iter_mut().for_each(|byte| *byte = 0);
+					Ok(())
+				}
+				sector if self.compressed => {
+					let data = self.inflate(sector)?;
+					out.copy_from_slice(&data[within as usize..][..out.len()]);
+					Ok(())
+				}
+				sector => {
+					self.inner.seek(SeekFrom::Start(
+						u64::from(sector) * SECTOR + within,
+					))?;
+					self.inner.read_exact(out)
+				}
+			}
+		})
+	}
+}
+
+fn invalid(what: &'static str) -> Error { Error::InvalidImage(FORMAT, what) }
+
+fn unsupported(what: &'static str) -> Error {
+	Error::UnsupportedImage(FORMAT, what)
+}
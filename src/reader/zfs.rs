@@ -0,0 +1,280 @@
+use super::{bytes::Fields, source::BlockSource};
+use crate::{guid::Guid, Error, Result};
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, convert::TryInto, fmt};
+
+// Each vdev has four copies of its label, two at the front and two at the
+// back of the device, which is rounded down to whole labels
+const LABEL_SIZE: u64 = 256 * 1024;
+const LABELS: u64 = 4;
+// The nvlist follows 8K of blank space and an 8K boot block header
+const NVLIST_OFFSET: u64 = 16 * 1024;
+const NVLIST_SIZE: usize = 112 * 1024;
+// The embedded checksum that closes the nvlist area: a magic, then a
+// SHA-256 digest in four words
+const TAIL_SIZE: usize = 40;
+const TAIL_MAGIC: u64 = 0x0210_DA7A_B10C_7A11;
+const ENCODE_XDR: u8 = 1;
+// nvpair data types read here; the others are skipped
+const DATA_TYPE_UINT64: u32 = 8;
+const DATA_TYPE_STRING: u32 = 9;
+
+const GPT_TYPES: &[Guid] = &[
+	// FreeBSD
+	Guid::from_groups(0x516E7CBA, 0x6ECF, 0x11D6, 0x8FF8, 0x00022D09712B),
+	// Darwin, and Solaris /usr, which OpenZFS puts whole-disk pools in
+	Guid::from_groups(0x6A898CC3, 0x1DD2, 0x11B2, 0x99A6, 0x080020736631),
+	// MidnightBSD
+	Guid::from_groups(0x85D5E45D, 0x237C, 0x11E1, 0xB4B3, 0xE89A8F7FC3A7),
+];
+
+/// The labels of a ZFS vdev: front two first, then back two.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zfs {
+	/// `None` where a copy is missing or damaged. Only the front two when
+	/// the disk isn't [`seekable`](BlockSource::seekable), as the back two
+	/// aren't checked then.
+	pub labels: Vec<Option<Label>>,
+}
+
+/// What one copy of a vdev label says about its pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+	/// `None` on spares and cache devices, which belong to no pool
+	pub pool: Option<String>,
+	pub pool_guid: Option<u64>,
+	/// This vdev's GUID
+	pub guid: u64,
+	/// Transaction group the label was last written in
+	pub txg: u64,
+	pub state: PoolState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolState {
+	Active,
+	Exported,
+	Destroyed,
+	Spare,
+	L2Cache,
+	Uninitialized,
+	Unavailable,
+	PotentiallyActive,
+	Unknown(u64),
+}
+
+impl From<u64> for PoolState {
+	fn from(state: u64) -> PoolState {
+		match state {
+			0 => PoolState::Active,
+			1 => PoolState::Exported,
+			2 => PoolState::Destroyed,
+			3 => PoolState::Spare,
+			4 => PoolState::L2Cache,
+			5 => PoolState::Uninitialized,
+			6 => PoolState::Unavailable,
+			7 => PoolState::PotentiallyActive,
+			_ => PoolState::Unknown(state),
+		}
+	}
+}
+
+impl fmt::Display for PoolState {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			PoolState::Active => write!(f, "active"),
+			PoolState::Exported => write!(f, "exported"),
+			PoolState::Destroyed => write!(f, "destroyed"),
+			PoolState::Spare => write!(f, "spare"),
+			PoolState::L2Cache => write!(f, "cache"),
+			PoolState::Uninitialized => write!(f, "uninitialized"),
+			PoolState::Unavailable => write!(f, "unavailable"),
+			PoolState::PotentiallyActive => write!(f, "potentially active"),
+			PoolState::Unknown(state) => write!(f, "state {}", state),
+		}
+	}
+}
+
+impl Zfs {
+	/// The label written last, which ZFS itself would go by.
+	pub fn freshest(&self) -> Option<&Label> {
+		self.labels.iter().flatten().max_by_key(|label| label.txg)
+	}
+
+	/// Whether all the copies checked are there and say the same.
+	pub fn consistent(&self) -> bool {
+		let first = &self.labels[0];
+		first.is_some() && self.labels.iter().all(|label| label == first)
+	}
+}
+
+/// Whether GPT partitions of this type hold a ZFS vdev.
+pub fn gpt_type_holds_pool(type_guid: Guid) -> bool {
+	GPT_TYPES.contains(&type_guid)
+}
+
+/// Reads the four labels of the vdev in the `size` bytes from byte
+/// `offset` of `src`, or the front two if it isn't
+/// [`seekable`](BlockSource::seekable). Returns `None` if there are none,
+/// and an error if every copy there is is damaged.
+pub fn read(
+	src: &mut dyn BlockSource,
+	offset: u64,
+	size: u64,
+) -> Result<Option<Zfs>> {
+	let size = size & !(LABEL_SIZE - 1);
+	if size < LABELS * LABEL_SIZE {
+		return Ok(None);
+	}
+
+	// Going back to the start of the next partition from the end of this
+	// one would mean decompressing the image again
+	let checked = if src.seekable() { LABELS } else { LABELS / 2 };
+	let mut labels = vec![];
+	let mut damage = None;
+	for idx in 0..checked {
+		let at = if idx < LABELS / 2 {
+			idx * LABEL_SIZE
+		} else {
+			size - (LABELS - idx) * LABEL_SIZE
+		};
+		match read_label(src, offset, at + NVLIST_OFFSET) {
+			Ok(label) => labels.push(label),
+			Err(err) => {
+				damage.get_or_insert(err);
+				labels.push(None);
+			}
+		}
+	}
+
+	match damage {
+		Some(err) if labels.iter().all(Option::is_none) => Err(err),
+		_ if labels.iter().all(Option::is_none) => Ok(None),
+		_ => Ok(Some(Zfs { labels })),
+	}
+}
+
+/// Reads the nvlist `at` bytes into the vdev, checking it against the
+/// checksum that closes it. Returns `None` if there is none.
+fn read_label(
+	src: &mut dyn BlockSource,
+	offset: u64,
+	at: u64,
+) -> Result<Option<Label>> {
+	let invalid = Error::InvalidZfs;
+
+	let mut raw = vec![0u8; NVLIST_SIZE];
+	if src.read_at(offset.saturating_add(at), &mut raw).is_err() {
+		return Ok(None);
+	}
+	let tail = NVLIST_SIZE - TAIL_SIZE;
+	let fields = Fields::new(&raw, "ZFS label", NVLIST_SIZE)?;
+	// The tail is in the byte order of whoever wrote the label
+	let little_endian = match fields.u64(tail)? {
+		TAIL_MAGIC => true,
+		magic if magic.swap_bytes() == TAIL_MAGIC => false,
+		_ => return Ok(None),
+	};
+	let word = |idx: usize| {
+		let at = tail + 8 + idx * 8;
+		if little_endian {
+			fields.u64(at)
+		} else {
+			fields.u64_be(at)
+		}
+	};
+	let expected = (0..4).map(word).collect::<Result<Vec<_>>>()?;
+
+	// The checksum is taken with the label's own offset in place of the
+	// digest, so a copy moved elsewhere doesn't pass
+	let mut verifier = [0u8; 32];
+	verifier[..8].copy_from_slice(
+		&if little_endian {
+			at.to_le_bytes()
+		} else {
+			at.to_be_bytes()
+		},
+	);
+	let mut full = raw.clone();
+	full[tail + 8..].copy_from_slice(&verifier);
+	let digest = Sha256::digest(&full);
+	let matches = digest
+		.chunks_exact(8)
+		.map(|chunk| u64::from_be_bytes(chunk.try_into().unwrap()))
+		.eq(expected);
+	if !matches {
+		return Err(invalid("checksum mismatch"));
+	}
+
+	if raw[0] != ENCODE_XDR {
+		return Err(invalid("unsupported nvlist encoding"));
+	}
+	let pairs = read_nvlist(&raw[4..tail])?;
+	let number = |name: &str| match pairs.get(name) {
+		Some(Value::Number(value)) => Some(*value),
+		_ => None,
+	};
+	let pool = match pairs.get("name") {
+		Some(Value::String(name)) => Some(name.clone()),
+		_ => None,
+	};
+
+	Ok(Some(Label {
+		pool,
+		pool_guid: number("pool_guid"),
+		guid: number("guid").ok_or(invalid("no vdev GUID"))?,
+		txg: number("txg").unwrap_or(0),
+		state: PoolState::from(
+			number("state").ok_or(invalid("no pool state"))?,
+		),
+	}))
+}
+
+enum Value {
+	Number(u64),
+	String(String),
+}
+
+/// Reads the top level of an XDR-encoded nvlist, keeping its numbers and
+/// strings. Nested lists, such as the vdev tree, are skipped.
+fn read_nvlist(raw: &[u8]) -> Result<BTreeMap<String, Value>> {
+	let invalid = Error::InvalidZfs;
+	let fields = Fields::new(raw, "ZFS nvlist", raw.len())?;
+
+	let string = |at: usize| -> Result<(String, usize)> {
+		let len = fields.u32_be(at)? as usize;
+		let bytes = fields.slice(at + 4, len)?;
+		// Strings are padded to whole words
+		let end = (at + 4 + len + 3) & !3;
+		Ok((String::from_utf8_lossy(bytes).into_owned(), end))
+	};
+
+	// After the version and flags, each pair starts with its encoded and
+	// decoded sizes, and two zeros end the list
+	let mut pairs = BTreeMap::new();
+	let mut at = 8;
+	loop {
+		let encoded = fields.u32_be(at)? as usize;
+		let decoded = fields.u32_be(at + 4)?;
+		if encoded == 0 && decoded == 0 {
+			break;
+		}
+		if encoded < 8 || encoded % 4 != 0 {
+			return Err(invalid("invalid nvpair size"));
+		}
+		let end = at + encoded;
+		let (name, next) = string(at + 8)?;
+		let kind = fields.u32_be(next)?;
+		// The element count is skipped: both types kept hold one value
+		let value = match kind {
+			DATA_TYPE_UINT64 => Some(Value::Number(fields.u64_be(next + 8)?)),
+			DATA_TYPE_STRING => Some(Value::String(string(next + 8)?.0)),
+			_ => None,
+		};
+		if let Some(value) = value {
+			pairs.insert(name, value);
+		}
+		at = end;
+	}
+	Ok(pairs)
+}
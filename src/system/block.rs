@@ -0,0 +1,148 @@
+use super::{mounts::parse_dev, read_attr, read_u64_attr};
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+const SYS_BLOCK: &str = "/sys/block";
+// sysfs counts sizes and offsets in 512-byte units regardless of the
+// device's logical sector size
+pub(super) const SYSFS_SECTOR: u64 = 512;
+
+/// A whole-disk block device, as listed in `/sys/block`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockDevice {
+	/// Kernel name, e.g. `nvme0n1`
+	pub name: String,
+	/// Device node, e.g. `/dev/nvme0n1`
+	pub path: PathBuf,
+	/// `(major, minor)`
+	pub dev: (u32, u32),
+	/// Size in bytes
+	pub size: u64,
+	pub model: Option<String>,
+	/// Whether the medium can be taken out, as with USB sticks and card
+	/// readers
+	pub removable: bool,
+	/// The partitions the kernel has registered, ordered by number
+	pub partitions: Vec<KernelPartition>,
+}
+
+/// A partition as registered with the kernel, which may differ from what
+/// is on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelPartition {
+	/// Kernel name, e.g. `nvme0n1p3`
+	pub name: String,
+	pub number: u32,
+	/// `(major, minor)`
+	pub dev: (u32, u32),
+	/// Offset from the start of the disk in bytes
+	pub start: u64,
+	/// Size in bytes
+	pub size: u64,
+}
+
+/// Lists whole disks in `/sys/block`, sorted by name. RAM disks, unbacked
+/// loop devices, empty drives and devices stacked on others (device-mapper
+/// and md-raid volumes) are skipped.
+pub fn block_devices() -> io::Result<Vec<BlockDevice>> {
+	block_devices_in(Path::new(SYS_BLOCK))
+}
+
+/// Like [`block_devices`], with `root` standing in for `/sys/block`.
+pub fn block_devices_in(root: &Path) -> io::Result<Vec<BlockDevice>> {
+	let mut devices = vec![];
+	for dir in fs::read_dir(root)? {
+		let dir = dir?.path();
+		let name = match dir.file_name().and_then(|name| name.to_str()) {
+			Some(name) => name.to_string(),
+			None => continue,
+		};
+
+		if name.starts_with("ram")
+			|| name.starts_with("zram")
+			|| dir.join("partition").exists()
+			|| is_stacked(&dir)?
+			|| (name.starts_with("loop")
+				&& !dir.join("loop/backing_file").exists())
+		{
+			continue;
+		}
+
+		let size = read_u64_attr(&dir.join("size"))? * SYSFS_SECTOR;
+		if size == 0 {
+			continue;
+		}
+
+		devices.push(BlockDevice {
+			path: Path::new("/dev").join(&name),
+			dev: read_dev_attr(&dir.join("dev"))?,
+			size,
+			model: read_attr(&dir.join("device/model"))
+				.ok()
+				.filter(|model| !model.is_empty()),
+			removable: read_attr(&dir.join("removable"))
+				.is_ok_and(|removable| removable == "1"),
+			partitions: kernel_partitions(&dir)?,
+			name,
+		});
+	}
+
+	devices.sort_by(|a, b| a.name.cmp(&b.name));
+	Ok(devices)
+}
+
+/// The disk to show when none is named: the first of `devices` that is
+/// built in, rather than a loop device or removable, if there is one.
+pub fn default_device(devices: &[BlockDevice]) -> Option<&BlockDevice> {
+	devices
+		.iter()
+		.find(|device| !device.removable && !device.name.starts_with("loop"))
+		.or_else(|| devices.first())
+}
+
+/// Whether the device at `dir` is built on other block devices, which
+/// `slaves/` lists.
+fn is_stacked(dir: &Path) -> io::Result<bool> {
+	match fs::read_dir(dir.join("slaves")) {
+		Ok(mut slaves) => Ok(slaves.next().is_some()),
+		Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+		Err(err) => Err(err),
+	}
+}
+
+impl KernelPartition {
+	/// Device node, e.g. `/dev/nvme0n1p3`
+	pub fn path(&self) -> PathBuf { Path::new("/dev").join(&self.name) }
+}
+
+pub(super) fn read_dev_attr(path: &Path) -> io::Result<(u32, u32)> {
+	parse_dev(&read_attr(path)?).ok_or_else(|| {
+		io::Error::new(io::ErrorKind::InvalidData, "malformed device number")
+	})
+}
+
+fn kernel_partitions(disk: &Path) -> io::Result<Vec<KernelPartition>> {
+	let mut partitions = vec![];
+	for dir in fs::read_dir(disk)? {
+		let dir = dir?.path();
+		if !dir.join("partition").exists() {
+			continue;
+		}
+
+		partitions.push(KernelPartition {
+			name: dir
+				.file_name()
+				.map(|name| name.to_string_lossy().into_owned())
+				.unwrap_or_default(),
+			number: read_u64_attr(&dir.join("partition"))? as u32,
+			dev: read_dev_attr(&dir.join("dev"))?,
+			start: read_u64_attr(&dir.join("start"))? * SYSFS_SECTOR,
+			size: read_u64_attr(&dir.join("size"))? * SYSFS_SECTOR,
+		});
+	}
+
+	partitions.sort_by_key(|partition| partition.number);
+	Ok(partitions)
+}
@@ -0,0 +1,78 @@
+use super::{KernelPartition, LiveDisk};
+use crate::reader::{Disk, PartitionEntry};
+
+/// A difference between the on-disk table and the partitions the kernel
+/// has registered, matched by partition number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+	/// The kernel's partition no longer matches the on-disk entry
+	Stale {
+		entry: PartitionEntry,
+		kernel: KernelPartition,
+	},
+	/// The on-disk entry has no kernel partition
+	Missing { entry: PartitionEntry },
+	/// The kernel partition has no on-disk entry
+	Extra { kernel: KernelPartition },
+}
+
+impl Divergence {
+	pub fn number(&self) -> u32 {
+		match self {
+			Self::Stale { entry, .. } | Self::Missing { entry } => entry.number,
+			Self::Extra { kernel } => kernel.number,
+		}
+	}
+}
+
+impl LiveDisk {
+	/// Compares the kernel's partitions with the entries of `disk`,
+	/// ordered by partition number.
+	pub fn divergences(&self, disk: &Disk) -> Vec<Divergence> {
+		let mut found = vec![];
+
+		for entry in disk.entries.iter() {
+			let kernel = self
+				.device
+				.partitions
+				.iter()
+				.find(|partition| partition.number == entry.number);
+			match kernel {
+				None => found.push(Divergence::Missing {
+					entry: entry.clone(),
+				}),
+				Some(kernel) if !covers(kernel, entry, disk.sector_size) => {
+					found.push(Divergence::Stale {
+						entry: entry.clone(),
+						kernel: kernel.clone(),
+					})
+				}
+				Some(_) => {}
+			}
+		}
+
+		for kernel in self.device.partitions.iter() {
+			if !disk
+				.entries
+				.iter()
+				.any(|entry| entry.number == kernel.number)
+			{
+				found.push(Divergence::Extra {
+					kernel: kernel.clone(),
+				});
+			}
+		}
+
+		found.sort_by_key(Divergence::number);
+		found
+	}
+}
+
+fn covers(
+	kernel: &KernelPartition,
+	entry: &PartitionEntry,
+	sector_size: u64,
+) -> bool {
+	Some(kernel.start) == entry.first_lba.checked_mul(sector_size)
+		&& Some(kernel.size) == entry.sectors().checked_mul(sector_size)
+}
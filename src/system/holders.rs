@@ -0,0 +1,135 @@
+use super::{
+	block::{read_dev_attr, SYSFS_SECTOR},
+	read_attr, read_u64_attr,
+};
+use std::{
+	fmt, fs, io,
+	path::{Path, PathBuf},
+};
+
+const SYS_CLASS_BLOCK: &str = "/sys/class/block";
+
+/// A device stacked on top of another one, such as a dm-crypt mapping on a
+/// partition or an LVM logical volume on that mapping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Holder {
+	/// Kernel name, e.g. `dm-0`
+	pub name: String,
+	/// `(major, minor)`
+	pub dev: (u32, u32),
+	/// Size in bytes
+	pub size: u64,
+	pub kind: HolderKind,
+	/// The devices stacked on this one in turn
+	pub holders: Vec<Holder>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HolderKind {
+	/// A dm-crypt mapping, with its device-mapper name
+	Crypt(String),
+	/// An LVM logical volume, with its device-mapper name
+	Lvm(String),
+	/// Any other device-mapper target (multipath, verity, ...)
+	DeviceMapper(String),
+	/// An md array and its RAID level, e.g. `raid1`
+	Md(Option<String>),
+	Bcache,
+	Other,
+}
+
+impl Holder {
+	/// Kernel device node, e.g. `/dev/dm-0`
+	pub fn node(&self) -> PathBuf { Path::new("/dev").join(&self.name) }
+
+	/// The path users know the device by: `/dev/mapper/<name>` for
+	/// device-mapper targets, the kernel node otherwise.
+	pub fn path(&self) -> PathBuf {
+		match self.mapper_name() {
+			Some(name) => Path::new("/dev/mapper").join(name),
+			None => self.node(),
+		}
+	}
+
+	/// The device-mapper name, or the kernel name for everything else.
+	pub fn display_name(&self) -> &str {
+		self.mapper_name().unwrap_or(&self.name)
+	}
+
+	fn mapper_name(&self) -> Option<&str> {
+		match &self.kind {
+			HolderKind::Crypt(name)
+			| HolderKind::Lvm(name)
+			| HolderKind::DeviceMapper(name) => Some(name),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for HolderKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			HolderKind::Crypt(_) => write!(f, "dm-crypt"),
+			HolderKind::Lvm(_) => write!(f, "LVM logical volume"),
+			HolderKind::DeviceMapper(_) => write!(f, "device-mapper"),
+			HolderKind::Md(Some(level)) => write!(f, "md {}", level),
+			HolderKind::Md(None) => write!(f, "md array"),
+			HolderKind::Bcache => write!(f, "bcache"),
+			HolderKind::Other => write!(f, "block device"),
+		}
+	}
+}
+
+/// The devices stacked on the block device `name` (a disk or partition
+/// kernel name), followed recursively through
+/// `/sys/class/block/*/holders`.
+pub fn holders(name: &str) -> io::Result<Vec<Holder>> {
+	holders_in(Path::new(SYS_CLASS_BLOCK), name)
+}
+
+/// Like [`holders`], with `root` standing in for `/sys/class/block`.
+pub fn holders_in(root: &Path, name: &str) -> io::Result<Vec<Holder>> {
+	let dir = match fs::read_dir(root.join(name).join("holders")) {
+		Ok(dir) => dir,
+		// Devices without a holders directory can't have any
+		Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+		Err(err) => return Err(err),
+	};
+
+	let mut holders = vec![];
+	for holder in dir {
+		let name = holder?.file_name().to_string_lossy().into_owned();
+		let sys = root.join(&name);
+
+		holders.push(Holder {
+			dev: read_dev_attr(&sys.join("dev"))?,
+			size: read_u64_attr(&sys.join("size"))? * SYSFS_SECTOR,
+			kind: holder_kind(&sys, &name),
+			holders: holders_in(root, &name)?,
+			name,
+		});
+	}
+
+	holders.sort_by(|a, b| a.name.cmp(&b.name));
+	Ok(holders)
+}
+
+fn holder_kind(sys: &Path, name: &str) -> HolderKind {
+	if let Ok(dm_name) = read_attr(&sys.join("dm/name")) {
+		// cryptsetup and LVM tag their mappings through the UUID prefix
+		let uuid = read_attr(&sys.join("dm/uuid")).unwrap_or_default();
+		if uuid.starts_with("CRYPT-") {
+			HolderKind::Crypt(dm_name)
+		} else if uuid.starts_with("LVM-") {
+			HolderKind::Lvm(dm_name)
+		} else {
+			HolderKind::DeviceMapper(dm_name)
+		}
+	} else if sys.join("md").is_dir() {
+		HolderKind::Md(read_attr(&sys.join("md/level")).ok())
+	} else if name.starts_with("bcache") {
+		HolderKind::Bcache
+	} else {
+		HolderKind::Other
+	}
+}
@@ -0,0 +1,147 @@
+use super::Divergence;
+use crate::reader::PartitionEntry;
+use nix::{errno::Errno, ioctl_none, ioctl_write_ptr_bad, request_code_none};
+use std::{
+	fs::File,
+	io,
+	os::{raw::c_int, unix::io::AsRawFd},
+};
+
+// <linux/fs.h> and <linux/blkpg.h>
+ioctl_none!(blkrrpart, 0x12, 95);
+ioctl_write_ptr_bad!(blkpg, request_code_none!(0x12, 105), BlkpgIoctlArg);
+
+const BLKPG_ADD_PARTITION: c_int = 1;
+const BLKPG_DEL_PARTITION: c_int = 2;
+const BLKPG_RESIZE_PARTITION: c_int = 3;
+
+#[repr(C)]
+struct BlkpgIoctlArg {
+	op: c_int,
+	flags: c_int,
+	datalen: c_int,
+	data: *mut BlkpgPartition,
+}
+
+#[repr(C)]
+struct BlkpgPartition {
+	start: i64,
+	length: i64,
+	pno: c_int,
+	devname: [u8; 64],
+	volname: [u8; 64],
+}
+
+/// What became of one divergence after [`sync_partitions`].
+#[derive(Debug)]
+pub struct SyncOutcome {
+	pub divergence: Divergence,
+	pub result: io::Result<()>,
+}
+
+/// Asks the kernel to re-read the whole partition table of `disk`
+/// (`BLKRRPART`). Fails with `EBUSY` while any partition is in use.
+pub fn reread_partitions(disk: &File) -> io::Result<()> {
+	// SAFETY: BLKRRPART takes no argument
+	unsafe { blkrrpart(disk.as_raw_fd()) }.map_err(nix_to_io)?;
+	Ok(())
+}
+
+/// Applies each divergence individually through `BLKPG`, the way `partx
+/// -u` does: stale partitions are resized (or removed and re-added when
+/// their start moved), missing ones added and extra ones removed.
+/// Partitions in use can't be removed, so each outcome is reported
+/// separately.
+pub fn sync_partitions(
+	disk: &File,
+	sector_size: u64,
+	divergences: Vec<Divergence>,
+) -> Vec<SyncOutcome> {
+	divergences
+		.into_iter()
+		.map(|divergence| {
+			let result = match &divergence {
+				Divergence::Stale { entry, kernel } => {
+					let start = entry.first_lba.checked_mul(sector_size);
+					if start == Some(kernel.start) {
+						blkpg_op(
+							disk,
+							BLKPG_RESIZE_PARTITION,
+							entry,
+							sector_size,
+						)
+					} else {
+						blkpg_del(disk, kernel.number).and_then(|_| {
+							blkpg_op(
+								disk,
+								BLKPG_ADD_PARTITION,
+								entry,
+								sector_size,
+							)
+						})
+					}
+				}
+				Divergence::Missing { entry } => {
+					blkpg_op(disk, BLKPG_ADD_PARTITION, entry, sector_size)
+				}
+				Divergence::Extra { kernel } => blkpg_del(disk, kernel.number),
+			};
+			SyncOutcome { divergence, result }
+		})
+		.collect()
+}
+
+fn blkpg_op(
+	disk: &File,
+	op: c_int,
+	entry: &PartitionEntry,
+	sector_size: u64,
+) -> io::Result<()> {
+	let bytes = |sectors: u64| {
+		sectors
+			.checked_mul(sector_size)
+			.filter(|&bytes| bytes <= i64::MAX as u64)
+			.ok_or_else(|| io::Error::from_raw_os_error(Errno::EINVAL as i32))
+	};
+	let (start, length) = (bytes(entry.first_lba)?, bytes(entry.sectors())?);
+
+	blkpg_call(disk, op, start as i64, length as i64, entry.number)
+}
+
+fn blkpg_del(disk: &File, number: u32) -> io::Result<()> {
+	blkpg_call(disk, BLKPG_DEL_PARTITION, 0, 0, number)
+}
+
+fn blkpg_call(
+	disk: &File,
+	op: c_int,
+	start: i64,
+	length: i64,
+	number: u32,
+) -> io::Result<()> {
+	let mut partition = BlkpgPartition {
+		start,
+		length,
+		pno: number as c_int,
+		devname: [0; 64],
+		volname: [0; 64],
+	};
+	let arg = BlkpgIoctlArg {
+		op,
+		flags: 0,
+		datalen: std::mem::size_of::<BlkpgPartition>() as c_int,
+		data: &mut partition,
+	};
+
+	// SAFETY: `arg` and the partition it points to outlive the call and
+	// match the kernel's layout
+	unsafe { blkpg(disk.as_raw_fd(), &arg) }.map_err(nix_to_io)?;
+	Ok(())
+}
+
+fn nix_to_io(err: nix::Error) -> io::Error {
+	match err.as_errno() {
+		Some(errno) => io::Error::from_raw_os_error(errno as i32),
+		None => io::Error::other(err),
+	}
+}
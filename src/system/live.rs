@@ -0,0 +1,68 @@
+use super::{
+	block::{block_devices, BlockDevice, KernelPartition},
+	mounts::{mounts, swaps, Mount},
+};
+use crate::reader::PartitionEntry;
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+/// A disk as the running kernel sees it: its registered partitions and
+/// what is mounted from them.
+#[derive(Debug, Clone)]
+pub struct LiveDisk {
+	pub device: BlockDevice,
+	pub mounts: Vec<Mount>,
+	pub swaps: Vec<PathBuf>,
+}
+
+impl LiveDisk {
+	/// Looks up the whole disk behind `path` (following symlinks such as
+	/// `/dev/disk/by-id/...`). Returns `Ok(None)` for image files and
+	/// anything else that isn't in `/sys/block`.
+	pub fn load(path: &Path) -> io::Result<Option<LiveDisk>> {
+		let path = match fs::canonicalize(path) {
+			Ok(path) => path,
+			Err(_) => return Ok(None),
+		};
+		let device = match block_devices()?
+			.into_iter()
+			.find(|device| device.path == path)
+		{
+			Some(device) => device,
+			None => return Ok(None),
+		};
+
+		Ok(Some(Self {
+			device,
+			mounts: mounts()?,
+			swaps: swaps()?,
+		}))
+	}
+
+	/// The kernel partition covering exactly the same bytes as `entry`.
+	pub fn partition(
+		&self,
+		entry: &PartitionEntry,
+		sector_size: u64,
+	) -> Option<&KernelPartition> {
+		let start = entry.first_lba.checked_mul(sector_size)?;
+		let size = entry.sectors().checked_mul(sector_size)?;
+		self.device.partitions.iter().find(|partition| {
+			partition.start == start && partition.size == size
+		})
+	}
+
+	/// Mounts of `partition`, in mount order.
+	pub fn mounts_of(&self, partition: &KernelPartition) -> Vec<&Mount> {
+		self.mounts
+			.iter()
+			.filter(|mount| mount.is_of(partition.dev, &partition.path()))
+			.collect()
+	}
+
+	pub fn is_swap(&self, partition: &KernelPartition) -> bool {
+		self.swaps.contains(&partition.path())
+	}
+}
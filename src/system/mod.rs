@@ -0,0 +1,31 @@
+//! What the running Linux kernel knows about block devices, read from
+//! sysfs and procfs.
+
+mod block;
+mod diverge;
+mod holders;
+mod ioctl;
+mod live;
+mod mounts;
+
+pub use block::{
+	block_devices, block_devices_in, default_device, BlockDevice,
+	KernelPartition,
+};
+pub use diverge::Divergence;
+pub use holders::{holders, holders_in, Holder, HolderKind};
+pub use ioctl::{reread_partitions, sync_partitions, SyncOutcome};
+pub use live::LiveDisk;
+pub use mounts::{mounts, parse_mountinfo, parse_swaps, swaps, Mount};
+use std::{fs, io, path::Path};
+
+/// Reads a sysfs attribute, without its trailing newline.
+fn read_attr(path: &Path) -> io::Result<String> {
+	Ok(fs::read_to_string(path)?.trim_end().to_string())
+}
+
+fn read_u64_attr(path: &Path) -> io::Result<u64> {
+	read_attr(path)?
+		.parse()
+		.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
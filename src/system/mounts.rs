@@ -0,0 +1,129 @@
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+const SWAPS: &str = "/proc/swaps";
+
+/// A line of `/proc/self/mountinfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+	/// `(major, minor)` of the mounted device
+	pub dev: (u32, u32),
+	/// The directory within the filesystem that is mounted, `/` unless this
+	/// is a bind mount or subvolume
+	pub root: String,
+	pub mount_point: PathBuf,
+	/// Per-mount and superblock options, comma-separated
+	pub options: String,
+	pub fs_type: String,
+	pub source: String,
+}
+
+impl Mount {
+	/// Whether this mounts the device numbered `dev` with node `node`.
+	/// btrfs and some others report an anonymous device number, so the
+	/// source is compared too, following links such as `/dev/mapper/*`.
+	pub fn is_of(&self, dev: (u32, u32), node: &Path) -> bool {
+		let source = Path::new(&self.source);
+		self.dev == dev
+			|| source == node
+			|| (source.is_absolute()
+				&& fs::canonicalize(source).is_ok_and(|source| source == node))
+	}
+}
+
+/// The mounts visible to this process.
+pub fn mounts() -> io::Result<Vec<Mount>> {
+	Ok(parse_mountinfo(&fs::read_to_string(MOUNTINFO)?))
+}
+
+/// Parses the `mountinfo` format, skipping malformed lines.
+pub fn parse_mountinfo(text: &str) -> Vec<Mount> {
+	text.lines().filter_map(parse_mount).collect()
+}
+
+// 36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root
+// rw,errors=continue
+fn parse_mount(line: &str) -> Option<Mount> {
+	let (mount, superblock) = {
+		let mut halves = line.splitn(2, " - ");
+		(halves.next()?, halves.next()?)
+	};
+
+	let mut fields = mount.split(' ');
+	let _id = fields.next()?;
+	let _parent = fields.next()?;
+	let dev = parse_dev(fields.next()?)?;
+	let root = unescape(fields.next()?);
+	let mount_point = PathBuf::from(unescape(fields.next()?));
+	let mount_options = fields.next()?;
+
+	let mut fields = superblock.split(' ');
+	let fs_type = fields.next()?.to_string();
+	let source = unescape(fields.next()?);
+	let super_options = fields.next().unwrap_or("");
+
+	let mut options = mount_options.to_string();
+	for option in super_options.split(',') {
+		// `rw`/`ro` are repeated in the superblock options
+		if !option.is_empty() && !mount_options.split(',').any(|o| o == option)
+		{
+			options.push(',');
+			options.push_str(option);
+		}
+	}
+
+	Some(Mount {
+		dev,
+		root,
+		mount_point,
+		options,
+		fs_type,
+		source,
+	})
+}
+
+/// Parses `major:minor`.
+pub(crate) fn parse_dev(input: &str) -> Option<(u32, u32)> {
+	let mut parts = input.trim().splitn(2, ':');
+	Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+// Spaces, tabs, newlines and backslashes are written as octal escapes
+fn unescape(field: &str) -> String {
+	let mut out = String::with_capacity(field.len());
+	let mut rest = field;
+	while let Some(idx) = rest.find('\\') {
+		out.push_str(&rest[..idx]);
+		let escape = rest.get(idx + 1..idx + 4);
+		match escape.and_then(|digits| u8::from_str_radix(digits, 8).ok()) {
+			Some(byte) => {
+				out.push(byte as char);
+				rest = &rest[idx + 4..];
+			}
+			None => {
+				out.push('\\');
+				rest = &rest[idx + 1..];
+			}
+		}
+	}
+	out.push_str(rest);
+	out
+}
+
+/// The files and devices in use as swap.
+pub fn swaps() -> io::Result<Vec<PathBuf>> {
+	Ok(parse_swaps(&fs::read_to_string(SWAPS)?))
+}
+
+/// Parses the `/proc/swaps` format.
+pub fn parse_swaps(text: &str) -> Vec<PathBuf> {
+	text.lines()
+		// Header: Filename Type Size Used Priority
+		.skip(1)
+		.filter_map(|line| line.split_whitespace().next())
+		.map(|name| PathBuf::from(unescape(name)))
+		.collect()
+}
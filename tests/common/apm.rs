@@ -0,0 +1,38 @@
+//! Writes Apple Partition Maps.
+
+/// Valid, allocated, in use, readable and writable
+pub const IN_USE: u32 = 0x37;
+
+/// A disk of `blocks` blocks with a partition map in `block_size` blocks,
+/// headed by its own entry. `descriptor` is the block size named by a
+/// driver descriptor in block 0, if there is one.
+pub fn build(
+	block_size: usize,
+	descriptor: Option<u16>,
+	blocks: u32,
+	entries: &[(&str, &str, u32, u32, u32)],
+) -> Vec<u8> {
+	let mut image = vec![0u8; block_size * blocks as usize];
+	if let Some(size) = descriptor {
+		image[0..2].copy_from_slice(b"ER");
+		image[2..4].copy_from_slice(&size.to_be_bytes());
+		image[4..8].copy_from_slice(&blocks.to_be_bytes());
+	}
+
+	let count = entries.len() as u32 + 1;
+	let own = ("Apple", "Apple_partition_map", 1, 63, IN_USE);
+	for (idx, &(name, kind, start, len, status)) in
+		std::iter::once(&own).chain(entries).enumerate()
+	{
+		let entry = &mut image[(idx + 1) * block_size..][..block_size];
+		entry[0..2].copy_from_slice(b"PM");
+		entry[4..8].copy_from_slice(&count.to_be_bytes());
+		entry[8..12].copy_from_slice(&start.to_be_bytes());
+		entry[12..16].copy_from_slice(&len.to_be_bytes());
+		entry[16..16 + name.len()].copy_from_slice(name.as_bytes());
+		entry[48..48 + kind.len()].copy_from_slice(kind.as_bytes());
+		entry[84..88].copy_from_slice(&len.to_be_bytes());
+		entry[88..92].copy_from_slice(&status.to_be_bytes());
+	}
+	image
+}
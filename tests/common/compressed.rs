@@ -0,0 +1,57 @@
+//! Compresses raw disk bytes the way release pipelines do.
+
+use flate2::{write::GzEncoder, Compression};
+use std::io::Write;
+use xz2::{
+	stream::{Check, MtStreamBuilder},
+	write::XzEncoder,
+};
+
+pub fn gzip(raw: &[u8]) -> Vec<u8> {
+	let mut encoder = GzEncoder::new(vec![], Compression::default());
+	encoder.write_all(raw).unwrap();
+	encoder.finish().unwrap()
+}
+
+/// A single xz stream; `block_size` splits it into independent blocks, as
+/// `xz --block-size` and multi-threaded `xz -T` do.
+pub fn xz(raw: &[u8], block_size: Option<u64>) -> Vec<u8> {
+	let mut builder = MtStreamBuilder::new();
+	builder.threads(1).preset(6).check(Check::Crc64);
+	// Without a block size, a block bigger than the input
+	builder.block_size(block_size.unwrap_or(raw.len() as u64 * 2));
+	let mut encoder = XzEncoder::new_stream(vec![], builder.encoder().unwrap());
+	encoder.write_all(raw).unwrap();
+	encoder.finish().unwrap()
+}
+
+pub fn zstd(raw: &[u8]) -> Vec<u8> { zstd::encode_all(raw, 3).unwrap() }
+
+/// Independent frames of `frame_size` bytes followed by a seek table, in
+/// zstd's seekable format.
+pub fn seekable_zstd(
+	raw: &[u8],
+	frame_size: usize,
+	checksums: bool,
+) -> Vec<u8> {
+	let mut image = vec![];
+	let mut table = vec![];
+	for chunk in raw.chunks(frame_size) {
+		let frame = zstd::encode_all(chunk, 3).unwrap();
+		table.extend(&(frame.len() as u32).to_le_bytes());
+		table.extend(&(chunk.len() as u32).to_le_bytes());
+		if checksums {
+			table.extend(&0u32.to_le_bytes());
+		}
+		image.extend(frame);
+	}
+	let frames = raw.chunks(frame_size).count() as u32;
+	table.extend(&frames.to_le_bytes());
+	table.push(if checksums { 1 << 7 } else { 0 });
+	table.extend(&0x8F92_EAB1u32.to_le_bytes());
+
+	image.extend(&0x184D_2A5Eu32.to_le_bytes());
+	image.extend(&(table.len() as u32).to_le_bytes());
+	image.extend(table);
+	image
+}
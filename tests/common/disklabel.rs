@@ -0,0 +1,84 @@
+//! Writes BSD disklabels and Solaris VTOCs.
+
+/// XOR of the 16-bit words of `bytes`, read with `word`.
+fn checksum(bytes: &[u8], word: fn([u8; 2]) -> u16) -> u16 {
+	bytes
+		.chunks_exact(2)
+		.fold(0, |sum, pair| sum ^ word([pair[0], pair[1]]))
+}
+
+/// A BSD disklabel with these `(fstype, offset, size)` partitions, to be
+/// written to the second sector of a slice. OpenBSD labels keep the high
+/// 16 bits of offsets and sizes apart.
+pub fn bsd(parts: &[(u8, u64, u64)], openbsd: bool) -> [u8; 512] {
+	let mut label = [0u8; 512];
+	label[0..4].copy_from_slice(&0x8256_4557u32.to_le_bytes());
+	label[132..136].copy_from_slice(&0x8256_4557u32.to_le_bytes());
+	label[40..44].copy_from_slice(&512u32.to_le_bytes());
+	if openbsd {
+		label[114..116].copy_from_slice(&1u16.to_le_bytes());
+	}
+	label[138..140].copy_from_slice(&(parts.len() as u16).to_le_bytes());
+
+	for (idx, &(fstype, offset, size)) in parts.iter().enumerate() {
+		let part = &mut label[148 + idx * 16..][..16];
+		part[0..4].copy_from_slice(&(size as u32).to_le_bytes());
+		part[4..8].copy_from_slice(&(offset as u32).to_le_bytes());
+		if openbsd {
+			part[8..10].copy_from_slice(&((offset >> 32) as u16).to_le_bytes());
+			part[10..12].copy_from_slice(&((size >> 32) as u16).to_le_bytes());
+		}
+		part[12] = fstype;
+	}
+
+	let end = 148 + parts.len() * 16;
+	let sum = checksum(&label[..end], u16::from_le_bytes);
+	label[136..138].copy_from_slice(&sum.to_le_bytes());
+	label
+}
+
+/// A SPARC label with these `(tag, cylinder, sectors)` slices, to be
+/// written to the first sector of the disk or partition.
+pub fn sun(heads: u16, sectors: u16, slices: &[(u16, u32, u32)]) -> [u8; 512] {
+	let mut label = [0u8; 512];
+	label[..17].copy_from_slice(b"gptinfo test disk");
+	label[128..132].copy_from_slice(&1u32.to_be_bytes());
+	label[140..142].copy_from_slice(&8u16.to_be_bytes());
+	label[188..192].copy_from_slice(&0x600D_DEEEu32.to_be_bytes());
+	label[436..438].copy_from_slice(&heads.to_be_bytes());
+	label[438..440].copy_from_slice(&sectors.to_be_bytes());
+
+	for (idx, &(tag, cylinder, len)) in slices.iter().enumerate() {
+		label[142 + idx * 4..][..2].copy_from_slice(&tag.to_be_bytes());
+		let slice = &mut label[444 + idx * 8..][..8];
+		slice[0..4].copy_from_slice(&cylinder.to_be_bytes());
+		slice[4..8].copy_from_slice(&len.to_be_bytes());
+	}
+
+	label[508..510].copy_from_slice(&0xDABEu16.to_be_bytes());
+	let sum = checksum(&label[..510], u16::from_be_bytes);
+	label[510..512].copy_from_slice(&sum.to_be_bytes());
+	label
+}
+
+/// A Solaris x86 VTOC with these `(tag, start, sectors)` slices, to be
+/// written to the second sector of a Solaris partition.
+pub fn solaris_x86(slices: &[(u16, u32, u32)]) -> [u8; 512] {
+	let mut label = [0u8; 512];
+	label[12..16].copy_from_slice(&0x600D_DEEEu32.to_le_bytes());
+	label[16..20].copy_from_slice(&1u32.to_le_bytes());
+	label[28..30].copy_from_slice(&512u16.to_le_bytes());
+	label[30..32].copy_from_slice(&(slices.len() as u16).to_le_bytes());
+
+	for (idx, &(tag, start, len)) in slices.iter().enumerate() {
+		let slice = &mut label[72 + idx * 12..][..12];
+		slice[0..2].copy_from_slice(&tag.to_le_bytes());
+		slice[4..8].copy_from_slice(&start.to_le_bytes());
+		slice[8..12].copy_from_slice(&len.to_le_bytes());
+	}
+
+	label[508..510].copy_from_slice(&0xDABEu16.to_le_bytes());
+	let sum = checksum(&label[..510], u16::from_le_bytes);
+	label[510..512].copy_from_slice(&sum.to_le_bytes());
+	label
+}
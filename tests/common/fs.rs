@@ -0,0 +1,51 @@
+//! Writes the superblocks and boot sectors filesystems are recognized by.
+
+/// The start of an ext4 filesystem labelled `label`.
+pub fn ext4(label: &str) -> Vec<u8> {
+	let mut raw = vec![0u8; 2048];
+	let superblock = &mut raw[1024..];
+	superblock[56..58].copy_from_slice(&0xEF53u16.to_le_bytes());
+	// A journal, extents and flexible block groups
+	superblock[92..96].copy_from_slice(&0x4u32.to_le_bytes());
+	superblock[96..100].copy_from_slice(&0x240u32.to_le_bytes());
+	superblock[120..120 + label.len()].copy_from_slice(label.as_bytes());
+	raw
+}
+
+/// The boot sector of a FAT32 filesystem labelled `label`, or `NO NAME`
+/// if it's empty.
+pub fn vfat(label: &str) -> Vec<u8> {
+	let mut raw = vec![0u8; 512];
+	raw[0..3].copy_from_slice(&[0xEB, 0x58, 0x90]);
+	raw[3..11].copy_from_slice(b"MSDOS5.0");
+	let label = if label.is_empty() { "NO NAME" } else { label };
+	raw[71..82].copy_from_slice(format!("{:<11}", label).as_bytes());
+	raw[82..90].copy_from_slice(b"FAT32   ");
+	raw[510..512].copy_from_slice(&[0x55, 0xAA]);
+	raw
+}
+
+/// The first page of a swap area labelled `label`.
+pub fn swap(label: &str) -> Vec<u8> {
+	let mut raw = vec![0u8; 4096];
+	raw[1024 + 28..1024 + 28 + label.len()].copy_from_slice(label.as_bytes());
+	raw[4086..4096].copy_from_slice(b"SWAPSPACE2");
+	raw
+}
+
+/// A GPT disk with a FAT32 EFI partition, an ext4 root and swap, and a
+/// fourth partition that's blank.
+pub fn gpt_disk() -> Vec<u8> {
+	let image = super::Image::new(512)
+		.part(super::ESP, "EFI", 64, 191)
+		.part(super::LINUX_FS, "root", 192, 1023)
+		.part(super::LINUX_SWAP, "swap", 1024, 1151)
+		.part(super::LINUX_FS, "blank", 1152, 1279);
+	let mut bytes = image.build();
+	for &(first, ref raw) in
+		[(64, vfat("")), (192, ext4("rootfs")), (1024, swap("swap0"))].iter()
+	{
+		bytes[first * 512..first * 512 + raw.len()].copy_from_slice(raw);
+	}
+	bytes
+}
@@ -0,0 +1,246 @@
+//! Writes Logical Disk Manager databases.
+
+pub const METADATA: &str = "5808C8AA-7E8F-42E0-85D2-E1E90434CFB3";
+pub const DATA: &str = "AF9B60A0-1431-4F62-BC68-3311714A69AD";
+
+pub const VBLK_SIZE: usize = 128;
+// The VMDB sits this many sectors into the config area, as on real disks
+const VMDB_LBA: u64 = 17;
+
+/// A length-prefixed big-endian number, as short as it fits.
+fn number(value: u64) -> Vec<u8> {
+	let bytes = value.to_be_bytes();
+	let skip = bytes.iter().take_while(|&&byte| byte == 0).count().min(7);
+	let mut out = vec![(8 - skip) as u8];
+	out.extend(&bytes[skip..]);
+	out
+}
+
+fn string(value: &str) -> Vec<u8> {
+	let mut out = vec![value.len() as u8];
+	out.extend(value.as_bytes());
+	out
+}
+
+/// A record of type `kind` whose fields after the ID and name are `rest`.
+fn record(kind: u8, flags: u8, id: u64, name: &str, rest: &[u8]) -> Vec<u8> {
+	let mut body = number(id);
+	body.extend(string(name));
+	body.extend(rest);
+
+	let mut record = vec![0u8; 0x18];
+	record[0x12] = flags;
+	record[0x13] = kind;
+	record[0x14..0x18].copy_from_slice(&(body.len() as u32).to_be_bytes());
+	record.extend(body);
+	record
+}
+
+pub fn volume(id: u64, name: &str, sectors: u64) -> Vec<u8> {
+	let mut rest = string("gen");
+	rest.extend(string("8000000000000000"));
+	rest.extend(b"ACTIVE\0\0\0\0\0\0\0\0");
+	rest.extend([3, 0, 0, 0, 0, 0, 0]);
+	rest.extend(number(1));
+	rest.extend([0; 16]);
+	rest.extend(number(sectors));
+	// Partition type and volume GUID
+	rest.extend([0, 0, 0, 0, 0x07]);
+	rest.extend([0xAB; 16]);
+	record(0x51, 0, id, name, &rest)
+}
+
+/// `layout` is 1 for striped, 2 for concatenated and 3 for RAID-5.
+pub fn component(
+	id: u64,
+	name: &str,
+	layout: u8,
+	volume: u64,
+	stripe: Option<u64>,
+) -> Vec<u8> {
+	let mut rest = string("ACTIVE");
+	rest.extend([layout, 0, 0, 0, 0]);
+	rest.extend(number(1));
+	rest.extend([0; 16]);
+	rest.extend(number(volume));
+	if let Some(stripe) = stripe {
+		rest.push(0);
+		rest.extend(number(stripe));
+		rest.extend(number(2));
+	}
+	record(
+		0x32,
+		if stripe.is_some() { 0x10 } else { 0 },
+		id,
+		name,
+		&rest,
+	)
+}
+
+pub fn extent(
+	id: u64,
+	name: &str,
+	(start, volume_offset, sectors): (u64, u64, u64),
+	component: u64,
+	disk: u64,
+) -> Vec<u8> {
+	let mut rest = vec![0; 12];
+	rest.extend(&start.to_be_bytes());
+	rest.extend(&volume_offset.to_be_bytes());
+	rest.extend(number(sectors));
+	rest.extend(number(component));
+	rest.extend(number(disk));
+	record(0x33, 0, id, name, &rest)
+}
+
+pub fn disk(id: u64, name: &str, guid: &str) -> Vec<u8> {
+	let mut rest = string(guid);
+	rest.extend(string(""));
+	record(0x34, 0, id, name, &rest)
+}
+
+pub fn disk_group(id: u64, name: &str) -> Vec<u8> {
+	record(
+		0x35,
+		0,
+		id,
+		name,
+		&string("00000000-0000-0000-0000-000000000000"),
+	)
+}
+
+/// Where a database goes and what it describes.
+pub struct Database<'a> {
+	pub disk_guid: &'a str,
+	pub group: &'a str,
+	pub data_start: u64,
+	pub data_sectors: u64,
+	pub config_start: u64,
+	pub config_sectors: u64,
+}
+
+impl Database<'_> {
+	/// The private header.
+	pub fn privhead(&self) -> [u8; 512] {
+		let mut head = [0u8; 512];
+		head[0..8].copy_from_slice(b"PRIVHEAD");
+		head[0x0C..0x0E].copy_from_slice(&2u16.to_be_bytes());
+		head[0x0E..0x10].copy_from_slice(&12u16.to_be_bytes());
+		head[0x30..0x30 + self.disk_guid.len()]
+			.copy_from_slice(self.disk_guid.as_bytes());
+		head[0xF0..0xF0 + self.group.len()]
+			.copy_from_slice(self.group.as_bytes());
+		for (offset, value) in [
+			(0x11B, self.data_start),
+			(0x123, self.data_sectors),
+			(0x12B, self.config_start),
+			(0x133, self.config_sectors),
+		]
+		.iter()
+		{
+			head[*offset..offset + 8].copy_from_slice(&value.to_be_bytes());
+		}
+		head
+	}
+
+	/// The config area holding `records`, split across VBLKs where they
+	/// don't fit in one.
+	pub fn config(&self, records: &[Vec<u8>]) -> Vec<u8> {
+		let mut config = vec![0u8; self.config_sectors as usize * 512];
+
+		let toc = &mut config[512..1024];
+		toc[0..8].copy_from_slice(b"TOCBLOCK");
+		toc[0x24..0x2A].copy_from_slice(b"config");
+		toc[0x2E..0x36].copy_from_slice(&VMDB_LBA.to_be_bytes());
+		toc[0x46..0x49].copy_from_slice(b"log");
+
+		let mut vblks = vec![];
+		for (group, record) in records.iter().enumerate() {
+			let (head, body) = record.split_at(16);
+			let chunks: Vec<_> = body.chunks(VBLK_SIZE - 16).collect();
+			for (number, chunk) in chunks.iter().enumerate() {
+				let mut vblk = vec![0u8; VBLK_SIZE];
+				vblk[..16].copy_from_slice(head);
+				vblk[16..16 + chunk.len()].copy_from_slice(chunk);
+				vblk[8..12].copy_from_slice(&(group as u32).to_be_bytes());
+				vblk[0x0C..0x0E]
+					.copy_from_slice(&(number as u16).to_be_bytes());
+				vblk[0x0E..0x10]
+					.copy_from_slice(&(chunks.len() as u16).to_be_bytes());
+				vblks.push(vblk);
+			}
+		}
+		// One spare, as the database always has some
+		vblks.push(vec![0u8; VBLK_SIZE]);
+
+		let first = 512 / VBLK_SIZE;
+		let vmdb = &mut config[VMDB_LBA as usize * 512..];
+		vmdb[0..4].copy_from_slice(b"VMDB");
+		let count = (first + vblks.len()) as u32;
+		vmdb[4..8].copy_from_slice(&count.to_be_bytes());
+		vmdb[8..12].copy_from_slice(&(VBLK_SIZE as u32).to_be_bytes());
+		vmdb[12..16].copy_from_slice(&512u32.to_be_bytes());
+		vmdb[0x12..0x14].copy_from_slice(&4u16.to_be_bytes());
+		vmdb[0x14..0x16].copy_from_slice(&10u16.to_be_bytes());
+		for (idx, vblk) in vblks.iter_mut().enumerate() {
+			vblk[0..4].copy_from_slice(b"VBLK");
+			vblk[4..8].copy_from_slice(&(idx as u32 + 4).to_be_bytes());
+			let offset = (first + idx) * VBLK_SIZE;
+			vmdb[offset..offset + VBLK_SIZE].copy_from_slice(vblk);
+		}
+		config
+	}
+}
+
+pub const DISK1: &str = "4b5c5ab4-36a1-4a53-a7c2-0a8ef1d9e3a1";
+pub const DISK2: &str = "9d2e4c1f-77b0-4f0e-8d6e-53c2b1a0f4e2";
+
+/// The records of a group with Disk1 and Disk2: a simple volume, one
+/// spanned over both disks, a mirror across them and a striped volume.
+pub fn records() -> Vec<Vec<u8>> {
+	vec![
+		disk_group(1, "WIN-DG0"),
+		disk(2, "Disk1", DISK1),
+		disk(3, "Disk2", DISK2),
+		volume(4, "Volume1", 500),
+		component(5, "Volume1-01", 2, 4, None),
+		extent(6, "Disk1-01", (0, 0, 500), 5, 2),
+		volume(7, "Volume2", 1000),
+		component(8, "Volume2-01", 2, 7, None),
+		extent(9, "Disk2-01", (0, 300, 700), 8, 3),
+		extent(10, "Disk1-02", (500, 0, 300), 8, 2),
+		// Long enough to be split across two VBLKs
+		volume(11, "Volume3, mirrored across both disks of the group", 200),
+		component(12, "Volume3-01", 2, 11, None),
+		component(13, "Volume3-02", 2, 11, None),
+		extent(14, "Disk1-03", (800, 0, 200), 12, 2),
+		extent(15, "Disk2-02", (700, 0, 200), 13, 3),
+		volume(16, "Volume4", 256),
+		component(17, "Volume4-01", 1, 16, Some(128)),
+		extent(18, "Disk1-04", (1000, 0, 128), 17, 2),
+		extent(19, "Disk2-03", (900, 0, 128), 17, 3),
+	]
+}
+
+/// Disk1 of the group, with the GPT partitions of a dynamic disk: a
+/// 64-sector metadata partition holding the database and a data partition
+/// after it.
+pub fn gpt_disk() -> Vec<u8> {
+	let image = super::Image::new(512)
+		.part(METADATA, "LDM metadata partition", 34, 97)
+		.part(DATA, "LDM data partition", 98, 2014);
+	let mut bytes = image.build();
+
+	let database = Database {
+		disk_guid: DISK1,
+		group: "WIN-DG0",
+		data_start: 98,
+		data_sectors: 1917,
+		config_start: 34,
+		config_sectors: 64,
+	};
+	bytes[34 * 512..98 * 512].copy_from_slice(&database.config(&records()));
+	// The private header's last copy ends the metadata partition
+	bytes[97 * 512..98 * 512].copy_from_slice(&database.privhead());
+	bytes
+}
@@ -0,0 +1,129 @@
+//! Writes LUKS1 and LUKS2 headers.
+
+use sha2::{Digest, Sha256};
+
+pub const LUKS: &str = "CA7D7CCB-63ED-4C53-861C-1742536059CC";
+pub const PLAIN: &str = "7FFEC5C9-2D00-49B7-8941-3EA10A5586B7";
+
+pub const UUID: &str = "4f3c9d1e-2b7a-4c55-9e08-6a1d2f3b4c5d";
+// The smallest LUKS2 header, JSON area included
+const LUKS2_SIZE: usize = 16 * 1024;
+
+/// A LUKS1 header for aes-xts-plain64 with a 512-bit key and data at
+/// sector 256, with `(slot, iterations)` in use.
+pub fn luks1(slots: &[(usize, u32)]) -> Vec<u8> {
+	let mut header = vec![0u8; 1024];
+	header[0..6].copy_from_slice(b"LUKS\xBA\xBE");
+	header[6..8].copy_from_slice(&1u16.to_be_bytes());
+	header[8..11].copy_from_slice(b"aes");
+	header[40..52].copy_from_slice(b"xts-plain64\0");
+	header[72..78].copy_from_slice(b"sha256");
+	header[104..108].copy_from_slice(&256u32.to_be_bytes());
+	header[108..112].copy_from_slice(&64u32.to_be_bytes());
+	// Key material that must never show up in the output
+	header[112..164].fill(0x5A);
+	header[168..168 + UUID.len()].copy_from_slice(UUID.as_bytes());
+	for idx in 0..8 {
+		let slot = &mut header[208 + idx * 48..256 + idx * 48];
+		let state: u32 = match slots.iter().find(|&&(used, _)| used == idx) {
+			Some(&(_, iterations)) => {
+				slot[4..8].copy_from_slice(&iterations.to_be_bytes());
+				slot[8..40].fill(0x5A);
+				0x00AC_71F3
+			}
+			None => 0x0000_DEAD,
+		};
+		slot[0..4].copy_from_slice(&state.to_be_bytes());
+	}
+	header
+}
+
+/// LUKS2 metadata with an argon2id key slot 0, a PBKDF2 key slot 2 and
+/// data at 32K.
+pub fn metadata() -> String {
+	r#"{
+  "keyslots": {
+    "0": {
+      "type": "luks2",
+      "key_size": 64,
+      "af": {"type": "luks1", "stripes": 4000, "hash": "sha256"},
+      "area": {"type": "raw", "offset": "32768", "size": "258048",
+        "encryption": "aes-xts-plain64", "key_size": 64},
+      "kdf": {"type": "argon2id", "time": 4, "memory": 1048576, "cpus": 4,
+        "salt": "c2VjcmV0IHNhbHQgdGhhdCBtdXN0IG5vdCBiZSBzaG93bg=="}
+    },
+    "2": {
+      "type": "luks2",
+      "key_size": 64,
+      "af": {"type": "luks1", "stripes": 4000, "hash": "sha256"},
+      "area": {"type": "raw", "offset": "290816", "size": "258048",
+        "encryption": "aes-xts-plain64", "key_size": 64},
+      "kdf": {"type": "pbkdf2", "hash": "sha512", "iterations": 1000000,
+        "salt": "c2VjcmV0IHNhbHQgdGhhdCBtdXN0IG5vdCBiZSBzaG93bg=="}
+    }
+  },
+  "tokens": {},
+  "segments": {
+    "0": {"type": "crypt", "offset": "32768", "size": "dynamic",
+      "iv_tweak": "0", "encryption": "aes-xts-plain64", "sector_size": 512}
+  },
+  "digests": {
+    "0": {"type": "pbkdf2", "keyslots": ["0", "2"], "segments": ["0"],
+      "hash": "sha256", "iterations": 100000,
+      "salt": "c2VjcmV0IHNhbHQgdGhhdCBtdXN0IG5vdCBiZSBzaG93bg==",
+      "digest": "c2VjcmV0IGRpZ2VzdCB0aGF0IG11c3Qgbm90IGJlIHNob3du"}
+  },
+  "config": {"json_size": "12288", "keyslots_size": "16744448"}
+}"#
+	.to_string()
+}
+
+/// One copy of a LUKS2 header: the primary if `secondary` is false.
+pub fn luks2_header(
+	json: &str,
+	label: &str,
+	seqid: u64,
+	secondary: bool,
+) -> Vec<u8> {
+	let mut header = vec![0u8; LUKS2_SIZE];
+	let magic = if secondary {
+		b"SKUL\xBA\xBE"
+	} else {
+		b"LUKS\xBA\xBE"
+	};
+	header[0..6].copy_from_slice(magic);
+	header[6..8].copy_from_slice(&2u16.to_be_bytes());
+	header[8..16].copy_from_slice(&(LUKS2_SIZE as u64).to_be_bytes());
+	header[16..24].copy_from_slice(&seqid.to_be_bytes());
+	header[24..24 + label.len()].copy_from_slice(label.as_bytes());
+	header[72..78].copy_from_slice(b"sha256");
+	header[168..168 + UUID.len()].copy_from_slice(UUID.as_bytes());
+	let offset = if secondary { LUKS2_SIZE as u64 } else { 0 };
+	header[256..264].copy_from_slice(&offset.to_be_bytes());
+	header[4096..4096 + json.len()].copy_from_slice(json.as_bytes());
+	let digest = Sha256::digest(&header);
+	header[448..480].copy_from_slice(&digest);
+	header
+}
+
+/// Both copies of a LUKS2 header.
+pub fn luks2(json: &str, label: &str) -> Vec<u8> {
+	let mut header = luks2_header(json, label, 1, false);
+	header.extend(luks2_header(json, label, 1, true));
+	header
+}
+
+/// A disk with a LUKS2 volume in partition 1, a LUKS1 one in partition 2
+/// and a plain dm-crypt one in partition 3.
+pub fn gpt_disk() -> Vec<u8> {
+	let image = super::Image::new(512)
+		.part(LUKS, "root", 64, 1023)
+		.part(LUKS, "home", 1024, 1535)
+		.part(PLAIN, "swap", 1536, 2014);
+	let mut bytes = image.build();
+	let luks2 = luks2(&metadata(), "cryptroot");
+	bytes[64 * 512..64 * 512 + luks2.len()].copy_from_slice(&luks2);
+	let luks1 = luks1(&[(0, 2_000_000), (3, 1_500_000)]);
+	bytes[1024 * 512..1024 * 512 + luks1.len()].copy_from_slice(&luks1);
+	bytes
+}
@@ -0,0 +1,248 @@
+//! Writes LVM2 physical volumes.
+
+pub const PV: &str = "E6D6D379-F507-44C2-A23C-238F2A3DF928";
+
+pub const PV_UUID: &str = "xXFbCw-8ymQ-3TtJ-Qd2c-lwJq-4hAu-ESCBqy";
+const OTHER_UUID: &str = "RoGaYn-KXdt-Wp2s-E1Fv-0dXz-nV5k-P7cH1m";
+
+// Where the metadata area starts and how long it is, in bytes from the
+// start of the PV
+const MDA_START: usize = 4096;
+const MDA_SIZE: usize = 12288;
+/// Where the PV's extents start, in sectors
+pub const PE_START: u64 = 32;
+/// Size of an extent, in sectors
+pub const EXTENT_SIZE: u64 = 8;
+
+/// LVM's CRC-32, without the final inversion.
+pub fn checksum(bytes: &[u8]) -> u32 {
+	let mut hasher = crc32fast::Hasher::new_with_initial(!0xF597_A6CF);
+	hasher.update(bytes);
+	!hasher.finalize()
+}
+
+/// Metadata of group vg0 with this PV and another: `root` has a segment
+/// on each, `swap` is only on this one, `data` is striped across both, a
+/// hidden volume sits on this one and `home` only on the other.
+pub fn metadata() -> String {
+	format!(
+		r#"# Generated by LVM2: Tue Oct  6 12:00:00 2026
+
+contents = "Text Format Volume Group"
+version = 1
+
+description = "Created *after* executing 'lvcreate -n data vg0'"
+
+creation_host = "test"	# Linux test 6.1.0
+creation_time = 1791288000	# Tue Oct  6 12:00:00 2026
+
+vg0 {{
+	id = "JW3fzE-ZxvM-Xm2D-VLwq-1qGw-zeBn-G0Rkyo"
+	seqno = 7
+	format = "lvm2"
+	status = ["RESIZEABLE", "READ", "WRITE"]
+	flags = []
+	extent_size = {extent}
+	max_lv = 0
+	max_pv = 0
+	metadata_copies = 0
+
+	physical_volumes {{
+
+		pv0 {{
+			id = "{other}"
+			device = "/dev/sdb1"
+
+			status = ["ALLOCATABLE"]
+			flags = []
+			dev_size = 2048
+			pe_start = 32
+			pe_count = 252
+		}}
+
+		pv1 {{
+			id = "{this}"
+			device = "/dev/sda1"
+
+			status = ["ALLOCATABLE"]
+			flags = []
+			dev_size = 960
+			pe_start = {pe_start}
+			pe_count = 100
+		}}
+	}}
+
+	logical_volumes {{
+
+		root {{
+			id = "c0X1yB-Ddbq-7sSx-ZTdo-aw9I-QJyf-GwR6Dd"
+			status = ["READ", "WRITE", "VISIBLE"]
+			flags = []
+			creation_time = 1791288000
+			creation_host = "test"
+			segment_count = 2
+
+			segment1 {{
+				start_extent = 0
+				extent_count = 40
+
+				type = "striped"
+				stripe_count = 1	# linear
+
+				stripes = [
+					"pv1", 0
+				]
+			}}
+			segment2 {{
+				start_extent = 40
+				extent_count = 10
+
+				type = "striped"
+				stripe_count = 1	# linear
+
+				stripes = [
+					"pv0", 0
+				]
+			}}
+		}}
+
+		swap {{
+			id = "Xu2S4P-j0Jb-WmKr-vZ0q-8r3O-6PtH-jIT0l9"
+			status = ["READ", "WRITE", "VISIBLE"]
+			flags = []
+			segment_count = 1
+
+			segment1 {{
+				start_extent = 0
+				extent_count = 20
+
+				type = "striped"
+				stripe_count = 1	# linear
+
+				stripes = [
+					"pv1", 40
+				]
+			}}
+		}}
+
+		home {{
+			id = "w1DLr4-Z5dZ-7J6z-qg5W-Q2Fo-sYJ1-aC7zpR"
+			status = ["READ", "WRITE", "VISIBLE"]
+			flags = []
+			segment_count = 1
+
+			segment1 {{
+				start_extent = 0
+				extent_count = 100
+
+				type = "striped"
+				stripe_count = 1	# linear
+
+				stripes = [
+					"pv0", 10
+				]
+			}}
+		}}
+
+		data {{
+			id = "3mFQvB-qR1j-Hn0E-xT8k-Lc2W-9sYp-Ud5Gao"
+			status = ["READ", "WRITE", "VISIBLE"]
+			flags = []
+			segment_count = 1
+
+			segment1 {{
+				start_extent = 0
+				extent_count = 20
+
+				type = "striped"
+				stripe_count = 2
+				stripe_size = 128
+
+				stripes = [
+					"pv0", 110,
+					"pv1", 60
+				]
+			}}
+		}}
+
+		lvol0_pmspare {{
+			id = "a8Ue0K-3Mvq-kz1S-pH4d-Rj7N-Bc9L-YtWf2x"
+			status = ["READ", "WRITE"]
+			flags = []
+			segment_count = 1
+
+			segment1 {{
+				start_extent = 0
+				extent_count = 2
+
+				type = "striped"
+				stripe_count = 1	# linear
+
+				stripes = [
+					"pv1", 70
+				]
+			}}
+		}}
+	}}
+}}
+"#,
+		extent = EXTENT_SIZE,
+		pe_start = PE_START,
+		this = PV_UUID,
+		other = OTHER_UUID,
+	)
+}
+
+/// The start of a PV whose metadata area holds `text` at byte `offset` of
+/// the area, wrapping around to just after the area's header if it runs
+/// past the end. Extents follow at `PE_START`.
+pub fn pv(text: &str, offset: usize) -> Vec<u8> {
+	let mut pv = vec![0u8; PE_START as usize * 512];
+
+	let label = &mut pv[512..1024];
+	label[0..8].copy_from_slice(b"LABELONE");
+	label[8..16].copy_from_slice(&1u64.to_le_bytes());
+	label[20..24].copy_from_slice(&32u32.to_le_bytes());
+	label[24..32].copy_from_slice(b"LVM2 001");
+	label[32..64].copy_from_slice(PV_UUID.replace('-', "").as_bytes());
+	label[64..72].copy_from_slice(&(960u64 * 512).to_le_bytes());
+	// One data area, then one metadata area, each list ending empty
+	for (idx, value) in
+		[PE_START * 512, 0, 0, 0, MDA_START as u64, MDA_SIZE as u64]
+			.iter()
+			.enumerate()
+	{
+		let at = 72 + idx * 8;
+		label[at..at + 8].copy_from_slice(&value.to_le_bytes());
+	}
+	let crc = checksum(&label[20..]);
+	label[16..20].copy_from_slice(&crc.to_le_bytes());
+
+	let mut text = text.as_bytes().to_vec();
+	text.push(0);
+	let mda = &mut pv[MDA_START..MDA_START + MDA_SIZE];
+	let first = text.len().min(MDA_SIZE - offset);
+	mda[offset..offset + first].copy_from_slice(&text[..first]);
+	mda[512..512 + text.len() - first].copy_from_slice(&text[first..]);
+
+	mda[4..20].copy_from_slice(b" LVM2 x[5A%r0N*>");
+	mda[20..24].copy_from_slice(&1u32.to_le_bytes());
+	mda[24..32].copy_from_slice(&(MDA_START as u64).to_le_bytes());
+	mda[32..40].copy_from_slice(&(MDA_SIZE as u64).to_le_bytes());
+	mda[40..48].copy_from_slice(&(offset as u64).to_le_bytes());
+	mda[48..56].copy_from_slice(&(text.len() as u64).to_le_bytes());
+	mda[56..60].copy_from_slice(&checksum(&text).to_le_bytes());
+	let crc = checksum(&mda[4..512]);
+	mda[0..4].copy_from_slice(&crc.to_le_bytes());
+	pv
+}
+
+/// A disk with the PV of `metadata()` in a GPT partition spanning LBAs
+/// 64..1023.
+pub fn gpt_disk() -> Vec<u8> {
+	let image = super::Image::new(512).part(PV, "pv", 64, 1023);
+	let mut bytes = image.build();
+	let pv = pv(&metadata(), 512);
+	bytes[64 * 512..64 * 512 + pv.len()].copy_from_slice(&pv);
+	bytes
+}
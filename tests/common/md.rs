@@ -0,0 +1,154 @@
+//! Writes md-raid superblocks.
+
+pub const RAID: &str = "A19D880F-05FC-4D3B-A006-743F0F84911E";
+
+pub const UUID: [u8; 16] = [
+	0x6b, 0x3f, 0x1c, 0x2e, 0x94, 0xd0, 0x4a, 0x57, 0x8e, 0x21, 0x0c, 0x7b,
+	0x5d, 0x93, 0xa4, 0xf6,
+];
+pub const OTHER_UUID: [u8; 16] = [0x11; 16];
+pub const LEGACY_UUID: [u8; 16] = [0x22; 16];
+
+/// What a superblock says about its array and this member.
+pub struct Member<'a> {
+	pub uuid: [u8; 16],
+	pub name: &'a str,
+	pub level: i32,
+	pub raid_disks: u32,
+	/// 0xFFFF for a spare and 0xFFFE for a faulty member
+	pub role: u16,
+	pub events: u64,
+	pub clean: bool,
+}
+
+impl Member<'_> {
+	/// A clean member of `UUID`, a two-disk RAID-1 named `host:0`, in
+	/// slot `role`.
+	pub fn raid1(role: u16, events: u64) -> Member<'static> {
+		Member {
+			uuid: UUID,
+			name: "host:0",
+			level: 1,
+			raid_disks: 2,
+			role,
+			events,
+			clean: true,
+		}
+	}
+}
+
+/// md's checksum: the sum of the little-endian words, with the carry
+/// folded back in.
+fn checksum(bytes: &[u8]) -> u32 {
+	let sum: u64 = bytes
+		.chunks_exact(4)
+		.map(|word| {
+			u64::from(u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+		})
+		.sum();
+	((sum & 0xFFFF_FFFF) + (sum >> 32)) as u32
+}
+
+/// A 1.x superblock written `super_offset` sectors into the device.
+pub fn v1(member: &Member, super_offset: u64) -> [u8; 4096] {
+	let mut sb = [0u8; 4096];
+	sb[0..4].copy_from_slice(&0xA92B_4EFCu32.to_le_bytes());
+	sb[4..8].copy_from_slice(&1u32.to_le_bytes());
+	sb[16..32].copy_from_slice(&member.uuid);
+	sb[32..32 + member.name.len()].copy_from_slice(member.name.as_bytes());
+	sb[72..76].copy_from_slice(&member.level.to_le_bytes());
+	sb[92..96].copy_from_slice(&member.raid_disks.to_le_bytes());
+	sb[128..136].copy_from_slice(&2048u64.to_le_bytes());
+	sb[144..152].copy_from_slice(&super_offset.to_le_bytes());
+	// This device is number 1 of 4
+	sb[160..164].copy_from_slice(&1u32.to_le_bytes());
+	sb[200..208].copy_from_slice(&member.events.to_le_bytes());
+	let resync: u64 = if member.clean { u64::MAX } else { 0 };
+	sb[208..216].copy_from_slice(&resync.to_le_bytes());
+	sb[220..224].copy_from_slice(&4u32.to_le_bytes());
+	for (idx, role) in [0, member.role, 1, 0xFFFF].iter().enumerate() {
+		sb[256 + idx * 2..258 + idx * 2].copy_from_slice(&role.to_le_bytes());
+	}
+	let csum = checksum(&sb[..264]);
+	sb[216..220].copy_from_slice(&csum.to_le_bytes());
+	sb
+}
+
+/// A 0.90 superblock. Its UUID is the words of `member.uuid`, and a
+/// `role` of 0xFFFF makes a spare.
+pub fn v0(member: &Member) -> [u8; 4096] {
+	let mut sb = [0u8; 4096];
+	let word = |idx: usize| {
+		u32::from_le_bytes([
+			member.uuid[idx * 4],
+			member.uuid[idx * 4 + 1],
+			member.uuid[idx * 4 + 2],
+			member.uuid[idx * 4 + 3],
+		])
+	};
+	let words: [(usize, u32); 14] = [
+		(0, 0xA92B_4EFC),
+		(2, 90),
+		(5, word(0)),
+		(7, member.level as u32),
+		(10, member.raid_disks),
+		(13, word(1)),
+		(14, word(2)),
+		(15, word(3)),
+		(33, member.clean as u32),
+		(39, member.events as u32),
+		(40, (member.events >> 32) as u32),
+		// The descriptor of this device: its slot and state
+		(992 + 3, u32::from(member.role.min(0xFF))),
+		(992 + 4, if member.role == 0xFFFF { 0 } else { 0b110 }),
+		(992, 1),
+	];
+	for &(idx, value) in words.iter() {
+		sb[idx * 4..idx * 4 + 4].copy_from_slice(&value.to_le_bytes());
+	}
+	let csum = checksum(&sb);
+	sb[152..156].copy_from_slice(&csum.to_le_bytes());
+	sb
+}
+
+/// A disk with four RAID partitions of 256 sectors: two members of
+/// `host:0` with 1.2 superblocks, the second one behind, a spare of
+/// another array with a 1.0 superblock and a 0.90 member of a third.
+pub fn gpt_disk() -> Vec<u8> {
+	let image = super::Image::new(512)
+		.part(RAID, "md0a", 64, 319)
+		.part(RAID, "md0b", 320, 575)
+		.part(RAID, "md1", 576, 831)
+		.part(RAID, "md2", 832, 1087);
+	let mut bytes = image.build();
+
+	let write = |bytes: &mut Vec<u8>, at: u64, sb: &[u8; 4096]| {
+		let at = at as usize * 512;
+		bytes[at..at + 4096].copy_from_slice(sb);
+	};
+	write(&mut bytes, 64 + 8, &v1(&Member::raid1(0, 120), 8));
+	write(&mut bytes, 320 + 8, &v1(&Member::raid1(1, 115), 8));
+	let spare = Member {
+		uuid: OTHER_UUID,
+		name: "host:1",
+		level: 5,
+		raid_disks: 3,
+		role: 0xFFFF,
+		events: 9,
+		clean: false,
+	};
+	// 8K from the end of the partition, aligned down to 4K
+	write(&mut bytes, 576 + 240, &v1(&spare, 240));
+	let legacy = Member {
+		uuid: LEGACY_UUID,
+		name: "",
+		level: 0,
+		raid_disks: 2,
+		role: 1,
+		events: 0x1_0000_0002,
+		clean: true,
+	};
+	// The last 64K-aligned 64K of the partition
+	write(&mut bytes, 832 + 128, &v0(&legacy));
+	bytes
+}
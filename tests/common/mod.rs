@@ -0,0 +1,259 @@
+//! Builds small synthetic disk images for the integration tests.
+
+#![allow(dead_code)]
+
+pub mod apm;
+pub mod compressed;
+pub mod disklabel;
+pub mod fs;
+pub mod ldm;
+pub mod luks;
+pub mod lvm;
+pub mod md;
+pub mod qcow2;
+pub mod slots;
+pub mod vhd;
+pub mod vhdx;
+pub mod vmdk;
+pub mod zfs;
+
+use gptinfo::Guid;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+pub const ESP: &str = "C12A7328-F81F-11D2-BA4B-00A0C93EC93B";
+pub const LINUX_FS: &str = "0FC63DAF-8483-4772-8E79-3D69D8477DE4";
+pub const LINUX_SWAP: &str = "0657FD6D-A4AB-43C4-84E5-0933C84B4F4F";
+pub const BASIC_DATA: &str = "EBD0A0A2-B9E5-4433-87C0-68B6B72699C7";
+pub const DISK_GUID: &str = "5B1F5BD4-8DBC-4B36-9D5C-F61A0BD0B94D";
+
+pub fn guid(input: &str) -> Guid { input.parse().unwrap() }
+
+/// GUID used for the `idx`th partition's unique GUID.
+pub fn unique_guid(idx: usize) -> Guid {
+	guid(&format!("00000000-0000-4000-8000-{:012X}", idx + 1))
+}
+
+pub struct Part {
+	pub type_guid: Guid,
+	/// Raw UTF-16 code units, so tests can write invalid names
+	pub name: Vec<u16>,
+	pub first_lba: u64,
+	pub last_lba: u64,
+	pub attributes: u64,
+}
+
+pub enum Mbr {
+	None,
+	Protective,
+	/// A protective entry followed by these `(type, first_lba, sectors)`
+	Hybrid(Vec<(u8, u32, u32)>),
+	/// A plain MBR disk with these `(type, first_lba, sectors)` and no GPT
+	Legacy(Vec<(u8, u32, u32)>),
+}
+
+pub struct Image {
+	pub sector_size: u64,
+	pub sectors: u64,
+	pub num_entries: u32,
+	pub entry_size: u32,
+	pub mbr: Mbr,
+	pub backup: bool,
+	pub parts: Vec<Part>,
+}
+
+impl Image {
+	/// A 1MiB (at 512-byte sectors) disk with a protective MBR and both
+	/// tables.
+	pub fn new(sector_size: u64) -> Image {
+		Image {
+			sector_size,
+			sectors: 2048,
+			num_entries: 128,
+			entry_size: 128,
+			mbr: Mbr::Protective,
+			backup: true,
+			parts: vec![],
+		}
+	}
+
+	pub fn part(
+		mut self,
+		type_guid: &str,
+		name: &str,
+		first_lba: u64,
+		last_lba: u64,
+	) -> Image {
+		self.parts.push(Part {
+			type_guid: guid(type_guid),
+			name: name.encode_utf16().collect(),
+			first_lba,
+			last_lba,
+			attributes: 0,
+		});
+		self
+	}
+
+	/// Leaves the next `count` slots of the entry array unused.
+	pub fn unused(mut self, count: u32) -> Image {
+		for _ in 0..count {
+			self.parts.push(Part {
+				type_guid: Guid::default(),
+				name: vec![],
+				first_lba: 0,
+				last_lba: 0,
+				attributes: 0,
+			});
+		}
+		self
+	}
+
+	pub fn entry_array_sectors(&self) -> u64 {
+		let len = u64::from(self.num_entries) * u64::from(self.entry_size);
+		len.div_ceil(self.sector_size)
+	}
+
+	pub fn first_usable_lba(&self) -> u64 { 2 + self.entry_array_sectors() }
+
+	pub fn last_usable_lba(&self) -> u64 {
+		self.sectors - 2 - self.entry_array_sectors()
+	}
+
+	pub fn backup_lba(&self) -> u64 { self.sectors - 1 }
+
+	/// Byte offset of the primary header.
+	pub fn primary_offset(&self) -> usize { self.sector_size as usize }
+
+	/// Byte offset of the primary entry array.
+	pub fn primary_entries_offset(&self) -> usize {
+		2 * self.sector_size as usize
+	}
+
+	/// Byte offset of the backup header.
+	pub fn backup_offset(&self) -> usize {
+		(self.backup_lba() * self.sector_size) as usize
+	}
+
+	fn entry_array(&self) -> Vec<u8> {
+		let entry_size = self.entry_size as usize;
+		let mut array = vec![0u8; self.num_entries as usize * entry_size];
+		for (idx, part) in self.parts.iter().enumerate() {
+			let entry = &mut array[idx * entry_size..];
+			entry[0..16].copy_from_slice(&part.type_guid.to_bytes_le());
+			entry[16..32].copy_from_slice(&unique_guid(idx).to_bytes_le());
+			entry[32..40].copy_from_slice(&part.first_lba.to_le_bytes());
+			entry[40..48].copy_from_slice(&part.last_lba.to_le_bytes());
+			entry[48..56].copy_from_slice(&part.attributes.to_le_bytes());
+			for (unit, raw) in
+				part.name.iter().zip(entry[56..128].chunks_exact_mut(2))
+			{
+				raw.copy_from_slice(&unit.to_le_bytes());
+			}
+		}
+		array
+	}
+
+	fn header(
+		&self,
+		current: u64,
+		backup: u64,
+		entries: u64,
+		crc: u32,
+	) -> Vec<u8> {
+		let mut header = vec![0u8; 92];
+		header[0..8].copy_from_slice(b"EFI PART");
+		header[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+		header[12..16].copy_from_slice(&92u32.to_le_bytes());
+		header[24..32].copy_from_slice(&current.to_le_bytes());
+		header[32..40].copy_from_slice(&backup.to_le_bytes());
+		header[40..48].copy_from_slice(&self.first_usable_lba().to_le_bytes());
+		header[48..56].copy_from_slice(&self.last_usable_lba().to_le_bytes());
+		header[56..72].copy_from_slice(&guid(DISK_GUID).to_bytes_le());
+		header[72..80].copy_from_slice(&entries.to_le_bytes());
+		header[80..84].copy_from_slice(&self.num_entries.to_le_bytes());
+		header[84..88].copy_from_slice(&self.entry_size.to_le_bytes());
+		header[88..92].copy_from_slice(&crc.to_le_bytes());
+		let header_crc = crc32fast::hash(&header);
+		header[16..20].copy_from_slice(&header_crc.to_le_bytes());
+		header
+	}
+
+	fn mbr_entries(&self) -> Vec<(u8, u32, u32)> {
+		let protective =
+			(0xEE, 1, (self.sectors - 1).min(u64::from(u32::MAX)) as u32);
+		match &self.mbr {
+			Mbr::None => vec![],
+			Mbr::Protective => vec![protective],
+			Mbr::Hybrid(rest) => {
+				// The protective entry only covers the GPT structures
+				let gpt = (0xEE, 1, self.first_usable_lba() as u32 - 1);
+				let mut entries = vec![gpt];
+				entries.extend(rest);
+				entries
+			}
+			Mbr::Legacy(entries) => entries.clone(),
+		}
+	}
+
+	pub fn build(&self) -> Vec<u8> {
+		let sector = self.sector_size as usize;
+		let mut bytes = vec![0u8; self.sectors as usize * sector];
+
+		let entries = self.mbr_entries();
+		if !entries.is_empty() {
+			write_mbr(&mut bytes, &entries);
+		}
+		if let Mbr::Legacy(_) = self.mbr {
+			return bytes;
+		}
+
+		let array = self.entry_array();
+		let crc = crc32fast::hash(&array);
+		let backup_entries = self.backup_lba() - self.entry_array_sectors();
+
+		let primary = self.header(1, self.backup_lba(), 2, crc);
+		bytes[sector..sector + 92].copy_from_slice(&primary);
+		let offset = self.primary_entries_offset();
+		bytes[offset..offset + array.len()].copy_from_slice(&array);
+
+		if self.backup {
+			let backup = self.header(self.backup_lba(), 1, backup_entries, crc);
+			let offset = self.backup_offset();
+			bytes[offset..offset + 92].copy_from_slice(&backup);
+			let offset = (backup_entries * self.sector_size) as usize;
+			bytes[offset..offset + array.len()].copy_from_slice(&array);
+		}
+
+		bytes
+	}
+}
+
+/// Writes a boot sector with these `(type, first_lba, sectors)` entries
+/// at the start of `sector`, as in an MBR or extended boot record.
+pub fn write_mbr(sector: &mut [u8], entries: &[(u8, u32, u32)]) {
+	for (idx, &(kind, first, len)) in entries.iter().enumerate() {
+		let entry = &mut sector[446 + idx * 16..446 + (idx + 1) * 16];
+		entry[4] = kind;
+		entry[8..12].copy_from_slice(&first.to_le_bytes());
+		entry[12..16].copy_from_slice(&len.to_le_bytes());
+	}
+	sector[510] = 0x55;
+	sector[511] = 0xAA;
+}
+
+pub fn temp_image(bytes: &[u8]) -> NamedTempFile {
+	let mut file = NamedTempFile::new().unwrap();
+	file.write_all(bytes).unwrap();
+	file.flush().unwrap();
+	file
+}
+
+/// The layout most tests start from: an ESP, a root and a swap partition.
+pub fn basic(sector_size: u64) -> Image {
+	let image = Image::new(sector_size);
+	let first = image.first_usable_lba();
+	image
+		.part(ESP, "EFI", first, first + 99)
+		.part(LINUX_FS, "root", first + 100, first + 999)
+		.part(LINUX_SWAP, "swap", first + 1000, first + 1099)
+}
@@ -0,0 +1,111 @@
+//! Writes raw disk bytes out as a qcow2 (version 3) image.
+
+use flate2::{write::DeflateEncoder, Compression};
+use std::io::Write;
+
+const OFFSET_MASK: u64 = 0x00ff_ffff_ffff_fe00;
+
+pub struct Qcow2 {
+	pub cluster_bits: u32,
+	/// Stores every allocated cluster compressed
+	pub compress: bool,
+	/// Compresses with zstd instead, setting the compression type
+	pub zstd: bool,
+	/// Marks all-zero clusters with the zero flag instead of leaving them
+	/// unallocated
+	pub zero_flag: bool,
+	pub backing_file: Option<String>,
+}
+
+impl Qcow2 {
+	pub fn new(cluster_bits: u32) -> Qcow2 {
+		Qcow2 {
+			cluster_bits,
+			compress: false,
+			zstd: false,
+			zero_flag: false,
+			backing_file: None,
+		}
+	}
+
+	fn cluster_size(&self) -> usize { 1 << self.cluster_bits }
+
+	/// Lays out the header, L1 table, L2 tables and data clusters in that
+	/// order. Clusters of `raw` that are all zeros aren't allocated.
+	pub fn build(&self, raw: &[u8]) -> Vec<u8> {
+		let cluster = self.cluster_size();
+		let per_table = cluster / 8;
+		let clusters = raw.len().div_ceil(cluster);
+		let l1_size = clusters.div_ceil(per_table);
+		let l1_clusters = (l1_size * 8).div_ceil(cluster);
+
+		let mut image = vec![0u8; cluster * (1 + l1_clusters)];
+		image[0..4].copy_from_slice(b"QFI\xfb");
+		image[4..8].copy_from_slice(&3u32.to_be_bytes());
+		image[20..24].copy_from_slice(&self.cluster_bits.to_be_bytes());
+		image[24..32].copy_from_slice(&(raw.len() as u64).to_be_bytes());
+		image[36..40].copy_from_slice(&(l1_size as u32).to_be_bytes());
+		image[40..48].copy_from_slice(&(cluster as u64).to_be_bytes());
+		image[96..100].copy_from_slice(&4u32.to_be_bytes());
+		if self.zstd {
+			// The compression type byte pads the header to 112 bytes
+			image[72..80].copy_from_slice(&(1u64 << 3).to_be_bytes());
+			image[100..104].copy_from_slice(&112u32.to_be_bytes());
+			image[104] = 1;
+		} else {
+			image[100..104].copy_from_slice(&104u32.to_be_bytes());
+		}
+		if let Some(name) = &self.backing_file {
+			// After the (empty) header extension area
+			image[8..16].copy_from_slice(&200u64.to_be_bytes());
+			image[16..20].copy_from_slice(&(name.len() as u32).to_be_bytes());
+			image[200..200 + name.len()].copy_from_slice(name.as_bytes());
+		}
+
+		let mut l2_tables = vec![vec![0u64; per_table]; l1_size];
+		let mut data = vec![];
+		let data_start = image.len() + l1_size * cluster;
+		for (idx, chunk) in raw.chunks(cluster).enumerate() {
+			let entry = &mut l2_tables[idx / per_table][idx % per_table];
+			if chunk.iter().all(|&byte| byte == 0) {
+				if self.zero_flag {
+					*entry = 1;
+				}
+				continue;
+			}
+
+			let host = (data_start + data.len()) as u64;
+			if self.compress {
+				let compressed = if self.zstd {
+					zstd::encode_all(chunk, 3).unwrap()
+				} else {
+					let mut encoder =
+						DeflateEncoder::new(vec![], Compression::default());
+					encoder.write_all(chunk).unwrap();
+					encoder.finish().unwrap()
+				};
+
+				let offset_bits = 62 - (self.cluster_bits - 8);
+				let sectors =
+					(host + compressed.len() as u64 - 1) / 512 - host / 512;
+				*entry = 1 << 62 | sectors << offset_bits | host;
+				data.extend(compressed);
+			} else {
+				*entry = host;
+				data.extend(chunk);
+				data.resize(data.len() + cluster - chunk.len(), 0);
+			}
+		}
+
+		for (idx, table) in l2_tables.iter().enumerate() {
+			let offset = (image.len() as u64) & OFFSET_MASK;
+			let at = cluster + idx * 8;
+			image[at..at + 8].copy_from_slice(&offset.to_be_bytes());
+			for entry in table {
+				image.extend(&entry.to_be_bytes());
+			}
+		}
+		image.extend(data);
+		image
+	}
+}
@@ -0,0 +1,55 @@
+//! Writes ChromeOS and Android disks with A/B boot slots.
+
+pub const CHROMEOS_KERNEL: &str = "FE3A2A5D-4F32-41A7-B725-ACCC3285A309";
+pub const CHROMEOS_ROOTFS: &str = "3CB8E202-3B7E-47DD-8A3C-7FF2A13CFCEC";
+
+/// The attribute bits of a ChromeOS kernel.
+pub fn kernel_attributes(priority: u64, tries: u64, successful: bool) -> u64 {
+	priority << 48 | tries << 52 | (successful as u64) << 56
+}
+
+/// A ChromeOS disk: slot A booted fine and slot B was just updated, so it
+/// is tried first.
+pub fn chromeos_disk() -> Vec<u8> {
+	let mut image = super::Image::new(512)
+		.part(super::LINUX_FS, "STATE", 64, 127)
+		.part(CHROMEOS_KERNEL, "KERN-A", 128, 191)
+		.part(CHROMEOS_ROOTFS, "ROOT-A", 192, 255)
+		.part(CHROMEOS_KERNEL, "KERN-B", 256, 319)
+		.part(CHROMEOS_ROOTFS, "ROOT-B", 320, 383);
+	image.parts[1].attributes = kernel_attributes(1, 0, true);
+	image.parts[3].attributes = kernel_attributes(2, 6, false);
+	image.build()
+}
+
+/// The boot control block of misc for slots of `(priority, tries,
+/// successful)`.
+pub fn boot_control(slots: &[(u8, u8, bool)]) -> [u8; 32] {
+	let mut block = [0u8; 32];
+	block[0..3].copy_from_slice(b"_a\0");
+	block[4..8].copy_from_slice(&0x4241_4342u32.to_le_bytes());
+	block[8] = 1;
+	block[9] = slots.len() as u8;
+	for (idx, &(priority, tries, successful)) in slots.iter().enumerate() {
+		block[12 + idx * 2] = priority | tries << 4 | (successful as u8) << 7;
+	}
+	let crc = crc32fast::hash(&block[..28]);
+	block[28..32].copy_from_slice(&crc.to_le_bytes());
+	block
+}
+
+/// An Android disk with misc, two slots of boot and system, and userdata.
+/// Slot a is active and has booted; slot b was given up on.
+pub fn android_disk() -> Vec<u8> {
+	let image = super::Image::new(512)
+		.part(super::BASIC_DATA, "misc", 64, 127)
+		.part(super::BASIC_DATA, "boot_a", 128, 191)
+		.part(super::BASIC_DATA, "boot_b", 192, 255)
+		.part(super::LINUX_FS, "system_a", 256, 511)
+		.part(super::LINUX_FS, "system_b", 512, 767)
+		.part(super::LINUX_FS, "userdata", 768, 1023);
+	let mut bytes = image.build();
+	let block = boot_control(&[(15, 0, true), (0, 0, false)]);
+	bytes[64 * 512 + 2048..64 * 512 + 2080].copy_from_slice(&block);
+	bytes
+}
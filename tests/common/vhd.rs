@@ -0,0 +1,73 @@
+//! Writes raw disk bytes out as fixed or dynamic VHD images.
+
+pub const FIXED: u32 = 2;
+pub const DYNAMIC: u32 = 3;
+pub const DIFFERENCING: u32 = 4;
+
+/// The 512-byte footer, checksummed.
+pub fn footer(size: u64, disk_type: u32, header_offset: u64) -> Vec<u8> {
+	let mut footer = vec![0u8; 512];
+	footer[0..8].copy_from_slice(b"conectix");
+	footer[8..12].copy_from_slice(&2u32.to_be_bytes());
+	footer[12..16].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+	footer[16..24].copy_from_slice(&header_offset.to_be_bytes());
+	footer[28..32].copy_from_slice(b"gpti");
+	footer[40..48].copy_from_slice(&size.to_be_bytes());
+	footer[48..56].copy_from_slice(&size.to_be_bytes());
+	footer[60..64].copy_from_slice(&disk_type.to_be_bytes());
+	let sum = checksum(&footer);
+	footer[64..68].copy_from_slice(&sum.to_be_bytes());
+	footer
+}
+
+fn checksum(bytes: &[u8]) -> u32 {
+	!bytes
+		.iter()
+		.fold(0u32, |sum, &byte| sum.wrapping_add(u32::from(byte)))
+}
+
+pub fn fixed(raw: &[u8]) -> Vec<u8> {
+	let mut image = raw.to_vec();
+	image.extend(footer(raw.len() as u64, FIXED, u64::MAX));
+	image
+}
+
+/// A footer copy, the dynamic header and the block allocation table,
+/// followed by the non-zero blocks of `raw` and the footer.
+pub fn dynamic(raw: &[u8], block_size: usize, disk_type: u32) -> Vec<u8> {
+	let blocks = raw.len().div_ceil(block_size);
+	let bat_len = (blocks * 4).div_ceil(512) * 512;
+	let bitmap_len = (block_size / 512).div_ceil(8).div_ceil(512) * 512;
+	let footer = footer(raw.len() as u64, disk_type, 512);
+
+	let mut header = vec![0u8; 1024];
+	header[0..8].copy_from_slice(b"cxsparse");
+	header[8..16].copy_from_slice(&u64::MAX.to_be_bytes());
+	header[16..24].copy_from_slice(&1536u64.to_be_bytes());
+	header[24..28].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+	header[28..32].copy_from_slice(&(blocks as u32).to_be_bytes());
+	header[32..36].copy_from_slice(&(block_size as u32).to_be_bytes());
+	let sum = checksum(&header);
+	header[36..40].copy_from_slice(&sum.to_be_bytes());
+
+	let mut image = footer.clone();
+	image.extend(header);
+	let mut bat = vec![0xFFu8; bat_len];
+	let mut data = vec![];
+	let data_start = image.len() + bat_len;
+	for (idx, block) in raw.chunks(block_size).enumerate() {
+		if block.iter().all(|&byte| byte == 0) {
+			continue;
+		}
+		let sector = ((data_start + data.len()) / 512) as u32;
+		bat[idx * 4..idx * 4 + 4].copy_from_slice(&sector.to_be_bytes());
+		data.extend(vec![0xFFu8; bitmap_len]);
+		data.extend(block);
+		data.resize(data.len() + block_size - block.len(), 0);
+	}
+
+	image.extend(bat);
+	image.extend(data);
+	image.extend(footer);
+	image
+}
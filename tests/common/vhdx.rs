@@ -0,0 +1,102 @@
+//! Writes raw disk bytes out as a VHDX image.
+
+const MIB: usize = 1024 * 1024;
+const BAT_REGION: &str = "2DC27766-F623-4200-9D64-115E9BFD4A08";
+const METADATA_REGION: &str = "8B7CA206-4790-4B9A-B8FE-575F050F886E";
+const FILE_PARAMETERS: &str = "CAA16737-FA36-4D43-B3B6-33F0AA44E76B";
+const VIRTUAL_DISK_SIZE: &str = "2FA54224-CD1B-4876-B211-5DBED83BF4B8";
+const LOGICAL_SECTOR_SIZE: &str = "8141BF1D-A96F-4709-BA47-F233A8FAAB5F";
+
+fn guid(input: &str) -> [u8; 16] { super::guid(input).to_bytes_le() }
+
+fn crc32c(bytes: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for &byte in bytes {
+		crc ^= u32::from(byte);
+		for _ in 0..8 {
+			let mask = (crc & 1).wrapping_neg();
+			crc = (crc >> 1) ^ (0x82F6_3B78 & mask);
+		}
+	}
+	!crc
+}
+
+fn seal(table: &mut [u8]) {
+	let crc = crc32c(table);
+	table[4..8].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Headers, region tables, metadata at 1MiB and the block allocation
+/// table at 2MiB, followed by the non-zero blocks of `raw`. The second
+/// header is newer; `stale_header` corrupts it to exercise the fallback.
+pub fn build(raw: &[u8], block_size: usize, stale_header: bool) -> Vec<u8> {
+	let blocks = raw.len().div_ceil(block_size);
+	let bat_len = (blocks * 8).div_ceil(MIB) * MIB;
+	let mut image = vec![0u8; 2 * MIB + bat_len];
+	image[0..8].copy_from_slice(b"vhdxfile");
+
+	for (idx, &offset) in [64 * 1024, 128 * 1024].iter().enumerate() {
+		let header = &mut image[offset..offset + 4096];
+		header[0..4].copy_from_slice(b"head");
+		header[8..16].copy_from_slice(&(idx as u64 + 1).to_le_bytes());
+		header[66..68].copy_from_slice(&1u16.to_le_bytes());
+		seal(header);
+	}
+	if stale_header {
+		image[128 * 1024 + 100] ^= 1;
+	}
+
+	for &offset in [192 * 1024, 256 * 1024].iter() {
+		let table = &mut image[offset..offset + 64 * 1024];
+		table[0..4].copy_from_slice(b"regi");
+		table[8..12].copy_from_slice(&2u32.to_le_bytes());
+		let regions =
+			[(BAT_REGION, 2 * MIB, bat_len), (METADATA_REGION, MIB, MIB)];
+		for (idx, &(id, start, len)) in regions.iter().enumerate() {
+			let entry = &mut table[16 + idx * 32..48 + idx * 32];
+			entry[0..16].copy_from_slice(&guid(id));
+			entry[16..24].copy_from_slice(&(start as u64).to_le_bytes());
+			entry[24..28].copy_from_slice(&(len as u32).to_le_bytes());
+			entry[28..32].copy_from_slice(&1u32.to_le_bytes());
+		}
+		seal(table);
+	}
+
+	let metadata = &mut image[MIB..2 * MIB];
+	metadata[0..8].copy_from_slice(b"metadata");
+	metadata[10..12].copy_from_slice(&3u16.to_le_bytes());
+	let items: [(&str, Vec<u8>); 3] = [
+		// Block size, then flags: no parent
+		(
+			FILE_PARAMETERS,
+			[(block_size as u32).to_le_bytes(), [0; 4]].concat(),
+		),
+		(VIRTUAL_DISK_SIZE, (raw.len() as u64).to_le_bytes().to_vec()),
+		(LOGICAL_SECTOR_SIZE, 512u32.to_le_bytes().to_vec()),
+	];
+	for (idx, (id, value)) in items.iter().enumerate() {
+		let offset = 64 * 1024 + idx * 8;
+		let entry = &mut metadata[32 + idx * 32..64 + idx * 32];
+		entry[0..16].copy_from_slice(&guid(id));
+		entry[16..20].copy_from_slice(&(offset as u32).to_le_bytes());
+		entry[20..24].copy_from_slice(&(value.len() as u32).to_le_bytes());
+		entry[24..28].copy_from_slice(&(1u32 << 2).to_le_bytes());
+		metadata[offset..offset + value.len()].copy_from_slice(value);
+	}
+
+	for (idx, block) in raw.chunks(block_size).enumerate() {
+		let entry = if block.iter().all(|&byte| byte == 0) {
+			// PAYLOAD_BLOCK_ZERO
+			2
+		} else {
+			let offset = image.len() as u64;
+			image.extend(block);
+			image.resize(image.len() + block_size - block.len(), 0);
+			offset | 6
+		};
+		let at = 2 * MIB + idx * 8;
+		image[at..at + 8].copy_from_slice(&entry.to_le_bytes());
+	}
+
+	image
+}
@@ -0,0 +1,85 @@
+//! Writes raw disk bytes out as a monolithic sparse VMDK extent.
+
+use flate2::{write::ZlibEncoder, Compression};
+use std::io::Write;
+
+const GRAIN_SECTORS: usize = 16;
+const GTES_PER_GT: usize = 512;
+
+fn header(capacity: u64, gd_sector: u64, stream: bool) -> Vec<u8> {
+	let mut header = vec![0u8; 512];
+	header[0..4].copy_from_slice(b"KDMV");
+	header[4..8].copy_from_slice(&if stream { 3u32 } else { 1 }.to_le_bytes());
+	// Valid newline test, plus compressed grains with markers
+	let flags = if stream { 1 | 1 << 16 | 1 << 17 } else { 1 };
+	header[8..12].copy_from_slice(&(flags as u32).to_le_bytes());
+	header[12..20].copy_from_slice(&capacity.to_le_bytes());
+	header[20..28].copy_from_slice(&(GRAIN_SECTORS as u64).to_le_bytes());
+	header[28..36].copy_from_slice(&1u64.to_le_bytes());
+	header[36..44].copy_from_slice(&1u64.to_le_bytes());
+	header[44..48].copy_from_slice(&(GTES_PER_GT as u32).to_le_bytes());
+	header[56..64].copy_from_slice(&gd_sector.to_le_bytes());
+	header[73..77].copy_from_slice(b"\n \r\n");
+	if stream {
+		header[77..79].copy_from_slice(&1u16.to_le_bytes());
+	}
+	header
+}
+
+/// The header, a one-sector descriptor, then grains, grain tables and the
+/// grain directory. Stream-optimized extents compress every grain and keep
+/// the real header in a footer, as `ovftool` writes them.
+pub fn build(raw: &[u8], descriptor: &str, stream: bool) -> Vec<u8> {
+	let grain = GRAIN_SECTORS * 512;
+	let capacity = (raw.len() / 512) as u64;
+	let grains = raw.len().div_ceil(grain);
+	let tables = grains.div_ceil(GTES_PER_GT);
+
+	let mut image = header(capacity, if stream { u64::MAX } else { 0 }, stream);
+	let mut text = descriptor.as_bytes().to_vec();
+	text.resize(512, 0);
+	image.extend(text);
+
+	let mut gt = vec![0u32; tables * GTES_PER_GT];
+	for (idx, chunk) in raw.chunks(grain).enumerate() {
+		if chunk.iter().all(|&byte| byte == 0) {
+			continue;
+		}
+		gt[idx] = (image.len() / 512) as u32;
+		if stream {
+			let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+			encoder.write_all(chunk).unwrap();
+			let deflated = encoder.finish().unwrap();
+			let lba = (idx * GRAIN_SECTORS) as u64;
+			image.extend(&lba.to_le_bytes());
+			image.extend(&(deflated.len() as u32).to_le_bytes());
+			image.extend(deflated);
+		} else {
+			image.extend(chunk);
+		}
+		image.resize(image.len().div_ceil(512) * 512, 0);
+	}
+
+	let mut gd = vec![];
+	for table in gt.chunks(GTES_PER_GT) {
+		gd.push((image.len() / 512) as u32);
+		for entry in table {
+			image.extend(&entry.to_le_bytes());
+		}
+	}
+	let gd_sector = (image.len() / 512) as u64;
+	for entry in gd {
+		image.extend(&entry.to_le_bytes());
+	}
+	image.resize(image.len().div_ceil(512) * 512, 0);
+
+	if stream {
+		// Footer marker, footer and end-of-stream marker
+		image.extend(vec![0u8; 512]);
+		image.extend(header(capacity, gd_sector, true));
+		image.extend(vec![0u8; 512]);
+	} else {
+		image[56..64].copy_from_slice(&gd_sector.to_le_bytes());
+	}
+	image
+}
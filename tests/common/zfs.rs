@@ -0,0 +1,171 @@
+//! Writes ZFS vdev labels.
+
+use sha2::{Digest, Sha256};
+
+pub const FREEBSD_ZFS: &str = "516E7CBA-6ECF-11D6-8FF8-00022D09712B";
+pub const SOLARIS_USR: &str = "6A898CC3-1DD2-11B2-99A6-080020736631";
+
+pub const POOL_GUID: u64 = 0x1234_5678_9ABC_DEF0;
+const LABEL_SIZE: usize = 256 * 1024;
+const NVLIST_SIZE: usize = 112 * 1024;
+
+/// What a label says about its pool.
+#[derive(Clone)]
+pub struct Vdev<'a> {
+	pub pool: Option<&'a str>,
+	pub guid: u64,
+	pub txg: u64,
+	pub state: u64,
+}
+
+impl Vdev<'_> {
+	/// A vdev of the active pool `tank`.
+	pub fn tank(guid: u64, txg: u64) -> Vdev<'static> {
+		Vdev {
+			pool: Some("tank"),
+			guid,
+			txg,
+			state: 0,
+		}
+	}
+}
+
+fn xdr_string(out: &mut Vec<u8>, value: &str) {
+	out.extend(&(value.len() as u32).to_be_bytes());
+	out.extend(value.as_bytes());
+	while out.len() % 4 != 0 {
+		out.push(0);
+	}
+}
+
+/// An nvpair of `kind` holding `value`, already encoded.
+fn pair(name: &str, kind: u32, value: &[u8]) -> Vec<u8> {
+	let mut body = vec![];
+	xdr_string(&mut body, name);
+	body.extend(&kind.to_be_bytes());
+	body.extend(&1u32.to_be_bytes());
+	body.extend(value);
+	let mut out = vec![];
+	out.extend(&(body.len() as u32 + 8).to_be_bytes());
+	// The decoded size doesn't matter to readers of the encoding
+	out.extend(&64u32.to_be_bytes());
+	out.extend(body);
+	out
+}
+
+fn number(name: &str, value: u64) -> Vec<u8> {
+	pair(name, 8, &value.to_be_bytes())
+}
+
+fn string(name: &str, value: &str) -> Vec<u8> {
+	let mut encoded = vec![];
+	xdr_string(&mut encoded, value);
+	pair(name, 9, &encoded)
+}
+
+/// An nvlist with its version, flags and end marker around `pairs`.
+fn nvlist(pairs: &[Vec<u8>]) -> Vec<u8> {
+	let mut out = vec![];
+	out.extend(&0u32.to_be_bytes());
+	out.extend(&1u32.to_be_bytes());
+	for pair in pairs {
+		out.extend(pair);
+	}
+	out.extend(&[0; 8]);
+	out
+}
+
+/// The nvlist area of a label written `at` bytes into the vdev, checksum
+/// included.
+pub fn label(vdev: &Vdev, at: u64) -> Vec<u8> {
+	let tree = nvlist(&[
+		string("type", "disk"),
+		number("id", 0),
+		number("guid", vdev.guid),
+		string("path", "/dev/ada0p1"),
+	]);
+	let mut pairs = vec![number("version", 5000)];
+	if let Some(pool) = vdev.pool {
+		pairs.push(string("name", pool));
+		pairs.push(number("pool_guid", POOL_GUID));
+	}
+	pairs.extend(vec![
+		number("state", vdev.state),
+		number("txg", vdev.txg),
+		string("hostname", "host"),
+		number("top_guid", vdev.guid),
+		number("guid", vdev.guid),
+		// The vdev tree, which readers skip
+		pair("vdev_tree", 19, &tree),
+	]);
+
+	let mut area = vec![0u8; NVLIST_SIZE];
+	// XDR encoding, written on a little-endian host
+	area[0] = 1;
+	area[1] = 1;
+	let list = nvlist(&pairs);
+	area[4..4 + list.len()].copy_from_slice(&list);
+	let tail = NVLIST_SIZE - 40;
+	area[tail..tail + 8]
+		.copy_from_slice(&0x0210_DA7A_B10C_7A11u64.to_le_bytes());
+	area[tail + 8..tail + 16].copy_from_slice(&at.to_le_bytes());
+	let digest = Sha256::digest(&area);
+	for (idx, word) in digest.chunks_exact(8).enumerate() {
+		let mut word = word.to_vec();
+		word.reverse();
+		area[tail + 8 + idx * 8..tail + 16 + idx * 8].copy_from_slice(&word);
+	}
+	area
+}
+
+/// Writes up to four labels into the `size` bytes of a vdev at byte
+/// `offset` of `bytes`, front two first.
+pub fn write_labels(
+	bytes: &mut [u8],
+	offset: usize,
+	size: usize,
+	labels: &[Option<Vdev>],
+) {
+	let size = size & !(LABEL_SIZE - 1);
+	for (idx, vdev) in labels.iter().enumerate() {
+		let vdev = match vdev {
+			Some(vdev) => vdev,
+			None => continue,
+		};
+		let start = if idx < 2 {
+			idx * LABEL_SIZE
+		} else {
+			size - (4 - idx) * LABEL_SIZE
+		};
+		let at = start + 16 * 1024;
+		let area = label(vdev, at as u64);
+		bytes[offset + at..offset + at + NVLIST_SIZE].copy_from_slice(&area);
+	}
+}
+
+/// A disk with two 1MiB vdevs of `tank`: partition 1 with four matching
+/// labels, and partition 2 whose back labels are from an older TXG.
+pub fn gpt_disk() -> Vec<u8> {
+	let mut image = super::Image::new(512)
+		.part(FREEBSD_ZFS, "zfs0", 64, 2111)
+		.part(SOLARIS_USR, "zfs1", 2112, 4159);
+	image.sectors = 4224;
+	let mut bytes = image.build();
+
+	let first = Vdev::tank(101, 1234);
+	write_labels(&mut bytes, 64 * 512, 2048 * 512, &vec![Some(first); 4]);
+	let second = Vdev::tank(202, 1234);
+	let stale = Vdev::tank(202, 1200);
+	write_labels(
+		&mut bytes,
+		2112 * 512,
+		2048 * 512,
+		&[
+			Some(second.clone()),
+			Some(second),
+			Some(stale.clone()),
+			Some(stale),
+		],
+	);
+	bytes
+}
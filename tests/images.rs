@@ -0,0 +1,1313 @@
+mod common;
+
+use common::{
+	apm, basic, disklabel as label, fs, guid, ldm, luks, lvm, md, slots,
+	temp_image, unique_guid, write_mbr, zfs, Image, Mbr, BASIC_DATA, DISK_GUID,
+	ESP, LINUX_FS,
+};
+use gptinfo::{
+	read_gpt, read_gpt_from, read_table,
+	reader::{
+		disklabel::{self, Disklabel, LabelKind},
+		fs::Filesystem,
+		ldm::{Layout, Ldm},
+		luks::{Kdf, Keyslot, Luks},
+		lvm::{PhysicalVolume, Segment},
+		md::{Level, Probe, Role, Superblock},
+		slots::{Scheme, Slot, SlotState, Slots},
+		source::{BlockSource, Raw},
+		zfs::{Label, PoolState, Zfs},
+	},
+	Disk, Error, Guid, PartitionTable, TableStatus,
+};
+use std::{
+	fs::File,
+	io::{self, Cursor},
+};
+
+fn read(bytes: &[u8]) -> Result<Disk, Error> {
+	let file = temp_image(bytes);
+	read_gpt(File::open(file.path()).unwrap())
+}
+
+#[test]
+fn parses_guids() {
+	let parsed: Guid = LINUX_FS.to_lowercase().parse().unwrap();
+	assert_eq!(parsed.to_string(), LINUX_FS);
+	assert_eq!(
+		parsed,
+		Guid::from_groups(0x0FC63DAF, 0x8483, 0x4772, 0x8E79, 0x3D69D8477DE4)
+	);
+
+	for input in &[
+		"0FC63DAF-8483-4772-8E79-3D69D8477DE",
+		"0FC63DAF-8483-4772-8E793D69-D8477DE4",
+		"0FC63DAF-8483-4772-8E79-3D69D8477DEG",
+		// Each byte would parse as a signed number on its own
+		"+FC63DAF-8483-4772-8E79-3D69D8477DE4",
+		"0FC63DAF-+483-4772-8E79-3D69D8477DE4",
+		"0FC63DAF-8483-4772-8E79-3D69D8477D+4",
+	] {
+		assert!(matches!(
+			input.parse::<Guid>(),
+			Err(Error::InvalidGUID(ref bad)) if bad == input
+		));
+	}
+}
+
+#[test]
+fn reads_basic_layout() {
+	let image = basic(512);
+	let disk = read(&image.build()).unwrap();
+
+	assert_eq!(disk.sector_size, 512);
+	assert_eq!(disk.primary, TableStatus::Valid);
+	assert_eq!(disk.backup, TableStatus::Valid);
+	assert_eq!(disk.header.current_lba, 1);
+	assert_eq!(disk.header.backup_lba, 2047);
+	assert_eq!(disk.header.first_usable_lba, 34);
+	assert_eq!(disk.header.last_usable_lba, 2014);
+	assert_eq!(disk.header.disk_guid, guid(DISK_GUID));
+
+	let summary: Vec<_> = disk
+		.entries
+		.iter()
+		.map(|entry| {
+			(
+				entry.number,
+				entry.name.as_str(),
+				entry.partition_type().name.unwrap(),
+				entry.first_lba,
+				entry.last_lba,
+				entry.sectors(),
+			)
+		})
+		.collect();
+	assert_eq!(
+		summary,
+		vec![
+			(1, "EFI", "EFI System partition".into(), 34, 133, 100),
+			(2, "root", "Linux filesystem data".into(), 134, 1033, 900),
+			(3, "swap", "Linux Swap partition".into(), 1034, 1133, 100),
+		]
+	);
+	assert_eq!(disk.entries[1].unique_guid, unique_guid(1));
+}
+
+#[test]
+fn reads_4k_sectors() {
+	let mut image = basic(4096);
+	image.sectors = 512;
+	let disk = read(&image.build()).unwrap();
+
+	assert_eq!(disk.sector_size, 4096);
+	// 128 entries fit in 4 sectors of 4KiB
+	assert_eq!(disk.header.first_usable_lba, 6);
+	assert_eq!(disk.entries.len(), 3);
+	assert_eq!(disk.backup, TableStatus::Valid);
+}
+
+#[test]
+fn numbers_entries_by_slot() {
+	for &num_entries in [4, 128, 256].iter() {
+		let mut image = Image::new(512)
+			.part(ESP, "first", 600, 699)
+			.unused(num_entries - 2)
+			.part(LINUX_FS, "last", 700, 799);
+		image.num_entries = num_entries;
+
+		let disk = read(&image.build()).unwrap();
+		let numbers: Vec<_> =
+			disk.entries.iter().map(|entry| entry.number).collect();
+		assert_eq!(numbers, vec![1, num_entries]);
+	}
+}
+
+#[test]
+fn reads_larger_entries() {
+	let mut image = basic(512);
+	image.entry_size = 256;
+	let disk = read(&image.build()).unwrap();
+
+	assert_eq!(disk.header.partition_entry_size, 256);
+	assert_eq!(disk.entries.len(), 3);
+	assert_eq!(disk.entries[2].name, "swap");
+}
+
+#[test]
+fn falls_back_on_corrupt_header_crc() {
+	let image = basic(512);
+	let mut bytes = image.build();
+	// Flip a bit of the first usable LBA
+	bytes[image.primary_offset() + 40] ^= 1;
+	let disk = read(&bytes).unwrap();
+
+	assert_eq!(disk.primary, TableStatus::HeaderChecksum);
+	assert_eq!(disk.backup, TableStatus::Valid);
+	assert_eq!(disk.header.current_lba, 2047);
+	assert_eq!(disk.header.first_usable_lba, 34);
+	assert_eq!(disk.entries.len(), 3);
+}
+
+#[test]
+fn falls_back_on_corrupt_entries_crc() {
+	let image = basic(512);
+	let mut bytes = image.build();
+	bytes[image.primary_entries_offset() + 56] ^= 1;
+	let disk = read(&bytes).unwrap();
+
+	assert_eq!(disk.primary, TableStatus::EntriesChecksum);
+	assert_eq!(disk.backup, TableStatus::Valid);
+	assert_eq!(disk.entries[0].name, "EFI");
+}
+
+#[test]
+fn fails_when_both_copies_are_corrupt() {
+	let image = basic(512);
+	let mut bytes = image.build();
+	bytes[image.primary_offset() + 40] ^= 1;
+	bytes[image.backup_offset() + 40] ^= 1;
+
+	assert!(matches!(read(&bytes), Err(Error::HeaderChecksum)));
+}
+
+#[test]
+fn reports_missing_backup() {
+	let mut image = basic(512);
+	image.backup = false;
+	let disk = read(&image.build()).unwrap();
+
+	assert_eq!(disk.primary, TableStatus::Valid);
+	assert_eq!(disk.backup, TableStatus::Missing);
+	assert_eq!(disk.entries.len(), 3);
+}
+
+#[test]
+fn uses_backup_when_primary_is_missing() {
+	let image = basic(512);
+	let mut bytes = image.build();
+	let offset = image.primary_offset();
+	bytes[offset..offset + 512]
+		.iter_mut()
+		.for_each(|byte| *byte = 0);
+	let disk = read(&bytes).unwrap();
+
+	assert_eq!(disk.primary, TableStatus::Missing);
+	assert_eq!(disk.backup, TableStatus::Valid);
+	assert_eq!(disk.header.current_lba, 2047);
+	assert_eq!(disk.entries.len(), 3);
+}
+
+#[test]
+fn rejects_out_of_range_entry_size() {
+	let mut image = basic(512);
+	image.entry_size = 64;
+	image.backup = false;
+
+	assert!(matches!(
+		read(&image.build()),
+		Err(Error::InvalidHeader("partition entry size out of range"))
+	));
+}
+
+#[test]
+fn reads_hybrid_mbr() {
+	let mut image = basic(512);
+	// Mirror the ESP as a FAT32 LBA partition
+	image.mbr = Mbr::Hybrid(vec![(0x0C, 34, 100)]);
+	let bytes = image.build();
+	assert_eq!(bytes[446 + 4], 0xEE);
+	assert_eq!(bytes[446 + 16 + 4], 0x0C);
+
+	let disk = read(&bytes).unwrap();
+	assert_eq!(disk.primary, TableStatus::Valid);
+	assert_eq!(disk.entries.len(), 3);
+}
+
+#[test]
+fn reads_without_protective_mbr() {
+	let mut image = basic(512);
+	image.mbr = Mbr::None;
+	assert_eq!(read(&image.build()).unwrap().entries.len(), 3);
+}
+
+#[test]
+fn rejects_legacy_mbr() {
+	let mut image = Image::new(512);
+	image.mbr = Mbr::Legacy(vec![(0x83, 2048, 4096)]);
+
+	assert!(matches!(read(&image.build()), Err(Error::InvalidSignature)));
+}
+
+#[test]
+fn decodes_unicode_names() {
+	let image = Image::new(512)
+		.part(BASIC_DATA, "データ", 100, 199)
+		.part(LINUX_FS, "émoji 🚀", 200, 299)
+		// 36 code units fill the field without a terminator
+		.part(LINUX_FS, &"x".repeat(36), 300, 399);
+	let disk = read(&image.build()).unwrap();
+
+	let names: Vec<_> = disk
+		.entries
+		.iter()
+		.map(|entry| entry.name.as_str())
+		.collect();
+	assert_eq!(names, vec!["データ", "émoji 🚀", &"x".repeat(36)]);
+}
+
+#[test]
+fn rejects_unpaired_surrogates() {
+	let mut image = Image::new(512).part(LINUX_FS, "", 100, 199);
+	image.parts[0].name = vec![0x0041, 0xD800, 0x0042];
+
+	assert!(matches!(
+		read(&image.build()),
+		Err(Error::InvalidEntry(1, "name isn't valid UTF-16"))
+	));
+}
+
+#[test]
+fn rejects_inverted_entries() {
+	let image = Image::new(512).part(LINUX_FS, "backwards", 500, 100);
+
+	assert!(matches!(
+		read(&image.build()),
+		Err(Error::InvalidEntry(1, _))
+	));
+}
+
+#[test]
+fn falls_back_on_a_bad_entry() {
+	let image = basic(512);
+	let mut bytes = image.build();
+	// The same table with its second entry ending before it starts, in the
+	// primary copy only
+	let mut bad = basic(512);
+	bad.parts[1].last_lba = 100;
+	let end = image.first_usable_lba() as usize * 512;
+	bytes[512..end].copy_from_slice(&bad.build()[512..end]);
+	let disk = read(&bytes).unwrap();
+
+	assert_eq!(
+		disk.primary,
+		TableStatus::InvalidEntry(2, "ends before it starts")
+	);
+	assert_eq!(disk.backup, TableStatus::Valid);
+	assert_eq!(disk.entries.len(), 3);
+	assert_eq!(disk.entries[1].last_lba, 1033);
+}
+
+#[test]
+fn rejects_headers_away_from_their_lba() {
+	let image = basic(512);
+	let mut bytes = image.build();
+	// A copy of the backup header where the primary belongs
+	let backup = bytes[image.backup_offset()..][..512].to_vec();
+	bytes[image.primary_offset()..][..512].copy_from_slice(&backup);
+	let disk = read(&bytes).unwrap();
+
+	assert_eq!(
+		disk.primary,
+		TableStatus::Invalid("header is not at its own LBA")
+	);
+	assert_eq!(disk.backup, TableStatus::Valid);
+}
+
+/// A disk that can't be read past its first sector.
+struct FailingDisk(Vec<u8>);
+
+impl BlockSource for FailingDisk {
+	fn size(&self) -> u64 { self.0.len() as u64 }
+
+	fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+		if offset == 0 {
+			buf.copy_from_slice(&self.0[..buf.len()]);
+			Ok(())
+		} else {
+			Err(io::Error::other("bad sector"))
+		}
+	}
+}
+
+#[test]
+fn reports_read_errors() {
+	let mut disk = FailingDisk(basic(512).build());
+
+	match read_gpt_from(&mut disk) {
+		Err(Error::Io(err)) => assert_eq!(err.to_string(), "bad sector"),
+		other => panic!("expected an I/O error, got {:?}", other),
+	}
+}
+
+#[test]
+fn rejects_truncated_images() {
+	let bytes = basic(512).build();
+	for &len in [0, 100, 600, 1500].iter() {
+		assert!(read_gpt(Cursor::new(&bytes[..len])).is_err(), "{}", len);
+	}
+}
+
+fn read_any(bytes: Vec<u8>) -> Result<PartitionTable, Error> {
+	read_table(&mut Raw::new(Cursor::new(bytes)).unwrap())
+}
+
+#[test]
+fn reads_apple_partition_maps() {
+	let entries = [
+		("Macintosh HD", "Apple_HFS", 64, 3000, apm::IN_USE),
+		("", "Apple_Free", 3064, 1032, 0),
+	];
+	// Hard disks, CDs, a descriptor naming the wrong size as on some hybrid
+	// images, and no descriptor at all
+	let layouts = [
+		(512, Some(512)),
+		(2048, Some(2048)),
+		(2048, Some(512)),
+		(512, None),
+	];
+	for &(block_size, descriptor) in layouts.iter() {
+		let image = apm::build(block_size, descriptor, 4096, &entries);
+		let map = match read_any(image).unwrap() {
+			PartitionTable::Apm(map) => map,
+			table => panic!("{:?}", table),
+		};
+
+		assert_eq!(map.block_size, block_size as u64);
+		let summary: Vec<_> = map
+			.entries
+			.iter()
+			.map(|entry| {
+				let (name, kind) = (entry.name.as_str(), entry.kind.as_str());
+				(entry.number, name, kind, entry.start, entry.blocks)
+			})
+			.collect();
+		assert_eq!(
+			summary,
+			vec![
+				(1, "Apple", "Apple_partition_map", 1, 63),
+				(2, "Macintosh HD", "Apple_HFS", 64, 3000),
+				(3, "", "Apple_Free", 3064, 1032),
+			]
+		);
+		assert_eq!(
+			map.entries[1].status_flags(),
+			["valid", "allocated", "in use", "readable", "writable"]
+		);
+		assert!(map.entries[2].status_flags().is_empty());
+		assert_eq!(map.entries[2].end(), 4095);
+	}
+}
+
+#[test]
+fn prefers_gpt_to_apm() {
+	assert!(matches!(
+		read_any(basic(512).build()),
+		Ok(PartitionTable::Gpt(_))
+	));
+	assert!(matches!(
+		read_any(vec![0; 1024 * 1024]),
+		Err(Error::InvalidSignature)
+	));
+
+	let mut image = apm::build(512, Some(512), 64, &[]);
+	image[516..520].copy_from_slice(&0u32.to_be_bytes());
+	assert!(matches!(read_any(image), Err(Error::InvalidApm(_))));
+}
+
+/// A 1MiB disk with a plain MBR holding these entries.
+fn mbr_disk(entries: &[(u8, u32, u32)]) -> Vec<u8> {
+	let mut image = Image::new(512);
+	image.mbr = Mbr::Legacy(entries.to_vec());
+	image.build()
+}
+
+#[test]
+fn reads_mbr_disks() {
+	let mut bytes = mbr_disk(&[(0x0C, 63, 500), (0x05, 563, 1000)]);
+	// Two logical partitions, each after its extended boot record
+	write_mbr(
+		&mut bytes[563 * 512..],
+		&[(0x83, 63, 200), (0x05, 300, 400)],
+	);
+	write_mbr(&mut bytes[863 * 512..], &[(0x82, 63, 100)]);
+
+	let mbr = match read_any(bytes).unwrap() {
+		PartitionTable::Mbr(mbr) => mbr,
+		table => panic!("{:?}", table),
+	};
+	let summary: Vec<_> = mbr
+		.entries
+		.iter()
+		.map(|entry| (entry.number, entry.kind, entry.first_lba, entry.sectors))
+		.collect();
+	assert_eq!(
+		summary,
+		vec![
+			(1, 0x0C, 63, 500),
+			(2, 0x05, 563, 1000),
+			(5, 0x83, 626, 200),
+			(6, 0x82, 926, 100),
+		]
+	);
+	assert!(mbr.entries[1].is_extended());
+	assert_eq!(mbr.entries[2].type_name(), Some("Linux"));
+	assert_eq!(mbr.entries[3].last_lba(), 1025);
+}
+
+#[test]
+fn numbers_logical_partitions_past_empty_records() {
+	let mut bytes = mbr_disk(&[(0x05, 63, 1900)]);
+	// The first record only links to the next
+	write_mbr(&mut bytes[63 * 512..], &[(0, 0, 0), (0x05, 100, 400)]);
+	write_mbr(
+		&mut bytes[163 * 512..],
+		&[(0x83, 63, 200), (0x05, 500, 400)],
+	);
+	write_mbr(&mut bytes[563 * 512..], &[(0x82, 63, 100)]);
+
+	let mbr = match read_any(bytes).unwrap() {
+		PartitionTable::Mbr(mbr) => mbr,
+		table => panic!("{:?}", table),
+	};
+	let numbers: Vec<_> = mbr
+		.entries
+		.iter()
+		.map(|entry| (entry.number, entry.first_lba))
+		.collect();
+	assert_eq!(numbers, vec![(1, 63), (5, 226), (6, 626)]);
+}
+
+#[test]
+fn rejects_protective_mbr_alone() {
+	let mut bytes = vec![0; 1024 * 1024];
+	write_mbr(&mut bytes, &[(0xEE, 1, 2047)]);
+	assert!(matches!(read_any(bytes), Err(Error::InvalidSignature)));
+
+	// A filesystem boot sector has the signature but no table
+	let mut bytes = vec![0; 1024 * 1024];
+	write_mbr(&mut bytes, &[]);
+	bytes[446] = 0xEB;
+	assert!(matches!(read_any(bytes), Err(Error::InvalidSignature)));
+}
+
+/// The label of the partition at `first_lba` of `bytes`.
+fn read_label(bytes: Vec<u8>, first_lba: u64) -> Result<Disklabel, Error> {
+	let mut src = Raw::new(Cursor::new(bytes)).unwrap();
+	disklabel::read(&mut src, first_lba, 512).map(Option::unwrap)
+}
+
+/// Name, type, first LBA and size of each partition of `label`.
+fn label_summary(label: &Disklabel) -> Vec<(&str, &str, u64, u64)> {
+	label
+		.partitions
+		.iter()
+		.map(|part| {
+			(
+				part.name.as_str(),
+				part.kind.as_str(),
+				part.first_lba,
+				part.sectors,
+			)
+		})
+		.collect()
+}
+
+#[test]
+fn reads_bsd_disklabels() {
+	// FreeBSD counts from the start of the slice
+	let mut bytes = mbr_disk(&[(0xA5, 63, 1985)]);
+	let freebsd =
+		label::bsd(&[(7, 0, 1000), (1, 1000, 500), (0, 0, 1985)], false);
+	bytes[64 * 512..65 * 512].copy_from_slice(&freebsd);
+	let parsed = read_label(bytes, 63).unwrap();
+	assert_eq!(parsed.kind, LabelKind::Bsd);
+	assert_eq!(
+		label_summary(&parsed),
+		vec![
+			("a", "4.2BSD", 63, 1000),
+			("b", "swap", 1063, 500),
+			("c", "unused", 63, 1985),
+		]
+	);
+
+	// NetBSD from the start of the disk, with `d` covering all of it
+	let mut bytes = mbr_disk(&[(0xA9, 63, 1985)]);
+	let netbsd = label::bsd(
+		&[
+			(7, 63, 1000),
+			(0, 0, 0),
+			(0, 63, 1985),
+			(0, 0, 2048),
+			(42, 1063, 8),
+		],
+		false,
+	);
+	bytes[64 * 512..65 * 512].copy_from_slice(&netbsd);
+	assert_eq!(
+		label_summary(&read_label(bytes, 63).unwrap()),
+		vec![
+			("a", "4.2BSD", 63, 1000),
+			("c", "unused", 63, 1985),
+			("d", "unused", 0, 2048),
+			("e", "type 42", 1063, 8),
+		]
+	);
+}
+
+#[test]
+fn reads_openbsd_disklabels() {
+	let mut bytes = vec![0; 4096];
+	let huge = 1 << 33;
+	let openbsd = label::bsd(&[(7, 64, huge), (1, huge + 64, 1 << 32)], true);
+	bytes[512 * 2..512 * 3].copy_from_slice(&openbsd);
+
+	let parsed = read_label(bytes, 1).unwrap();
+	assert_eq!(parsed.kind, LabelKind::OpenBsd);
+	assert_eq!(
+		label_summary(&parsed),
+		vec![("a", "4.2BSD", 64, huge), ("b", "swap", huge + 64, 1 << 32)]
+	);
+}
+
+#[test]
+fn reads_sun_labels() {
+	// 16 heads of 63 sectors make 1008-sector cylinders
+	let mut bytes = vec![0; 4096];
+	let sun = label::sun(
+		16,
+		63,
+		&[(2, 0, 1008), (3, 1, 2016), (0, 0, 0), (5, 0, 3024)],
+	);
+	bytes[..512].copy_from_slice(&sun);
+	let parsed = read_label(bytes, 0).unwrap();
+	assert_eq!(parsed.kind, LabelKind::Sun);
+	assert_eq!(
+		label_summary(&parsed),
+		vec![
+			("s0", "root", 0, 1008),
+			("s1", "swap", 1008, 2016),
+			("s3", "backup", 0, 3024),
+		]
+	);
+
+	// Solaris on x86 counts from the start of its MBR partition
+	let mut bytes = mbr_disk(&[(0xBF, 63, 1985)]);
+	let vtoc = label::solaris_x86(&[(2, 10, 500), (0, 0, 0), (0x20, 1, 9)]);
+	bytes[64 * 512..65 * 512].copy_from_slice(&vtoc);
+	let parsed = read_label(bytes, 63).unwrap();
+	assert_eq!(parsed.kind, LabelKind::SolarisX86);
+	assert_eq!(
+		label_summary(&parsed),
+		vec![("s0", "root", 73, 500), ("s2", "tag 0x20", 64, 9)]
+	);
+}
+
+#[test]
+fn rejects_damaged_labels() {
+	let mut bytes = mbr_disk(&[(0xA5, 63, 1985)]);
+	assert!(matches!(
+		disklabel::read(
+			&mut Raw::new(Cursor::new(bytes.clone())).unwrap(),
+			63,
+			512
+		),
+		Ok(None)
+	));
+
+	let mut freebsd = label::bsd(&[(7, 0, 1000)], false);
+	freebsd[148] ^= 1;
+	bytes[64 * 512..65 * 512].copy_from_slice(&freebsd);
+	assert!(matches!(
+		read_label(bytes, 63),
+		Err(Error::InvalidLabel("BSD disklabel", "checksum mismatch"))
+	));
+}
+
+fn read_ldm(bytes: Vec<u8>) -> Result<Option<Ldm>, Error> {
+	let mut src = Raw::new(Cursor::new(bytes)).unwrap();
+	let table = read_table(&mut src)?;
+	gptinfo::reader::ldm::read(&mut src, &table)
+}
+
+#[test]
+fn reads_ldm_databases() {
+	let ldm = read_ldm(ldm::gpt_disk()).unwrap().unwrap();
+
+	assert_eq!(ldm.version, (2, 12));
+	assert_eq!(ldm.disk_group, "WIN-DG0");
+	assert_eq!((ldm.data_start, ldm.data_sectors), (98, 1917));
+	assert_eq!(ldm.this_disk().unwrap().name, "Disk1");
+	assert_eq!(ldm.disk_name(3), Some("Disk2"));
+
+	let summary: Vec<_> = ldm
+		.volumes
+		.iter()
+		.map(|volume| {
+			let components: Vec<_> = volume
+				.components
+				.iter()
+				.map(|component| {
+					let extents: Vec<_> = component
+						.extents
+						.iter()
+						.map(|extent| {
+							(extent.name.as_str(), extent.disk, extent.start)
+						})
+						.collect();
+					(component.layout, component.stripe_sectors, extents)
+				})
+				.collect();
+			(
+				volume.name.as_str(),
+				volume.layout(),
+				volume.sectors,
+				components,
+			)
+		})
+		.collect();
+	assert_eq!(
+		summary,
+		vec![
+			(
+				"Volume1",
+				"simple",
+				500,
+				vec![(Layout::Concatenated, 0, vec![("Disk1-01", 2, 0)])]
+			),
+			(
+				"Volume2",
+				"spanned",
+				1000,
+				vec![(
+					Layout::Concatenated,
+					0,
+					vec![("Disk1-02", 2, 500), ("Disk2-01", 3, 0)]
+				)]
+			),
+			(
+				"Volume3, mirrored across both disks of the group",
+				"mirror",
+				200,
+				vec![
+					(Layout::Concatenated, 0, vec![("Disk1-03", 2, 800)]),
+					(Layout::Concatenated, 0, vec![("Disk2-02", 3, 700)]),
+				]
+			),
+			(
+				"Volume4",
+				"striped",
+				256,
+				vec![(
+					Layout::Striped,
+					128,
+					vec![("Disk1-04", 2, 1000), ("Disk2-03", 3, 900)]
+				)]
+			),
+		]
+	);
+
+	assert!(read_ldm(basic(512).build()).unwrap().is_none());
+}
+
+#[test]
+fn reads_ldm_on_mbr_disks() {
+	let mut bytes = mbr_disk(&[(0x42, 63, 1985)]);
+	let database = ldm::Database {
+		disk_guid: ldm::DISK2,
+		group: "WIN-DG0",
+		data_start: 63,
+		data_sectors: 1921,
+		config_start: 1984,
+		config_sectors: 64,
+	};
+	bytes[1984 * 512..].copy_from_slice(&database.config(&ldm::records()));
+	bytes[6 * 512..7 * 512].copy_from_slice(&database.privhead());
+
+	let ldm = read_ldm(bytes).unwrap().unwrap();
+	assert_eq!(ldm.this_disk().unwrap().name, "Disk2");
+	assert_eq!(ldm.volumes.len(), 4);
+}
+
+#[test]
+fn rejects_damaged_ldm_databases() {
+	let mut bytes = ldm::gpt_disk();
+	bytes[35 * 512..35 * 512 + 8].copy_from_slice(b"TOCBLOCX");
+	assert!(matches!(
+		read_ldm(bytes),
+		Err(Error::InvalidLdm("no table of contents"))
+	));
+
+	// A VMDB claiming more VBLKs than the database holds
+	let mut bytes = ldm::gpt_disk();
+	bytes[51 * 512 + 4..51 * 512 + 8].copy_from_slice(&u32::MAX.to_be_bytes());
+	assert!(matches!(
+		read_ldm(bytes),
+		Err(Error::InvalidLdm("VBLK area out of bounds"))
+	));
+}
+
+fn read_pv(
+	bytes: &[u8],
+	first_lba: u64,
+) -> Result<Option<PhysicalVolume>, Error> {
+	let mut src = Raw::new(Cursor::new(bytes)).unwrap();
+	gptinfo::reader::lvm::read(&mut src, first_lba * 512)
+}
+
+fn segment(kind: &str, start: u64, extents: u64, stripes: u64) -> Segment {
+	Segment {
+		kind: kind.into(),
+		volume_extent: 0,
+		start,
+		extents,
+		stripes,
+	}
+}
+
+#[test]
+fn reads_lvm_physical_volumes() {
+	let pv = read_pv(&lvm::gpt_disk(), 64).unwrap().unwrap();
+
+	assert_eq!(pv.uuid, lvm::PV_UUID);
+	assert_eq!(pv.device_size, 960 * 512);
+	let group = pv.group.unwrap();
+	assert_eq!(group.name, "vg0");
+	assert_eq!(group.uuid, "JW3fzE-ZxvM-Xm2D-VLwq-1qGw-zeBn-G0Rkyo");
+	assert_eq!(group.seqno, 7);
+	assert_eq!(group.extent_size, lvm::EXTENT_SIZE * 512);
+	assert_eq!((group.pe_start, group.pe_count), (lvm::PE_START * 512, 100));
+	assert_eq!(group.extent_bytes(40, 20), (16384 + 40 * 4096, 20 * 4096));
+
+	// `home` is only on the other PV, and `root` keeps the segment on this
+	// one
+	let summary: Vec<_> = group
+		.volumes
+		.iter()
+		.map(|volume| {
+			(
+				volume.name.as_str(),
+				volume.visible,
+				volume.extents,
+				volume.segments.clone(),
+			)
+		})
+		.collect();
+	assert_eq!(
+		summary,
+		vec![
+			("root", true, 50, vec![segment("linear", 0, 40, 1)]),
+			("swap", true, 20, vec![segment("linear", 40, 20, 1)]),
+			("data", true, 20, vec![segment("striped", 60, 10, 2)]),
+			("lvol0_pmspare", false, 2, vec![segment("linear", 70, 2, 1)]),
+		]
+	);
+}
+
+#[test]
+fn reads_wrapped_lvm_metadata() {
+	let mut bytes = mbr_disk(&[(0x8E, 64, 960)]);
+	// The text runs past the end of the metadata area and on after its
+	// header
+	let pv = lvm::pv(&lvm::metadata(), 12288 - 1000);
+	bytes[64 * 512..64 * 512 + pv.len()].copy_from_slice(&pv);
+
+	let pv = read_pv(&bytes, 64).unwrap().unwrap();
+	let group = pv.group.unwrap();
+	assert_eq!(group.name, "vg0");
+	assert_eq!(group.volumes.len(), 4);
+}
+
+#[test]
+fn reads_lvm_physical_volumes_without_groups() {
+	let mut bytes = mbr_disk(&[(0x8E, 64, 960)]);
+	let mut pv = lvm::pv("", 512);
+	// A PV that was never added to a group has no committed metadata
+	pv[4096 + 40..4096 + 56].fill(0);
+	let crc = lvm::checksum(&pv[4096 + 4..4096 + 512]);
+	pv[4096..4096 + 4].copy_from_slice(&crc.to_le_bytes());
+	bytes[64 * 512..64 * 512 + pv.len()].copy_from_slice(&pv);
+
+	let pv = read_pv(&bytes, 64).unwrap().unwrap();
+	assert_eq!(pv.uuid, lvm::PV_UUID);
+	assert!(pv.group.is_none());
+	assert!(matches!(read_pv(&bytes, 0), Ok(None)));
+}
+
+#[test]
+fn rejects_damaged_lvm_physical_volumes() {
+	let damaged = |at: usize| {
+		let mut bytes = lvm::gpt_disk();
+		bytes[64 * 512 + at] ^= 1;
+		read_pv(&bytes, 64)
+	};
+
+	assert!(matches!(
+		damaged(512 + 100),
+		Err(Error::InvalidLvm("label checksum mismatch"))
+	));
+	assert!(matches!(
+		damaged(4096 + 30),
+		Err(Error::InvalidLvm("metadata area checksum mismatch"))
+	));
+	assert!(matches!(
+		damaged(4096 + 600),
+		Err(Error::InvalidLvm("metadata checksum mismatch"))
+	));
+
+	// Metadata that doesn't list the PV
+	let mut bytes = lvm::gpt_disk();
+	let text = lvm::metadata().replace(lvm::PV_UUID, "gone");
+	let pv = lvm::pv(&text, 512);
+	bytes[64 * 512..64 * 512 + pv.len()].copy_from_slice(&pv);
+	assert!(matches!(
+		read_pv(&bytes, 64),
+		Err(Error::InvalidLvm("metadata doesn't list this PV"))
+	));
+
+	let text = lvm::metadata().replace("logical_volumes {", "logical_volumes");
+	let pv = lvm::pv(&text, 512);
+	bytes[64 * 512..64 * 512 + pv.len()].copy_from_slice(&pv);
+	assert!(read_pv(&bytes, 64).is_err());
+}
+
+fn read_luks(bytes: &[u8], first_lba: u64) -> Result<Option<Luks>, Error> {
+	let mut src = Raw::new(Cursor::new(bytes)).unwrap();
+	gptinfo::reader::luks::read(&mut src, first_lba * 512)
+}
+
+#[test]
+fn reads_luks_headers() {
+	let bytes = luks::gpt_disk();
+
+	assert_eq!(
+		read_luks(&bytes, 64).unwrap().unwrap(),
+		Luks {
+			version: 2,
+			uuid: luks::UUID.into(),
+			label: "cryptroot".into(),
+			cipher: "aes-xts-plain64".into(),
+			key_bits: Some(512),
+			data_offset: 32768,
+			slot_count: 32,
+			keyslots: vec![
+				Keyslot {
+					number: 0,
+					kdf: Kdf::Argon2 {
+						kind: "argon2id".into(),
+						time: 4,
+						memory: 1048576,
+						cpus: 4,
+					},
+				},
+				Keyslot {
+					number: 2,
+					kdf: Kdf::Pbkdf2 {
+						hash: "sha512".into(),
+						iterations: 1000000,
+					},
+				},
+			],
+		}
+	);
+
+	let luks1 = read_luks(&bytes, 1024).unwrap().unwrap();
+	assert_eq!(luks1.version, 1);
+	assert_eq!(luks1.cipher, "aes-xts-plain64");
+	assert_eq!(luks1.key_bits, Some(512));
+	assert_eq!(luks1.data_offset, 256 * 512);
+	assert_eq!(luks1.slot_count, 8);
+	assert_eq!(
+		luks1.keyslots,
+		vec![
+			Keyslot {
+				number: 0,
+				kdf: Kdf::Pbkdf2 {
+					hash: "sha256".into(),
+					iterations: 2000000,
+				},
+			},
+			Keyslot {
+				number: 3,
+				kdf: Kdf::Pbkdf2 {
+					hash: "sha256".into(),
+					iterations: 1500000,
+				},
+			},
+		]
+	);
+
+	// Plain dm-crypt has no header
+	assert!(matches!(read_luks(&bytes, 1536), Ok(None)));
+}
+
+#[test]
+fn reads_newer_luks2_header_copy() {
+	let mut bytes = luks::gpt_disk();
+	// An update interrupted after writing the secondary copy
+	let secondary = luks::luks2_header(&luks::metadata(), "renamed", 2, true);
+	bytes[(64 + 32) * 512..(64 + 64) * 512].copy_from_slice(&secondary);
+	assert_eq!(read_luks(&bytes, 64).unwrap().unwrap().label, "renamed");
+
+	// A damaged primary leaves the secondary
+	bytes[64 * 512 + 4200] ^= 1;
+	assert_eq!(read_luks(&bytes, 64).unwrap().unwrap().label, "renamed");
+}
+
+#[test]
+fn rejects_damaged_luks_headers() {
+	let mut bytes = luks::gpt_disk();
+	bytes[64 * 512 + 4200] ^= 1;
+	bytes[(64 + 32) * 512 + 4200] ^= 1;
+	assert!(matches!(
+		read_luks(&bytes, 64),
+		Err(Error::InvalidLuks("header checksum mismatch"))
+	));
+
+	let mut bytes = luks::gpt_disk();
+	let luks2 = luks::luks2("{\"keyslots\": {}}", "");
+	bytes[64 * 512..64 * 512 + luks2.len()].copy_from_slice(&luks2);
+	assert!(matches!(
+		read_luks(&bytes, 64),
+		Err(Error::InvalidLuks("malformed JSON metadata"))
+	));
+
+	let mut bytes = luks::gpt_disk();
+	bytes[1024 * 512 + 208 + 48] = 0xFF;
+	assert!(matches!(
+		read_luks(&bytes, 1024),
+		Err(Error::InvalidLuks("invalid key slot state"))
+	));
+}
+
+fn read_md(
+	bytes: &[u8],
+	first_lba: u64,
+	sectors: u64,
+) -> Result<Option<Superblock>, Error> {
+	let mut src = Raw::new(Cursor::new(bytes)).unwrap();
+	let probe =
+		gptinfo::reader::md::read(&mut src, first_lba * 512, sectors * 512)?;
+	Ok(match probe {
+		Probe::Found(superblock) => Some(superblock),
+		Probe::Missing => None,
+		Probe::Skipped => panic!("a raw image is seekable"),
+	})
+}
+
+#[test]
+fn reads_md_superblocks() {
+	let bytes = md::gpt_disk();
+
+	assert_eq!(
+		read_md(&bytes, 64, 256).unwrap().unwrap(),
+		Superblock {
+			version: "1.2",
+			uuid: "6b3f1c2e:94d04a57:8e210c7b:5d93a4f6".into(),
+			name: "host:0".into(),
+			level: Level::Raid(1),
+			raid_disks: 2,
+			role: Role::Active(0),
+			events: 120,
+			clean: true,
+		}
+	);
+
+	let spare = read_md(&bytes, 576, 256).unwrap().unwrap();
+	assert_eq!(spare.version, "1.0");
+	assert_eq!(spare.level, Level::Raid(5));
+	assert_eq!(
+		(spare.role, spare.events, spare.clean),
+		(Role::Spare, 9, false)
+	);
+
+	let legacy = read_md(&bytes, 832, 256).unwrap().unwrap();
+	assert_eq!(legacy.version, "0.90");
+	assert_eq!(legacy.uuid, "22222222:22222222:22222222:22222222");
+	assert_eq!(legacy.name, "");
+	assert_eq!(legacy.level, Level::Raid(0));
+	assert_eq!(legacy.role, Role::Active(1));
+	assert_eq!(legacy.events, 0x1_0000_0002);
+
+	// 1.1 superblocks start the device
+	let mut bytes = md::gpt_disk();
+	bytes[64 * 512 + 4096..64 * 512 + 8192].fill(0);
+	let faulty = md::v1(&md::Member::raid1(0xFFFE, 3), 0);
+	bytes[64 * 512..64 * 512 + 4096].copy_from_slice(&faulty);
+	let faulty = read_md(&bytes, 64, 256).unwrap().unwrap();
+	assert_eq!((faulty.version, faulty.role), ("1.1", Role::Faulty));
+
+	assert!(matches!(read_md(&bytes, 1088, 256), Ok(None)));
+}
+
+#[test]
+fn rejects_damaged_md_superblocks() {
+	let mut bytes = md::gpt_disk();
+	bytes[(64 + 8) * 512 + 200] ^= 1;
+	assert!(matches!(
+		read_md(&bytes, 64, 256),
+		Err(Error::InvalidMd("checksum mismatch"))
+	));
+
+	bytes[(832 + 128) * 512 + 156] ^= 1;
+	assert!(matches!(
+		read_md(&bytes, 832, 256),
+		Err(Error::InvalidMd("checksum mismatch"))
+	));
+
+	// A 1.2 superblock that says it's somewhere else isn't one
+	let mut bytes = md::gpt_disk();
+	let misplaced = md::v1(&md::Member::raid1(0, 1), 0);
+	bytes[(320 + 8) * 512..(320 + 16) * 512].copy_from_slice(&misplaced);
+	assert!(matches!(read_md(&bytes, 320, 256), Ok(None)));
+}
+
+fn read_zfs(
+	bytes: &[u8],
+	first_lba: u64,
+	sectors: u64,
+) -> Result<Option<Zfs>, Error> {
+	let mut src = Raw::new(Cursor::new(bytes)).unwrap();
+	gptinfo::reader::zfs::read(&mut src, first_lba * 512, sectors * 512)
+}
+
+#[test]
+fn reads_zfs_labels() {
+	let bytes = zfs::gpt_disk();
+
+	let tank = Label {
+		pool: Some("tank".into()),
+		pool_guid: Some(zfs::POOL_GUID),
+		guid: 101,
+		txg: 1234,
+		state: PoolState::Active,
+	};
+	let vdev = read_zfs(&bytes, 64, 2048).unwrap().unwrap();
+	assert_eq!(vdev.labels, vec![Some(tank.clone()); 4]);
+	assert!(vdev.consistent());
+
+	// The back labels of the second vdev fell behind
+	let vdev = read_zfs(&bytes, 2112, 2048).unwrap().unwrap();
+	assert!(!vdev.consistent());
+	assert_eq!(vdev.freshest().unwrap().txg, 1234);
+	let txgs: Vec<_> = vdev.labels.iter().flatten().map(|l| l.txg).collect();
+	assert_eq!(txgs, vec![1234, 1234, 1200, 1200]);
+
+	// Spares belong to no pool
+	let mut bytes = zfs::gpt_disk();
+	let spare = zfs::Vdev {
+		pool: None,
+		guid: 303,
+		txg: 0,
+		state: 3,
+	};
+	let labels = vec![Some(spare); 4];
+	zfs::write_labels(&mut bytes, 64 * 512, 2048 * 512, &labels);
+	let spare = read_zfs(&bytes, 64, 2048).unwrap().unwrap();
+	assert_eq!(
+		spare.freshest().unwrap(),
+		&Label {
+			pool: None,
+			pool_guid: None,
+			guid: 303,
+			txg: 0,
+			state: PoolState::Spare,
+		}
+	);
+
+	assert!(matches!(read_zfs(&bytes, 4160, 32), Ok(None)));
+}
+
+#[test]
+fn rejects_damaged_zfs_labels() {
+	// A damaged copy is left out while the others are read
+	let mut bytes = zfs::gpt_disk();
+	bytes[(64 + 32) * 512 + 100] ^= 1;
+	let vdev = read_zfs(&bytes, 64, 2048).unwrap().unwrap();
+	assert_eq!(vdev.labels[0], None);
+	assert!(vdev.labels[1..].iter().all(Option::is_some));
+	assert!(!vdev.consistent());
+
+	// A copy checksummed for the first label isn't valid anywhere else
+	let mut bytes = zfs::gpt_disk();
+	bytes[(64 + 32) * 512..(64 + 256) * 512].fill(0);
+	let moved = zfs::label(&zfs::Vdev::tank(101, 1234), 16 * 1024);
+	for idx in 1..4 {
+		let at = (64 + 32 + idx * 512) * 512;
+		bytes[at..at + moved.len()].copy_from_slice(&moved);
+	}
+	assert!(matches!(
+		read_zfs(&bytes, 64, 2048),
+		Err(Error::InvalidZfs("checksum mismatch"))
+	));
+}
+
+fn read_slots(bytes: &[u8]) -> Result<Option<Slots>, Error> {
+	let disk = read(bytes).unwrap();
+	let mut src = Raw::new(Cursor::new(bytes)).unwrap();
+	gptinfo::reader::slots::read(&disk, &mut src)
+}
+
+#[test]
+fn reads_chromeos_slots() {
+	let slots = read_slots(&slots::chromeos_disk()).unwrap().unwrap();
+
+	assert_eq!(
+		slots,
+		Slots {
+			scheme: Scheme::ChromeOs,
+			slots: vec![
+				Slot {
+					name: "A".into(),
+					partitions: vec![2, 3],
+					state: Some(SlotState {
+						priority: 1,
+						tries: 0,
+						successful: true,
+						bootable: true,
+						active: false,
+					}),
+				},
+				Slot {
+					name: "B".into(),
+					partitions: vec![4, 5],
+					state: Some(SlotState {
+						priority: 2,
+						tries: 6,
+						successful: false,
+						bootable: true,
+						active: true,
+					}),
+				},
+			],
+		}
+	);
+
+	// Out of tries, slot B can't boot and A is next
+	let mut image = Image::new(512)
+		.part(slots::CHROMEOS_KERNEL, "KERN-A", 64, 127)
+		.part(slots::CHROMEOS_KERNEL, "KERN-B", 128, 191);
+	image.parts[0].attributes = slots::kernel_attributes(1, 0, true);
+	image.parts[1].attributes = slots::kernel_attributes(2, 0, false);
+	let slots = read_slots(&image.build()).unwrap().unwrap();
+	let states: Vec<_> = slots
+		.slots
+		.iter()
+		.map(|slot| {
+			let state = slot.state.unwrap();
+			(state.bootable, state.active)
+		})
+		.collect();
+	assert_eq!(states, vec![(true, true), (false, false)]);
+
+	assert!(matches!(read_slots(&basic(512).build()), Ok(None)));
+}
+
+#[test]
+fn reads_android_slots() {
+	let slots = read_slots(&slots::android_disk()).unwrap().unwrap();
+
+	assert_eq!(slots.scheme, Scheme::AndroidMisc);
+	assert_eq!(slots.slots[0].name, "a");
+	assert_eq!(slots.slots[0].partitions, vec![2, 4]);
+	assert_eq!(
+		slots.slots[0].state,
+		Some(SlotState {
+			priority: 15,
+			tries: 0,
+			successful: true,
+			bootable: true,
+			active: true,
+		})
+	);
+	assert_eq!(slots.slots[1].partitions, vec![3, 5]);
+	let b = slots.slots[1].state.unwrap();
+	assert!(!b.bootable && !b.active);
+
+	// Without a boot control block, the bootloader's attribute bits on
+	// the boot partitions are used
+	let mut image = Image::new(512)
+		.part(BASIC_DATA, "boot_a", 64, 127)
+		.part(BASIC_DATA, "boot_b", 128, 191);
+	image.parts[0].attributes = 3 << 48 | 1 << 54;
+	image.parts[1].attributes = 2 << 48 | 1 << 50 | 7 << 51;
+	let slots = read_slots(&image.build()).unwrap().unwrap();
+	assert_eq!(slots.scheme, Scheme::AndroidAttributes);
+	let b = slots.slots[1].state.unwrap();
+	assert_eq!((b.priority, b.tries, b.successful), (2, 7, false));
+	assert!(b.active && b.bootable);
+
+	let image = Image::new(512)
+		.part(BASIC_DATA, "boot_a", 64, 127)
+		.part(BASIC_DATA, "boot_b", 128, 191);
+	let slots = read_slots(&image.build()).unwrap().unwrap();
+	assert_eq!(slots.scheme, Scheme::Android);
+	assert!(slots.slots.iter().all(|slot| slot.state.is_none()));
+}
+
+#[test]
+fn rejects_damaged_boot_control() {
+	let mut bytes = slots::android_disk();
+	bytes[64 * 512 + 2048 + 12] ^= 1;
+	assert!(matches!(
+		read_slots(&bytes),
+		Err(Error::InvalidBootControl("checksum mismatch"))
+	));
+}
+
+fn probe(bytes: &[u8], first_lba: u64, sectors: u64) -> Option<Filesystem> {
+	let mut src = Raw::new(Cursor::new(bytes)).unwrap();
+	gptinfo::reader::fs::probe(&mut src, first_lba * 512, sectors * 512)
+}
+
+#[test]
+fn probes_filesystems() {
+	let bytes = fs::gpt_disk();
+	let found = |first_lba| probe(&bytes, first_lba, 128);
+
+	assert_eq!(
+		found(64),
+		Some(Filesystem {
+			kind: "vfat",
+			label: "".into()
+		})
+	);
+	assert_eq!(
+		probe(&bytes, 192, 832),
+		Some(Filesystem {
+			kind: "ext4",
+			label: "rootfs".into()
+		})
+	);
+	assert_eq!(
+		found(1024),
+		Some(Filesystem {
+			kind: "swap",
+			label: "swap0".into()
+		})
+	);
+	assert_eq!(found(1152), None);
+}
+
+#[test]
+fn probes_volume_metadata() {
+	let bytes = luks::gpt_disk();
+	assert_eq!(
+		probe(&bytes, 64, 960),
+		Some(Filesystem {
+			kind: "crypto_LUKS",
+			label: "cryptroot".into()
+		})
+	);
+	// LUKS1 has no label
+	assert_eq!(probe(&bytes, 1024, 512).unwrap().label, "");
+
+	let bytes = zfs::gpt_disk();
+	assert_eq!(
+		probe(&bytes, 64, 2048),
+		Some(Filesystem {
+			kind: "zfs_member",
+			label: "tank".into()
+		})
+	);
+}
@@ -0,0 +1,912 @@
+mod common;
+
+use common::{
+	apm, basic, compressed, disklabel, fs as filesystems, ldm, luks, lvm, md,
+	qcow2::Qcow2, slots, write_mbr, zfs, Image, Mbr,
+};
+use std::{fs, process::Command};
+use tempfile::TempDir;
+
+/// Runs the binary on `bytes` saved as `disk.img` in a scratch directory,
+/// which also serves as an empty config home.
+fn run(bytes: &[u8], args: &[&str]) -> (bool, String, String) {
+	let dir = TempDir::new().unwrap();
+	fs::write(dir.path().join("disk.img"), bytes).unwrap();
+	run_in(&dir, args)
+}
+
+fn run_in(dir: &TempDir, args: &[&str]) -> (bool, String, String) {
+	run_device(dir, "disk.img", args)
+}
+
+fn run_device(
+	dir: &TempDir,
+	device: &str,
+	args: &[&str],
+) -> (bool, String, String) {
+	let output = Command::new(env!("CARGO_BIN_EXE_gptinfo"))
+		.current_dir(dir.path())
+		.env("XDG_CONFIG_HOME", dir.path())
+		.env("NO_COLOR", "1")
+		.env_remove("COLUMNS")
+		.args(["--device", device])
+		.args(args)
+		.output()
+		.unwrap();
+
+	(
+		output.status.success(),
+		String::from_utf8(output.stdout).unwrap(),
+		String::from_utf8(output.stderr).unwrap(),
+	)
+}
+
+#[test]
+fn renders_table() {
+	let (ok, stdout, stderr) = run(&basic(512).build(), &[]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(stderr, "");
+	assert_eq!(
+		stdout.lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────────┬───────────────────────┬───────┬──────┬─────────┬───────────╮",
+			"│ Name            │ Type                  │ Start │ End  │ Sectors │ Size      │",
+			"│ Disk (disk.img) │ Block Device          │ 34    │ 2014 │ 1981    │ 990.5 KiB │",
+			"│ EFI             │ EFI System partition  │ 34    │ 133  │ 100     │ 50.0 KiB  │",
+			"│ root            │ Linux filesystem data │ 134   │ 1033 │ 900     │ 450.0 KiB │",
+			"│ swap            │ Linux Swap partition  │ 1034  │ 1133 │ 100     │ 50.0 KiB  │",
+			"╰─────────────────┴───────────────────────┴───────┴──────┴─────────┴───────────╯",
+		]
+	);
+}
+
+#[test]
+fn renders_qcow2_images() {
+	let raw = basic(512).build();
+	let (_, expected, _) = run(&raw, &[]);
+	let (ok, stdout, stderr) = run(&Qcow2::new(16).build(&raw), &[]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(stdout, expected);
+}
+
+#[test]
+fn renders_nested_disks() {
+	let raw = basic(512).build();
+	let (_, expected, _) = run(&raw, &[]);
+	let dir = TempDir::new().unwrap();
+	let mut outer = vec![0xAA; 1024 * 1024];
+	outer.extend(&raw);
+	outer.extend(vec![0xAA; 4096]);
+	fs::write(dir.path().join("disk.img"), outer).unwrap();
+
+	// The longer name widens the first column
+	let cells = |table: &str| {
+		table
+			.replace("(disk.img@1048576)", "(disk.img)")
+			.lines()
+			.filter(|line| line.contains('│'))
+			.map(|line| {
+				line.split('│').map(str::trim).collect::<Vec<_>>().join("|")
+			})
+			.collect::<Vec<_>>()
+			.join("\n")
+	};
+	let expected = cells(&expected);
+	for device in ["disk.img@1M", "disk.img@0x100000"].iter() {
+		let (ok, stdout, stderr) = run_device(&dir, device, &[]);
+		assert!(ok, "{}", stderr);
+		assert_eq!(cells(&stdout), expected);
+	}
+	let length = raw.len().to_string();
+	let (ok, stdout, stderr) =
+		run_in(&dir, &["--offset", "1024K", "--length", &length]);
+	assert!(ok, "{}", stderr);
+	assert_eq!(cells(&stdout), expected);
+
+	// 1MiB is 2048 sectors in
+	let (ok, stdout, _) = run_device(&dir, "disk.img@1M", &["--absolute"]);
+	assert!(ok);
+	let row = stdout.lines().find(|line| line.contains("│ EFI ")).unwrap();
+	assert!(row.contains("│ 2082  │ 2181 │"), "{}", row);
+
+	let (ok, _, stderr) = run_device(&dir, "disk.img@1M", &["--mounts"]);
+	assert!(!ok);
+	assert!(stderr.contains("whole disks"), "{}", stderr);
+	let (ok, _, stderr) = run_in(&dir, &["--offset", "2M", "--length", "1M"]);
+	assert!(!ok);
+	assert!(stderr.contains("past the end of the disk"), "{}", stderr);
+}
+
+#[test]
+fn renders_apple_partition_maps() {
+	let entries = [("Macintosh HD", "Apple_HFS", 64, 3000, apm::IN_USE)];
+	let image = apm::build(512, Some(512), 4096, &entries);
+	let (ok, stdout, stderr) = run(&image, &[]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────────┬─────────────────────┬───────┬──────┬────────┬──────────┬──────────────────────────────────────────────╮",
+			"│ Name            │ Type                │ Start │ End  │ Blocks │ Size     │ Status                                       │",
+			"│ Disk (disk.img) │ Apple Partition Map │ 0     │ 3063 │ 3064   │ 1.5 MiB  │                                              │",
+			"│ Apple           │ Apple_partition_map │ 1     │ 63   │ 63     │ 31.5 KiB │ valid, allocated, in use, readable, writable │",
+			"│ Macintosh HD    │ Apple_HFS           │ 64    │ 3063 │ 3000   │ 1.5 MiB  │ valid, allocated, in use, readable, writable │",
+			"╰─────────────────┴─────────────────────┴───────┴──────┴────────┴──────────┴──────────────────────────────────────────────╯",
+		]
+	);
+
+	let (ok, _, stderr) = run(&image, &["--mounts"]);
+	assert!(!ok);
+	assert!(stderr.contains("need a GPT disk"), "{}", stderr);
+}
+
+#[test]
+fn renders_guid_column() {
+	let (ok, stdout, _) = run(
+		&basic(512).build(),
+		&["--columns", "name,uuid,type,start,end,sectors,size"],
+	);
+
+	assert!(ok);
+	let lines: Vec<_> = stdout.lines().collect();
+	assert!(lines[1].contains("│ Unique GUID "));
+	assert!(lines[2].contains("│ 5B1F5BD4-8DBC-4B36-9D5C-F61A0BD0B94D │"));
+	assert!(lines[4].contains("│ 00000000-0000-4000-8000-000000000002 │"));
+}
+
+#[test]
+fn renders_unicode_names() {
+	let image = basic(512).part(common::LINUX_FS, "データ", 1200, 1299);
+	let (ok, stdout, _) = run(&image.build(), &[]);
+
+	assert!(ok);
+	assert!(stdout.lines().nth(6).unwrap().starts_with("│ データ"));
+}
+
+#[test]
+fn warns_about_damaged_copies() {
+	let image = basic(512);
+	let mut bytes = image.build();
+	bytes[image.primary_offset() + 40] ^= 1;
+	let (ok, stdout, stderr) = run(&bytes, &[]);
+
+	assert!(ok);
+	assert_eq!(
+		stderr,
+		"Warning: Primary GPT is corrupt (header checksum)\n"
+	);
+	assert_eq!(stdout.lines().count(), 7);
+}
+
+#[test]
+fn shows_unknown_types_as_guids() {
+	let unknown = "11111111-2222-3333-4444-555555555555";
+	let image = basic(512).part(unknown, "fw_a", 1200, 1299);
+	let (ok, stdout, stderr) =
+		run(&image.build(), &["--columns", "name,type,start"]);
+
+	assert!(ok, "{}", stderr);
+	assert!(
+		stdout.contains(&format!("│ fw_a            │ {} │ 1200  │", unknown))
+	);
+}
+
+#[test]
+fn names_user_defined_types() {
+	let unknown = "11111111-2222-3333-4444-555555555555";
+	let image = basic(512).part(unknown, "fw_a", 1200, 1299);
+	let dir = TempDir::new().unwrap();
+	fs::write(dir.path().join("disk.img"), image.build()).unwrap();
+	fs::create_dir(dir.path().join("gptinfo")).unwrap();
+	fs::write(
+		dir.path().join("gptinfo/types.toml"),
+		format!(
+			"[types]\n{} = {{ name = \"Firmware slot\", aliases = [\"fw\"] \
+			 }}\n",
+			unknown.to_lowercase()
+		),
+	)
+	.unwrap();
+	let (ok, stdout, stderr) = run_in(&dir, &[]);
+
+	assert!(ok, "{}", stderr);
+	let row = stdout
+		.lines()
+		.find(|line| line.contains("│ fw_a "))
+		.unwrap();
+	assert!(row.contains("│ Firmware slot (fw) "));
+}
+
+#[test]
+fn renders_mbr_disks() {
+	let mut image = Image::new(512);
+	image.mbr = Mbr::Legacy(vec![(0x0C, 63, 500), (0xA5, 563, 1485)]);
+	let mut bytes = image.build();
+	let label = disklabel::bsd(&[(7, 0, 1000), (1, 1000, 485)], false);
+	bytes[564 * 512..565 * 512].copy_from_slice(&label);
+	let (ok, stdout, stderr) = run(&bytes, &[]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────────┬────────────────────────┬───────┬──────┬─────────┬───────────╮",
+			"│ Name            │ Type                   │ Start │ End  │ Sectors │ Size      │",
+			"│ Disk (disk.img) │ MBR                    │ 0     │ 2047 │ 2048    │ 1.0 MiB   │",
+			"│ 1               │ W95 FAT32 (LBA) (0x0c) │ 63    │ 562  │ 500     │ 250.0 KiB │",
+			"│ 2               │ FreeBSD (0xa5)         │ 563   │ 2047 │ 1485    │ 742.5 KiB │",
+			"│ ├─ a            │ 4.2BSD                 │ 563   │ 1562 │ 1000    │ 500.0 KiB │",
+			"│ └─ b            │ swap                   │ 1563  │ 2047 │ 485     │ 242.5 KiB │",
+			"╰─────────────────┴────────────────────────┴───────┴──────┴─────────┴───────────╯",
+		]
+	);
+
+	let (ok, _, stderr) = run(&bytes, &["--check"]);
+	assert!(!ok);
+	assert!(stderr.contains("need a GPT disk, not an MBR"), "{}", stderr);
+}
+
+#[test]
+fn renders_disklabels_in_gpt_partitions() {
+	let image = Image::new(512).part(
+		"516E7CB4-6ECF-11D6-8FF8-00022D09712B",
+		"freebsd",
+		100,
+		1099,
+	);
+	let mut bytes = image.build();
+	let label = disklabel::bsd(&[(7, 0, 800), (1, 800, 200)], false);
+	bytes[101 * 512..102 * 512].copy_from_slice(&label);
+	let (ok, stdout, stderr) = run(
+		&bytes,
+		&["--columns", "name,uuid,type,start,end,sectors,size"],
+	);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────────┬──────────────────────────────────────┬────────────────────────┬───────┬──────┬─────────┬───────────╮",
+			"│ Name            │ Unique GUID                          │ Type                   │ Start │ End  │ Sectors │ Size      │",
+			"│ Disk (disk.img) │ 5B1F5BD4-8DBC-4B36-9D5C-F61A0BD0B94D │ Block Device           │ 34    │ 2014 │ 1981    │ 990.5 KiB │",
+			"│ freebsd         │ 00000000-0000-4000-8000-000000000001 │ FreeBSD Data partition │ 100   │ 1099 │ 1000    │ 500.0 KiB │",
+			"│ ├─ a            │                                      │ 4.2BSD                 │ 100   │ 899  │ 800     │ 400.0 KiB │",
+			"│ └─ b            │                                      │ swap                   │ 900   │ 1099 │ 200     │ 100.0 KiB │",
+			"╰─────────────────┴──────────────────────────────────────┴────────────────────────┴───────┴──────┴─────────┴───────────╯",
+		]
+	);
+}
+
+#[test]
+fn warns_about_damaged_disklabels() {
+	let mut image = Image::new(512);
+	image.mbr = Mbr::Legacy(vec![(0xA5, 63, 1985)]);
+	let mut bytes = image.build();
+	let mut label = disklabel::bsd(&[(7, 0, 1000)], false);
+	label[148] ^= 1;
+	bytes[64 * 512..65 * 512].copy_from_slice(&label);
+	let (ok, stdout, stderr) = run(&bytes, &[]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stderr,
+		"Warning: Partition 1: Invalid BSD disklabel: checksum mismatch.\n"
+	);
+	assert_eq!(stdout.lines().count(), 5);
+}
+
+#[test]
+fn renders_ldm_volumes() {
+	let (ok, stdout, stderr) = run(&ldm::gpt_disk(), &[]);
+
+	assert!(ok, "{}", stderr);
+	let tables: Vec<_> = stdout.split("\n\n").collect();
+	assert_eq!(tables.len(), 2);
+	assert_eq!(
+		tables[1].lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭──────────────────────────────────────────────────┬───────────────────────────┬───────┬───────┬──────┬─────────┬───────────╮",
+			"│ Name                                             │ Type                      │ Disk  │ Start │ End  │ Sectors │ Size      │",
+			"│ Disk Group (WIN-DG0)                             │ LDM 2.12                  │ Disk1 │ 98    │ 2014 │ 1917    │ 958.5 KiB │",
+			"│ Volume1                                          │ simple                    │       │       │      │ 500     │ 250.0 KiB │",
+			"│ └─ Volume1-01                                    │ concatenated              │       │       │      │ 500     │ 250.0 KiB │",
+			"│    └─ Disk1-01                                   │ extent                    │ Disk1 │ 98    │ 597  │ 500     │ 250.0 KiB │",
+			"│ Volume2                                          │ spanned                   │       │       │      │ 1000    │ 500.0 KiB │",
+			"│ └─ Volume2-01                                    │ concatenated              │       │       │      │ 1000    │ 500.0 KiB │",
+			"│    ├─ Disk1-02                                   │ extent                    │ Disk1 │ 598   │ 897  │ 300     │ 150.0 KiB │",
+			"│    └─ Disk2-01                                   │ extent                    │ Disk2 │       │      │ 700     │ 350.0 KiB │",
+			"│ Volume3, mirrored across both disks of the group │ mirror                    │       │       │      │ 200     │ 100.0 KiB │",
+			"│ ├─ Volume3-01                                    │ concatenated              │       │       │      │ 200     │ 100.0 KiB │",
+			"│ │  └─ Disk1-03                                   │ extent                    │ Disk1 │ 898   │ 1097 │ 200     │ 100.0 KiB │",
+			"│ └─ Volume3-02                                    │ concatenated              │       │       │      │ 200     │ 100.0 KiB │",
+			"│    └─ Disk2-02                                   │ extent                    │ Disk2 │       │      │ 200     │ 100.0 KiB │",
+			"│ Volume4                                          │ striped                   │       │       │      │ 256     │ 128.0 KiB │",
+			"│ └─ Volume4-01                                    │ striped, 64.0 KiB stripes │       │       │      │ 256     │ 128.0 KiB │",
+			"│    ├─ Disk1-04                                   │ extent                    │ Disk1 │ 1098  │ 1225 │ 128     │ 64.0 KiB  │",
+			"│    └─ Disk2-03                                   │ extent                    │ Disk2 │       │      │ 128     │ 64.0 KiB  │",
+			"╰──────────────────────────────────────────────────┴───────────────────────────┴───────┴───────┴──────┴─────────┴───────────╯",
+		]
+	);
+}
+
+#[test]
+fn renders_lvm_physical_volumes() {
+	let (ok, stdout, stderr) = run(&lvm::gpt_disk(), &[]);
+
+	assert!(ok, "{}", stderr);
+	let tables: Vec<_> = stdout.split("\n\n").collect();
+	assert_eq!(tables.len(), 2);
+	assert_eq!(
+		tables[1].lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭────────────────────┬───────────────────────────────────────────┬───────┬─────┬─────────┬───────────╮",
+			"│ Name               │ Type                                      │ Start │ End │ Sectors │ Size      │",
+			"│ Volume Group (vg0) │ PV xXFbCw-8ymQ-3TtJ-Qd2c-lwJq-4hAu-ESCBqy │ 96    │ 895 │ 800     │ 400.0 KiB │",
+			"│ root               │ logical volume                            │       │     │ 400     │ 200.0 KiB │",
+			"│ └─ extents 0-39    │ linear                                    │ 96    │ 415 │ 320     │ 160.0 KiB │",
+			"│ swap               │ logical volume                            │       │     │ 160     │ 80.0 KiB  │",
+			"│ └─ extents 0-19    │ linear                                    │ 416   │ 575 │ 160     │ 80.0 KiB  │",
+			"│ data               │ logical volume                            │       │     │ 160     │ 80.0 KiB  │",
+			"│ └─ extents 0-19    │ striped, 2 stripes                        │ 576   │ 655 │ 80      │ 40.0 KiB  │",
+			"│ [lvol0_pmspare]    │ logical volume                            │       │     │ 16      │ 8.0 KiB   │",
+			"│ └─ extents 0-1     │ linear                                    │ 656   │ 671 │ 16      │ 8.0 KiB   │",
+			"╰────────────────────┴───────────────────────────────────────────┴───────┴─────┴─────────┴───────────╯",
+		]
+	);
+}
+
+#[test]
+fn warns_about_damaged_lvm_physical_volumes() {
+	let mut bytes = lvm::gpt_disk();
+	bytes[64 * 512 + 4096 + 600] ^= 1;
+	let (ok, stdout, stderr) = run(&bytes, &[]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stderr,
+		"Warning: Partition 1: Invalid LVM physical volume: metadata checksum \
+		 mismatch.\n"
+	);
+	assert!(!stdout.contains("\n\n"));
+}
+
+#[test]
+fn renders_luks_headers() {
+	let (ok, stdout, stderr) = run(&luks::gpt_disk(), &[]);
+
+	assert!(ok, "{}", stderr);
+	let tables: Vec<_> = stdout.split("\n\n").collect();
+	assert_eq!(tables.len(), 2);
+	assert_eq!(
+		tables[1].lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────────────────┬────────────────┬──────────────────────────────┬───────────┬────────────────────────────────────────────┬────────────┬──────────────────────────────────────╮",
+			"│ Name                    │ Type           │ Cipher                       │ Key Slots │ PBKDF                                      │ Data Start │ UUID                                 │",
+			"│ Partition 1 (cryptroot) │ LUKS2          │ aes-xts-plain64, 512-bit key │ 2 of 32   │                                            │ 128        │ 4f3c9d1e-2b7a-4c55-9e08-6a1d2f3b4c5d │",
+			"│ ├─ Slot 0               │                │                              │           │ argon2id, 4 iterations, 1.0 GiB, 4 threads │            │                                      │",
+			"│ └─ Slot 2               │                │                              │           │ pbkdf2-sha512, 1000000 iterations          │            │                                      │",
+			"│ Partition 2             │ LUKS1          │ aes-xts-plain64, 512-bit key │ 2 of 8    │                                            │ 1280       │ 4f3c9d1e-2b7a-4c55-9e08-6a1d2f3b4c5d │",
+			"│ ├─ Slot 0               │                │                              │           │ pbkdf2-sha256, 2000000 iterations          │            │                                      │",
+			"│ └─ Slot 3               │                │                              │           │ pbkdf2-sha256, 1500000 iterations          │            │                                      │",
+			"│ Partition 3             │ plain dm-crypt │                              │           │                                            │            │                                      │",
+			"╰─────────────────────────┴────────────────┴──────────────────────────────┴───────────┴────────────────────────────────────────────┴────────────┴──────────────────────────────────────╯",
+		]
+	);
+	// Nothing derived from keys is shown
+	assert!(!stdout.contains("c2VjcmV0"));
+}
+
+#[test]
+fn warns_about_missing_luks_headers() {
+	let mut bytes = luks::gpt_disk();
+	bytes[1024 * 512] = 0;
+	let (ok, stdout, stderr) = run(&bytes, &[]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(stderr, "Warning: Partition 2: No LUKS header found.\n");
+	assert!(stdout.contains("│ Partition 2             │ no header"));
+}
+
+#[test]
+fn renders_md_superblocks() {
+	let (ok, stdout, stderr) = run(&md::gpt_disk(), &[]);
+
+	assert!(ok, "{}", stderr);
+	let tables: Vec<_> = stdout.split("\n\n").collect();
+	assert_eq!(tables.len(), 3);
+	assert_eq!(
+		tables[1].lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────┬─────────┬────────┬─────────────────────────────────────┬────────────────┬──────────┬────────────┬────────╮",
+			"│ Name        │ Version │ Array  │ Array UUID                          │ Level          │ Role     │ Events     │ State  │",
+			"│ Partition 1 │ 1.2     │ host:0 │ 6b3f1c2e:94d04a57:8e210c7b:5d93a4f6 │ raid1, 2 disks │ active 0 │ 120        │ clean  │",
+			"│ Partition 2 │ 1.2     │ host:0 │ 6b3f1c2e:94d04a57:8e210c7b:5d93a4f6 │ raid1, 2 disks │ active 1 │ 115        │ clean  │",
+			"│ Partition 3 │ 1.0     │ host:1 │ 11111111:11111111:11111111:11111111 │ raid5, 3 disks │ spare    │ 9          │ active │",
+			"│ Partition 4 │ 0.90    │        │ 22222222:22222222:22222222:22222222 │ raid0, 2 disks │ active 1 │ 4294967298 │ clean  │",
+			"╰─────────────┴─────────┴────────┴─────────────────────────────────────┴────────────────┴──────────┴────────────┴────────╯",
+		]
+	);
+	// The two members of host:0, compared
+	assert_eq!(
+		tables[2].lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────────────────┬───────┬──────────┬────────┬────────────────────────╮",
+			"│ Name                    │ Level │ Role     │ Events │ Freshness              │",
+			"│ Array (host:0)          │ raid1 │          │ 120    │                        │",
+			"│ ├─ disk.img partition 1 │       │ active 0 │ 120    │ freshest               │",
+			"│ └─ disk.img partition 2 │       │ active 1 │ 115    │ stale, 5 events behind │",
+			"╰─────────────────────────┴───────┴──────────┴────────┴────────────────────────╯",
+		]
+	);
+}
+
+#[test]
+fn renders_md_superblocks_left_unread() {
+	let (ok, stdout, stderr) = run(&compressed::gzip(&md::gpt_disk()), &[]);
+
+	assert!(ok, "{}", stderr);
+	let tables: Vec<_> = stdout.split("\n\n").collect();
+	assert_eq!(tables.len(), 3);
+	// Partitions 3 and 4 keep their superblocks at the end
+	assert_eq!(
+		tables[1].lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────┬────────────────────────────────────────────────────┬────────┬─────────────────────────────────────┬────────────────┬──────────┬────────┬───────╮",
+			"│ Name        │ Version                                            │ Array  │ Array UUID                          │ Level          │ Role     │ Events │ State │",
+			"│ Partition 1 │ 1.2                                                │ host:0 │ 6b3f1c2e:94d04a57:8e210c7b:5d93a4f6 │ raid1, 2 disks │ active 0 │ 120    │ clean │",
+			"│ Partition 2 │ 1.2                                                │ host:0 │ 6b3f1c2e:94d04a57:8e210c7b:5d93a4f6 │ raid1, 2 disks │ active 1 │ 115    │ clean │",
+			"│ Partition 3 │ not checked (the image can't be read out of order) │        │                                     │                │          │        │       │",
+			"│ Partition 4 │ not checked (the image can't be read out of order) │        │                                     │                │          │        │       │",
+			"╰─────────────┴────────────────────────────────────────────────────┴────────┴─────────────────────────────────────┴────────────────┴──────────┴────────┴───────╯",
+		]
+	);
+}
+
+#[test]
+fn renders_zfs_labels() {
+	let (ok, stdout, stderr) = run(&zfs::gpt_disk(), &[]);
+
+	assert!(ok, "{}", stderr);
+	let tables: Vec<_> = stdout.split("\n\n").collect();
+	assert_eq!(tables.len(), 2);
+	// The back labels of partition 2 are listed against the front ones
+	assert_eq!(
+		tables[1].lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────┬──────┬─────────────────────┬───────────┬──────┬────────┬────────────────╮",
+			"│ Name        │ Pool │ Pool GUID           │ Vdev GUID │ TXG  │ State  │ Labels         │",
+			"│ Partition 1 │ tank │ 1311768467463790320 │ 101       │ 1234 │ active │ 4 of 4 agree   │",
+			"│ Partition 2 │ tank │ 1311768467463790320 │ 202       │ 1234 │ active │ inconsistent   │",
+			"│ ├─ Label 0  │ tank │ 1311768467463790320 │ 202       │ 1234 │ active │ front, matches │",
+			"│ ├─ Label 1  │ tank │ 1311768467463790320 │ 202       │ 1234 │ active │ front, matches │",
+			"│ ├─ Label 2  │ tank │ 1311768467463790320 │ 202       │ 1200 │ active │ back, differs  │",
+			"│ └─ Label 3  │ tank │ 1311768467463790320 │ 202       │ 1200 │ active │ back, differs  │",
+			"╰─────────────┴──────┴─────────────────────┴───────────┴──────┴────────┴────────────────╯",
+		]
+	);
+}
+
+#[test]
+fn renders_zfs_labels_left_unread() {
+	let mut bytes = zfs::gpt_disk();
+	bytes[(64 + 32) * 512 + 100] ^= 1;
+	let (ok, stdout, stderr) = run(&compressed::gzip(&bytes), &[]);
+
+	assert!(ok, "{}", stderr);
+	let tables: Vec<_> = stdout.split("\n\n").collect();
+	assert_eq!(tables.len(), 2);
+	// Only the front labels are read, and partition 1 lost one of those
+	assert_eq!(
+		tables[1].lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────┬──────┬─────────────────────┬───────────┬──────┬────────┬────────────────────────────────────────────────────────────────────────╮",
+			"│ Name        │ Pool │ Pool GUID           │ Vdev GUID │ TXG  │ State  │ Labels                                                                 │",
+			"│ Partition 1 │ tank │ 1311768467463790320 │ 101       │ 1234 │ active │ inconsistent, back not checked (the image can't be read out of order)  │",
+			"│ ├─ Label 0  │      │                     │           │      │        │ front, missing or damaged                                              │",
+			"│ ├─ Label 1  │ tank │ 1311768467463790320 │ 101       │ 1234 │ active │ front, matches                                                         │",
+			"│ ├─ Label 2  │      │                     │           │      │        │ back, not checked (the image can't be read out of order)               │",
+			"│ └─ Label 3  │      │                     │           │      │        │ back, not checked (the image can't be read out of order)               │",
+			"│ Partition 2 │ tank │ 1311768467463790320 │ 202       │ 1234 │ active │ front 2 agree, back not checked (the image can't be read out of order) │",
+			"╰─────────────┴──────┴─────────────────────┴───────────┴──────┴────────┴────────────────────────────────────────────────────────────────────────╯",
+		]
+	);
+}
+
+#[test]
+fn renders_boot_slots() {
+	let (ok, stdout, stderr) = run(&slots::chromeos_disk(), &[]);
+
+	assert!(ok, "{}", stderr);
+	let tables: Vec<_> = stdout.split("\n\n").collect();
+	assert_eq!(tables.len(), 2);
+	// Slot B was updated and is tried first
+	assert_eq!(
+		tables[1].lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭──────────────────────────────┬────────────────┬──────────┬────────────┬────────────┬──────────┬────────╮",
+			"│ Name                         │ Partitions     │ Priority │ Tries Left │ Successful │ Bootable │ Active │",
+			"│ ChromeOS (kernel attributes) │                │          │            │            │          │        │",
+			"│ ├─ Slot A                    │ KERN-A, ROOT-A │ 1        │ 0          │ yes        │ yes      │ no     │",
+			"│ └─ Slot B                    │ KERN-B, ROOT-B │ 2        │ 6          │ no         │ yes      │ yes    │",
+			"╰──────────────────────────────┴────────────────┴──────────┴────────────┴────────────┴──────────┴────────╯",
+		]
+	);
+
+	let (ok, stdout, stderr) = run(&slots::android_disk(), &[]);
+
+	assert!(ok, "{}", stderr);
+	let tables: Vec<_> = stdout.split("\n\n").collect();
+	// Partitions of a slot are named without its suffix
+	assert_eq!(
+		tables[1].lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭────────────────┬──────────────┬──────────┬────────────┬────────────┬──────────┬────────╮",
+			"│ Name           │ Partitions   │ Priority │ Tries Left │ Successful │ Bootable │ Active │",
+			"│ Android (misc) │              │          │            │            │          │        │",
+			"│ ├─ Slot a      │ boot, system │ 15       │ 0          │ yes        │ yes      │ yes    │",
+			"│ └─ Slot b      │ boot, system │ 0        │ 0          │ no         │ no       │ no     │",
+			"╰────────────────┴──────────────┴──────────┴────────────┴────────────┴──────────┴────────╯",
+		]
+	);
+}
+
+#[test]
+fn warns_about_damaged_boot_control() {
+	let mut bytes = slots::android_disk();
+	bytes[64 * 512 + 2048 + 12] ^= 1;
+	let (ok, stdout, stderr) = run(&bytes, &[]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stderr,
+		"Warning: Invalid Android boot control block: checksum mismatch.\n"
+	);
+	assert!(!stdout.contains("Tries Left"));
+}
+
+#[test]
+fn fails_without_partition_table() {
+	let mut bytes = vec![0; 1024 * 1024];
+	// Only the protective MBR of a GPT disk is left
+	write_mbr(&mut bytes, &[(0xEE, 1, 2047)]);
+	let (ok, _, stderr) = run(&bytes, &[]);
+
+	assert!(!ok);
+	assert!(stderr.contains("Failed to validate signature of EFI partition."));
+}
+
+#[test]
+fn renders_csv() {
+	let (ok, stdout, stderr) = run(
+		&filesystems::gpt_disk(),
+		&[
+			"--format",
+			"csv",
+			"--columns",
+			"name,type,start,size,fs,label",
+		],
+	);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().collect::<Vec<_>>(),
+		vec![
+			"Name,Type,Start,Size,Filesystem,Label",
+			"EFI,EFI System partition,64,64.0 KiB,vfat,",
+			"root,Linux filesystem data,192,416.0 KiB,ext4,rootfs",
+			"swap,Linux Swap partition,1024,64.0 KiB,swap,swap0",
+			"blank,Linux filesystem data,1152,64.0 KiB,,",
+		]
+	);
+}
+
+#[test]
+fn renders_tsv_without_header() {
+	let (ok, stdout, stderr) = run(
+		&basic(512).build(),
+		&["--format", "tsv", "--no-header", "--columns", "name,uuid"],
+	);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().collect::<Vec<_>>(),
+		vec![
+			"EFI\t00000000-0000-4000-8000-000000000001",
+			"root\t00000000-0000-4000-8000-000000000002",
+			"swap\t00000000-0000-4000-8000-000000000003",
+		]
+	);
+}
+
+#[test]
+fn quotes_csv_fields() {
+	let mut image = basic(512);
+	image.parts[0].attributes = 1 | 1 << 48;
+	let (ok, stdout, stderr) = run(
+		&image.build(),
+		&["--format", "csv", "--columns", "name,attrs"],
+	);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().collect::<Vec<_>>(),
+		vec![
+			"Name,Attributes",
+			"EFI,\"required, bit 48\"",
+			"root,",
+			"swap,"
+		]
+	);
+}
+
+#[test]
+fn renders_attributes_column() {
+	let (ok, stdout, stderr) =
+		run(&slots::chromeos_disk(), &["--columns", "name,attrs"]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout
+			.lines()
+			.take(9)
+			.map(str::trim_end)
+			.collect::<Vec<_>>(),
+		vec![
+			"╭─────────────────┬─────────────────────────────────╮",
+			"│ Name            │ Attributes                      │",
+			"│ Disk (disk.img) │                                 │",
+			"│ STATE           │                                 │",
+			"│ KERN-A          │ priority 1, tries 0, successful │",
+			"│ ROOT-A          │                                 │",
+			"│ KERN-B          │ priority 2, tries 6             │",
+			"│ ROOT-B          │                                 │",
+			"╰─────────────────┴─────────────────────────────────╯",
+		]
+	);
+}
+
+#[test]
+fn renders_mbr_as_csv() {
+	let mut image = Image::new(512);
+	image.mbr = Mbr::Legacy(vec![(0x83, 64, 960), (0x82, 1024, 512)]);
+	let (ok, stdout, stderr) = run(
+		&image.build(),
+		&["--format", "csv", "--columns", "name,type,sectors,attrs"],
+	);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().collect::<Vec<_>>(),
+		vec![
+			"Name,Type,Sectors,Attributes",
+			"1,Linux (0x83),960,",
+			"2,Linux swap / Solaris (0x82),512,",
+		]
+	);
+}
+
+#[test]
+fn rejects_unknown_columns() {
+	let (ok, _, stderr) = run(&basic(512).build(), &["--columns", "name,guid"]);
+
+	assert!(!ok);
+	assert!(stderr.contains("Unknown column `guid`"), "{}", stderr);
+}
+
+#[test]
+fn renders_si_units() {
+	let (ok, stdout, stderr) = run(
+		&basic(512).build(),
+		&["--units", "si", "--format", "csv", "--columns", "name,size"],
+	);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().collect::<Vec<_>>(),
+		vec!["Name,Size", "EFI,51.2 kB", "root,460.8 kB", "swap,51.2 kB"]
+	);
+}
+
+#[test]
+fn renders_exact_bytes() {
+	let (ok, stdout, stderr) =
+		run(&basic(512).build(), &["--bytes", "--columns", "name,size"]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────────┬─────────╮",
+			"│ Name            │ Size    │",
+			"│ Disk (disk.img) │ 1014272 │",
+			"│ EFI             │ 51200   │",
+			"│ root            │ 460800  │",
+			"│ swap            │ 51200   │",
+			"╰─────────────────┴─────────╯",
+		]
+	);
+}
+
+#[test]
+fn renders_ascii_borders() {
+	let image = Image::new(512).part(
+		"516E7CB4-6ECF-11D6-8FF8-00022D09712B",
+		"freebsd",
+		100,
+		1099,
+	);
+	let mut bytes = image.build();
+	let label = disklabel::bsd(&[(7, 0, 800), (1, 800, 200)], false);
+	bytes[101 * 512..102 * 512].copy_from_slice(&label);
+	let (ok, stdout, stderr) =
+		run(&bytes, &["--ascii", "--columns", "name,type,size"]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"+-----------------+------------------------+-----------+",
+			"| Name            | Type                   | Size      |",
+			"| Disk (disk.img) | Block Device           | 990.5 KiB |",
+			"| freebsd         | FreeBSD Data partition | 500.0 KiB |",
+			"| |- a            | 4.2BSD                 | 400.0 KiB |",
+			"| `- b            | swap                   | 100.0 KiB |",
+			"+-----------------+------------------------+-----------+",
+		]
+	);
+}
+
+#[test]
+fn aligns_wide_characters() {
+	let image = basic(512)
+		.part(common::LINUX_FS, "データ", 1200, 1299)
+		.part(common::LINUX_FS, "💾 backup", 1300, 1399);
+	let (ok, stdout, stderr) =
+		run(&image.build(), &["--columns", "name,start,end"]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────────┬───────┬──────╮",
+			"│ Name            │ Start │ End  │",
+			"│ Disk (disk.img) │ 34    │ 2014 │",
+			"│ EFI             │ 34    │ 133  │",
+			"│ root            │ 134   │ 1033 │",
+			"│ swap            │ 1034  │ 1133 │",
+			"│ データ          │ 1200  │ 1299 │",
+			"│ 💾 backup       │ 1300  │ 1399 │",
+			"╰─────────────────┴───────┴──────╯",
+		]
+	);
+}
+
+#[test]
+fn wraps_to_terminal_width() {
+	let dir = TempDir::new().unwrap();
+	fs::write(dir.path().join("disk.img"), basic(512).build()).unwrap();
+	let output = Command::new(env!("CARGO_BIN_EXE_gptinfo"))
+		.current_dir(dir.path())
+		.env("XDG_CONFIG_HOME", dir.path())
+		.env("NO_COLOR", "1")
+		.env("COLUMNS", "64")
+		.args(["--device", "disk.img"])
+		.output()
+		.unwrap();
+	let stdout = String::from_utf8(output.stdout).unwrap();
+
+	assert!(output.status.success());
+	assert_eq!(
+		stdout.lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭────────────┬────────────┬───────┬──────┬─────────┬───────────╮",
+			"│ Name       │ Type       │ Start │ End  │ Sectors │ Size      │",
+			"│ Disk       │ Block      │ 34    │ 2014 │ 1981    │ 990.5 KiB │",
+			"│ (disk.img) │ Device     │       │      │         │           │",
+			"│ EFI        │ EFI System │ 34    │ 133  │ 100     │ 50.0 KiB  │",
+			"│            │ partition  │       │      │         │           │",
+			"│ root       │ Linux      │ 134   │ 1033 │ 900     │ 450.0 KiB │",
+			"│            │ filesystem │       │      │         │           │",
+			"│            │ data       │       │      │         │           │",
+			"│ swap       │ Linux Swap │ 1034  │ 1133 │ 100     │ 50.0 KiB  │",
+			"│            │ partition  │       │      │         │           │",
+			"╰────────────┴────────────┴───────┴──────┴─────────┴───────────╯",
+		]
+	);
+}
+
+#[test]
+fn colors_only_when_asked() {
+	let image = basic(512).build();
+	let (_, plain, _) = run(&image, &[]);
+	let (_, never, _) = run(&image, &["--color", "never"]);
+	let (ok, always, stderr) = run(&image, &["--color", "always"]);
+
+	assert!(ok, "{}", stderr);
+	assert!(!plain.contains('\x1b'));
+	assert!(!never.contains('\x1b'));
+	// Overrides NO_COLOR, and bolds the header
+	assert!(always.contains("\x1b[1mName"), "{}", always);
+}
+
+#[test]
+fn renders_disk_map() {
+	let image = Image::new(512)
+		.part(common::ESP, "EFI", 64, 191)
+		.part(common::LINUX_FS, "root", 192, 1535)
+		.part(common::LINUX_SWAP, "swap", 1792, 1983);
+	let (ok, stdout, stderr) =
+		run(&image.build(), &["--map", "--columns", "name"]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"╭─────────────────╮",
+			"│ Name            │",
+			"│ Disk (disk.img) │",
+			"│ EFI             │",
+			"│ root            │",
+			"│ swap            │",
+			"╰─────────────────╯",
+			"",
+			"│MHEE░░██1████████████████████████2██████████████████████░░░░░░░░░░███3███░░EEH│",
+			"1 EFI, 2 root, 3 swap, M protective MBR, H header, E partition entries, ░ free",
+			"Free: 34 to 63 (15.0 KiB)",
+			"Free: 1536 to 1791 (128.0 KiB)",
+			"Free: 1984 to 2014 (15.5 KiB)",
+		]
+	);
+}
+
+#[test]
+fn merges_regions_too_small_for_the_map() {
+	let mut image = Image::new(512);
+	image.num_entries = 256;
+	image.sectors = 4096;
+	for idx in 0..200 {
+		let first = 100 + idx * 16;
+		image = image.part(common::LINUX_FS, "data", first, first + 7);
+	}
+	let (ok, stdout, stderr) = run(&image.build(), &["--map"]);
+
+	assert!(ok, "{}", stderr);
+	// 200 partitions and the free space between them in 78 cells
+	let bar = stdout
+		.lines()
+		.find(|line| line.starts_with('│') && !line.contains(' '))
+		.unwrap();
+	assert_eq!(bar.chars().count(), 80, "{}", bar);
+	assert_eq!(bar.matches('…').count(), 1, "{}", bar);
+	assert!(stdout.contains("… regions too small to show"));
+}
+
+#[test]
+fn renders_disk_map_in_ascii() {
+	let image = basic(512).build();
+	let (ok, stdout, stderr) =
+		run(&image, &["--map", "--ascii", "--columns", "name"]);
+
+	assert!(ok, "{}", stderr);
+	assert_eq!(
+		stdout.lines().map(str::trim_end).collect::<Vec<_>>(),
+		vec![
+			"+-----------------+",
+			"| Name            |",
+			"| Disk (disk.img) |",
+			"| EFI             |",
+			"| root            |",
+			"| swap            |",
+			"+-----------------+",
+			"",
+			"|MHEE##1#################2################3##....................\
+			 ...........EEH|",
+			"1 EFI, 2 root, 3 swap, M protective MBR, H header, E partition \
+			 entries, . free",
+			"Free: 1134 to 2014 (440.5 KiB)",
+		]
+	);
+}
@@ -0,0 +1,374 @@
+mod common;
+
+use common::{
+	basic, compressed, md, qcow2::Qcow2, vhd, vhdx, vmdk, zfs, Image, LINUX_FS,
+};
+use gptinfo::{
+	read_gpt, read_gpt_from,
+	reader::{
+		md::Probe,
+		source::{self, BlockSource, Slice},
+	},
+	Error, TableStatus,
+};
+use std::{fs, io::Cursor, path::Path};
+use tempfile::TempDir;
+
+/// Reads all of `source` in chunks that straddle cluster boundaries.
+fn read_all(source: &mut dyn BlockSource) -> Vec<u8> {
+	let mut bytes = vec![0u8; source.size() as usize];
+	for (idx, chunk) in bytes.chunks_mut(3000).enumerate() {
+		source.read_at(idx as u64 * 3000, chunk).unwrap();
+	}
+	bytes
+}
+
+fn write(dir: &TempDir, name: &str, bytes: &[u8]) -> Box<dyn BlockSource> {
+	let path = dir.path().join(name);
+	fs::write(&path, bytes).unwrap();
+	source::open(&path).unwrap()
+}
+
+#[test]
+fn reads_raw_images() {
+	let dir = TempDir::new().unwrap();
+	let raw = basic(512).build();
+	let mut source = write(&dir, "disk.img", &raw);
+
+	assert_eq!(source.size(), raw.len() as u64);
+	assert_eq!(read_all(&mut *source), raw);
+	assert!(source.read_at(raw.len() as u64 - 10, &mut [0; 20]).is_err());
+}
+
+#[test]
+fn reads_qcow2_images() {
+	let dir = TempDir::new().unwrap();
+	let raw = basic(512).build();
+
+	// 512-byte clusters need many L2 tables; 64KiB ones a single one
+	for &cluster_bits in [9, 16].iter() {
+		for &(compress, zstd, zero_flag) in [
+			(false, false, false),
+			(true, false, true),
+			(true, true, false),
+		]
+		.iter()
+		{
+			let mut image = Qcow2::new(cluster_bits);
+			image.compress = compress;
+			image.zstd = zstd;
+			image.zero_flag = zero_flag;
+			let mut source = write(&dir, "disk.qcow2", &image.build(&raw));
+
+			assert_eq!(source.size(), raw.len() as u64);
+			assert!(read_all(&mut *source) == raw, "{}", cluster_bits);
+			let disk = read_gpt_from(&mut *source).unwrap();
+			assert_eq!(
+				disk.entries,
+				read_gpt(Cursor::new(&raw)).unwrap().entries
+			);
+		}
+	}
+}
+
+#[test]
+fn follows_qcow2_backing_files() {
+	let dir = TempDir::new().unwrap();
+	let base = basic(512).build();
+	fs::write(dir.path().join("base.img"), &base).unwrap();
+
+	// The overlay only stores the clusters the extra partition changed
+	let cluster = 4096;
+	let changed = basic(512).part(LINUX_FS, "home", 1200, 1299).build();
+	let mut delta = changed.clone();
+	for (new, old) in delta.chunks_mut(cluster).zip(base.chunks(cluster)) {
+		if new == old {
+			new.iter_mut().for_each(|byte| *byte = 0);
+		}
+	}
+	let mut overlay = Qcow2::new(12);
+	overlay.backing_file = Some("base.img".into());
+	fs::write(dir.path().join("overlay.qcow2"), overlay.build(&delta)).unwrap();
+
+	// Which is itself the backing file of an empty image
+	let mut top = Qcow2::new(16);
+	top.backing_file = Some("overlay.qcow2".into());
+	let mut source = write(&dir, "top.qcow2", &top.build(&vec![0; base.len()]));
+
+	assert!(read_all(&mut *source) == changed);
+	let disk = read_gpt_from(&mut *source).unwrap();
+	assert_eq!(disk.entries.len(), 4);
+	assert_eq!(disk.entries[3].name, "home");
+}
+
+#[test]
+fn refuses_backing_files_outside_the_image_directory() {
+	let dir = TempDir::new().unwrap();
+	let base = basic(512).build();
+	let base_path = dir.path().join("base.img");
+	fs::write(&base_path, &base).unwrap();
+
+	let mut overlay = Qcow2::new(16);
+	overlay.backing_file = Some(base_path.to_str().unwrap().into());
+	let path = dir.path().join("overlay.qcow2");
+	fs::write(&path, overlay.build(&vec![0; base.len()])).unwrap();
+
+	match source::open(&path) {
+		Err(Error::UnsafeBackingFile(name)) => {
+			assert_eq!(Path::new(&name), base_path)
+		}
+		other => panic!("expected UnsafeBackingFile, got {:?}", other.err()),
+	}
+
+	let file = fs::File::open(&path).unwrap();
+	let mut source = source::from_file_with(file, &path, true).unwrap();
+	assert!(read_all(&mut *source) == base);
+}
+
+#[test]
+fn rejects_unsupported_qcow2_features() {
+	let dir = TempDir::new().unwrap();
+	let mut image = Qcow2::new(16).build(&basic(512).build());
+	// AES encryption
+	image[35] = 1;
+
+	let path = dir.path().join("disk.qcow2");
+	fs::write(&path, &image).unwrap();
+	assert!(matches!(
+		source::open(&path),
+		Err(Error::UnsupportedImage("qcow2", "encrypted images"))
+	));
+
+	image[35] = 0;
+	image[4..8].copy_from_slice(&4u32.to_be_bytes());
+	fs::write(&path, &image).unwrap();
+	assert!(matches!(
+		source::open(Path::new(&path)),
+		Err(Error::UnsupportedImage("qcow2", "unknown version"))
+	));
+}
+
+/// A 4MiB disk, so images span several blocks
+fn larger_disk() -> Vec<u8> {
+	let mut image = Image::new(512)
+		.part(common::ESP, "EFI", 2048, 4095)
+		.part(LINUX_FS, "root", 4096, 8000);
+	image.sectors = 8192;
+	image.build()
+}
+
+fn assert_reads_as(source: &mut dyn BlockSource, raw: &[u8]) {
+	assert_eq!(source.size(), raw.len() as u64);
+	assert!(read_all(source) == raw);
+	let disk = read_gpt_from(source).unwrap();
+	assert_eq!(disk.entries, read_gpt(Cursor::new(raw)).unwrap().entries);
+}
+
+#[test]
+fn reads_vhd_images() {
+	let dir = TempDir::new().unwrap();
+	let raw = larger_disk();
+
+	let mut source = write(&dir, "fixed.vhd", &vhd::fixed(&raw));
+	assert_reads_as(&mut *source, &raw);
+
+	let dynamic = vhd::dynamic(&raw, 64 * 1024, vhd::DYNAMIC);
+	let mut source = write(&dir, "dynamic.vhd", &dynamic);
+	assert_reads_as(&mut *source, &raw);
+
+	// The copy of the footer at the start stands in for a lost one
+	let mut source = write(&dir, "cut.vhd", &dynamic[..dynamic.len() - 512]);
+	assert_reads_as(&mut *source, &raw);
+}
+
+#[test]
+fn reads_vhdx_images() {
+	let dir = TempDir::new().unwrap();
+	let raw = larger_disk();
+
+	for &stale_header in [false, true].iter() {
+		let image = vhdx::build(&raw, 1024 * 1024, stale_header);
+		let mut source = write(&dir, "disk.vhdx", &image);
+		assert_reads_as(&mut *source, &raw);
+	}
+}
+
+#[test]
+fn reads_vmdk_images() {
+	let dir = TempDir::new().unwrap();
+	let raw = larger_disk();
+	let descriptor = "# Disk DescriptorFile\nversion=1\nparentCID=ffffffff\n";
+
+	for &stream in [false, true].iter() {
+		let image = vmdk::build(&raw, descriptor, stream);
+		let mut source = write(&dir, "disk.vmdk", &image);
+		assert_reads_as(&mut *source, &raw);
+	}
+}
+
+#[test]
+fn streams_compressed_images() {
+	let dir = TempDir::new().unwrap();
+	let raw = larger_disk();
+	let expected = read_gpt(Cursor::new(&raw)).unwrap();
+
+	let images = [
+		("disk.img.gz", compressed::gzip(&raw)),
+		("disk.img.xz", compressed::xz(&raw, None)),
+		("disk.img.zst", compressed::zstd(&raw)),
+	];
+	for (name, bytes) in images.iter() {
+		let mut source = write(&dir, name, bytes);
+		assert!(!source.seekable(), "{}", name);
+
+		let disk = read_gpt_from(&mut *source).unwrap();
+		assert_eq!(disk.entries, expected.entries, "{}", name);
+		assert_eq!(disk.primary, TableStatus::Valid);
+		assert_eq!(disk.backup, TableStatus::Skipped);
+
+		// Going back starts over
+		let mut sector = [0u8; 512];
+		source.read_at(1024 * 1024, &mut sector).unwrap();
+		assert!(sector[..] == raw[1024 * 1024..][..512], "{}", name);
+		source.read_at(512, &mut sector).unwrap();
+		assert!(sector[..] == raw[512..1024], "{}", name);
+		let past_end = source.read_at(raw.len() as u64, &mut sector);
+		assert!(past_end.is_err(), "{}", name);
+	}
+
+	// Only the xz index records the size
+	let source = write(&dir, "disk.img.xz", &images[1].1);
+	assert_eq!(source.size(), raw.len() as u64);
+
+	// A partial download still has the primary table
+	let xz = compressed::xz(&raw, Some(64 * 1024));
+	let mut source = write(&dir, "partial.img.xz", &xz[..xz.len() / 2]);
+	assert!(!source.seekable());
+	let disk = read_gpt_from(&mut *source).unwrap();
+	assert_eq!(disk.entries, expected.entries);
+}
+
+#[test]
+fn leaves_the_end_of_streamed_partitions_unread() {
+	let dir = TempDir::new().unwrap();
+	let mut source =
+		write(&dir, "md.img.gz", &compressed::gzip(&md::gpt_disk()));
+	let mut read_md = |first_lba: u64| {
+		gptinfo::reader::md::read(&mut *source, first_lba * 512, 256 * 512)
+			.unwrap()
+	};
+
+	// 1.2 superblocks are near the start, but 1.0 and 0.90 ones at the end
+	assert!(matches!(read_md(64), Probe::Found(sb) if sb.version == "1.2"));
+	assert_eq!(read_md(576), Probe::Skipped);
+	assert_eq!(read_md(832), Probe::Skipped);
+
+	// Only the front ZFS labels, which don't see that the back ones of the
+	// second vdev are stale
+	let mut source =
+		write(&dir, "zfs.img.gz", &compressed::gzip(&zfs::gpt_disk()));
+	let vdev = gptinfo::reader::zfs::read(&mut *source, 2112 * 512, 2048 * 512)
+		.unwrap()
+		.unwrap();
+	assert_eq!(vdev.labels.len(), 2);
+	assert!(vdev.consistent());
+}
+
+#[test]
+fn seeks_in_compressed_images() {
+	let dir = TempDir::new().unwrap();
+	let raw = larger_disk();
+
+	let xz = compressed::xz(&raw, Some(64 * 1024));
+	// Concatenated streams, with stream padding in between
+	let mut concatenated = compressed::xz(&raw[..1024 * 1024], None);
+	concatenated.extend(&[0; 8]);
+	concatenated.extend(compressed::xz(&raw[1024 * 1024..], Some(100_000)));
+	let images = [
+		("disk.img.xz", xz),
+		("concatenated.img.xz", concatenated),
+		(
+			"disk.img.zst",
+			compressed::seekable_zstd(&raw, 64 * 1024, false),
+		),
+		(
+			"checked.img.zst",
+			compressed::seekable_zstd(&raw, 100_000, true),
+		),
+	];
+	for (name, bytes) in images.iter() {
+		let mut source = write(&dir, name, bytes);
+		assert!(source.seekable(), "{}", name);
+		assert_reads_as(&mut *source, &raw);
+		let disk = read_gpt_from(&mut *source).unwrap();
+		assert_eq!(disk.backup, TableStatus::Valid, "{}", name);
+	}
+}
+
+#[test]
+fn reads_slices() {
+	let dir = TempDir::new().unwrap();
+	let raw = basic(512).build();
+	let mut outer = vec![0xAA; 3000];
+	outer.extend(&raw);
+	outer.extend(vec![0xAA; 5000]);
+
+	// Everything after the offset, or exactly the disk
+	let source = write(&dir, "outer.img", &outer);
+	let mut slice = Slice::new(source, 3000, None).unwrap();
+	assert_eq!(slice.size(), raw.len() as u64 + 5000);
+	let disk = read_gpt_from(&mut slice).unwrap();
+	assert_eq!(disk.entries, read_gpt(Cursor::new(&raw)).unwrap().entries);
+
+	let source = write(&dir, "outer.img", &outer);
+	let mut slice = Slice::new(source, 3000, Some(raw.len() as u64)).unwrap();
+	assert_reads_as(&mut slice, &raw);
+	assert!(slice.read_at(raw.len() as u64 - 10, &mut [0; 20]).is_err());
+
+	// Slices of compressed images stay sequential
+	let xz = compressed::xz(&outer, None);
+	let mut slice =
+		Slice::new(write(&dir, "outer.xz", &xz), 3000, None).unwrap();
+	assert!(!slice.seekable());
+	assert_eq!(
+		read_gpt_from(&mut slice).unwrap().backup,
+		TableStatus::Skipped
+	);
+
+	for &(offset, length) in
+		[(0, Some(outer.len() as u64 + 1)), (9_000_000, None)].iter()
+	{
+		let source = write(&dir, "outer.img", &outer);
+		assert!(matches!(
+			Slice::new(source, offset, length),
+			Err(Error::OutOfRange(..))
+		));
+	}
+}
+
+#[test]
+fn rejects_unsupported_images() {
+	let dir = TempDir::new().unwrap();
+	let raw = larger_disk();
+	let open = |name: &str, bytes: &[u8]| {
+		let path = dir.path().join(name);
+		fs::write(&path, bytes).unwrap();
+		source::open(&path).err().unwrap()
+	};
+
+	let child = vhd::dynamic(&raw, 64 * 1024, vhd::DIFFERENCING);
+	assert!(matches!(
+		open("child.vhd", &child),
+		Error::UnsupportedImage("vhd", "differencing disks")
+	));
+
+	let delta = vmdk::build(&raw, "parentFileNameHint=\"base.vmdk\"\n", false);
+	assert!(matches!(
+		open("delta.vmdk", &delta),
+		Error::UnsupportedImage("vmdk", "delta disks")
+	));
+	assert!(matches!(
+		open("disk.vmdk", b"# Disk DescriptorFile\nversion=1\n"),
+		Error::UnsupportedImage("vmdk", _)
+	));
+}
@@ -0,0 +1,355 @@
+mod common;
+
+use gptinfo::{
+	read_gpt,
+	system::{
+		block_devices_in, default_device, holders_in, parse_mountinfo,
+		parse_swaps, BlockDevice, Divergence, HolderKind, KernelPartition,
+		LiveDisk,
+	},
+	Guid, PartitionEntry,
+};
+use std::{fs, io::Cursor, path::Path};
+use tempfile::TempDir;
+
+fn attr(dir: &Path, name: &str, value: &str) {
+	let path = dir.join(name);
+	fs::create_dir_all(path.parent().unwrap()).unwrap();
+	fs::write(path, format!("{}\n", value)).unwrap();
+}
+
+#[test]
+fn lists_whole_disks() {
+	let root = TempDir::new().unwrap();
+	let sys = root.path();
+
+	for &name in ["nvme0n1", "vda", "ram0", "loop0", "loop1", "sr0"].iter() {
+		attr(&sys.join(name), "dev", "1:0");
+	}
+	attr(&sys.join("nvme0n1"), "size", "2000409264");
+	attr(&sys.join("nvme0n1"), "device/model", "Samsung SSD 970 EVO");
+	let partitions = [
+		("nvme0n1p2", "2", "1050624", "4096"),
+		("nvme0n1p1", "1", "2048", "1048576"),
+	];
+	for &(name, number, start, size) in partitions.iter() {
+		let part = sys.join("nvme0n1").join(name);
+		attr(&part, "partition", number);
+		attr(&part, "dev", &format!("259:{}", number));
+		attr(&part, "start", start);
+		attr(&part, "size", size);
+	}
+	attr(&sys.join("vda"), "size", "2048");
+	attr(&sys.join("ram0"), "size", "8192");
+	attr(&sys.join("loop0"), "size", "0");
+	attr(&sys.join("loop1"), "size", "4096");
+	attr(
+		&sys.join("loop1"),
+		"loop/backing_file",
+		"/var/lib/image.img",
+	);
+	attr(&sys.join("sr0"), "size", "0");
+
+	let devices = block_devices_in(sys).unwrap();
+	let names: Vec<_> = devices.iter().map(|dev| dev.name.as_str()).collect();
+	assert_eq!(names, vec!["loop1", "nvme0n1", "vda"]);
+
+	let nvme = &devices[1];
+	assert_eq!(nvme.path, Path::new("/dev/nvme0n1"));
+	assert_eq!(nvme.size, 2000409264 * 512);
+	assert_eq!(nvme.model.as_deref(), Some("Samsung SSD 970 EVO"));
+	assert_eq!(
+		nvme.partitions,
+		vec![
+			KernelPartition {
+				name: "nvme0n1p1".into(),
+				number: 1,
+				dev: (259, 1),
+				start: 2048 * 512,
+				size: 1048576 * 512,
+			},
+			KernelPartition {
+				name: "nvme0n1p2".into(),
+				number: 2,
+				dev: (259, 2),
+				start: 1050624 * 512,
+				size: 4096 * 512,
+			},
+		]
+	);
+	assert_eq!(devices[2].model, None);
+}
+
+#[test]
+fn follows_holders() {
+	let root = TempDir::new().unwrap();
+	let sys = root.path();
+
+	let devices = [
+		("nvme0n1p2", "259:2"),
+		("nvme0n1p3", "259:3"),
+		("sda1", "8:1"),
+		("dm-0", "253:0"),
+		("dm-1", "253:1"),
+		("dm-2", "253:2"),
+		("md127", "9:127"),
+	];
+	for &(name, dev) in devices.iter() {
+		attr(&sys.join(name), "dev", dev);
+		attr(&sys.join(name), "size", "2048");
+		fs::create_dir_all(sys.join(name).join("holders")).unwrap();
+	}
+	let stack = [
+		("nvme0n1p3", "dm-0"),
+		("dm-0", "dm-2"),
+		("dm-0", "dm-1"),
+		("nvme0n1p2", "md127"),
+		("sda1", "md127"),
+	];
+	for &(lower, upper) in stack.iter() {
+		fs::create_dir(sys.join(lower).join("holders").join(upper)).unwrap();
+	}
+	attr(&sys.join("dm-0"), "dm/name", "cryptroot");
+	attr(
+		&sys.join("dm-0"),
+		"dm/uuid",
+		"CRYPT-LUKS2-0123abcd-cryptroot",
+	);
+	attr(&sys.join("dm-1"), "dm/name", "vg-root");
+	attr(&sys.join("dm-1"), "dm/uuid", "LVM-aBcD");
+	attr(&sys.join("dm-2"), "dm/name", "vg-swap");
+	attr(&sys.join("dm-2"), "dm/uuid", "LVM-eFgH");
+	attr(&sys.join("md127"), "md/level", "raid1");
+
+	let crypt = holders_in(sys, "nvme0n1p3").unwrap();
+	assert_eq!(crypt.len(), 1);
+	assert_eq!(crypt[0].kind, HolderKind::Crypt("cryptroot".into()));
+	assert_eq!(crypt[0].dev, (253, 0));
+	assert_eq!(crypt[0].size, 2048 * 512);
+	assert_eq!(crypt[0].path(), Path::new("/dev/mapper/cryptroot"));
+	assert_eq!(crypt[0].node(), Path::new("/dev/dm-0"));
+
+	let volumes: Vec<_> = crypt[0]
+		.holders
+		.iter()
+		.map(|holder| (holder.name.as_str(), holder.display_name()))
+		.collect();
+	assert_eq!(volumes, vec![("dm-1", "vg-root"), ("dm-2", "vg-swap")]);
+	assert_eq!(crypt[0].holders[0].kind, HolderKind::Lvm("vg-root".into()));
+
+	let raid = holders_in(sys, "sda1").unwrap();
+	assert_eq!(raid, holders_in(sys, "nvme0n1p2").unwrap());
+	assert_eq!(raid[0].kind, HolderKind::Md(Some("raid1".into())));
+	assert_eq!(raid[0].kind.to_string(), "md raid1");
+	assert_eq!(raid[0].path(), Path::new("/dev/md127"));
+
+	assert_eq!(holders_in(sys, "dm-1").unwrap(), vec![]);
+	assert_eq!(holders_in(sys, "vanished").unwrap(), vec![]);
+}
+
+#[test]
+fn skips_stacked_devices() {
+	let root = TempDir::new().unwrap();
+	let sys = root.path();
+
+	for &name in ["dm-0", "md127", "sda", "zram0"].iter() {
+		attr(&sys.join(name), "dev", "1:0");
+		attr(&sys.join(name), "size", "2048");
+	}
+	let part = sys.join("sda").join("sda2");
+	for &(name, value) in [
+		("partition", "2"),
+		("dev", "8:2"),
+		("start", "1024"),
+		("size", "1024"),
+	]
+	.iter()
+	{
+		attr(&part, name, value);
+	}
+	// LVM on a partition and md-raid on the whole disk
+	fs::create_dir_all(sys.join("dm-0/slaves/sda2")).unwrap();
+	fs::create_dir_all(sys.join("md127/slaves/sda")).unwrap();
+	fs::create_dir_all(sys.join("sda/slaves")).unwrap();
+
+	let devices = block_devices_in(sys).unwrap();
+	let names: Vec<_> = devices.iter().map(|dev| dev.name.as_str()).collect();
+	assert_eq!(names, vec!["sda"]);
+	assert_eq!(default_device(&devices).unwrap().name, "sda");
+}
+
+#[test]
+fn prefers_built_in_disks_by_default() {
+	let root = TempDir::new().unwrap();
+	let sys = root.path();
+
+	for &name in ["loop0", "sda", "vda"].iter() {
+		attr(&sys.join(name), "dev", "1:0");
+		attr(&sys.join(name), "size", "2048");
+	}
+	attr(
+		&sys.join("loop0"),
+		"loop/backing_file",
+		"/var/lib/image.img",
+	);
+	attr(&sys.join("sda"), "removable", "1");
+	attr(&sys.join("vda"), "removable", "0");
+
+	// Sorted first, but an image and a USB stick
+	let devices = block_devices_in(sys).unwrap();
+	assert!(devices[1].removable);
+	assert_eq!(default_device(&devices).unwrap().name, "vda");
+
+	// Still better than nothing
+	assert_eq!(default_device(&devices[..2]).unwrap().name, "loop0");
+	assert_eq!(default_device(&[]), None);
+}
+
+#[test]
+fn parses_mountinfo() {
+	let mounts = parse_mountinfo(
+		"\
+22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw,errors=remount-ro
+23 22 259:1 / /boot/efi rw,relatime shared:2 - vfat /dev/nvme0n1p1 \
+		 rw,fmask=0077
+24 22 259:2 /srv /mnt/my\\040data ro,relatime - ext4 /dev/nvme0n1p2 rw
+malformed line
+",
+	);
+
+	assert_eq!(mounts.len(), 3);
+	assert_eq!(mounts[0].dev, (259, 2));
+	assert_eq!(mounts[0].options, "rw,relatime,errors=remount-ro");
+	assert_eq!(mounts[1].mount_point, Path::new("/boot/efi"));
+	assert_eq!(mounts[1].fs_type, "vfat");
+	assert_eq!(mounts[2].root, "/srv");
+	assert_eq!(mounts[2].mount_point, Path::new("/mnt/my data"));
+	assert_eq!(mounts[2].options, "ro,relatime,rw");
+}
+
+fn kernel_partition(
+	name: &str,
+	number: u32,
+	start: u64,
+	size: u64,
+) -> KernelPartition {
+	KernelPartition {
+		name: name.into(),
+		number,
+		dev: (259, number),
+		start: start * 512,
+		size: size * 512,
+	}
+}
+
+fn live_disk(partitions: Vec<KernelPartition>) -> LiveDisk {
+	LiveDisk {
+		device: BlockDevice {
+			name: "nvme0n1".into(),
+			path: "/dev/nvme0n1".into(),
+			dev: (259, 0),
+			size: 1 << 30,
+			model: None,
+			removable: false,
+			partitions,
+		},
+		mounts: parse_mountinfo(
+			"22 1 259:1 / /boot/efi rw - vfat /dev/nvme0n1p1 rw\n",
+		),
+		swaps: parse_swaps(
+			"Filename\tType\tSize\tUsed\tPriority\n/dev/nvme0n1p2 partition \
+			 4194300 0 -2\n",
+		),
+	}
+}
+
+fn entry(number: u32, first_lba: u64, last_lba: u64) -> PartitionEntry {
+	PartitionEntry {
+		number,
+		type_guid: Guid::default(),
+		unique_guid: Guid::default(),
+		first_lba,
+		last_lba,
+		attributes: 0,
+		name: String::new(),
+	}
+}
+
+#[test]
+fn matches_entries_to_kernel_partitions() {
+	let live = live_disk(vec![
+		kernel_partition("nvme0n1p1", 1, 2048, 2048),
+		kernel_partition("nvme0n1p2", 2, 4096, 8192),
+	]);
+
+	let esp = live.partition(&entry(1, 2048, 4095), 512).unwrap();
+	assert_eq!(esp.path(), Path::new("/dev/nvme0n1p1"));
+	assert_eq!(live.mounts_of(esp)[0].mount_point, Path::new("/boot/efi"));
+	assert!(!live.is_swap(esp));
+
+	let swap = live.partition(&entry(2, 4096, 12287), 512).unwrap();
+	assert!(live.mounts_of(swap).is_empty());
+	assert!(live.is_swap(swap));
+
+	// Same start, different size: the kernel's view is stale
+	assert!(live.partition(&entry(2, 4096, 9999), 512).is_none());
+}
+
+#[test]
+fn matches_mounts_by_source() {
+	let mut live =
+		live_disk(vec![kernel_partition("nvme0n1p3", 3, 2048, 2048)]);
+	// btrfs gives each subvolume an anonymous device number
+	live.mounts = parse_mountinfo(
+		"\
+30 1 0:31 /@home /home rw,relatime shared:1 - btrfs /dev/nvme0n1p3 \
+		 rw,subvol=/@home
+31 1 0:31 /@ / rw,relatime shared:2 - btrfs /dev/nvme0n1p3 rw,subvol=/@
+32 1 0:32 / /tmp rw - tmpfs tmpfs rw
+",
+	);
+
+	let root = live.partition(&entry(3, 2048, 4095), 512).unwrap();
+	let points: Vec<_> = live
+		.mounts_of(root)
+		.iter()
+		.map(|mount| mount.mount_point.clone())
+		.collect();
+	assert_eq!(points, vec![Path::new("/home"), Path::new("/")]);
+}
+
+#[test]
+fn finds_divergences() {
+	let live = live_disk(vec![
+		kernel_partition("nvme0n1p1", 1, 2048, 2048),
+		kernel_partition("nvme0n1p2", 2, 4096, 8192),
+		kernel_partition("nvme0n1p4", 4, 20480, 2048),
+	]);
+	let mut image = common::Image::new(512)
+		.part(common::ESP, "", 2048, 4095)
+		.part(common::LINUX_FS, "", 4096, 16383)
+		.part(common::LINUX_FS, "", 16384, 18431);
+	image.sectors = 40000;
+	let mut disk = read_gpt(Cursor::new(image.build())).unwrap();
+
+	assert_eq!(
+		live.divergences(&disk),
+		vec![
+			Divergence::Stale {
+				entry: disk.entries[1].clone(),
+				kernel: live.device.partitions[1].clone(),
+			},
+			Divergence::Missing {
+				entry: disk.entries[2].clone(),
+			},
+			Divergence::Extra {
+				kernel: live.device.partitions[2].clone(),
+			},
+		]
+	);
+
+	disk.entries.truncate(1);
+	disk.entries.push(entry(2, 4096, 12287));
+	disk.entries.push(entry(4, 20480, 22527));
+	assert_eq!(live.divergences(&disk), vec![]);
+}